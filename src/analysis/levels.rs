@@ -0,0 +1,357 @@
+use crate::wav::{PCMWaveFormatChunk, PCMWaveInfo};
+
+use super::true_peak::TruePeakMeter;
+use super::{full_scale, BlockAnalyzer};
+
+/// Represents a run of consecutive inter-channel samples
+/// 
+/// `start` is the index of the first inter-channel sample of the run
+/// counted from the start of the file and `len` is the number of
+/// inter-channel samples in the run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SampleRun {
+    pub start: u64,
+    pub len: u64,
+}
+
+/// Represents the level analysis of a single channel
+/// 
+/// All amplitudes are relative to full scale, so a full-scale sample has
+/// an amplitude of 1.0. Use `analysis::to_dbfs` to convert them into dBFS.
+#[derive(Debug)]
+pub struct ChannelAnalysis {
+    pub sample_peak: f64,
+    pub true_peak: f64,
+    pub rms: f64,
+    pub dc_offset: f64,
+    pub clipped_runs: Vec <SampleRun>,
+    pub silent_runs: Vec <SampleRun>,
+}
+
+/// Represents the level analysis of a whole file
+/// 
+/// The file-wide values are computed over all channels: the peaks are the
+/// maximum of each channel, the RMS and DC offset are computed over every
+/// sample of every channel, a clipped run contains inter-channel samples
+/// where *at least one* channel is clipped, and a silent run contains
+/// inter-channel samples where *all* channels are digitally silent.
+#[derive(Debug)]
+pub struct AudioAnalysis {
+    pub num_samples: u64,
+    pub sample_peak: f64,
+    pub true_peak: f64,
+    pub rms: f64,
+    pub dc_offset: f64,
+    pub clipped_runs: Vec <SampleRun>,
+    pub silent_runs: Vec <SampleRun>,
+    pub channels: Vec <ChannelAnalysis>,
+}
+
+/// Represents a level analyzer of PCM audio
+/// 
+/// The analyzer is fed blocks of inter-channel samples, either directly
+/// from a `PCMWaveDataChunkWindow` or by the `FlacWriter` while encoding,
+/// and produces an `AudioAnalysis` once all blocks have been processed.
+pub struct AudioAnalyzer {
+    clip_max: i64,
+    clip_min: i64,
+    scale: f64,
+    num_samples: u64,
+    channels: Vec <ChannelState>,
+    clipped: RunTracker,
+    silent: RunTracker,
+}
+
+/// Represents the running state of a single channel in an `AudioAnalyzer`
+struct ChannelState {
+    sum: i128,
+    sum_sq: f64,
+    peak: u64,
+    true_peak: TruePeakMeter,
+    clipped: RunTracker,
+    silent: RunTracker,
+}
+
+/// Represents a collector of runs of consecutive flagged samples
+struct RunTracker {
+    min_len: u64,
+    curr_start: u64,
+    curr_len: u64,
+    runs: Vec <SampleRun>,
+}
+
+impl AudioAnalyzer {
+    /// Create a new analyzer for audio of the given format
+    /// 
+    /// Stretches of digital silence shorter than `min_silence_len`
+    /// inter-channel samples are not reported.
+    pub fn new(format: &PCMWaveFormatChunk, min_silence_len: u64) -> Self {
+        let clip_max = (1i64 << (format.bps.max(1) - 1)) - 1;
+        let channels = (0..format.num_channels)
+            .map(|_| ChannelState::new(min_silence_len))
+            .collect();
+
+        Self {
+            clip_max,
+            clip_min: -clip_max - 1,
+            scale: full_scale(format.bps),
+            num_samples: 0,
+            channels,
+            clipped: RunTracker::new(1),
+            silent: RunTracker::new(min_silence_len),
+        }
+    }
+
+    /// Analyze all data chunks of a PCM WAV file
+    /// 
+    /// This reads the whole file once. To analyze a file while encoding
+    /// it, pass an `AudioAnalyzer` to `FlacWriter::write_from_wave`
+    /// instead.
+    pub fn analyze(wav: PCMWaveInfo, min_silence_len: u64) -> AudioAnalysis {
+        let mut analyzer = Self::new(&wav.fmt_header, min_silence_len);
+        let block_size = wav.fmt_header.samp_rate as usize;
+
+        for data_chunk in wav.data_chunks {
+            for sample_block in data_chunk.chunks(block_size) {
+                analyzer.process_block(&sample_block);
+            }
+        }
+
+        analyzer.finish()
+    }
+
+    /// Finish the analysis and get its results
+    pub fn finish(mut self) -> AudioAnalysis {
+        let num_samples = self.num_samples;
+        let scale = self.scale;
+
+        self.clipped.close();
+        self.silent.close();
+
+        let channels: Vec <ChannelAnalysis> = self.channels
+            .into_iter()
+            .map(|ch| ch.finish(num_samples, scale))
+            .collect();
+
+        let total_samples = num_samples * channels.len() as u64;
+        let (sum_sq, sum) = channels.iter().fold((0.0, 0.0), |(sq, s), ch| {
+            (sq + ch.rms * ch.rms * num_samples as f64, s + ch.dc_offset * num_samples as f64)
+        });
+
+        let (rms, dc_offset) = if total_samples == 0 {
+            (0.0, 0.0)
+        } else {
+            ((sum_sq / total_samples as f64).sqrt(), sum / total_samples as f64)
+        };
+
+        AudioAnalysis {
+            num_samples,
+            sample_peak: channels.iter().fold(0.0, |acc, ch| ch.sample_peak.max(acc)),
+            true_peak: channels.iter().fold(0.0, |acc, ch| ch.true_peak.max(acc)),
+            rms,
+            dc_offset,
+            clipped_runs: self.clipped.runs,
+            silent_runs: self.silent.runs,
+            channels,
+        }
+    }
+}
+
+impl BlockAnalyzer for AudioAnalyzer {
+    fn process_block(&mut self, sample_block: &[Vec <i64>]) {
+        for inter_sample in sample_block {
+            let index = self.num_samples;
+            let mut any_clipped = false;
+            let mut all_silent = true;
+
+            for (ch, &sample) in self.channels.iter_mut().zip(inter_sample.iter()) {
+                let is_clipped = sample >= self.clip_max || sample <= self.clip_min;
+                let is_silent = sample == 0;
+
+                ch.sum += sample as i128;
+                ch.sum_sq += (sample as f64) * (sample as f64);
+                ch.peak = ch.peak.max(sample.unsigned_abs());
+                ch.true_peak.process(sample as f64 / self.scale);
+                ch.clipped.update(index, is_clipped);
+                ch.silent.update(index, is_silent);
+
+                any_clipped |= is_clipped;
+                all_silent &= is_silent;
+            }
+
+            self.clipped.update(index, any_clipped);
+            self.silent.update(index, all_silent);
+            self.num_samples += 1;
+        }
+    }
+}
+
+impl ChannelState {
+    fn new(min_silence_len: u64) -> Self {
+        Self {
+            sum: 0,
+            sum_sq: 0.0,
+            peak: 0,
+            true_peak: TruePeakMeter::new(),
+            clipped: RunTracker::new(1),
+            silent: RunTracker::new(min_silence_len),
+        }
+    }
+
+    fn finish(mut self, num_samples: u64, scale: f64) -> ChannelAnalysis {
+        self.clipped.close();
+        self.silent.close();
+
+        let (rms, dc_offset) = if num_samples == 0 {
+            (0.0, 0.0)
+        } else {
+            let n = num_samples as f64;
+            ((self.sum_sq / n).sqrt() / scale, self.sum as f64 / n / scale)
+        };
+        let sample_peak = self.peak as f64 / scale;
+
+        ChannelAnalysis {
+            sample_peak,
+            true_peak: self.true_peak.peak().max(sample_peak),
+            rms,
+            dc_offset,
+            clipped_runs: self.clipped.runs,
+            silent_runs: self.silent.runs,
+        }
+    }
+}
+
+impl RunTracker {
+    fn new(min_len: u64) -> Self {
+        Self {
+            min_len: min_len.max(1),
+            curr_start: 0,
+            curr_len: 0,
+            runs: Vec::new(),
+        }
+    }
+
+    /// Mark whether the sample at `index` belongs to a run
+    fn update(&mut self, index: u64, is_flagged: bool) {
+        if is_flagged {
+            if self.curr_len == 0 {
+                self.curr_start = index;
+            }
+            self.curr_len += 1;
+        }
+        else {
+            self.close();
+        }
+    }
+
+    /// Close the current run, if any
+    fn close(&mut self) {
+        if self.curr_len >= self.min_len {
+            self.runs.push(SampleRun {
+                start: self.curr_start,
+                len: self.curr_len,
+            });
+        }
+        self.curr_len = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format(num_channels: u16, bps: u16) -> PCMWaveFormatChunk {
+        PCMWaveFormatChunk {
+            num_channels,
+            samp_rate: 44100,
+            bps,
+            channel_mask: None,
+        }
+    }
+
+    #[test]
+    fn sample_mono_levels() {
+        let mut analyzer = AudioAnalyzer::new(&format(1, 16), 2);
+        let block = vec![
+            vec![16384], vec![-16384], vec![16384], vec![-16384],
+        ];
+
+        analyzer.process_block(&block);
+        let analysis = analyzer.finish();
+
+        assert_eq!(analysis.num_samples, 4);
+        assert_eq!(analysis.sample_peak, 0.5);
+        assert_eq!(analysis.rms, 0.5);
+        assert_eq!(analysis.dc_offset, 0.0);
+        assert!(analysis.clipped_runs.is_empty());
+        assert!(analysis.silent_runs.is_empty());
+    }
+
+    #[test]
+    fn sample_dc_offset() {
+        let mut analyzer = AudioAnalyzer::new(&format(1, 8), 1);
+
+        analyzer.process_block(&[vec![32], vec![32], vec![32], vec![32]]);
+        let analysis = analyzer.finish();
+
+        assert_eq!(analysis.dc_offset, 0.25);
+        assert_eq!(analysis.channels[0].dc_offset, 0.25);
+    }
+
+    #[test]
+    fn sample_clipped_runs_across_blocks() {
+        let mut analyzer = AudioAnalyzer::new(&format(2, 16), 1);
+
+        analyzer.process_block(&[vec![0, 1], vec![32767, 1], vec![32767, -32768]]);
+        analyzer.process_block(&[vec![1000, -32768], vec![1, 1], vec![-32768, 5]]);
+        let analysis = analyzer.finish();
+
+        assert_eq!(analysis.clipped_runs, vec![
+            SampleRun { start: 1, len: 3 },
+            SampleRun { start: 5, len: 1 },
+        ]);
+        assert_eq!(analysis.channels[0].clipped_runs, vec![
+            SampleRun { start: 1, len: 2 },
+            SampleRun { start: 5, len: 1 },
+        ]);
+        assert_eq!(analysis.channels[1].clipped_runs, vec![
+            SampleRun { start: 2, len: 2 },
+        ]);
+        assert_eq!(analysis.sample_peak, 1.0);
+    }
+
+    #[test]
+    fn sample_silent_runs() {
+        let mut analyzer = AudioAnalyzer::new(&format(2, 16), 3);
+        let block = vec![
+            vec![0, 0], vec![0, 0], vec![0, 0],
+            vec![0, 7], vec![0, 0], vec![0, 0],
+            vec![5, 0], vec![0, 0], vec![0, 0], vec![0, 0],
+        ];
+
+        analyzer.process_block(&block);
+        let analysis = analyzer.finish();
+
+        assert_eq!(analysis.silent_runs, vec![
+            SampleRun { start: 0, len: 3 },
+            SampleRun { start: 7, len: 3 },
+        ]);
+        assert_eq!(analysis.channels[0].silent_runs, vec![
+            SampleRun { start: 0, len: 6 },
+            SampleRun { start: 7, len: 3 },
+        ]);
+        assert_eq!(analysis.channels[1].silent_runs, vec![
+            SampleRun { start: 0, len: 3 },
+            SampleRun { start: 4, len: 6 },
+        ]);
+    }
+
+    #[test]
+    fn sample_empty() {
+        let analysis = AudioAnalyzer::new(&format(2, 24), 1).finish();
+
+        assert_eq!(analysis.num_samples, 0);
+        assert_eq!(analysis.rms, 0.0);
+        assert_eq!(analysis.channels.len(), 2);
+    }
+}
@@ -0,0 +1,532 @@
+use std::collections::VecDeque;
+
+use crate::wav::{PCMWaveFormatChunk, PCMWaveInfo, Speaker};
+
+use super::{full_scale, BlockAnalyzer};
+
+/// Loudness of the absolute gate in LUFS
+const ABSOLUTE_GATE: f64 = -70.0;
+
+/// Offset of the relative gate from the absolute-gated loudness in LU
+const RELATIVE_GATE: f64 = -10.0;
+
+/// Offset of the relative gate used for the loudness range in LU
+const LRA_RELATIVE_GATE: f64 = -20.0;
+
+/// Number of 100 ms steps in one 400 ms momentary gating block
+const STEPS_PER_BLOCK: usize = 4;
+
+/// Number of 100 ms steps in one 3 s short-term window
+const STEPS_PER_SHORT_TERM: usize = 30;
+
+/// Represents a biquad IIR filter in transposed direct form II
+#[derive(Clone, Copy)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 3],
+    z: [f64; 2],
+}
+
+/// Represents the K-weighting filter of ITU-R BS.1770 for a single channel
+/// 
+/// The filter is a cascade of a high-shelf "pre-filter" modelling the
+/// acoustic effect of the head and a high-pass "RLB" filter. The
+/// coefficients are derived for the sample rate of the audio so that
+/// the response matches the reference coefficients at 48 kHz.
+#[derive(Clone, Copy)]
+pub struct KWeightingFilter {
+    shelf: Biquad,
+    highpass: Biquad,
+}
+
+/// Represents the loudness of a file as defined in EBU R128
+/// 
+/// The integrated loudness and the maxima are in LUFS while the loudness
+/// range is in LU. Any of them is negative infinity (or zero for the
+/// loudness range) if the audio is too short or too quiet to be measured.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LoudnessReport {
+    pub integrated: f64,
+    pub loudness_range: f64,
+    pub momentary_max: f64,
+    pub short_term_max: f64,
+}
+
+/// Represents a BS.1770 loudness meter
+/// 
+/// The meter K-weights every channel and sums the weighted mean-square energy
+/// of all channels in 100 ms steps. Four steps make up a 400 ms momentary block,
+/// used for gating the integrated loudness, and thirty steps make up a 3 s
+/// short-term window, used for the loudness range. Both slide by one step.
+pub struct LoudnessMeter {
+    scale: f64,
+    filters: Vec <KWeightingFilter>,
+    weights: Vec <f64>,
+    step_len: usize,
+    step_pos: usize,
+    step_energy: f64,
+    steps: VecDeque <f64>,
+    block_energies: Vec <f64>,
+    short_term_energies: Vec <f64>,
+}
+
+impl Biquad {
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b[0] * x + self.z[0];
+
+        self.z[0] = self.b[1] * x - self.a[1] * y + self.z[1];
+        self.z[1] = self.b[2] * x - self.a[2] * y;
+
+        y
+    }
+}
+
+impl KWeightingFilter {
+    /// Create a K-weighting filter for some sample rate
+    pub fn new(samp_rate: u32) -> Self {
+        let rate = samp_rate as f64;
+
+        // High-shelf pre-filter
+        let f0 = 1681.974450955533;
+        let gain = 3.999843853973347;
+        let q = 0.7071752369554196;
+        let k = (std::f64::consts::PI * f0 / rate).tan();
+        let vh = 10f64.powf(gain / 20.0);
+        let vb = vh.powf(0.4996667741545416);
+        let a0 = 1.0 + k / q + k * k;
+        let shelf = Biquad {
+            b: [
+                (vh + vb * k / q + k * k) / a0,
+                2.0 * (k * k - vh) / a0,
+                (vh - vb * k / q + k * k) / a0,
+            ],
+            a: [1.0, 2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+            z: [0.0; 2],
+        };
+
+        // RLB high-pass filter
+        let f0 = 38.13547087602444;
+        let q = 0.5003270373238773;
+        let k = (std::f64::consts::PI * f0 / rate).tan();
+        let a0 = 1.0 + k / q + k * k;
+        let highpass = Biquad {
+            b: [1.0, -2.0, 1.0],
+            a: [1.0, 2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+            z: [0.0; 2],
+        };
+
+        Self {
+            shelf,
+            highpass,
+        }
+    }
+
+    /// Filter a single sample normalized to full scale
+    pub fn process(&mut self, sample: f64) -> f64 {
+        self.highpass.process(self.shelf.process(sample))
+    }
+}
+
+impl LoudnessMeter {
+    /// Create a new loudness meter for audio of the given format
+    /// 
+    /// The channels are weighted according to the speaker layout of the WAV
+    /// file, as given by its channel mask.
+    pub fn new(format: &PCMWaveFormatChunk) -> Self {
+        Self::with_layout(format, &format.channel_layout())
+    }
+
+    /// Create a new loudness meter for audio with a specific speaker layout
+    /// 
+    /// `layout[c]` is the speaker position of channel `c`. Channels with no
+    /// known position are weighted as front channels.
+    pub fn with_layout(format: &PCMWaveFormatChunk, layout: &[Option <Speaker>]) -> Self {
+        let weights = (0..format.num_channels as usize)
+            .map(|c| Self::channel_weight(layout.get(c).copied().flatten()))
+            .collect();
+
+        Self {
+            scale: full_scale(format.bps),
+            filters: vec![KWeightingFilter::new(format.samp_rate); format.num_channels as usize],
+            weights,
+            step_len: (format.samp_rate as usize / 10).max(1),
+            step_pos: 0,
+            step_energy: 0.0,
+            steps: VecDeque::with_capacity(STEPS_PER_SHORT_TERM),
+            block_energies: Vec::new(),
+            short_term_energies: Vec::new(),
+        }
+    }
+
+    /// Measure the loudness of all data chunks of a PCM WAV file
+    pub fn measure(wav: PCMWaveInfo) -> LoudnessReport {
+        let mut meter = Self::new(&wav.fmt_header);
+        let block_size = wav.fmt_header.samp_rate as usize;
+
+        for data_chunk in wav.data_chunks {
+            for sample_block in data_chunk.chunks(block_size) {
+                meter.process_block(&sample_block);
+            }
+        }
+
+        meter.report()
+    }
+
+    /// Get the weight of a channel given its speaker position
+    /// 
+    /// Following ITU-R BS.1770, the LFE channel is excluded, the surround
+    /// channels are weighted by +1.5 dB (1.41) and all other channels
+    /// have unity weight.
+    pub fn channel_weight(speaker: Option <Speaker>) -> f64 {
+        match speaker {
+            Some(Speaker::LowFrequency) => 0.0,
+            Some(Speaker::BackLeft) | Some(Speaker::BackRight) | Some(Speaker::BackCenter)
+                | Some(Speaker::SideLeft) | Some(Speaker::SideRight) => 1.41,
+            _ => 1.0,
+        }
+    }
+
+    /// Get the loudness report of all samples seen so far
+    pub fn report(&self) -> LoudnessReport {
+        LoudnessReport {
+            integrated: self.integrated_loudness(),
+            loudness_range: self.loudness_range(),
+            momentary_max: Self::max_loudness(&self.block_energies),
+            short_term_max: Self::max_loudness(&self.short_term_energies),
+        }
+    }
+
+    /// Get the mean-square energies of all complete 400 ms gating blocks
+    pub fn block_energies(&self) -> &Vec <f64> {
+        &self.block_energies
+    }
+
+    /// Get the gated integrated loudness in LUFS of all samples seen so far
+    /// 
+    /// Returns negative infinity if no gating block is above the absolute gate.
+    pub fn integrated_loudness(&self) -> f64 {
+        Self::gated_loudness(&self.block_energies)
+    }
+
+    /// Get the loudness range in LU of all samples seen so far
+    /// 
+    /// Following EBU Tech 3342, the short-term loudness values are gated with
+    /// an absolute gate of -70 LUFS and a relative gate 20 LU below their mean,
+    /// and the loudness range is the difference between the 95th and the 10th
+    /// percentile of the remaining values.
+    pub fn loudness_range(&self) -> f64 {
+        let abs_threshold = Self::loudness_to_energy(ABSOLUTE_GATE);
+        let abs_gated: Vec <f64> = self.short_term_energies
+            .iter()
+            .copied()
+            .filter(|&e| e > abs_threshold)
+            .collect();
+
+        if abs_gated.is_empty() {
+            return 0.0;
+        }
+
+        let abs_mean = abs_gated.iter().sum::<f64>() / abs_gated.len() as f64;
+        let rel_threshold = abs_mean * 10f64.powf(LRA_RELATIVE_GATE / 10.0);
+        let mut loudness: Vec <f64> = abs_gated
+            .into_iter()
+            .filter(|&e| e > rel_threshold)
+            .map(Self::energy_to_loudness)
+            .collect();
+
+        if loudness.is_empty() {
+            return 0.0;
+        }
+
+        loudness.sort_by(|a, b| a.total_cmp(b));
+
+        let percentile = |p: f64| loudness[((loudness.len() - 1) as f64 * p).round() as usize];
+
+        percentile(0.95) - percentile(0.10)
+    }
+
+    /// Compute the gated integrated loudness of a list of gating block energies
+    /// 
+    /// Blocks below the absolute gate of -70 LUFS are discarded first. Then,
+    /// blocks more than 10 LU below the loudness of the remaining blocks are
+    /// also discarded, and the loudness of what remains is returned.
+    pub fn gated_loudness(block_energies: &[f64]) -> f64 {
+        let abs_threshold = Self::loudness_to_energy(ABSOLUTE_GATE);
+        let abs_gated: Vec <f64> = block_energies
+            .iter()
+            .copied()
+            .filter(|&e| e > abs_threshold)
+            .collect();
+
+        if abs_gated.is_empty() {
+            return f64::NEG_INFINITY;
+        }
+
+        let abs_mean = abs_gated.iter().sum::<f64>() / abs_gated.len() as f64;
+        let rel_threshold = abs_mean * 10f64.powf(RELATIVE_GATE / 10.0);
+        let (sum, count) = abs_gated
+            .iter()
+            .filter(|&&e| e > rel_threshold)
+            .fold((0.0, 0usize), |(s, n), &e| (s + e, n + 1));
+
+        if count == 0 {
+            return f64::NEG_INFINITY;
+        }
+
+        Self::energy_to_loudness(sum / count as f64)
+    }
+
+    /// Convert a mean-square energy into loudness in LUFS
+    pub fn energy_to_loudness(energy: f64) -> f64 {
+        -0.691 + 10.0 * energy.log10()
+    }
+
+    /// Convert loudness in LUFS into a mean-square energy
+    pub fn loudness_to_energy(loudness: f64) -> f64 {
+        10f64.powf((loudness + 0.691) / 10.0)
+    }
+
+    /// Get the loudness of the loudest of some energies
+    fn max_loudness(energies: &[f64]) -> f64 {
+        energies
+            .iter()
+            .copied()
+            .fold(f64::NEG_INFINITY, |acc, e| acc.max(Self::energy_to_loudness(e)))
+    }
+
+    /// Close the current 100 ms step and emit the windows ending at this step
+    fn end_step(&mut self) {
+        if self.steps.len() == STEPS_PER_SHORT_TERM {
+            self.steps.pop_front();
+        }
+        self.steps.push_back(self.step_energy);
+        self.step_energy = 0.0;
+        self.step_pos = 0;
+
+        if self.steps.len() >= STEPS_PER_BLOCK {
+            let block_len = (self.step_len * STEPS_PER_BLOCK) as f64;
+            let block_sum: f64 = self.steps.iter().rev().take(STEPS_PER_BLOCK).sum();
+
+            self.block_energies.push(block_sum / block_len);
+        }
+
+        if self.steps.len() == STEPS_PER_SHORT_TERM {
+            let window_len = (self.step_len * STEPS_PER_SHORT_TERM) as f64;
+
+            self.short_term_energies.push(self.steps.iter().sum::<f64>() / window_len);
+        }
+    }
+}
+
+impl BlockAnalyzer for LoudnessMeter {
+    fn process_block(&mut self, sample_block: &[Vec <i64>]) {
+        for inter_sample in sample_block {
+            let channels = self.filters.iter_mut().zip(self.weights.iter());
+
+            for ((filter, &weight), &sample) in channels.zip(inter_sample.iter()) {
+                let filtered = filter.process(sample as f64 / self.scale);
+                self.step_energy += weight * filtered * filtered;
+            }
+
+            self.step_pos += 1;
+            if self.step_pos == self.step_len {
+                self.end_step();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine_block(samp_rate: u32, freq: f64, amplitude: f64, num_channels: usize, secs: f64) -> Vec <Vec <i64>> {
+        let num_samples = (samp_rate as f64 * secs) as usize;
+
+        (0..num_samples)
+            .map(|i| {
+                let t = i as f64 / samp_rate as f64;
+                let sample = (amplitude * 32767.0 * (2.0 * std::f64::consts::PI * freq * t).sin()).round() as i64;
+                vec![sample; num_channels]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn sample_k_weighting_48k() {
+        // Reference coefficients from ITU-R BS.1770-4 for 48 kHz
+        let filter = KWeightingFilter::new(48000);
+
+        assert!((filter.shelf.b[0] - 1.53512485958697).abs() < 1e-9);
+        assert!((filter.shelf.b[1] + 2.69169618940638).abs() < 1e-9);
+        assert!((filter.shelf.b[2] - 1.19839281085285).abs() < 1e-9);
+        assert!((filter.shelf.a[1] + 1.69065929318241).abs() < 1e-9);
+        assert!((filter.shelf.a[2] - 0.73248077421585).abs() < 1e-9);
+        assert!((filter.highpass.a[1] + 1.99004745483398).abs() < 1e-9);
+        assert!((filter.highpass.a[2] - 0.99007225036621).abs() < 1e-9);
+    }
+
+    #[test]
+    fn sample_sine_1khz_stereo() {
+        // A full-scale 1 kHz sine on two channels reads as about -0.0 LUFS
+        // since K-weighting has a gain of about +0.691 dB at 1 kHz
+        let format = PCMWaveFormatChunk {
+            num_channels: 2,
+            samp_rate: 48000,
+            bps: 16,
+            channel_mask: None,
+        };
+        let mut meter = LoudnessMeter::new(&format);
+
+        meter.process_block(&sine_block(48000, 1000.0, 1.0, 2, 3.0));
+
+        assert!(meter.integrated_loudness().abs() < 0.1);
+    }
+
+    #[test]
+    fn sample_sine_1khz_mono_minus_20() {
+        // A -20 dBFS 1 kHz sine on a single channel reads as about -23 LUFS
+        let format = PCMWaveFormatChunk {
+            num_channels: 1,
+            samp_rate: 44100,
+            bps: 16,
+            channel_mask: None,
+        };
+        let mut meter = LoudnessMeter::new(&format);
+
+        meter.process_block(&sine_block(44100, 1000.0, 0.1, 1, 5.0));
+
+        assert!((meter.integrated_loudness() + 23.01).abs() < 0.1);
+    }
+
+    #[test]
+    fn sample_block_count() {
+        // 1 s of audio has 10 steps, and hence 7 overlapping gating blocks
+        let format = PCMWaveFormatChunk {
+            num_channels: 1,
+            samp_rate: 8000,
+            bps: 16,
+            channel_mask: None,
+        };
+        let mut meter = LoudnessMeter::new(&format);
+
+        meter.process_block(&sine_block(8000, 440.0, 0.5, 1, 1.0));
+
+        assert_eq!(meter.block_energies().len(), 7);
+    }
+
+    #[test]
+    fn sample_report_constant_sine() {
+        // A steady signal has equal momentary, short-term and integrated
+        // loudness, and no loudness range
+        let format = PCMWaveFormatChunk {
+            num_channels: 2,
+            samp_rate: 48000,
+            bps: 16,
+            channel_mask: None,
+        };
+        let mut meter = LoudnessMeter::new(&format);
+
+        meter.process_block(&sine_block(48000, 1000.0, 0.5, 2, 5.0));
+        let report = meter.report();
+
+        assert!((report.integrated + 6.02).abs() < 0.05);
+        assert!((report.momentary_max - report.integrated).abs() < 0.05);
+        assert!((report.short_term_max - report.integrated).abs() < 0.05);
+        assert!(report.loudness_range < 0.05);
+    }
+
+    #[test]
+    fn sample_loudness_range() {
+        // Two 10 s halves 10 dB apart span a loudness range of about 10 LU
+        let format = PCMWaveFormatChunk {
+            num_channels: 1,
+            samp_rate: 8000,
+            bps: 16,
+            channel_mask: None,
+        };
+        let mut meter = LoudnessMeter::new(&format);
+
+        meter.process_block(&sine_block(8000, 1000.0, 0.1, 1, 10.0));
+        meter.process_block(&sine_block(8000, 1000.0, 0.316, 1, 10.0));
+        let report = meter.report();
+
+        assert!((report.loudness_range - 10.0).abs() < 0.2);
+        assert!(report.momentary_max > report.integrated);
+    }
+
+    #[test]
+    fn sample_channel_weights() {
+        // The LFE of 5.1 audio is excluded and the surround channels are boosted
+        let format = PCMWaveFormatChunk {
+            num_channels: 6,
+            samp_rate: 48000,
+            bps: 16,
+            channel_mask: None,
+        };
+        let lfe_only: Vec <Vec <i64>> = sine_block(48000, 1000.0, 0.5, 1, 3.0)
+            .into_iter()
+            .map(|s| vec![0, 0, 0, s[0], 0, 0])
+            .collect();
+        let front_only: Vec <Vec <i64>> = sine_block(48000, 1000.0, 0.5, 1, 3.0)
+            .into_iter()
+            .map(|s| vec![s[0], 0, 0, 0, 0, 0])
+            .collect();
+        let back_only: Vec <Vec <i64>> = sine_block(48000, 1000.0, 0.5, 1, 3.0)
+            .into_iter()
+            .map(|s| vec![0, 0, 0, 0, s[0], 0])
+            .collect();
+
+        let mut meter = LoudnessMeter::new(&format);
+        meter.process_block(&lfe_only);
+        assert_eq!(meter.integrated_loudness(), f64::NEG_INFINITY);
+
+        let mut front_meter = LoudnessMeter::new(&format);
+        front_meter.process_block(&front_only);
+        let mut back_meter = LoudnessMeter::new(&format);
+        back_meter.process_block(&back_only);
+
+        let diff = back_meter.integrated_loudness() - front_meter.integrated_loudness();
+        assert!((diff - 1.49).abs() < 0.01);
+    }
+
+    #[test]
+    fn sample_channel_mask_weights() {
+        // The fourth channel is the LFE of a 3.1 channel mask, rather than
+        // the back right channel of the default layout of four channels
+        let mut format = PCMWaveFormatChunk {
+            num_channels: 4,
+            samp_rate: 48000,
+            bps: 16,
+            channel_mask: Some(0xf),
+        };
+        let fourth_only: Vec <Vec <i64>> = sine_block(48000, 1000.0, 0.5, 1, 3.0)
+            .into_iter()
+            .map(|s| vec![0, 0, 0, s[0]])
+            .collect();
+
+        let mut meter = LoudnessMeter::new(&format);
+        meter.process_block(&fourth_only);
+        assert_eq!(meter.integrated_loudness(), f64::NEG_INFINITY);
+
+        format.channel_mask = None;
+        let mut default_meter = LoudnessMeter::new(&format);
+        default_meter.process_block(&fourth_only);
+        assert!(default_meter.integrated_loudness().is_finite());
+    }
+
+    #[test]
+    fn sample_silence_is_gated() {
+        let format = PCMWaveFormatChunk {
+            num_channels: 2,
+            samp_rate: 44100,
+            bps: 16,
+            channel_mask: None,
+        };
+        let mut meter = LoudnessMeter::new(&format);
+
+        meter.process_block(&vec![vec![0, 0]; 44100]);
+
+        assert_eq!(meter.integrated_loudness(), f64::NEG_INFINITY);
+    }
+}
@@ -0,0 +1,50 @@
+pub mod levels;
+pub mod loudness;
+pub mod replaygain;
+pub mod silence;
+pub mod true_peak;
+
+/// Represents an analysis that consumes blocks of inter-channel samples
+/// 
+/// A sample block has the same layout as the items returned by a
+/// `PCMWaveDataChunkWindow`, that is, `sample_block[i][c]` is the `i`th
+/// inter-channel sample of channel `c`. Analyzers can be passed to the
+/// `FlacWriter` so that they see the exact same blocks being encoded.
+pub trait BlockAnalyzer {
+    /// Feed the next block of inter-channel samples into this analyzer
+    fn process_block(&mut self, sample_block: &[Vec <i64>]);
+}
+
+/// Get the value of a full-scale sample for some bit depth
+/// 
+/// Samples are signed, so a full-scale sample of bit depth `bps` has
+/// a magnitude of `2 ^ (bps - 1)`.
+pub fn full_scale(bps: u16) -> f64 {
+    (1u64 << (bps.max(1) - 1)) as f64
+}
+
+/// Convert a linear amplitude relative to full scale into dBFS
+/// 
+/// An amplitude of zero is mapped into negative infinity.
+pub fn to_dbfs(amplitude: f64) -> f64 {
+    20.0 * amplitude.log10()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sample_full_scale_01() {
+        assert_eq!(full_scale(8), 128.0);
+        assert_eq!(full_scale(16), 32768.0);
+        assert_eq!(full_scale(24), 8_388_608.0);
+    }
+
+    #[test]
+    fn sample_to_dbfs_01() {
+        assert_eq!(to_dbfs(1.0), 0.0);
+        assert!((to_dbfs(0.5) + 6.0206).abs() < 1e-4);
+        assert_eq!(to_dbfs(0.0), f64::NEG_INFINITY);
+    }
+}
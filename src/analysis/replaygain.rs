@@ -0,0 +1,206 @@
+use crate::wav::PCMWaveFormatChunk;
+
+use super::loudness::LoudnessMeter;
+use super::true_peak::TruePeakMeter;
+use super::{full_scale, BlockAnalyzer};
+
+/// Reference loudness of ReplayGain 2.0 in LUFS
+pub const REFERENCE_LOUDNESS: f64 = -18.0;
+
+/// Loudness in LUFS assumed for tracks that are entirely below the absolute gate
+const MIN_LOUDNESS: f64 = -70.0;
+
+/// Represents a ReplayGain 2.0 gain and peak pair
+/// 
+/// `gain` is the adjustment in dB needed to bring the audio to the reference
+/// loudness of -18 LUFS and `peak` is the true peak of the audio relative to
+/// full scale.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReplayGain {
+    pub gain: f64,
+    pub peak: f64,
+}
+
+/// Represents a ReplayGain 2.0 scanner of a single track
+/// 
+/// The scanner measures the integrated loudness of a track according to
+/// EBU R128 and its true peak. It is fed blocks of inter-channel samples,
+/// usually by the `FlacWriter` while the track is being encoded.
+pub struct ReplayGainScanner {
+    scale: f64,
+    meter: LoudnessMeter,
+    peaks: Vec <TruePeakMeter>,
+}
+
+/// Represents an accumulator of the ReplayGain of several tracks of an album
+/// 
+/// The album gain is not the average of the track gains. Instead, the
+/// gating blocks of all tracks are pooled together and the integrated
+/// loudness is computed over all of them, as if the album was one track.
+pub struct ReplayGainAlbum {
+    block_energies: Vec <f64>,
+    peak: f64,
+}
+
+impl ReplayGain {
+    /// Compute the ReplayGain from an integrated loudness and a peak
+    pub fn from_loudness(loudness: f64, peak: f64) -> Self {
+        Self {
+            gain: REFERENCE_LOUDNESS - loudness.max(MIN_LOUDNESS),
+            peak,
+        }
+    }
+
+    /// Format the gain as written in a `REPLAYGAIN_*_GAIN` Vorbis comment
+    pub fn gain_string(&self) -> String {
+        format!("{:+.2} dB", self.gain)
+    }
+
+    /// Format the peak as written in a `REPLAYGAIN_*_PEAK` Vorbis comment
+    pub fn peak_string(&self) -> String {
+        format!("{:.6}", self.peak)
+    }
+}
+
+impl ReplayGainScanner {
+    /// Create a new scanner for a track of the given format
+    pub fn new(format: &PCMWaveFormatChunk) -> Self {
+        Self {
+            scale: full_scale(format.bps),
+            meter: LoudnessMeter::new(format),
+            peaks: (0..format.num_channels).map(|_| TruePeakMeter::new()).collect(),
+        }
+    }
+
+    /// Finish scanning and get the track ReplayGain
+    pub fn finish(self) -> ReplayGain {
+        ReplayGain::from_loudness(self.meter.integrated_loudness(), self.peak())
+    }
+
+    /// Get the true peak across all channels seen so far
+    fn peak(&self) -> f64 {
+        self.peaks.iter().fold(0.0, |acc, meter| meter.peak().max(acc))
+    }
+}
+
+impl BlockAnalyzer for ReplayGainScanner {
+    fn process_block(&mut self, sample_block: &[Vec <i64>]) {
+        self.meter.process_block(sample_block);
+
+        for inter_sample in sample_block {
+            for (meter, &sample) in self.peaks.iter_mut().zip(inter_sample.iter()) {
+                meter.process(sample as f64 / self.scale);
+            }
+        }
+    }
+}
+
+impl ReplayGainAlbum {
+    /// Create a new empty album
+    pub fn new() -> Self {
+        Self {
+            block_energies: Vec::new(),
+            peak: 0.0,
+        }
+    }
+
+    /// Add a scanned track to the album and get its track ReplayGain
+    pub fn add_track(&mut self, scanner: ReplayGainScanner) -> ReplayGain {
+        let peak = scanner.peak();
+
+        self.block_energies.extend_from_slice(scanner.meter.block_energies());
+        self.peak = self.peak.max(peak);
+
+        ReplayGain::from_loudness(scanner.meter.integrated_loudness(), peak)
+    }
+
+    /// Get the album ReplayGain of all tracks added so far
+    pub fn finish(&self) -> ReplayGain {
+        ReplayGain::from_loudness(LoudnessMeter::gated_loudness(&self.block_energies), self.peak)
+    }
+}
+
+impl Default for ReplayGainAlbum {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format() -> PCMWaveFormatChunk {
+        PCMWaveFormatChunk {
+            num_channels: 1,
+            samp_rate: 44100,
+            bps: 16,
+            channel_mask: None,
+        }
+    }
+
+    fn sine_block(amplitude: f64, secs: f64) -> Vec <Vec <i64>> {
+        let num_samples = (44100.0 * secs) as usize;
+
+        (0..num_samples)
+            .map(|i| {
+                let t = i as f64 / 44100.0;
+                vec![(amplitude * 32767.0 * (2.0 * std::f64::consts::PI * 1000.0 * t).sin()).round() as i64]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn sample_track_gain() {
+        // A -20 dBFS 1 kHz mono sine is about -23 LUFS, so about +5 dB is needed
+        let mut scanner = ReplayGainScanner::new(&format());
+
+        scanner.process_block(&sine_block(0.1, 5.0));
+        let rg = scanner.finish();
+
+        assert!((rg.gain - 5.01).abs() < 0.1);
+        assert!((rg.peak - 0.1).abs() < 0.001);
+    }
+
+    #[test]
+    fn sample_album_gain() {
+        let mut album = ReplayGainAlbum::new();
+
+        let mut quiet = ReplayGainScanner::new(&format());
+        quiet.process_block(&sine_block(0.25, 5.0));
+        let quiet_rg = album.add_track(quiet);
+
+        let mut loud = ReplayGainScanner::new(&format());
+        loud.process_block(&sine_block(0.5, 5.0));
+        let loud_rg = album.add_track(loud);
+
+        let album_rg = album.finish();
+
+        assert!(album_rg.gain < quiet_rg.gain);
+        assert!(album_rg.gain > loud_rg.gain);
+        assert_eq!(album_rg.peak, loud_rg.peak);
+    }
+
+    #[test]
+    fn sample_silence() {
+        let mut scanner = ReplayGainScanner::new(&format());
+
+        scanner.process_block(&vec![vec![0]; 44100]);
+        let rg = scanner.finish();
+
+        assert_eq!(rg.gain, 52.0);
+        assert_eq!(rg.peak, 0.0);
+    }
+
+    #[test]
+    fn sample_tag_strings() {
+        let rg = ReplayGain {
+            gain: -7.891,
+            peak: 0.98855,
+        };
+
+        assert_eq!(rg.gain_string(), "-7.89 dB");
+        assert_eq!(rg.peak_string(), "0.988550");
+        assert_eq!(ReplayGain { gain: 3.0, peak: 1.0 }.gain_string(), "+3.00 dB");
+    }
+}
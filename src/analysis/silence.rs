@@ -0,0 +1,332 @@
+use std::collections::VecDeque;
+
+use crate::wav::{PCMWaveFormatChunk, PCMWaveInfo};
+
+use super::full_scale;
+
+/// Represents the options of leading and trailing silence trimming
+/// 
+/// An inter-channel sample is silent if all of its channels are at or below
+/// `threshold_dbfs`. Use `f64::NEG_INFINITY` to only trim digital silence.
+/// Silence at the start or end is only trimmed if it lasts at least
+/// `min_duration_ms` milliseconds. If `tag_offsets` is set, the `FlacWriter`
+/// also stores the trimmed offsets as Vorbis comments.
+#[derive(Debug, Clone, Copy)]
+pub struct SilenceTrimOptions {
+    pub threshold_dbfs: f64,
+    pub min_duration_ms: u64,
+    pub tag_offsets: bool,
+}
+
+/// Represents the result of leading and trailing silence trimming
+/// 
+/// All values are in inter-channel samples. The original timing can be
+/// reconstructed by inserting `leading` samples of silence before and
+/// `trailing` samples of silence after the trimmed audio.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SilenceTrim {
+    pub leading: u64,
+    pub trailing: u64,
+    pub num_samples: u64,
+}
+
+/// Represents an iterator that trims leading and trailing silence
+/// 
+/// This wraps an iterator of inter-channel samples, such as a
+/// `PCMWaveDataChunk`, and skips silent samples at the start and the end.
+/// Silent samples after the start are held back until a non-silent
+/// sample arrives. They are held as runs of identical samples, so a
+/// stretch of digital silence takes the same memory however long it lasts.
+pub struct SilenceTrimmer <I: Iterator <Item = Vec <i64>>> {
+    samples: I,
+    threshold: u64,
+    min_len: u64,
+    is_started: bool,
+    is_finished: bool,
+    leading_run: u64,
+    pending: VecDeque <SampleRun>,
+    pending_len: u64,
+    ready: VecDeque <SampleRun>,
+    trim: SilenceTrim,
+}
+
+/// Represents `len` repetitions of the same inter-channel sample
+type SampleRun = (Vec <i64>, u64);
+
+impl SilenceTrimOptions {
+    /// Create new silence trimming options
+    pub fn new(threshold_dbfs: f64, min_duration_ms: u64) -> Self {
+        Self {
+            threshold_dbfs,
+            min_duration_ms,
+            tag_offsets: false,
+        }
+    }
+}
+
+impl SilenceTrim {
+    /// Get the index of the first sample kept in the original audio
+    pub fn start(&self) -> u64 {
+        self.leading
+    }
+
+    /// Get the index one past the last sample kept in the original audio
+    pub fn end(&self) -> u64 {
+        self.num_samples - self.trailing
+    }
+}
+
+impl <I: Iterator <Item = Vec <i64>>> SilenceTrimmer <I> {
+    /// Create a new trimmer over some inter-channel samples of the given format
+    pub fn new(samples: I, format: &PCMWaveFormatChunk, options: &SilenceTrimOptions) -> Self {
+        let threshold = (10f64.powf(options.threshold_dbfs / 20.0) * full_scale(format.bps)).floor();
+
+        Self {
+            samples,
+            threshold: threshold as u64,
+            min_len: (options.min_duration_ms * format.samp_rate as u64 / 1000).max(1),
+            is_started: false,
+            is_finished: false,
+            leading_run: 0,
+            pending: VecDeque::new(),
+            pending_len: 0,
+            ready: VecDeque::new(),
+            trim: SilenceTrim {
+                leading: 0,
+                trailing: 0,
+                num_samples: 0,
+            },
+        }
+    }
+
+    /// Get the trimmed offsets
+    /// 
+    /// The offsets are only final once the trimmer has been exhausted.
+    pub fn trim(&self) -> SilenceTrim {
+        self.trim
+    }
+
+    /// Check whether all channels of an inter-channel sample are silent
+    fn is_silent(&self, inter_sample: &[i64]) -> bool {
+        inter_sample.iter().all(|s| s.unsigned_abs() <= self.threshold)
+    }
+
+    /// Handle a sample while still within the leading silence
+    fn push_leading(&mut self, inter_sample: Vec <i64>) {
+        if self.is_silent(&inter_sample) {
+            self.leading_run += 1;
+
+            // Past the minimum length, the held samples will be trimmed anyway
+            if self.leading_run < self.min_len {
+                self.hold(inter_sample);
+            }
+            else {
+                self.drop_pending();
+            }
+            return;
+        }
+
+        self.is_started = true;
+        self.end_leading();
+        self.ready.push_back((inter_sample, 1));
+    }
+
+    /// Hold back a silent sample, extending the last run if it is the same sample
+    fn hold(&mut self, inter_sample: Vec <i64>) {
+        self.pending_len += 1;
+
+        match self.pending.back_mut() {
+            Some((run_sample, len)) if *run_sample == inter_sample => *len += 1,
+            _ => self.pending.push_back((inter_sample, 1)),
+        }
+    }
+
+    /// Release the samples held back so far, which are then kept
+    fn release_pending(&mut self) {
+        self.ready.extend(self.pending.drain(..));
+        self.pending_len = 0;
+    }
+
+    /// Drop the samples held back so far, which are then trimmed
+    fn drop_pending(&mut self) {
+        self.pending.clear();
+        self.pending_len = 0;
+    }
+
+    /// Decide whether the leading silence seen so far is trimmed or kept
+    fn end_leading(&mut self) {
+        if self.leading_run >= self.min_len {
+            self.trim.leading = self.leading_run;
+        }
+        else {
+            self.release_pending();
+        }
+    }
+}
+
+impl <I: Iterator <Item = Vec <i64>>> Iterator for SilenceTrimmer <I> {
+    type Item = Vec <i64>;
+
+    fn next(&mut self) -> Option <Self::Item> {
+        loop {
+            if let Some((inter_sample, len)) = self.ready.front_mut() {
+                if *len > 1 {
+                    *len -= 1;
+                    return Some(inter_sample.clone());
+                }
+
+                return self.ready.pop_front().map(|(inter_sample, _)| inter_sample);
+            }
+
+            if self.is_finished {
+                return None;
+            }
+
+            match self.samples.next() {
+                Some(inter_sample) => {
+                    self.trim.num_samples += 1;
+
+                    if !self.is_started {
+                        self.push_leading(inter_sample);
+                    }
+                    else if self.is_silent(&inter_sample) {
+                        self.hold(inter_sample);
+                    }
+                    else {
+                        self.release_pending();
+                        self.ready.push_back((inter_sample, 1));
+                    }
+                },
+                None => {
+                    self.is_finished = true;
+
+                    if !self.is_started {
+                        self.end_leading();
+                    }
+                    else if self.pending_len >= self.min_len {
+                        self.trim.trailing = self.pending_len;
+                        self.drop_pending();
+                    }
+                    else {
+                        self.release_pending();
+                    }
+                },
+            }
+        }
+    }
+}
+
+/// Detect the leading and trailing silence of a PCM WAV file without trimming it
+pub fn detect_silence(wav: PCMWaveInfo, options: &SilenceTrimOptions) -> SilenceTrim {
+    let fmt_header = wav.fmt_header;
+    let mut trimmer = SilenceTrimmer::new(wav.data_chunks.into_iter().flatten(), &fmt_header, options);
+
+    trimmer.by_ref().for_each(drop);
+    trimmer.trim()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format() -> PCMWaveFormatChunk {
+        PCMWaveFormatChunk {
+            num_channels: 2,
+            samp_rate: 1000,
+            bps: 16,
+            channel_mask: None,
+        }
+    }
+
+    fn run(samples: Vec <Vec <i64>>, options: &SilenceTrimOptions) -> (Vec <Vec <i64>>, SilenceTrim) {
+        let mut trimmer = SilenceTrimmer::new(samples.into_iter(), &format(), options);
+        let kept = trimmer.by_ref().collect();
+
+        (kept, trimmer.trim())
+    }
+
+    #[test]
+    fn sample_digital_silence() {
+        let samples = vec![
+            vec![0, 0], vec![0, 0], vec![0, 0],
+            vec![1, 0], vec![0, 0], vec![0, -1],
+            vec![0, 0], vec![0, 0],
+        ];
+        let (kept, trim) = run(samples, &SilenceTrimOptions::new(f64::NEG_INFINITY, 1));
+
+        assert_eq!(kept, vec![vec![1, 0], vec![0, 0], vec![0, -1]]);
+        assert_eq!(trim, SilenceTrim {
+            leading: 3,
+            trailing: 2,
+            num_samples: 8,
+        });
+        assert_eq!(trim.start(), 3);
+        assert_eq!(trim.end(), 6);
+    }
+
+    #[test]
+    fn sample_threshold() {
+        // -60 dBFS of 16-bit audio is a magnitude of about 32
+        let samples = vec![
+            vec![30, -32], vec![5, 5], vec![100, 0],
+            vec![-20, 0], vec![0, 33], vec![0, 0],
+        ];
+        let (kept, trim) = run(samples, &SilenceTrimOptions::new(-60.0, 1));
+
+        assert_eq!(kept, vec![vec![100, 0], vec![-20, 0], vec![0, 33]]);
+        assert_eq!((trim.leading, trim.trailing), (2, 1));
+    }
+
+    #[test]
+    fn sample_min_duration() {
+        // 3 ms at 1 kHz is 3 samples, so only the trailing silence is trimmed
+        let mut samples = vec![vec![0, 0]; 2];
+        samples.push(vec![7, 7]);
+        samples.extend(vec![vec![0, 0]; 3]);
+
+        let (kept, trim) = run(samples, &SilenceTrimOptions::new(f64::NEG_INFINITY, 3));
+
+        assert_eq!(kept, vec![vec![0, 0], vec![0, 0], vec![7, 7]]);
+        assert_eq!((trim.leading, trim.trailing), (0, 3));
+    }
+
+    #[test]
+    fn sample_long_leading_silence() {
+        let mut samples = vec![vec![0, 0]; 1000];
+        samples.push(vec![7, 7]);
+
+        let (kept, trim) = run(samples, &SilenceTrimOptions::new(f64::NEG_INFINITY, 10));
+
+        assert_eq!(kept, vec![vec![7, 7]]);
+        assert_eq!((trim.leading, trim.trailing), (1000, 0));
+    }
+
+    #[test]
+    fn sample_long_inner_silence() {
+        let mut samples = vec![vec![5000, 5000]];
+        samples.extend(vec![vec![0, 0]; 1000]);
+        samples.extend([vec![1, 0], vec![1, 0], vec![0, -1], vec![5000, 5000]]);
+
+        let mut trimmer = SilenceTrimmer::new(samples.clone().into_iter(), &format(), &SilenceTrimOptions::new(-60.0, 10));
+        let first = trimmer.next();
+
+        // The inner silence is held as runs of identical samples until it ends
+        assert_eq!(first, Some(vec![5000, 5000]));
+        assert_eq!(trimmer.next(), Some(vec![0, 0]));
+        assert_eq!(trimmer.pending.len(), 0);
+        assert_eq!(trimmer.ready.len(), 4);
+
+        let (kept, trim) = run(samples, &SilenceTrimOptions::new(-60.0, 10));
+
+        assert_eq!(kept.len(), 1005);
+        assert_eq!((trim.leading, trim.trailing), (0, 0));
+    }
+
+    #[test]
+    fn sample_all_silent() {
+        let (kept, trim) = run(vec![vec![0, 0]; 5], &SilenceTrimOptions::new(f64::NEG_INFINITY, 1));
+
+        assert!(kept.is_empty());
+        assert_eq!((trim.leading, trim.trailing, trim.num_samples), (5, 0, 5));
+    }
+}
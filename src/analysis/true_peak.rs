@@ -0,0 +1,133 @@
+/// Number of interpolated values computed for every input sample
+const OVERSAMPLING_FACTOR: usize = 4;
+
+/// Number of taps of each phase of the interpolation filter
+const PHASE_TAPS: usize = 12;
+
+/// Polyphase interpolation filter from ITU-R BS.1770-4, Annex 2
+/// 
+/// Each row is one phase of the 48-tap lowpass FIR filter used to
+/// oversample the signal by a factor of four.
+const INTERPOLATION_FILTER: [[f64; PHASE_TAPS]; OVERSAMPLING_FACTOR] = [
+    [
+        0.0017089843750, 0.0109863281250, -0.0196533203125, 0.0332031250000,
+        -0.0594482421875, 0.1373291015625, 0.9721679687500, -0.1022949218750,
+        0.0476074218750, -0.0266113281250, 0.0148925781250, -0.0083007812500,
+    ],
+    [
+        -0.0291748046875, 0.0292968750000, -0.0517578125000, 0.0891113281250,
+        -0.1665039062500, 0.4650878906250, 0.7797851562500, -0.2003173828125,
+        0.1015625000000, -0.0582275390625, 0.0330810546875, -0.0189208984375,
+    ],
+    [
+        -0.0189208984375, 0.0330810546875, -0.0582275390625, 0.1015625000000,
+        -0.2003173828125, 0.7797851562500, 0.4650878906250, -0.1665039062500,
+        0.0891113281250, -0.0517578125000, 0.0292968750000, -0.0291748046875,
+    ],
+    [
+        -0.0083007812500, 0.0148925781250, -0.0266113281250, 0.0476074218750,
+        -0.1022949218750, 0.9721679687500, 0.1373291015625, -0.0594482421875,
+        0.0332031250000, -0.0196533203125, 0.0109863281250, 0.0017089843750,
+    ],
+];
+
+/// Represents a true-peak meter for a single channel
+/// 
+/// The true peak is an estimate of the maximum absolute amplitude of the
+/// continuous signal reconstructed from the samples. It is computed by
+/// oversampling the signal four times and taking the maximum absolute
+/// value of both the original and the interpolated samples.
+pub struct TruePeakMeter {
+    history: [f64; PHASE_TAPS],
+    history_pos: usize,
+    peak: f64,
+}
+
+impl TruePeakMeter {
+    /// Create a new true-peak meter
+    pub fn new() -> Self {
+        Self {
+            history: [0.0; PHASE_TAPS],
+            history_pos: 0,
+            peak: 0.0,
+        }
+    }
+
+    /// Feed a sample normalized to full scale into the meter
+    pub fn process(&mut self, sample: f64) {
+        self.history[self.history_pos] = sample;
+        self.history_pos = (self.history_pos + 1) % PHASE_TAPS;
+
+        let mut peak = self.peak.max(sample.abs());
+
+        for phase in INTERPOLATION_FILTER.iter() {
+            let mut acc = 0.0;
+
+            // The newest sample is multiplied with the first tap
+            for (tap, coef) in phase.iter().enumerate() {
+                let idx = (self.history_pos + PHASE_TAPS - 1 - tap) % PHASE_TAPS;
+                acc += coef * self.history[idx];
+            }
+
+            peak = peak.max(acc.abs());
+        }
+
+        self.peak = peak;
+    }
+
+    /// Get the true peak of all samples seen so far, relative to full scale
+    pub fn peak(&self) -> f64 {
+        self.peak
+    }
+}
+
+impl Default for TruePeakMeter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sample_silence() {
+        let mut meter = TruePeakMeter::new();
+
+        for _ in 0..100 {
+            meter.process(0.0);
+        }
+
+        assert_eq!(meter.peak(), 0.0);
+    }
+
+    #[test]
+    fn sample_at_least_sample_peak() {
+        let mut meter = TruePeakMeter::new();
+
+        for sample in [0.1, -0.5, 0.25, 0.0] {
+            meter.process(sample);
+        }
+
+        assert!(meter.peak() >= 0.5);
+    }
+
+    #[test]
+    fn sample_intersample_peak() {
+        // A quarter of the sample rate sine wave with a 45 degree phase offset
+        // never hits its true peak on a sample
+        let mut meter = TruePeakMeter::new();
+        let amplitude = 0.5;
+
+        for i in 0..256 {
+            let phase = std::f64::consts::FRAC_PI_2 * i as f64 + std::f64::consts::FRAC_PI_4;
+            meter.process(amplitude * phase.sin());
+        }
+
+        let sample_peak = amplitude * std::f64::consts::FRAC_1_SQRT_2;
+
+        assert!(meter.peak() > sample_peak + 0.1);
+        assert!((meter.peak() - amplitude).abs() < 0.02);
+    }
+}
@@ -0,0 +1,371 @@
+use core::fmt;
+use std::error;
+use std::fs;
+use std::io;
+
+use crate::wav::WaveCuePoint;
+
+/// Number of CD frames in one second of a cue sheet timestamp
+const FRAMES_PER_SEC: u64 = 75;
+
+/// Largest track number of a cue sheet
+pub const MAX_TRACK_NUMBER: u8 = 99;
+
+/// Represents a cue sheet
+/// 
+/// A cue sheet describes the tracks of an audio file, usually a full
+/// album or a live recording, along with their titles and performers.
+/// The positions of the tracks are in inter-channel samples.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CueSheet {
+    pub title: Option <String>,
+    pub performer: Option <String>,
+    pub catalog: Option <String>,
+    pub tracks: Vec <CueTrack>,
+}
+
+/// Represents a track of a cue sheet
+/// 
+/// `start` is the position of the `INDEX 01` of the track, where the track
+/// itself starts. `pregap_start` is the position of the `INDEX 00` of the
+/// track, if any, where the gap before the track starts.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CueTrack {
+    pub number: u8,
+    pub title: Option <String>,
+    pub performer: Option <String>,
+    pub isrc: Option <String>,
+    pub pregap_start: Option <u64>,
+    pub start: u64,
+}
+
+/// Represents an error in reading a cue sheet
+#[derive(Debug)]
+pub enum CueSheetError {
+    ReadError,
+    SyntaxError(usize),
+    TrackOrderError,
+    MissingIndexError,
+    FileCountError,
+    TrackCountError,
+}
+
+impl CueSheet {
+    /// Read a cue sheet file
+    /// 
+    /// The timestamps of the cue sheet are converted into inter-channel samples
+    /// using the sample rate `samp_rate` of the audio it describes.
+    /// 
+    /// # Errors
+    /// Returns a `CueSheetError` if the file cannot be read or parsed.
+    pub fn read(file_path: &str, samp_rate: u32) -> Result <CueSheet, CueSheetError> {
+        let text = fs::read_to_string(file_path)?;
+
+        Self::parse(&text, samp_rate)
+    }
+
+    /// Parse the contents of a cue sheet
+    /// 
+    /// Only the commands needed to locate and describe the tracks are
+    /// interpreted. Other commands such as `FLAGS` and `REM` are ignored. The
+    /// positions of the tracks are relative to a single audio file, so the
+    /// file itself is not kept but only one `FILE` command is allowed.
+    /// 
+    /// # Errors
+    /// Returns a `CueSheetError::SyntaxError` with the 1-based line number of a
+    /// malformed line, a `CueSheetError::MissingIndexError` if a track has no
+    /// `INDEX 01`, and a `CueSheetError::TrackOrderError` if the tracks do not
+    /// start in increasing order. Returns a `CueSheetError::FileCountError` if
+    /// the cue sheet describes more than one audio file.
+    pub fn parse(text: &str, samp_rate: u32) -> Result <CueSheet, CueSheetError> {
+        let mut sheet = CueSheet {
+            title: None,
+            performer: None,
+            catalog: None,
+            tracks: Vec::new(),
+        };
+        let mut has_start = Vec::new();
+        let mut has_file = false;
+
+        for (line_idx, line) in text.lines().enumerate() {
+            let syntax_error = CueSheetError::SyntaxError(line_idx + 1);
+            let (command, args) = match line.trim().split_once(char::is_whitespace) {
+                Some((command, args)) => (command, args.trim()),
+                None => (line.trim(), ""),
+            };
+
+            match command.to_ascii_uppercase().as_str() {
+                "TITLE" | "PERFORMER" | "ISRC" | "CATALOG" => {
+                    let value = Some(Self::unquote(args));
+
+                    match (command.to_ascii_uppercase().as_str(), sheet.tracks.last_mut()) {
+                        ("TITLE", Some(track)) => track.title = value,
+                        ("PERFORMER", Some(track)) => track.performer = value,
+                        ("ISRC", Some(track)) => track.isrc = value,
+                        ("TITLE", None) => sheet.title = value,
+                        ("PERFORMER", None) => sheet.performer = value,
+                        ("CATALOG", _) => sheet.catalog = value,
+                        _ => return Err(syntax_error),
+                    }
+                },
+                "TRACK" => {
+                    let number = args
+                        .split_whitespace()
+                        .next()
+                        .and_then(|num| num.parse::<u8>().ok())
+                        .ok_or(syntax_error)?;
+
+                    sheet.tracks.push(CueTrack {
+                        number,
+                        title: None,
+                        performer: None,
+                        isrc: None,
+                        pregap_start: None,
+                        start: 0,
+                    });
+                    has_start.push(false);
+                },
+                "FILE" => {
+                    if has_file {
+                        return Err(CueSheetError::FileCountError);
+                    }
+
+                    has_file = true;
+                },
+                "INDEX" => {
+                    let mut parts = args.split_whitespace();
+                    let index = parts.next().and_then(|num| num.parse::<u8>().ok());
+                    let position = parts.next().and_then(|time| Self::parse_time(time, samp_rate));
+                    let track = sheet.tracks.last_mut();
+
+                    match (index, position, track) {
+                        (Some(0), Some(position), Some(track)) => track.pregap_start = Some(position),
+                        (Some(1), Some(position), Some(track)) => {
+                            track.start = position;
+                            *has_start.last_mut().unwrap() = true;
+                        },
+                        (Some(_), Some(_), Some(_)) => {},
+                        _ => return Err(syntax_error),
+                    }
+                },
+                _ => {},
+            }
+        }
+
+        if has_start.iter().any(|&is_set| !is_set) {
+            return Err(CueSheetError::MissingIndexError);
+        }
+
+        if sheet.tracks.windows(2).any(|pair| pair[0].start >= pair[1].start) {
+            return Err(CueSheetError::TrackOrderError);
+        }
+
+        Ok(sheet)
+    }
+
+    /// Create a cue sheet from the cue points embedded in a WAV file
+    /// 
+    /// Each cue point starts a track, numbered from 1 in the order of
+    /// their positions, and the label of a cue point becomes the title
+    /// of its track. Cue points at the same position are merged.
+    /// 
+    /// # Errors
+    /// Returns a `CueSheetError::TrackCountError` if there are more than
+    /// `MAX_TRACK_NUMBER` distinct cue points.
+    pub fn from_wave_cue_points(cue_points: &[WaveCuePoint]) -> Result <CueSheet, CueSheetError> {
+        let mut sorted: Vec <&WaveCuePoint> = cue_points.iter().collect();
+        sorted.sort_by_key(|cue_point| cue_point.position);
+        sorted.dedup_by_key(|cue_point| cue_point.position);
+
+        if sorted.len() > MAX_TRACK_NUMBER as usize {
+            return Err(CueSheetError::TrackCountError);
+        }
+
+        let tracks = sorted
+            .into_iter()
+            .enumerate()
+            .map(|(i, cue_point)| CueTrack {
+                number: (i + 1) as u8,
+                title: cue_point.label.clone(),
+                performer: None,
+                isrc: None,
+                pregap_start: None,
+                start: cue_point.position,
+            })
+            .collect();
+
+        Ok(CueSheet {
+            title: None,
+            performer: None,
+            catalog: None,
+            tracks,
+        })
+    }
+
+    /// Get the range of inter-channel samples covered by each track
+    /// 
+    /// A track ends where the next one starts, and the last track ends at
+    /// `num_samples`. The first track always starts at sample 0 so that
+    /// audio before its `INDEX 01` is not lost.
+    pub fn track_ranges(&self, num_samples: u64) -> Vec <(u64, u64)> {
+        (0..self.tracks.len())
+            .map(|i| {
+                let start = if i == 0 { 0 } else { self.tracks[i].start };
+                let end = self.tracks.get(i + 1).map_or(num_samples, |next| next.start);
+
+                (start.min(num_samples), end.min(num_samples))
+            })
+            .collect()
+    }
+
+    /// Convert a `mm:ss:ff` timestamp into inter-channel samples
+    fn parse_time(time: &str, samp_rate: u32) -> Option <u64> {
+        let mut parts = time.split(':').map(|part| part.parse::<u64>().ok());
+        let (min, sec, frame) = (parts.next()??, parts.next()??, parts.next()??);
+
+        if parts.next().is_some() || sec >= 60 || frame >= FRAMES_PER_SEC {
+            return None;
+        }
+
+        let frames = (min * 60 + sec) * FRAMES_PER_SEC + frame;
+
+        Some(frames * samp_rate as u64 / FRAMES_PER_SEC)
+    }
+
+    /// Remove the surrounding double quotes of a value, if any
+    fn unquote(value: &str) -> String {
+        let value = value.trim();
+
+        match value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+            Some(unquoted) => String::from(unquoted),
+            None => String::from(value),
+        }
+    }
+}
+
+impl error::Error for CueSheetError {}
+
+impl fmt::Display for CueSheetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Error: {:?}", self)
+    }
+}
+
+impl From <io::Error> for CueSheetError {
+    fn from(_: io::Error) -> Self {
+        CueSheetError::ReadError
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_SHEET: &str = "REM GENRE Rock
+PERFORMER \"The Band\"
+TITLE \"Live at Home\"
+CATALOG 0123456789012
+FILE \"live.wav\" WAVE
+  TRACK 01 AUDIO
+    TITLE \"Intro\"
+    INDEX 01 00:00:00
+  TRACK 02 AUDIO
+    TITLE \"Second Song\"
+    PERFORMER \"The Band feat. Someone\"
+    ISRC USABC1234567
+    INDEX 00 03:59:74
+    INDEX 01 04:00:00
+  TRACK 03 AUDIO
+    TITLE \"Encore\"
+    INDEX 01 10:00:30
+";
+
+    #[test]
+    fn sample_parse() {
+        let sheet = CueSheet::parse(SAMPLE_SHEET, 44100).unwrap();
+
+        assert_eq!(sheet.title.as_deref(), Some("Live at Home"));
+        assert_eq!(sheet.performer.as_deref(), Some("The Band"));
+        assert_eq!(sheet.catalog.as_deref(), Some("0123456789012"));
+        assert_eq!(sheet.tracks.len(), 3);
+
+        assert_eq!(sheet.tracks[0].number, 1);
+        assert_eq!(sheet.tracks[0].title.as_deref(), Some("Intro"));
+        assert_eq!(sheet.tracks[0].performer, None);
+        assert_eq!(sheet.tracks[0].start, 0);
+
+        assert_eq!(sheet.tracks[1].performer.as_deref(), Some("The Band feat. Someone"));
+        assert_eq!(sheet.tracks[1].isrc.as_deref(), Some("USABC1234567"));
+        assert_eq!(sheet.tracks[1].pregap_start, Some(240 * 44100 - 588));
+        assert_eq!(sheet.tracks[1].start, 240 * 44100);
+
+        assert_eq!(sheet.tracks[2].start, 600 * 44100 + 30 * 588);
+    }
+
+    #[test]
+    fn sample_track_ranges() {
+        let sheet = CueSheet::parse(SAMPLE_SHEET, 75).unwrap();
+
+        assert_eq!(sheet.track_ranges(50_000), vec![
+            (0, 240 * 75),
+            (240 * 75, 600 * 75 + 30),
+            (600 * 75 + 30, 50_000),
+        ]);
+    }
+
+    #[test]
+    fn sample_bad_index() {
+        let result = CueSheet::parse("TRACK 01 AUDIO\n  INDEX 01 00:61:00\n", 44100);
+
+        assert!(matches!(result, Err(CueSheetError::SyntaxError(2))));
+    }
+
+    #[test]
+    fn sample_missing_index() {
+        let result = CueSheet::parse("TRACK 01 AUDIO\n  TITLE \"x\"\n", 44100);
+
+        assert!(matches!(result, Err(CueSheetError::MissingIndexError)));
+    }
+
+    #[test]
+    fn sample_unordered_tracks() {
+        let text = "TRACK 01 AUDIO\n INDEX 01 01:00:00\nTRACK 02 AUDIO\n INDEX 01 00:30:00\n";
+        let result = CueSheet::parse(text, 44100);
+
+        assert!(matches!(result, Err(CueSheetError::TrackOrderError)));
+    }
+
+    #[test]
+    fn sample_from_wave_cue_points() {
+        let cue_points = vec![
+            WaveCuePoint { id: 2, position: 1000, label: Some(String::from("B")) },
+            WaveCuePoint { id: 1, position: 0, label: Some(String::from("A")) },
+            WaveCuePoint { id: 3, position: 1000, label: None },
+        ];
+        let sheet = CueSheet::from_wave_cue_points(&cue_points).unwrap();
+
+        assert_eq!(sheet.tracks.len(), 2);
+        assert_eq!(sheet.tracks[0].title.as_deref(), Some("A"));
+        assert_eq!(sheet.tracks[1].number, 2);
+        assert_eq!(sheet.tracks[1].start, 1000);
+        assert_eq!(sheet.tracks[1].title.as_deref(), Some("B"));
+    }
+
+    #[test]
+    fn sample_multiple_files() {
+        let text = "FILE \"a.wav\" WAVE\nTRACK 01 AUDIO\n INDEX 01 00:00:00\nFILE \"b.wav\" WAVE\nTRACK 02 AUDIO\n INDEX 01 00:00:00\n";
+        let result = CueSheet::parse(text, 44100);
+
+        assert!(matches!(result, Err(CueSheetError::FileCountError)));
+    }
+
+    #[test]
+    fn sample_too_many_cue_points() {
+        let cue_points: Vec <WaveCuePoint> = (0..=MAX_TRACK_NUMBER as u64)
+            .map(|i| WaveCuePoint { id: i as u32, position: i * 1000, label: None })
+            .collect();
+
+        assert!(matches!(CueSheet::from_wave_cue_points(&cue_points), Err(CueSheetError::TrackCountError)));
+        assert_eq!(CueSheet::from_wave_cue_points(&cue_points[1..]).unwrap().tracks.len(), MAX_TRACK_NUMBER as usize);
+    }
+}
@@ -0,0 +1,135 @@
+/// Represents a writer of big-endian bit fields into a vector of bytes
+/// 
+/// Bits fill each byte from its most significant bit. The last byte may be
+/// filled only partly, in which case its unused low bits are zero.
+#[derive(Debug, Default)]
+pub struct BitstreamWriter {
+    bytes: Vec <u8>,
+    num_unused_bits: u8,
+}
+
+impl BitstreamWriter {
+    /// Create a new, empty bit writer
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the number of bits written so far
+    pub fn len_bits(&self) -> u64 {
+        self.bytes.len() as u64 * 8 - self.num_unused_bits as u64
+    }
+
+    /// Get the number of unused low bits in the last byte
+    pub fn num_unused_bits(&self) -> u8 {
+        self.num_unused_bits
+    }
+
+    /// Write the low `num_bits` bits of `value`, up to 64
+    pub fn write_bits(&mut self, value: u64, num_bits: u32) {
+        let mut num_left = num_bits;
+
+        while num_left > 0 {
+            if self.num_unused_bits == 0 {
+                self.bytes.push(0);
+                self.num_unused_bits = 8;
+            }
+
+            let num_taken = num_left.min(self.num_unused_bits as u32);
+            let bits = (value >> (num_left - num_taken)) & ((1 << num_taken) - 1);
+
+            *self.bytes.last_mut().unwrap() |= (bits << (self.num_unused_bits as u32 - num_taken)) as u8;
+            num_left -= num_taken;
+            self.num_unused_bits -= num_taken as u8;
+        }
+    }
+
+    /// Write a two's complement signed number in `num_bits` bits, up to 64
+    pub fn write_signed(&mut self, value: i64, num_bits: u32) {
+        self.write_bits(value as u64, num_bits);
+    }
+
+    /// Write a unary number, that is `value` `0` bits followed by a `1` bit
+    pub fn write_unary(&mut self, value: u64) {
+        let mut num_zeros = value;
+
+        while num_zeros > 0 {
+            let num_taken = num_zeros.min(32) as u32;
+
+            self.write_bits(0, num_taken);
+            num_zeros -= num_taken as u64;
+        }
+
+        self.write_bits(1, 1);
+    }
+
+    /// Write the bits of another bit stream, given its bytes and the number
+    /// of unused low bits in its last byte
+    pub fn write_stream(&mut self, bytes: &[u8], num_unused_bits: u8) {
+        if self.num_unused_bits == 0 {
+            self.bytes.extend_from_slice(bytes);
+            self.num_unused_bits = if bytes.is_empty() { 0 } else { num_unused_bits };
+            return;
+        }
+
+        if let Some((last, rest)) = bytes.split_last() {
+            for byte in rest {
+                self.write_bits(*byte as u64, 8);
+            }
+
+            self.write_bits((*last >> num_unused_bits) as u64, 8 - num_unused_bits as u32);
+        }
+    }
+
+    /// Write whole bytes
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        self.write_stream(bytes, 0);
+    }
+
+    /// Pad with `0` bits up to the start of the next byte, unless already there
+    pub fn align(&mut self) {
+        self.num_unused_bits = 0;
+    }
+
+    /// Get the bytes written so far
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Finish writing, returning the bytes and the number of unused low bits
+    /// in the last byte, respectively
+    pub fn finish(self) -> (Vec <u8>, u8) {
+        (self.bytes, self.num_unused_bits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flac::decoder::bitreader::BitReader;
+
+    #[test]
+    fn sample_write_bits() {
+        let mut writer = BitstreamWriter::new();
+
+        writer.write_bits(0b101, 3);
+        writer.write_signed(-51, 7);
+        writer.write_unary(1);
+        writer.write_stream(&[0xa0], 5);
+        assert_eq!(writer.len_bits(), 15);
+
+        writer.align();
+        writer.write_bytes(&[0xff]);
+
+        let (bytes, num_unused_bits) = writer.finish();
+        let mut reader = BitReader::new(&bytes);
+
+        assert_eq!(num_unused_bits, 0);
+        assert_eq!(reader.read_bits(3).unwrap(), 0b101);
+        assert_eq!(reader.read_signed(7).unwrap(), -51);
+        assert_eq!(reader.read_unary().unwrap(), 1);
+        assert_eq!(reader.read_bits(3).unwrap(), 0b101);
+
+        reader.align();
+        assert_eq!(reader.read_bits(8).unwrap(), 0xff);
+    }
+}
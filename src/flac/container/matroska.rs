@@ -0,0 +1,599 @@
+use core::fmt;
+use std::error;
+use std::io::{self, Seek, SeekFrom, Write};
+
+use crate::flac::decoder::FlacDecoderError;
+use crate::flac::metadata::streaminfo::StreamInfo;
+use crate::flac::metadata::vorbis_comment::VENDOR_STRING;
+
+use super::{build_header_blocks, ContainerSink, NativeMuxer, NativeParser};
+
+/// Document type of the EBML header
+const DOC_TYPE: &str = "matroska";
+
+/// Codec ID of FLAC audio tracks
+const CODEC_ID: &str = "A_FLAC";
+
+/// Number of nanoseconds per timestamp tick, so that timestamps are in milliseconds
+const TIMESTAMP_SCALE: u64 = 1_000_000;
+
+/// Duration in timestamp ticks from the start of a cluster after which a new cluster is started
+/// 
+/// The timestamps of the blocks of a cluster are 16-bit offsets from that of
+/// the cluster, so this must stay below 32768.
+const CLUSTER_DURATION: u64 = 5000;
+
+/// Number of the audio track, the only track of the file
+const TRACK_NUMBER: u8 = 1;
+
+/// Flag of a SimpleBlock that can be decoded on its own
+const KEYFRAME_FLAG: u8 = 0x80;
+
+/// Element size of an element whose size is unknown, coded on 8 bytes
+const UNKNOWN_SIZE: [u8; 8] = [0x01, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff];
+
+/// Length of the Duration element, that is its 2-byte ID, its 1-byte size and a 64-bit float
+const DURATION_LEN: usize = 2 + 1 + 8;
+
+/// Length of the header of a Void element with an 8-byte size
+const VOID_HEADER_LEN: usize = 1 + 8;
+
+/// Represents a muxer of a native FLAC stream into a Matroska audio file
+/// 
+/// The native stream is written as to any other sink, for example by a
+/// `FlacEncoder`, and is cut into metadata blocks and audio frames by a
+/// `NativeParser`. The file holds a single audio track of codec `A_FLAC`
+/// whose CodecPrivate element is the `fLaC` marker followed by the metadata
+/// blocks given by `build_header_blocks`. Every audio frame is a SimpleBlock,
+/// and a new cluster is started every `CLUSTER_DURATION` milliseconds. The
+/// Cues element, with one cue point per cluster, closes the Segment.
+/// 
+/// The Segment is written with an unknown size, so that the file can be
+/// streamed as it is written, and void space is reserved for the SeekHead and
+/// Duration elements. `finish_and_patch` fills them in along with the size of
+/// the Segment and the final STREAMINFO block.
+pub struct MkaFlacWriter <W: Write> {
+    out: W,
+    native: NativeParser,
+    headers: Vec <Vec <u8>>,
+    sample_rate: u32,
+    cluster: Vec <u8>,
+    cluster_timestamp: u64,
+    cue_points: Vec <(u64, u64)>,
+    num_samples: u64,
+    segment_offset: u64,
+    info_position: u64,
+    tracks_position: u64,
+    cues_position: Option <u64>,
+    duration_offset: u64,
+    streaminfo_offset: u64,
+    stream_len: u64,
+}
+
+/// Represents the ID of a Matroska element
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ElementId {
+    Ebml = 0x1a45_dfa3,
+    EbmlVersion = 0x4286,
+    EbmlReadVersion = 0x42f7,
+    EbmlMaxIdLength = 0x42f2,
+    EbmlMaxSizeLength = 0x42f3,
+    DocType = 0x4282,
+    DocTypeVersion = 0x4287,
+    DocTypeReadVersion = 0x4285,
+    Segment = 0x1853_8067,
+    SeekHead = 0x114d_9b74,
+    Seek = 0x4dbb,
+    SeekId = 0x53ab,
+    SeekPosition = 0x53ac,
+    Info = 0x1549_a966,
+    TimestampScale = 0x2a_d7b1,
+    MuxingApp = 0x4d80,
+    WritingApp = 0x5741,
+    Duration = 0x4489,
+    Tracks = 0x1654_ae6b,
+    TrackEntry = 0xae,
+    TrackNumber = 0xd7,
+    TrackUid = 0x73c5,
+    TrackType = 0x83,
+    FlagLacing = 0x9c,
+    CodecId = 0x86,
+    CodecPrivate = 0x63a2,
+    Audio = 0xe1,
+    SamplingFrequency = 0xb5,
+    Channels = 0x9f,
+    BitDepth = 0x6264,
+    Cluster = 0x1f43_b675,
+    Timestamp = 0xe7,
+    SimpleBlock = 0xa3,
+    Cues = 0x1c53_bb6b,
+    CuePoint = 0xbb,
+    CueTime = 0xb3,
+    CueTrackPositions = 0xb7,
+    CueTrack = 0xf7,
+    CueClusterPosition = 0xf1,
+    Void = 0xec,
+}
+
+/// Represents an error in muxing a Matroska file
+#[derive(Debug)]
+pub enum MkaError {
+    MappingError,
+    FrameError(FlacDecoderError),
+}
+
+impl <W: Write> MkaFlacWriter <W> {
+    /// Create a new muxer writing a Matroska audio file to `out`
+    pub fn new(out: W) -> Self {
+        Self {
+            out,
+            native: NativeParser::new(),
+            headers: Vec::new(),
+            sample_rate: 0,
+            cluster: Vec::new(),
+            cluster_timestamp: 0,
+            cue_points: Vec::new(),
+            num_samples: 0,
+            segment_offset: 0,
+            info_position: 0,
+            tracks_position: 0,
+            cues_position: None,
+            duration_offset: 0,
+            streaminfo_offset: 0,
+            stream_len: 0,
+        }
+    }
+
+    /// Finish the Matroska file and return the sink
+    /// 
+    /// The last cluster and the Cues element are written. The Segment is left
+    /// with an unknown size and without SeekHead and Duration elements, as for
+    /// a live stream, and the STREAMINFO block is left as it was written.
+    /// 
+    /// # Errors
+    /// Returns an error of kind `io::ErrorKind::InvalidData` with
+    /// `MkaError::MappingError` if the native FLAC stream ended before its
+    /// first audio frame or within an audio frame.
+    pub fn finish(mut self) -> io::Result <W> {
+        self.close()?;
+
+        Ok(self.out)
+    }
+
+    /// Write out the pending cluster and add a cue point to it
+    fn write_cluster(&mut self) -> io::Result <()> {
+        if self.cluster.is_empty() {
+            return Ok(());
+        }
+
+        let cluster = Self::build_element(ElementId::Cluster, &[
+            Self::build_uint(ElementId::Timestamp, self.cluster_timestamp),
+            std::mem::take(&mut self.cluster),
+        ].concat());
+
+        self.cue_points.push((self.cluster_timestamp, self.stream_len - self.segment_offset));
+        self.write_out(&cluster)
+    }
+
+    /// Write bytes of the file to the sink
+    fn write_out(&mut self, bytes: &[u8]) -> io::Result <()> {
+        self.out.write_all(bytes)?;
+        self.stream_len += bytes.len() as u64;
+
+        Ok(())
+    }
+
+    /// Write the last cluster and the Cues element, once the native FLAC stream is complete
+    fn close(&mut self) -> io::Result <()> {
+        if !self.native.is_complete() {
+            return Err(Self::invalid(MkaError::MappingError));
+        }
+
+        self.write_cluster()?;
+
+        if !self.cue_points.is_empty() {
+            let cue_points: Vec <u8> = self.cue_points
+                .iter()
+                .flat_map(|&(timestamp, position)| {
+                    let positions = [
+                        Self::build_uint(ElementId::CueTrack, TRACK_NUMBER as u64),
+                        Self::build_uint(ElementId::CueClusterPosition, position),
+                    ].concat();
+
+                    Self::build_element(ElementId::CuePoint, &[
+                        Self::build_uint(ElementId::CueTime, timestamp),
+                        Self::build_element(ElementId::CueTrackPositions, &positions),
+                    ].concat())
+                })
+                .collect();
+
+            self.cues_position = Some(self.stream_len - self.segment_offset);
+            self.write_out(&Self::build_element(ElementId::Cues, &cue_points))?;
+        }
+
+        self.out.flush()
+    }
+
+    /// Get the length of the space reserved for the SeekHead element
+    /// 
+    /// This is the length of a SeekHead element pointing to the Info, Tracks
+    /// and Cues elements.
+    fn seek_head_len() -> usize {
+        Self::build_seek_head(&[(ElementId::Info, 0), (ElementId::Tracks, 0), (ElementId::Cues, 0)]).len()
+    }
+
+    /// Build a SeekHead element pointing to each element at its position in the Segment
+    /// 
+    /// Positions take 8 bytes, so that the length of the element only depends
+    /// on the number of elements.
+    fn build_seek_head(elements: &[(ElementId, u64)]) -> Vec <u8> {
+        let seeks: Vec <u8> = elements
+            .iter()
+            .flat_map(|&(id, position)| Self::build_element(ElementId::Seek, &[
+                Self::build_element(ElementId::SeekId, &Self::build_id(id)),
+                Self::build_element(ElementId::SeekPosition, &position.to_be_bytes()),
+            ].concat()))
+            .collect();
+
+        Self::build_element(ElementId::SeekHead, &seeks)
+    }
+
+    /// Build an element of the given ID and contents
+    fn build_element(id: ElementId, content: &[u8]) -> Vec <u8> {
+        [Self::build_id(id), Self::build_size(content.len() as u64), content.to_vec()].concat()
+    }
+
+    /// Build an unsigned integer element, on as few bytes as the value fits
+    fn build_uint(id: ElementId, value: u64) -> Vec <u8> {
+        let bytes = value.to_be_bytes();
+        let num_zeros = bytes.iter().take(7).take_while(|&&byte| byte == 0).count();
+
+        Self::build_element(id, &bytes[num_zeros..])
+    }
+
+    /// Build a Void element of `len` bytes, of at least `VOID_HEADER_LEN` bytes
+    fn build_void(len: usize) -> Vec <u8> {
+        let data_len = len.saturating_sub(VOID_HEADER_LEN);
+
+        [
+            Self::build_id(ElementId::Void),
+            (data_len as u64 | 1 << 56).to_be_bytes().to_vec(),
+            vec![0; data_len],
+        ].concat()
+    }
+
+    /// Build the bytes of an element ID, which keep their length marker
+    fn build_id(id: ElementId) -> Vec <u8> {
+        let bytes = (id as u32).to_be_bytes();
+        let num_zeros = bytes.iter().take_while(|&&byte| byte == 0).count();
+
+        bytes[num_zeros..].to_vec()
+    }
+
+    /// Build the element size of `size` bytes, on as few bytes as it fits
+    /// 
+    /// A size of `n` bytes has a length marker and `7 * n` bits of value, whose
+    /// bits cannot all be set as this means an unknown size.
+    fn build_size(size: u64) -> Vec <u8> {
+        let len = (1..8).find(|&len| size < (1 << (7 * len)) - 1).unwrap_or(8);
+
+        (size | 1 << (7 * len)).to_be_bytes()[8 - len..].to_vec()
+    }
+}
+
+impl <W: Write> NativeMuxer for MkaFlacWriter <W> {
+    type Error = MkaError;
+
+    const MAPPING_ERROR: MkaError = MkaError::MappingError;
+
+    fn native(&mut self) -> &mut NativeParser {
+        &mut self.native
+    }
+
+    fn headers(&mut self) -> &mut Vec <Vec <u8>> {
+        &mut self.headers
+    }
+
+    /// Write the EBML header and the start of the Segment once all metadata blocks are known
+    /// 
+    /// The Segment starts with void space for the SeekHead element, followed by
+    /// the Info element, with void space for its Duration element, and by the
+    /// Tracks element.
+    fn write_headers(&mut self) -> io::Result <()> {
+        let streaminfo = self.native.streaminfo().cloned().ok_or_else(|| Self::invalid(MkaError::MappingError))?;
+        self.sample_rate = streaminfo.sample_rate;
+
+        let ebml = Self::build_element(ElementId::Ebml, &[
+            Self::build_uint(ElementId::EbmlVersion, 1),
+            Self::build_uint(ElementId::EbmlReadVersion, 1),
+            Self::build_uint(ElementId::EbmlMaxIdLength, 4),
+            Self::build_uint(ElementId::EbmlMaxSizeLength, 8),
+            Self::build_element(ElementId::DocType, DOC_TYPE.as_bytes()),
+            Self::build_uint(ElementId::DocTypeVersion, 4),
+            Self::build_uint(ElementId::DocTypeReadVersion, 2),
+        ].concat());
+
+        self.write_out(&ebml)?;
+        self.write_out(&[Self::build_id(ElementId::Segment), UNKNOWN_SIZE.to_vec()].concat())?;
+        self.segment_offset = self.stream_len;
+        self.write_out(&Self::build_void(Self::seek_head_len()))?;
+
+        let info = Self::build_element(ElementId::Info, &[
+            Self::build_uint(ElementId::TimestampScale, TIMESTAMP_SCALE),
+            Self::build_element(ElementId::MuxingApp, VENDOR_STRING.as_bytes()),
+            Self::build_element(ElementId::WritingApp, VENDOR_STRING.as_bytes()),
+            Self::build_void(DURATION_LEN),
+        ].concat());
+
+        self.info_position = self.stream_len - self.segment_offset;
+        self.duration_offset = self.stream_len + (info.len() - DURATION_LEN) as u64;
+        self.write_out(&info)?;
+
+        let codec_private = [&b"fLaC"[..], &build_header_blocks(&streaminfo, &self.headers)].concat();
+        let audio = [
+            Self::build_element(ElementId::SamplingFrequency, &(streaminfo.sample_rate as f64).to_be_bytes()),
+            Self::build_uint(ElementId::Channels, streaminfo.num_channels as u64),
+            Self::build_uint(ElementId::BitDepth, streaminfo.bps as u64),
+        ].concat();
+        let track_entry = [
+            Self::build_uint(ElementId::TrackNumber, TRACK_NUMBER as u64),
+            Self::build_uint(ElementId::TrackUid, TRACK_NUMBER as u64),
+            Self::build_uint(ElementId::TrackType, 2),
+            Self::build_uint(ElementId::FlagLacing, 0),
+            Self::build_element(ElementId::CodecId, CODEC_ID.as_bytes()),
+            Self::build_element(ElementId::Audio, &audio),
+            Self::build_element(ElementId::CodecPrivate, &codec_private),
+        ].concat();
+        let tracks = Self::build_element(ElementId::Tracks, &Self::build_element(ElementId::TrackEntry, &track_entry));
+
+        // The CodecPrivate element ends the Tracks element, and its STREAMINFO data follows the marker and the block header
+        self.tracks_position = self.stream_len - self.segment_offset;
+        self.streaminfo_offset = self.stream_len + (tracks.len() - codec_private.len() + 4 + 4) as u64;
+        self.write_out(&tracks)
+    }
+
+    /// Add an audio frame of the given number of inter-channel samples to the pending cluster
+    /// 
+    /// The pending cluster is written out first if the audio frame starts
+    /// `CLUSTER_DURATION` milliseconds or more after it.
+    fn write_frame(&mut self, frame: &[u8], block_size: u32) -> io::Result <()> {
+        let timestamp = self.num_samples * 1000 / self.sample_rate.max(1) as u64;
+
+        if !self.cluster.is_empty() && timestamp - self.cluster_timestamp >= CLUSTER_DURATION {
+            self.write_cluster()?;
+        }
+        if self.cluster.is_empty() {
+            self.cluster_timestamp = timestamp;
+        }
+
+        let block = [
+            &[0x80 | TRACK_NUMBER][..],
+            &((timestamp - self.cluster_timestamp) as i16).to_be_bytes(),
+            &[KEYFRAME_FLAG],
+            frame,
+        ].concat();
+
+        self.cluster.extend(Self::build_element(ElementId::SimpleBlock, &block));
+        self.num_samples += block_size as u64;
+
+        Ok(())
+    }
+
+    fn frame_error(err: FlacDecoderError) -> MkaError {
+        MkaError::FrameError(err)
+    }
+}
+
+impl <W: Write + Seek> ContainerSink for MkaFlacWriter <W> {
+    type Sink = W;
+
+    /// Finish the Matroska file, patch its headers and return the sink
+    /// 
+    /// Once the last cluster and the Cues element are written, the size of the
+    /// Segment is set, the SeekHead element is written to point to the Info,
+    /// Tracks and Cues elements, the Duration element is written, and the
+    /// STREAMINFO block of the CodecPrivate element is overwritten with
+    /// `streaminfo`. The file may start anywhere in the sink, which is left at
+    /// the end of the file.
+    /// 
+    /// # Errors
+    /// Returns the errors of `finish`.
+    fn finish_and_patch(mut self, streaminfo: &StreamInfo) -> io::Result <W> {
+        self.close()?;
+
+        let end = self.out.stream_position()?;
+        let start = end - self.stream_len;
+
+        let mut elements = vec![(ElementId::Info, self.info_position), (ElementId::Tracks, self.tracks_position)];
+        elements.extend(self.cues_position.map(|position| (ElementId::Cues, position)));
+
+        let mut seek_head = Self::build_seek_head(&elements);
+
+        if seek_head.len() < Self::seek_head_len() {
+            seek_head.extend(Self::build_void(Self::seek_head_len() - seek_head.len()));
+        }
+
+        let duration = self.num_samples as f64 * 1000.0 / streaminfo.sample_rate.max(1) as f64;
+        let segment_size = (self.stream_len - self.segment_offset) | 1 << 56;
+
+        for (offset, bytes) in [
+            (self.segment_offset - 8, segment_size.to_be_bytes().to_vec()),
+            (self.segment_offset, seek_head),
+            (self.duration_offset, Self::build_element(ElementId::Duration, &duration.to_be_bytes())),
+            (self.streaminfo_offset, streaminfo.build_bytes()),
+        ] {
+            self.out.seek(SeekFrom::Start(start + offset))?;
+            self.out.write_all(&bytes)?;
+        }
+
+        self.out.seek(SeekFrom::Start(end))?;
+        self.out.flush()?;
+
+        Ok(self.out)
+    }
+}
+
+impl <W: Write> Write for MkaFlacWriter <W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result <usize> {
+        self.native.push(buf);
+        self.parse()?;
+
+        Ok(buf.len())
+    }
+
+    /// Write out the pending cluster, so that a live stream does not wait for a whole cluster
+    fn flush(&mut self) -> io::Result <()> {
+        self.write_cluster()?;
+        self.out.flush()
+    }
+}
+
+impl error::Error for MkaError {}
+
+impl fmt::Display for MkaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Error: {:?}", self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    use crate::flac::metadata::MetadataBlock;
+    use crate::wav::PCMWaveFormatChunk;
+
+    /// The frame of the first decoding example of RFC 9639, of one sample of 25588 and 10416
+    const FRAME: [u8; 15] = [0xff, 0xf8, 0x69, 0x18, 0x00, 0x00, 0xbf, 0x03, 0x58, 0xfd, 0x03, 0x12, 0x8b, 0xaa, 0x9a];
+
+    fn native_stream() -> Vec <u8> {
+        let streaminfo = StreamInfo::new(&PCMWaveFormatChunk {
+            num_channels: 2,
+            samp_rate: 44100,
+            bps: 16,
+            channel_mask: None,
+        });
+
+        [
+            &b"fLaC"[..],
+            &streaminfo.build_block().build_bytes(false),
+            &MetadataBlock::padding(300).build_bytes(true),
+            &FRAME,
+            &FRAME,
+        ].concat()
+    }
+
+    /// Read a variable-length integer, with or without its length marker, and its length
+    fn read_vint(data: &[u8], keep_marker: bool) -> (u64, usize) {
+        let len = data[0].leading_zeros() as usize + 1;
+        let value = data[..len].iter().fold(0u64, |value, &byte| value << 8 | byte as u64);
+
+        if keep_marker {
+            (value, len)
+        }
+        else {
+            (value & ((1 << (7 * len)) - 1), len)
+        }
+    }
+
+    /// Get the ID, contents and offset of each element of `data`, elements of unknown size ending with `data`
+    fn elements(data: &[u8]) -> Vec <(u32, &[u8], usize)> {
+        let mut elements = Vec::new();
+        let mut pos = 0;
+
+        while pos < data.len() {
+            let (id, id_len) = read_vint(&data[pos..], true);
+            let (size, size_len) = read_vint(&data[pos + id_len..], false);
+            let start = pos + id_len + size_len;
+            let end = if size == (1 << (7 * size_len)) - 1 { data.len() } else { start + size as usize };
+
+            elements.push((id as u32, &data[start..end], pos));
+            pos = end;
+        }
+
+        elements
+    }
+
+    /// Get the IDs of the elements of `data`
+    fn ids(data: &[u8]) -> Vec <u32> {
+        elements(data).iter().map(|&(id, _, _)| id).collect()
+    }
+
+    /// Get the contents of the first element found along the path of IDs
+    fn find <'a>(data: &'a [u8], path: &[ElementId]) -> &'a [u8] {
+        path.iter().fold(data, |data, &id| {
+            elements(data).into_iter().find(|&(found, _, _)| found == id as u32).unwrap().1
+        })
+    }
+
+    #[test]
+    fn sample_mka() {
+        let native = native_stream();
+        let mut writer = MkaFlacWriter::new(Cursor::new(vec![0xee; 5]));
+        writer.out.set_position(5);
+        writer.write_all(&native[..10]).unwrap();
+        writer.write_all(&native[10..]).unwrap();
+
+        let mut streaminfo = StreamInfo::parse(&native[8..42]).unwrap();
+        streaminfo.total_samples = 2;
+        let mka = writer.finish_and_patch(&streaminfo).unwrap().into_inner();
+        let mka = &mka[5..];
+
+        assert_eq!(ids(mka), vec![ElementId::Ebml as u32, ElementId::Segment as u32]);
+        assert_eq!(find(mka, &[ElementId::Ebml, ElementId::DocType]), b"matroska");
+
+        let segment = find(mka, &[ElementId::Segment]);
+        assert_eq!(segment.as_ptr_range().end, mka.as_ptr_range().end);
+
+        let children = elements(segment);
+        let ids: Vec <u32> = children.iter().map(|&(id, _, _)| id).collect();
+        let expected = [ElementId::SeekHead, ElementId::Info, ElementId::Tracks, ElementId::Cluster, ElementId::Cues];
+        assert_eq!(ids, expected.map(|id| id as u32));
+
+        let seek_positions: Vec <u64> = elements(find(segment, &[ElementId::SeekHead]))
+            .iter()
+            .map(|&(_, seek, _)| u64::from_be_bytes(find(seek, &[ElementId::SeekPosition]).try_into().unwrap()))
+            .collect();
+        assert_eq!(seek_positions, vec![children[1].2 as u64, children[2].2 as u64, children[4].2 as u64]);
+
+        let duration = f64::from_be_bytes(find(segment, &[ElementId::Info, ElementId::Duration]).try_into().unwrap());
+        assert_eq!(duration, 2000.0 / 44100.0);
+
+        let track_entry = find(segment, &[ElementId::Tracks, ElementId::TrackEntry]);
+        assert_eq!(find(track_entry, &[ElementId::CodecId]), b"A_FLAC");
+        assert_eq!(find(track_entry, &[ElementId::CodecPrivate]), &[&b"fLaC"[..], &streaminfo.build_block().build_bytes(true)].concat()[..]);
+
+        let cluster = elements(find(segment, &[ElementId::Cluster]));
+        let block = [&[0x81, 0, 0, KEYFRAME_FLAG][..], &FRAME].concat();
+        assert_eq!(cluster.iter().map(|&(_, content, _)| content).collect::<Vec <&[u8]>>(), vec![&[0][..], &block, &block]);
+
+        let cue_position = find(segment, &[ElementId::Cues, ElementId::CuePoint, ElementId::CueTrackPositions, ElementId::CueClusterPosition]);
+        assert_eq!(cue_position.iter().fold(0, |value, &byte| value << 8 | byte as usize), children[3].2);
+    }
+
+    #[test]
+    fn sample_mka_clusters() {
+        let native = native_stream();
+        let mut writer = MkaFlacWriter::new(Vec::new());
+        writer.write_all(&native).unwrap();
+        writer.write_frame(&FRAME, 44100 * 5).unwrap();
+        writer.write_frame(&FRAME, 1).unwrap();
+        let mka = writer.finish().unwrap();
+
+        let segment = find(&mka, &[ElementId::Segment]);
+        let expected = [ElementId::Void, ElementId::Info, ElementId::Tracks, ElementId::Cluster, ElementId::Cluster, ElementId::Cues];
+        assert_eq!(ids(segment), expected.map(|id| id as u32));
+        assert_eq!(elements(segment)[1].2, MkaFlacWriter::<Vec <u8>>::seek_head_len());
+
+        let clusters = elements(segment);
+        assert_eq!(ids(clusters[3].1).len(), 4);
+        assert_eq!(find(clusters[4].1, &[ElementId::Timestamp]), &[0x13, 0x88]);
+
+        let cue_times: Vec <&[u8]> = elements(find(segment, &[ElementId::Cues]))
+            .iter()
+            .map(|&(_, cue_point, _)| find(cue_point, &[ElementId::CueTime]))
+            .collect();
+        assert_eq!(cue_times, vec![&[0][..], &[0x13, 0x88]]);
+    }
+}
@@ -0,0 +1,248 @@
+pub mod matroska;
+pub mod mp4;
+pub mod ogg;
+
+use std::error;
+use std::io::{self, Write};
+
+use crate::flac::decoder::FlacDecoderError;
+use crate::flac::decoder::frame::DecodedFrame;
+use crate::flac::metadata::streaminfo::StreamInfo;
+
+/// Metadata block types left out of the headers of a container, that is PADDING and SEEKTABLE
+const SKIPPED_BLOCK_TYPES: [u8; 2] = [1, 3];
+
+/// Represents a packet of a native FLAC stream, as cut by `NativeParser`
+/// 
+/// A metadata block is given whole with its header, along with whether it is
+/// the last metadata block. An audio frame is given with its number of
+/// inter-channel samples.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NativePacket {
+    Metadata(Vec <u8>, bool),
+    Frame(Vec <u8>, u32),
+}
+
+/// Represents a parser cutting a native FLAC stream into metadata blocks and audio frames
+/// 
+/// The bytes of the native stream are pushed as they are written, for example
+/// by a `FlacEncoder`, and each packet is taken out once it is whole. Audio
+/// frames are delimited by decoding them, so only whole valid frames can be
+/// taken out.
+pub struct NativeParser {
+    buf: Vec <u8>,
+    state: NativeState,
+    streaminfo: Option <StreamInfo>,
+}
+
+/// Represents the part of the native FLAC stream expected next by `NativeParser`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NativeState {
+    Marker,
+    Metadata,
+    Frames,
+}
+
+impl NativeParser {
+    /// Create a new parser expecting the `fLaC` marker
+    pub fn new() -> Self {
+        Self {
+            buf: Vec::new(),
+            state: NativeState::Marker,
+            streaminfo: None,
+        }
+    }
+
+    /// Add bytes of the native FLAC stream
+    pub fn push(&mut self, bytes: &[u8]) {
+        self.buf.extend_from_slice(bytes);
+    }
+
+    /// Get the STREAMINFO block of the stream, once it is parsed
+    pub fn streaminfo(&self) -> Option <&StreamInfo> {
+        self.streaminfo.as_ref()
+    }
+
+    /// Check whether the stream could end here, after the metadata blocks and between audio frames
+    pub fn is_complete(&self) -> bool {
+        self.state == NativeState::Frames && self.buf.is_empty()
+    }
+
+    /// Take out the next whole packet of the stream
+    /// 
+    /// Returns `None` if more bytes are needed.
+    /// 
+    /// # Errors
+    /// Returns `FlacDecoderError::NotFlacError` if the stream does not start
+    /// with the `fLaC` marker, `FlacDecoderError::StreaminfoError` if its first
+    /// metadata block is not a valid STREAMINFO block, and the errors of
+    /// `DecodedFrame::decode` but `FlacDecoderError::TruncatedError` if an
+    /// audio frame is invalid.
+    pub fn next_packet(&mut self) -> Result <Option <NativePacket>, FlacDecoderError> {
+        loop {
+            match self.state {
+                NativeState::Marker => {
+                    if self.buf.len() < 4 {
+                        return Ok(None);
+                    }
+                    if &self.buf[..4] != b"fLaC" {
+                        return Err(FlacDecoderError::NotFlacError);
+                    }
+
+                    self.buf.drain(..4);
+                    self.state = NativeState::Metadata;
+                },
+                NativeState::Metadata => {
+                    if self.buf.len() < 4 {
+                        return Ok(None);
+                    }
+
+                    let block_len = 4 + u32::from_be_bytes([0, self.buf[1], self.buf[2], self.buf[3]]) as usize;
+
+                    if self.buf.len() < block_len {
+                        return Ok(None);
+                    }
+
+                    let block: Vec <u8> = self.buf.drain(..block_len).collect();
+                    let is_last = block[0] & 0x80 != 0;
+
+                    if self.streaminfo.is_none() {
+                        let streaminfo = (block[0] & 0x7f == 0)
+                            .then(|| StreamInfo::parse(&block[4..]))
+                            .flatten()
+                            .ok_or(FlacDecoderError::StreaminfoError)?;
+                        self.streaminfo = Some(streaminfo);
+                    }
+                    if is_last {
+                        self.state = NativeState::Frames;
+                    }
+
+                    return Ok(Some(NativePacket::Metadata(block, is_last)));
+                },
+                NativeState::Frames => {
+                    let Some(streaminfo) = &self.streaminfo else {
+                        return Err(FlacDecoderError::StreaminfoError);
+                    };
+                    if self.buf.is_empty() {
+                        return Ok(None);
+                    }
+
+                    return match DecodedFrame::decode(&self.buf, streaminfo) {
+                        Ok((frame, frame_len)) => {
+                            let packet = self.buf.drain(..frame_len).collect();
+                            Ok(Some(NativePacket::Frame(packet, frame.header.block_size)))
+                        },
+                        Err(FlacDecoderError::TruncatedError) => Ok(None),
+                        Err(err) => Err(err),
+                    };
+                },
+            }
+        }
+    }
+}
+
+impl Default for NativeParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Represents a muxer of a native FLAC stream into a container
+/// 
+/// The bytes of the native stream are pushed into the `NativeParser` of the
+/// muxer, and `parse` maps the packets taken out of it: the metadata blocks
+/// are collected until the last one, when the headers of the container are
+/// written, and the audio frames are written as they come. Errors are given
+/// as I/O errors of kind `InvalidData` wrapping the error of the container.
+pub trait NativeMuxer {
+    /// The error of the container
+    type Error: error::Error + Send + Sync + 'static;
+
+    /// The error of a native FLAC stream that cannot be mapped into the container
+    const MAPPING_ERROR: Self::Error;
+
+    /// Get the parser of the native FLAC stream
+    fn native(&mut self) -> &mut NativeParser;
+
+    /// Get the metadata blocks of the native FLAC stream taken out so far
+    fn headers(&mut self) -> &mut Vec <Vec <u8>>;
+
+    /// Write the headers of the container once all metadata blocks are known
+    fn write_headers(&mut self) -> io::Result <()>;
+
+    /// Write an audio frame of `block_size` inter-channel samples
+    fn write_frame(&mut self, frame: &[u8], block_size: u32) -> io::Result <()>;
+
+    /// Wrap an error of an audio frame of the native FLAC stream into an error of the container
+    fn frame_error(err: FlacDecoderError) -> Self::Error;
+
+    /// Parse the bytes of the native FLAC stream written so far into the container
+    fn parse(&mut self) -> io::Result <()> {
+        while let Some(packet) = self.native().next_packet().map_err(Self::native_error)? {
+            match packet {
+                NativePacket::Metadata(block, is_last) => {
+                    self.headers().push(block);
+
+                    if is_last {
+                        self.write_headers()?;
+                    }
+                },
+                NativePacket::Frame(frame, block_size) => self.write_frame(&frame, block_size)?,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Wrap an error of the container into an I/O error
+    fn invalid(err: Self::Error) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, err)
+    }
+
+    /// Wrap an error of the native FLAC stream into an I/O error
+    /// 
+    /// A stream without a valid start cannot be mapped, and any other error is
+    /// that of an audio frame.
+    fn native_error(err: FlacDecoderError) -> io::Error {
+        match err {
+            FlacDecoderError::NotFlacError | FlacDecoderError::StreaminfoError => Self::invalid(Self::MAPPING_ERROR),
+            err => Self::invalid(Self::frame_error(err)),
+        }
+    }
+}
+
+/// Represents a container writer fed with a native FLAC stream
+/// 
+/// The native stream is written into it as for any sink, and the headers of
+/// the container are patched with the final STREAMINFO block once the stream
+/// is finished, which needs the sink of the container to be seekable.
+pub trait ContainerSink: Write {
+    /// The sink of the container
+    type Sink;
+
+    /// Finish the container, patch its headers with `streaminfo` and return the sink
+    fn finish_and_patch(self, streaminfo: &StreamInfo) -> io::Result <Self::Sink>;
+}
+
+/// Build the metadata blocks carried in the headers of a container
+/// 
+/// `headers` are the metadata blocks of the native stream as taken out by a
+/// `NativeParser`, the first of which is replaced by `streaminfo`. PADDING
+/// blocks are left out, as a container is not edited in place, and so are
+/// SEEKTABLE blocks, whose offsets do not point into a container. The last
+/// of the remaining blocks is flagged as such.
+pub fn build_header_blocks(streaminfo: &StreamInfo, headers: &[Vec <u8>]) -> Vec <u8> {
+    let streaminfo_block = streaminfo.build_block().build_bytes(false);
+    let blocks: Vec <&Vec <u8>> = std::iter::once(&streaminfo_block)
+        .chain(headers.iter().skip(1).filter(|block| !SKIPPED_BLOCK_TYPES.contains(&(block[0] & 0x7f))))
+        .collect();
+    let mut bytes = Vec::new();
+
+    for (i, block) in blocks.iter().enumerate() {
+        let is_last = i + 1 == blocks.len();
+        bytes.push(block[0] & 0x7f | if is_last { 0x80 } else { 0 });
+        bytes.extend_from_slice(&block[1..]);
+    }
+
+    bytes
+}
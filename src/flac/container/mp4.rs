@@ -0,0 +1,609 @@
+use core::fmt;
+use std::error;
+use std::io::{self, Seek, SeekFrom, Write};
+
+use crate::flac::decoder::FlacDecoderError;
+use crate::flac::metadata::streaminfo::StreamInfo;
+
+use super::{build_header_blocks, ContainerSink, NativeMuxer, NativeParser};
+
+/// Major brand of the `ftyp` box, followed by its compatible brands
+const BRANDS: [[u8; 4]; 3] = [*b"isom", *b"iso2", *b"mp41"];
+
+/// Compatible brand added to the `ftyp` box of fragmented files
+const FRAGMENTED_BRAND: &[u8; 4] = b"iso6";
+
+/// Minor version of the `ftyp` box
+const MINOR_VERSION: u32 = 0x200;
+
+/// ID of the audio track, the only track of the file
+const TRACK_ID: u32 = 1;
+
+/// Length of the header of an `mdat` box with a 64-bit size
+const LARGE_HEADER_LEN: u64 = 16;
+
+/// Number of audio frames of each chunk of the sample tables
+const FRAMES_PER_CHUNK: usize = 64;
+
+/// Code of the undetermined language, packed as three 5-bit letters
+const LANGUAGE_UND: u16 = 0x55c4;
+
+/// Transformation matrix of the movie and track headers, which leaves the presentation as is
+const UNITY_MATRIX: [u32; 9] = [0x0001_0000, 0, 0, 0, 0x0001_0000, 0, 0, 0, 0x4000_0000];
+
+/// Track header flags of an enabled track used in the presentation
+const TRACK_FLAGS: u32 = 0x00_0003;
+
+/// Track fragment header flag of data offsets relative to the `moof` box
+const DEFAULT_BASE_IS_MOOF_FLAG: u32 = 0x02_0000;
+
+/// Track run flags of a data offset followed by the duration and size of each sample
+const TRUN_FLAGS: u32 = 0x00_0301;
+
+/// Represents a muxer of a native FLAC stream into an MP4 file, as specified by the FLAC-in-ISOBMFF encapsulation
+/// 
+/// The native stream is written as to any other sink, for example by a
+/// `FlacEncoder`, and is cut into metadata blocks and audio frames by a
+/// `NativeParser`. The audio track holds one sample per audio frame, of the
+/// duration of its block size in a timescale of the sample rate, and is
+/// described by an `fLaC` sample entry whose `dfLa` box holds the metadata
+/// blocks given by `build_header_blocks`.
+/// 
+/// Unless created with `new_fragmented`, the audio frames are written in a
+/// single `mdat` box followed by the `moov` box with the sample tables, so the
+/// size of the `mdat` box is patched by `finish_and_patch`. Fragmented files
+/// start with a `moov` box without samples, and every `frames_per_fragment`
+/// audio frames are written in a `moof` box followed by an `mdat` box, so that
+/// they can be streamed as they are written.
+pub struct Mp4FlacWriter <W: Write> {
+    out: W,
+    native: NativeParser,
+    headers: Vec <Vec <u8>>,
+    frames_per_fragment: Option <u32>,
+    sample_sizes: Vec <u32>,
+    sample_durations: Vec <u32>,
+    fragment: Vec <u8>,
+    sequence: u32,
+    num_samples: u64,
+    mdat_offset: u64,
+    streaminfo_offset: u64,
+    stream_len: u64,
+}
+
+/// Represents an error in muxing an MP4 file
+#[derive(Debug)]
+pub enum Mp4Error {
+    MappingError,
+    SeekError,
+    FrameError(FlacDecoderError),
+}
+
+impl <W: Write> Mp4FlacWriter <W> {
+    /// Create a new muxer writing an MP4 file with the sample tables in its `moov` box to `out`
+    pub fn new(out: W) -> Self {
+        Self {
+            out,
+            native: NativeParser::new(),
+            headers: Vec::new(),
+            frames_per_fragment: None,
+            sample_sizes: Vec::new(),
+            sample_durations: Vec::new(),
+            fragment: Vec::new(),
+            sequence: 0,
+            num_samples: 0,
+            mdat_offset: 0,
+            streaminfo_offset: 0,
+            stream_len: 0,
+        }
+    }
+
+    /// Create a new muxer writing a fragmented MP4 file to `out`
+    /// 
+    /// Each fragment holds `frames_per_fragment` audio frames, and at least one.
+    pub fn new_fragmented(out: W, frames_per_fragment: u32) -> Self {
+        Self {
+            frames_per_fragment: Some(frames_per_fragment.max(1)),
+            ..Self::new(out)
+        }
+    }
+
+    /// Finish the fragmented MP4 file and return the sink
+    /// 
+    /// The last fragment is written, and the STREAMINFO block is left as it
+    /// was written.
+    /// 
+    /// # Errors
+    /// Returns an error of kind `io::ErrorKind::InvalidInput` with
+    /// `Mp4Error::SeekError` if the file is not fragmented, as the size of its
+    /// `mdat` box can only be patched with `finish_and_patch`, and an error of
+    /// kind `io::ErrorKind::InvalidData` with `Mp4Error::MappingError` if the
+    /// native FLAC stream ended before its first audio frame or within an
+    /// audio frame.
+    pub fn finish(mut self) -> io::Result <W> {
+        if self.frames_per_fragment.is_none() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, Mp4Error::SeekError));
+        }
+
+        self.close()?;
+
+        Ok(self.out)
+    }
+
+    /// Write out the pending fragment as a `moof` box followed by an `mdat` box
+    fn write_fragment(&mut self) -> io::Result <()> {
+        if self.sample_sizes.is_empty() {
+            return Ok(());
+        }
+
+        self.sequence += 1;
+
+        let decode_time = self.num_samples - self.sample_durations.iter().map(|&duration| duration as u64).sum::<u64>();
+        let moof_len = self.build_moof(decode_time, 0).len();
+        let moof = self.build_moof(decode_time, moof_len as u32 + 8);
+        let mdat = Self::build_box(b"mdat", &std::mem::take(&mut self.fragment));
+
+        self.write_out(&moof)?;
+        self.write_out(&mdat)?;
+        self.sample_sizes.clear();
+        self.sample_durations.clear();
+
+        Ok(())
+    }
+
+    /// Write bytes of the file to the sink
+    fn write_out(&mut self, bytes: &[u8]) -> io::Result <()> {
+        self.out.write_all(bytes)?;
+        self.stream_len += bytes.len() as u64;
+
+        Ok(())
+    }
+
+    /// Write the last fragment of a fragmented file, once the native FLAC stream is complete
+    fn close(&mut self) -> io::Result <()> {
+        if !self.native.is_complete() {
+            return Err(Self::invalid(Mp4Error::MappingError));
+        }
+        if self.frames_per_fragment.is_some() {
+            self.write_fragment()?;
+        }
+
+        self.out.flush()
+    }
+
+    /// Build a `moof` box of the pending fragment
+    /// 
+    /// `data_offset` is the offset of the first audio frame from the start of
+    /// the `moof` box, and `decode_time` is the number of inter-channel samples
+    /// before the fragment.
+    fn build_moof(&self, decode_time: u64, data_offset: u32) -> Vec <u8> {
+        let mut trun = Vec::with_capacity(8 + 8 * self.sample_sizes.len());
+        trun.extend_from_slice(&(self.sample_sizes.len() as u32).to_be_bytes());
+        trun.extend_from_slice(&data_offset.to_be_bytes());
+
+        for (&duration, &size) in self.sample_durations.iter().zip(&self.sample_sizes) {
+            trun.extend_from_slice(&duration.to_be_bytes());
+            trun.extend_from_slice(&size.to_be_bytes());
+        }
+
+        let traf = [
+            Self::build_full_box(b"tfhd", 0, DEFAULT_BASE_IS_MOOF_FLAG, &TRACK_ID.to_be_bytes()),
+            Self::build_full_box(b"tfdt", 1, 0, &decode_time.to_be_bytes()),
+            Self::build_full_box(b"trun", 0, TRUN_FLAGS, &trun),
+        ].concat();
+
+        Self::build_box(b"moof", &[
+            Self::build_full_box(b"mfhd", 0, 0, &self.sequence.to_be_bytes()),
+            Self::build_box(b"traf", &traf),
+        ].concat())
+    }
+
+    /// Build the `moov` box of the file with the given STREAMINFO block
+    /// 
+    /// The sample tables hold the samples written so far, in chunks starting
+    /// at `chunk_offsets`, which are empty for fragmented files.
+    fn build_moov(&self, streaminfo: &StreamInfo, chunk_offsets: &[u64]) -> Vec <u8> {
+        let timescale = streaminfo.sample_rate.to_be_bytes();
+        let duration = self.sample_durations.iter().map(|&duration| duration as u64).sum::<u64>().to_be_bytes();
+        let matrix: Vec <u8> = UNITY_MATRIX.iter().flat_map(|value| value.to_be_bytes()).collect();
+
+        let mvhd = [
+            &[0; 16][..], &timescale, &duration,
+            &0x0001_0000u32.to_be_bytes(), &0x0100u16.to_be_bytes(), &[0; 10],
+            &matrix, &[0; 24], &(TRACK_ID + 1).to_be_bytes(),
+        ].concat();
+        let tkhd = [
+            &[0; 16][..], &TRACK_ID.to_be_bytes(), &[0; 4], &duration,
+            &[0; 12], &0x0100u16.to_be_bytes(), &[0; 2],
+            &matrix, &[0; 8],
+        ].concat();
+        let mdhd = [
+            &[0; 16][..], &timescale, &duration,
+            &LANGUAGE_UND.to_be_bytes(), &[0; 2],
+        ].concat();
+        let hdlr = [&[0; 4][..], b"soun", &[0; 12], b"SoundHandler\0"].concat();
+        let dref = [
+            &1u32.to_be_bytes()[..],
+            &Self::build_full_box(b"url ", 0, 1, &[]),
+        ].concat();
+
+        let minf = [
+            Self::build_full_box(b"smhd", 0, 0, &[0; 4]),
+            Self::build_box(b"dinf", &Self::build_full_box(b"dref", 0, 0, &dref)),
+            Self::build_box(b"stbl", &self.build_stbl(streaminfo, chunk_offsets)),
+        ].concat();
+        let mdia = [
+            Self::build_full_box(b"mdhd", 1, 0, &mdhd),
+            Self::build_full_box(b"hdlr", 0, 0, &hdlr),
+            Self::build_box(b"minf", &minf),
+        ].concat();
+        let trak = [
+            Self::build_full_box(b"tkhd", 1, TRACK_FLAGS, &tkhd),
+            Self::build_box(b"mdia", &mdia),
+        ].concat();
+
+        let mut moov = [
+            Self::build_full_box(b"mvhd", 1, 0, &mvhd),
+            Self::build_box(b"trak", &trak),
+        ].concat();
+
+        if self.frames_per_fragment.is_some() {
+            let trex = [&TRACK_ID.to_be_bytes()[..], &1u32.to_be_bytes(), &[0; 12]].concat();
+            moov.extend(Self::build_box(b"mvex", &Self::build_full_box(b"trex", 0, 0, &trex)));
+        }
+
+        Self::build_box(b"moov", &moov)
+    }
+
+    /// Build the contents of the `stbl` box, that is the sample description and the sample tables
+    /// 
+    /// The sample rate of the `fLaC` sample entry is 16.16 fixed-point, so it
+    /// is 0 for sample rates above 65535 Hz, whose value is then only that of
+    /// STREAMINFO. Samples are grouped into chunks of `FRAMES_PER_CHUNK` audio
+    /// frames, and 64-bit chunk offsets are used if any does not fit 32 bits.
+    fn build_stbl(&self, streaminfo: &StreamInfo, chunk_offsets: &[u64]) -> Vec <u8> {
+        let sample_rate = if streaminfo.sample_rate <= u16::MAX as u32 { streaminfo.sample_rate << 16 } else { 0 };
+        let sample_entry = [
+            &[0; 6][..], &1u16.to_be_bytes(), &[0; 8],
+            &(streaminfo.num_channels as u16).to_be_bytes(), &(streaminfo.bps as u16).to_be_bytes(),
+            &[0; 4], &sample_rate.to_be_bytes(),
+            &Self::build_full_box(b"dfLa", 0, 0, &build_header_blocks(streaminfo, &self.headers)),
+        ].concat();
+        let stsd = [&1u32.to_be_bytes()[..], &Self::build_box(b"fLaC", &sample_entry)].concat();
+
+        let mut stts_entries: Vec <(u32, u32)> = Vec::new();
+
+        for &duration in &self.sample_durations {
+            match stts_entries.last_mut() {
+                Some((count, last_duration)) if *last_duration == duration => *count += 1,
+                _ => stts_entries.push((1, duration)),
+            }
+        }
+
+        let num_frames = self.sample_sizes.len();
+        let mut stsc_entries = Vec::new();
+
+        if num_frames > 0 {
+            stsc_entries.push((1, num_frames.min(FRAMES_PER_CHUNK)));
+        }
+        if num_frames > FRAMES_PER_CHUNK && !num_frames.is_multiple_of(FRAMES_PER_CHUNK) {
+            stsc_entries.push((num_frames / FRAMES_PER_CHUNK + 1, num_frames % FRAMES_PER_CHUNK));
+        }
+
+        let stts = Self::build_table(stts_entries.iter().map(|&(count, duration)| [count.to_be_bytes(), duration.to_be_bytes()].concat()));
+        let stsc = Self::build_table(stsc_entries.iter().map(|&(first_chunk, num_samples)| {
+            [(first_chunk as u32).to_be_bytes(), (num_samples as u32).to_be_bytes(), 1u32.to_be_bytes()].concat()
+        }));
+        let stsz = [&0u32.to_be_bytes()[..], &Self::build_table(self.sample_sizes.iter().map(|size| size.to_be_bytes().to_vec()))].concat();
+        let chunk_offsets = if chunk_offsets.iter().all(|&offset| offset <= u32::MAX as u64) {
+            Self::build_full_box(b"stco", 0, 0, &Self::build_table(chunk_offsets.iter().map(|&offset| (offset as u32).to_be_bytes().to_vec())))
+        }
+        else {
+            Self::build_full_box(b"co64", 0, 0, &Self::build_table(chunk_offsets.iter().map(|offset| offset.to_be_bytes().to_vec())))
+        };
+
+        [
+            Self::build_full_box(b"stsd", 0, 0, &stsd),
+            Self::build_full_box(b"stts", 0, 0, &stts),
+            Self::build_full_box(b"stsc", 0, 0, &stsc),
+            Self::build_full_box(b"stsz", 0, 0, &stsz),
+            chunk_offsets,
+        ].concat()
+    }
+
+    /// Build a table of entries preceded by their number
+    fn build_table(entries: impl Iterator <Item = Vec <u8>>) -> Vec <u8> {
+        let mut num_entries = 0u32;
+        let mut table = vec![0; 4];
+
+        for entry in entries {
+            table.extend(entry);
+            num_entries += 1;
+        }
+
+        table[..4].copy_from_slice(&num_entries.to_be_bytes());
+
+        table
+    }
+
+    /// Build a box of the given type and contents
+    fn build_box(box_type: &[u8; 4], content: &[u8]) -> Vec <u8> {
+        [&(8 + content.len() as u32).to_be_bytes()[..], box_type, content].concat()
+    }
+
+    /// Build a box of the given type and contents starting with a version and flags
+    fn build_full_box(box_type: &[u8; 4], version: u8, flags: u32, content: &[u8]) -> Vec <u8> {
+        Self::build_box(box_type, &[&(((version as u32) << 24) | flags).to_be_bytes()[..], content].concat())
+    }
+}
+
+impl <W: Write> NativeMuxer for Mp4FlacWriter <W> {
+    type Error = Mp4Error;
+
+    const MAPPING_ERROR: Mp4Error = Mp4Error::MappingError;
+
+    fn native(&mut self) -> &mut NativeParser {
+        &mut self.native
+    }
+
+    fn headers(&mut self) -> &mut Vec <Vec <u8>> {
+        &mut self.headers
+    }
+
+    /// Write the boxes that precede the audio frames once all metadata blocks are known
+    /// 
+    /// A fragmented file gets its `moov` box right away, and any other file gets
+    /// the header of its `mdat` box, of a size to be patched.
+    fn write_headers(&mut self) -> io::Result <()> {
+        let mut brands = BRANDS.concat();
+
+        if self.frames_per_fragment.is_some() {
+            brands.extend_from_slice(FRAGMENTED_BRAND);
+        }
+
+        let ftyp = Self::build_box(b"ftyp", &[&BRANDS[0][..], &MINOR_VERSION.to_be_bytes(), &brands].concat());
+        self.write_out(&ftyp)?;
+
+        if self.frames_per_fragment.is_some() {
+            let streaminfo = self.native.streaminfo().cloned().ok_or_else(|| Self::invalid(Mp4Error::MappingError))?;
+            let moov = self.build_moov(&streaminfo, &[]);
+            let dfla_pos = moov.windows(4).position(|box_type| box_type == b"dfLa").unwrap_or(0);
+
+            // The STREAMINFO data follows the type and version of the `dfLa` box and its block header
+            self.streaminfo_offset = self.stream_len + dfla_pos as u64 + 4 + 4 + 4;
+            self.write_out(&moov)?;
+        }
+        else {
+            self.mdat_offset = self.stream_len;
+            self.write_out(&[&1u32.to_be_bytes()[..], b"mdat", &0u64.to_be_bytes()].concat())?;
+        }
+
+        Ok(())
+    }
+
+    /// Write an audio frame as a sample of the given duration
+    /// 
+    /// In a fragmented file, the pending fragment is written out once it holds
+    /// `frames_per_fragment` audio frames.
+    fn write_frame(&mut self, frame: &[u8], block_size: u32) -> io::Result <()> {
+        self.sample_sizes.push(frame.len() as u32);
+        self.sample_durations.push(block_size);
+        self.num_samples += block_size as u64;
+
+        match self.frames_per_fragment {
+            Some(frames_per_fragment) => {
+                self.fragment.extend_from_slice(frame);
+
+                if self.sample_sizes.len() >= frames_per_fragment as usize {
+                    self.write_fragment()?;
+                }
+            },
+            None => self.write_out(frame)?,
+        }
+
+        Ok(())
+    }
+
+    fn frame_error(err: FlacDecoderError) -> Mp4Error {
+        Mp4Error::FrameError(err)
+    }
+}
+
+impl <W: Write + Seek> ContainerSink for Mp4FlacWriter <W> {
+    type Sink = W;
+
+    /// Finish the MP4 file with the final STREAMINFO block and return the sink
+    /// 
+    /// The size of the `mdat` box is patched and the `moov` box is written with
+    /// `streaminfo`, or for fragmented files the last fragment is written and
+    /// the STREAMINFO block of the `moov` box is overwritten with `streaminfo`.
+    /// The file may start anywhere in the sink, which is left at the end of the
+    /// file.
+    /// 
+    /// # Errors
+    /// Returns an error of kind `io::ErrorKind::InvalidData` with
+    /// `Mp4Error::MappingError` if the native FLAC stream ended before its
+    /// first audio frame or within an audio frame.
+    fn finish_and_patch(mut self, streaminfo: &StreamInfo) -> io::Result <W> {
+        self.close()?;
+
+        let end = self.out.stream_position()?;
+        let start = end - self.stream_len;
+
+        if self.frames_per_fragment.is_some() {
+            self.out.seek(SeekFrom::Start(start + self.streaminfo_offset))?;
+            self.out.write_all(&streaminfo.build_bytes())?;
+            self.out.seek(SeekFrom::Start(end))?;
+        }
+        else {
+            let mut offset = self.mdat_offset + LARGE_HEADER_LEN;
+            let chunk_offsets: Vec <u64> = self.sample_sizes
+                .chunks(FRAMES_PER_CHUNK)
+                .map(|chunk| {
+                    let chunk_offset = offset;
+                    offset += chunk.iter().map(|&size| size as u64).sum::<u64>();
+                    chunk_offset
+                })
+                .collect();
+
+            self.out.seek(SeekFrom::Start(start + self.mdat_offset + 8))?;
+            self.out.write_all(&(self.stream_len - self.mdat_offset).to_be_bytes())?;
+            self.out.seek(SeekFrom::Start(end))?;
+
+            let moov = self.build_moov(streaminfo, &chunk_offsets);
+            self.write_out(&moov)?;
+        }
+
+        self.out.flush()?;
+
+        Ok(self.out)
+    }
+}
+
+impl <W: Write> Write for Mp4FlacWriter <W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result <usize> {
+        self.native.push(buf);
+        self.parse()?;
+
+        Ok(buf.len())
+    }
+
+    /// Write out the pending fragment, so that a live stream does not wait for a whole fragment
+    fn flush(&mut self) -> io::Result <()> {
+        if self.frames_per_fragment.is_some() {
+            self.write_fragment()?;
+        }
+
+        self.out.flush()
+    }
+}
+
+impl error::Error for Mp4Error {}
+
+impl fmt::Display for Mp4Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Error: {:?}", self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    use crate::flac::metadata::MetadataBlock;
+    use crate::flac::metadata::streaminfo::STREAMINFO_LEN;
+    use crate::wav::PCMWaveFormatChunk;
+
+    /// The frame of the first decoding example of RFC 9639, of one sample of 25588 and 10416
+    const FRAME: [u8; 15] = [0xff, 0xf8, 0x69, 0x18, 0x00, 0x00, 0xbf, 0x03, 0x58, 0xfd, 0x03, 0x12, 0x8b, 0xaa, 0x9a];
+
+    fn native_stream() -> Vec <u8> {
+        let streaminfo = StreamInfo::new(&PCMWaveFormatChunk {
+            num_channels: 2,
+            samp_rate: 44100,
+            bps: 16,
+            channel_mask: None,
+        });
+
+        [
+            &b"fLaC"[..],
+            &streaminfo.build_block().build_bytes(false),
+            &MetadataBlock::padding(300).build_bytes(true),
+            &FRAME,
+            &FRAME,
+        ].concat()
+    }
+
+    /// Get the type, contents and offset of each box of `data`
+    fn boxes(data: &[u8]) -> Vec <(&[u8], &[u8], usize)> {
+        let mut boxes = Vec::new();
+        let mut pos = 0;
+
+        while pos < data.len() {
+            let mut size = u32::from_be_bytes(data[pos..pos + 4].try_into().unwrap()) as usize;
+            let mut header_len = 8;
+
+            if size == 1 {
+                size = u64::from_be_bytes(data[pos + 8..pos + 16].try_into().unwrap()) as usize;
+                header_len = 16;
+            }
+
+            boxes.push((&data[pos + 4..pos + 8], &data[pos + header_len..pos + size], pos + header_len));
+            pos += size;
+        }
+
+        boxes
+    }
+
+    /// Get the contents of the first box found along the path of box types
+    fn find <'a>(data: &'a [u8], path: &[&[u8]]) -> &'a [u8] {
+        path.iter().fold(data, |data, &box_type| {
+            boxes(data).into_iter().find(|&(found, _, _)| found == box_type).unwrap().1
+        })
+    }
+
+    #[test]
+    fn sample_mp4() {
+        let native = native_stream();
+        let mut writer = Mp4FlacWriter::new(Cursor::new(vec![0xee; 5]));
+        writer.out.set_position(5);
+        writer.write_all(&native[..10]).unwrap();
+        writer.write_all(&native[10..]).unwrap();
+
+        let mut streaminfo = StreamInfo::parse(&native[8..42]).unwrap();
+        streaminfo.total_samples = 2;
+        let mp4 = writer.finish_and_patch(&streaminfo).unwrap().into_inner();
+        let mp4 = &mp4[5..];
+
+        let top: Vec <&[u8]> = boxes(mp4).iter().map(|&(box_type, _, _)| box_type).collect();
+        assert_eq!(top, vec![&b"ftyp"[..], b"mdat", b"moov"]);
+        assert_eq!(find(mp4, &[b"mdat"]), &[FRAME, FRAME].concat()[..]);
+
+        let stbl = find(mp4, &[b"moov", b"trak", b"mdia", b"minf", b"stbl"]);
+        assert_eq!(find(stbl, &[b"stts"]), &[0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0, 1]);
+        assert_eq!(find(stbl, &[b"stsc"]), &[0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0, 1]);
+        assert_eq!(find(stbl, &[b"stsz"]), &[0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 15, 0, 0, 0, 15]);
+
+        let mdat_pos = boxes(mp4)[1].2 as u32;
+        assert_eq!(find(stbl, &[b"stco"]), &[&[0, 0, 0, 0, 0, 0, 0, 1][..], &mdat_pos.to_be_bytes()].concat()[..]);
+
+        // The sample entry follows the version and entry count of the `stsd` box
+        let sample_entry = find(&find(stbl, &[b"stsd"])[8..], &[b"fLaC"]);
+        assert_eq!(&sample_entry[16..20], &[0, 2, 0, 16]);
+        assert_eq!(&sample_entry[24..28], &(44100u32 << 16).to_be_bytes());
+
+        // The metadata blocks follow the 28 bytes of the audio sample entry and the version of the `dfLa` box
+        let dfla = find(&sample_entry[28..], &[b"dfLa"]);
+        assert_eq!(dfla[..8], [0, 0, 0, 0, 0x80, 0, 0, STREAMINFO_LEN as u8]);
+        assert_eq!(StreamInfo::parse(&dfla[8..]), Some(streaminfo));
+    }
+
+    #[test]
+    fn sample_fragmented_mp4() {
+        let native = native_stream();
+        let mut writer = Mp4FlacWriter::new_fragmented(Vec::new(), 1);
+        writer.write_all(&native).unwrap();
+        let mp4 = writer.finish().unwrap();
+
+        let top = boxes(&mp4);
+        let types: Vec <&[u8]> = top.iter().map(|&(box_type, _, _)| box_type).collect();
+        assert_eq!(types, vec![&b"ftyp"[..], b"moov", b"moof", b"mdat", b"moof", b"mdat"]);
+        assert!(find(&mp4, &[b"ftyp"]).ends_with(FRAGMENTED_BRAND));
+        assert_eq!(find(&mp4, &[b"moov", b"mvex", b"trex"])[4..8], TRACK_ID.to_be_bytes());
+        assert_eq!(find(&mp4, &[b"moov", b"trak", b"mdia", b"minf", b"stbl", b"stsz"]), &[0; 12]);
+
+        for (i, fragment) in top[2..].chunks(2).enumerate() {
+            let (moof, moof_pos) = (fragment[0].1, fragment[0].2 - 8);
+            let trun = find(moof, &[b"traf", b"trun"]);
+            let data_offset = u32::from_be_bytes(trun[8..12].try_into().unwrap()) as usize;
+
+            assert_eq!(find(moof, &[b"mfhd"])[4..], (i as u32 + 1).to_be_bytes());
+            assert_eq!(find(moof, &[b"traf", b"tfdt"])[4..], (i as u64).to_be_bytes());
+            assert_eq!(trun[12..], [0, 0, 0, 1, 0, 0, 0, 15]);
+            assert_eq!(moof_pos + data_offset, fragment[1].2);
+            assert_eq!(fragment[1].1, FRAME);
+        }
+
+        assert!(Mp4FlacWriter::new(Vec::new()).finish().is_err());
+    }
+}
@@ -0,0 +1,660 @@
+use core::fmt;
+use std::collections::VecDeque;
+use std::error;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+use crate::flac::decoder::FlacDecoderError;
+use crate::flac::encoder::crc::CrcOptions;
+use crate::flac::metadata::streaminfo::{StreamInfo, STREAMINFO_LEN};
+
+use super::{ContainerSink, NativeMuxer, NativeParser};
+
+/// Capture pattern at the start of every Ogg page
+const CAPTURE_PATTERN: &[u8; 4] = b"OggS";
+
+/// Length of an Ogg page header up to its segment table
+const PAGE_HEADER_LEN: usize = 27;
+
+/// Offset of the CRC in an Ogg page header
+const PAGE_CRC_OFFSET: usize = 22;
+
+/// Maximum number of segments of an Ogg page
+const MAX_SEGMENTS: usize = 255;
+
+/// Maximum length of a segment, which continues the packet when reached
+const MAX_SEGMENT_LEN: usize = 255;
+
+/// Length of the packet data above which a page is written out
+const PAGE_DATA_LEN: usize = 4096;
+
+/// Header type flag of a page starting with the continuation of a packet
+const CONTINUED_FLAG: u8 = 0x01;
+
+/// Header type flag of the first page of a logical stream
+const BOS_FLAG: u8 = 0x02;
+
+/// Header type flag of the last page of a logical stream
+const EOS_FLAG: u8 = 0x04;
+
+/// Granule position of a page on which no packet ends
+const NO_GRANULE_POSITION: u64 = u64::MAX;
+
+/// Signature at the start of the first packet of an Ogg FLAC stream
+const MAPPING_SIGNATURE: &[u8; 5] = b"\x7fFLAC";
+
+/// Major and minor version of the Ogg FLAC mapping
+const MAPPING_VERSION: [u8; 2] = [1, 0];
+
+/// Length of the first packet of an Ogg FLAC stream
+/// 
+/// The packet holds the signature, the mapping version, the number of other
+/// header packets, the `fLaC` marker and the whole STREAMINFO block.
+const MAPPING_PACKET_LEN: usize = 5 + 2 + 2 + 4 + 4 + STREAMINFO_LEN;
+
+/// Represents an Ogg page
+/// 
+/// The packet data of a page is cut into segments of at most 255 bytes, whose
+/// lengths are the `lacing_values`. A packet ends at the first segment shorter
+/// than 255 bytes, so a packet whose length is a multiple of 255 ends with an
+/// empty segment, and a page whose last segment is 255 bytes long continues its
+/// last packet on the next page. The granule position is that of the last packet
+/// ending on the page, or `u64::MAX` if none does.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OggPage {
+    pub header_type: u8,
+    pub granule_position: u64,
+    pub serial: u32,
+    pub sequence: u32,
+    pub lacing_values: Vec <u8>,
+    pub data: Vec <u8>,
+}
+
+/// Represents a muxer of a native FLAC stream into an Ogg FLAC stream
+/// 
+/// The native stream is written as to any other sink, for example by a
+/// `FlacEncoder`. The STREAMINFO block is sent in the first packet along with
+/// the number of other metadata blocks, on a page of its own, and each other
+/// metadata block follows in a packet of its own. Every audio frame is then
+/// sent in a packet of its own, with the number of inter-channel samples up to
+/// the end of the frame as its granule position. The native stream is cut into
+/// packets by a `NativeParser`, so only whole valid frames can be written.
+/// 
+/// Pages are written out once they hold `PAGE_DATA_LEN` bytes or on `flush`.
+pub struct OggFlacWriter <W: Write> {
+    out: W,
+    serial: u32,
+    sequence: u32,
+    native: NativeParser,
+    headers: Vec <Vec <u8>>,
+    first_page: Option <OggPage>,
+    lacing_values: Vec <u8>,
+    data: Vec <u8>,
+    is_continued: bool,
+    granule_position: u64,
+    num_samples: u64,
+    stream_len: u64,
+}
+
+/// Represents a demuxer of the native FLAC stream of an Ogg FLAC stream
+/// 
+/// The pages of the first Ogg FLAC logical stream are read, and those of any
+/// other logical stream are skipped. Reading from the demuxer gives the native
+/// FLAC stream, that is the `fLaC` marker, the metadata blocks and the audio
+/// frames, so that it can be passed to `FlacDecoder::new`. A packet that lost
+/// some of its pages is dropped, and the decoder then skips to the next frame.
+pub struct OggFlacReader <R: Read> {
+    reader: R,
+    serial: u32,
+    sequence: u32,
+    packet: Vec <u8>,
+    packets: VecDeque <Vec <u8>>,
+    is_eos: bool,
+    out: Vec <u8>,
+    pos: usize,
+}
+
+/// Represents an error in an Ogg FLAC stream
+#[derive(Debug)]
+pub enum OggError {
+    ReadError,
+    CaptureError,
+    CrcError,
+    MappingError,
+    FrameError(FlacDecoderError),
+}
+
+impl OggPage {
+    /// Read the next Ogg page
+    /// 
+    /// Returns `None` at the end of the stream.
+    /// 
+    /// # Errors
+    /// Returns `OggError::CaptureError` if the page does not start with the
+    /// capture pattern or has an unknown version, `OggError::CrcError` if its
+    /// CRC does not match, and `OggError::ReadError` if the stream ends within
+    /// the page or cannot be read.
+    pub fn read(reader: &mut impl Read) -> Result <Option <Self>, OggError> {
+        let mut header = [0u8; PAGE_HEADER_LEN];
+        let num_read = Self::read_full(reader, &mut header)?;
+
+        if num_read == 0 {
+            return Ok(None);
+        }
+        if num_read < PAGE_HEADER_LEN {
+            return Err(OggError::ReadError);
+        }
+        if &header[..4] != CAPTURE_PATTERN || header[4] != 0 {
+            return Err(OggError::CaptureError);
+        }
+
+        let mut lacing_values = vec![0u8; header[26] as usize];
+        reader.read_exact(&mut lacing_values)?;
+
+        let mut data = vec![0u8; lacing_values.iter().map(|&len| len as usize).sum()];
+        reader.read_exact(&mut data)?;
+
+        let page = Self {
+            header_type: header[5],
+            granule_position: u64::from_le_bytes(header[6..14].try_into().unwrap()),
+            serial: u32::from_le_bytes(header[14..18].try_into().unwrap()),
+            sequence: u32::from_le_bytes(header[18..22].try_into().unwrap()),
+            lacing_values,
+            data,
+        };
+
+        if page.build_bytes()[PAGE_CRC_OFFSET..PAGE_CRC_OFFSET + 4] != header[PAGE_CRC_OFFSET..PAGE_CRC_OFFSET + 4] {
+            return Err(OggError::CrcError);
+        }
+
+        Ok(Some(page))
+    }
+
+    /// Convert this page into a vector of bytes, with its CRC
+    /// 
+    /// The CRC is computed over the whole page with the CRC field set to zero.
+    pub fn build_bytes(&self) -> Vec <u8> {
+        let mut bytes = Vec::with_capacity(PAGE_HEADER_LEN + self.lacing_values.len() + self.data.len());
+
+        bytes.extend_from_slice(CAPTURE_PATTERN);
+        bytes.push(0);
+        bytes.push(self.header_type);
+        bytes.extend_from_slice(&self.granule_position.to_le_bytes());
+        bytes.extend_from_slice(&self.serial.to_le_bytes());
+        bytes.extend_from_slice(&self.sequence.to_le_bytes());
+        bytes.extend_from_slice(&[0; 4]);
+        bytes.push(self.lacing_values.len() as u8);
+        bytes.extend_from_slice(&self.lacing_values);
+        bytes.extend_from_slice(&self.data);
+
+        let crc = CrcOptions::new(0x04c1_1db7u32, 32).build_crc32(&bytes);
+        bytes[PAGE_CRC_OFFSET..PAGE_CRC_OFFSET + 4].copy_from_slice(&crc.to_le_bytes());
+
+        bytes
+    }
+
+    /// Get the segments of each packet on this page
+    /// 
+    /// Each packet is returned with whether it ends on this page. The first
+    /// packet is the continuation of a packet of the previous page if the page
+    /// is flagged as such.
+    pub fn packets(&self) -> Vec <(&[u8], bool)> {
+        let mut packets = Vec::new();
+        let mut start = 0;
+        let mut end = 0;
+
+        for &len in &self.lacing_values {
+            end += len as usize;
+
+            if (len as usize) < MAX_SEGMENT_LEN {
+                packets.push((&self.data[start..end], true));
+                start = end;
+            }
+        }
+
+        if self.lacing_values.last().is_some_and(|&len| len as usize == MAX_SEGMENT_LEN) {
+            packets.push((&self.data[start..end], false));
+        }
+
+        packets
+    }
+
+    /// Read until `buf` is full or the stream ends, and return the number of bytes read
+    fn read_full(reader: &mut impl Read, buf: &mut [u8]) -> Result <usize, OggError> {
+        let mut num_read = 0;
+
+        while num_read < buf.len() {
+            match reader.read(&mut buf[num_read..]) {
+                Ok(0) => break,
+                Ok(len) => num_read += len,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {},
+                Err(_) => return Err(OggError::ReadError),
+            }
+        }
+
+        Ok(num_read)
+    }
+}
+
+impl <W: Write> OggFlacWriter <W> {
+    /// Create a new muxer writing an Ogg FLAC logical stream of the given serial number to `out`
+    pub fn new(out: W, serial: u32) -> Self {
+        Self {
+            out,
+            serial,
+            sequence: 0,
+            native: NativeParser::new(),
+            headers: Vec::new(),
+            first_page: None,
+            lacing_values: Vec::new(),
+            data: Vec::new(),
+            is_continued: false,
+            granule_position: NO_GRANULE_POSITION,
+            num_samples: 0,
+            stream_len: 0,
+        }
+    }
+
+    /// Finish the Ogg FLAC stream and return the sink
+    /// 
+    /// The last page is written with the end-of-stream flag, and the STREAMINFO
+    /// block is left as it was written.
+    /// 
+    /// # Errors
+    /// Returns an error of kind `io::ErrorKind::InvalidData` with
+    /// `OggError::MappingError` if the native FLAC stream ended before its
+    /// first audio frame or within an audio frame.
+    pub fn finish(mut self) -> io::Result <W> {
+        self.close()?;
+
+        Ok(self.out)
+    }
+
+    /// Add a packet ending at the given granule position to the pending page
+    /// 
+    /// The pending page is written out whenever it has no segment left, and once
+    /// the packet is added if it holds at least `PAGE_DATA_LEN` bytes.
+    fn write_packet(&mut self, packet: &[u8], granule_position: u64) -> io::Result <()> {
+        let num_segments = packet.len() / MAX_SEGMENT_LEN + 1;
+
+        for i in 0..num_segments {
+            if self.lacing_values.len() == MAX_SEGMENTS {
+                self.write_page(0)?;
+                self.is_continued = i > 0;
+            }
+
+            let segment = &packet[i * MAX_SEGMENT_LEN..packet.len().min((i + 1) * MAX_SEGMENT_LEN)];
+            self.lacing_values.push(segment.len() as u8);
+            self.data.extend_from_slice(segment);
+        }
+
+        self.granule_position = granule_position;
+
+        if self.data.len() >= PAGE_DATA_LEN {
+            self.write_page(0)?;
+        }
+
+        Ok(())
+    }
+
+    /// Write out the pending page with the given header type flags
+    fn write_page(&mut self, flags: u8) -> io::Result <OggPage> {
+        let page = OggPage {
+            header_type: flags | if self.is_continued { CONTINUED_FLAG } else { 0 },
+            granule_position: self.granule_position,
+            serial: self.serial,
+            sequence: self.sequence,
+            lacing_values: std::mem::take(&mut self.lacing_values),
+            data: std::mem::take(&mut self.data),
+        };
+        let bytes = page.build_bytes();
+
+        self.out.write_all(&bytes)?;
+        self.stream_len += bytes.len() as u64;
+        self.sequence += 1;
+        self.granule_position = NO_GRANULE_POSITION;
+        self.is_continued = false;
+
+        Ok(page)
+    }
+
+    /// Write the last page of the stream
+    fn close(&mut self) -> io::Result <()> {
+        if !self.native.is_complete() {
+            return Err(Self::invalid(OggError::MappingError));
+        }
+
+        self.granule_position = self.num_samples;
+        self.write_page(EOS_FLAG)?;
+        self.out.flush()
+    }
+}
+
+impl <W: Write> NativeMuxer for OggFlacWriter <W> {
+    type Error = OggError;
+
+    const MAPPING_ERROR: OggError = OggError::MappingError;
+
+    fn native(&mut self) -> &mut NativeParser {
+        &mut self.native
+    }
+
+    fn headers(&mut self) -> &mut Vec <Vec <u8>> {
+        &mut self.headers
+    }
+
+    /// Write the header packets once all metadata blocks are known
+    /// 
+    /// The first packet is alone on the first page, and the audio frames start on a new page.
+    fn write_headers(&mut self) -> io::Result <()> {
+        let mut packet = Vec::with_capacity(MAPPING_PACKET_LEN);
+        packet.extend_from_slice(MAPPING_SIGNATURE);
+        packet.extend_from_slice(&MAPPING_VERSION);
+        packet.extend_from_slice(&(self.headers.len() as u16 - 1).to_be_bytes());
+        packet.extend_from_slice(b"fLaC");
+        packet.extend_from_slice(&self.headers[0]);
+
+        self.write_packet(&packet, 0)?;
+        self.first_page = Some(self.write_page(BOS_FLAG)?);
+
+        for block in std::mem::take(&mut self.headers).iter().skip(1) {
+            self.write_packet(block, 0)?;
+        }
+
+        if !self.lacing_values.is_empty() {
+            self.write_page(0)?;
+        }
+
+        Ok(())
+    }
+
+    /// Write an audio frame as a packet ending at its last sample
+    fn write_frame(&mut self, frame: &[u8], block_size: u32) -> io::Result <()> {
+        self.num_samples += block_size as u64;
+        self.write_packet(frame, self.num_samples)
+    }
+
+    fn frame_error(err: FlacDecoderError) -> OggError {
+        OggError::FrameError(err)
+    }
+}
+
+impl <W: Write + Seek> ContainerSink for OggFlacWriter <W> {
+    type Sink = W;
+
+    /// Finish the Ogg FLAC stream, patch its STREAMINFO block and return the sink
+    /// 
+    /// The first page is written again with `streaminfo` and a new CRC. The
+    /// stream may start anywhere in the sink, which is left at the end of the
+    /// stream.
+    /// 
+    /// # Errors
+    /// Returns the errors of `finish`.
+    fn finish_and_patch(mut self, streaminfo: &StreamInfo) -> io::Result <W> {
+        self.close()?;
+
+        let Some(mut first_page) = self.first_page.take() else {
+            return Err(Self::invalid(OggError::MappingError));
+        };
+        let end = self.out.stream_position()?;
+
+        first_page.data[MAPPING_PACKET_LEN - STREAMINFO_LEN..].copy_from_slice(&streaminfo.build_bytes());
+        self.out.seek(SeekFrom::Start(end - self.stream_len))?;
+        self.out.write_all(&first_page.build_bytes())?;
+        self.out.seek(SeekFrom::Start(end))?;
+        self.out.flush()?;
+
+        Ok(self.out)
+    }
+}
+
+impl <W: Write> Write for OggFlacWriter <W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result <usize> {
+        self.native.push(buf);
+        self.parse()?;
+
+        Ok(buf.len())
+    }
+
+    /// Write out the pending page, so that a live stream does not wait for a whole page
+    fn flush(&mut self) -> io::Result <()> {
+        if !self.lacing_values.is_empty() {
+            self.write_page(0)?;
+        }
+
+        self.out.flush()
+    }
+}
+
+impl <R: Read> OggFlacReader <R> {
+    /// Create a new demuxer of the first Ogg FLAC logical stream of `reader`
+    /// 
+    /// # Errors
+    /// Returns `OggError::MappingError` if no logical stream starts with the
+    /// first packet of an Ogg FLAC stream of major version 1, and the errors of
+    /// `OggPage::read`.
+    pub fn new(mut reader: R) -> Result <Self, OggError> {
+        loop {
+            let page = OggPage::read(&mut reader)?.ok_or(OggError::MappingError)?;
+
+            if page.header_type & BOS_FLAG == 0 {
+                continue;
+            }
+
+            let Some(&(packet, true)) = page.packets().first() else {
+                continue;
+            };
+
+            if packet.len() < MAPPING_PACKET_LEN || &packet[..5] != MAPPING_SIGNATURE {
+                continue;
+            }
+            if packet[5] != MAPPING_VERSION[0] || &packet[9..13] != b"fLaC" {
+                return Err(OggError::MappingError);
+            }
+
+            let mut packets = VecDeque::new();
+            packets.extend(page.packets().into_iter().skip(1).filter(|&(_, is_complete)| is_complete).map(|(packet, _)| packet.to_vec()));
+
+            return Ok(Self {
+                reader,
+                serial: page.serial,
+                sequence: page.sequence.wrapping_add(1),
+                packet: Vec::new(),
+                packets,
+                is_eos: page.header_type & EOS_FLAG != 0,
+                out: packet[9..].to_vec(),
+                pos: 0,
+            });
+        }
+    }
+
+    /// Get the next packet of the logical stream
+    /// 
+    /// The first packet is not returned, but its `fLaC` marker and STREAMINFO
+    /// block start what is read from the demuxer. Returns `None` at the end of
+    /// the logical stream.
+    /// 
+    /// # Errors
+    /// Returns the errors of `OggPage::read`.
+    pub fn next_packet(&mut self) -> Result <Option <Vec <u8>>, OggError> {
+        loop {
+            if let Some(packet) = self.packets.pop_front() {
+                return Ok(Some(packet));
+            }
+            if self.is_eos {
+                return Ok(None);
+            }
+
+            match OggPage::read(&mut self.reader)? {
+                Some(page) if page.serial == self.serial => self.add_page(&page),
+                Some(_) => {},
+                None => self.is_eos = true,
+            }
+        }
+    }
+
+    /// Add the packets of a page of the logical stream
+    /// 
+    /// If pages were lost, the packet they continued is dropped, and so is the
+    /// end of a packet whose start was lost.
+    fn add_page(&mut self, page: &OggPage) {
+        if page.sequence != self.sequence {
+            self.packet.clear();
+        }
+
+        let mut is_orphan = page.header_type & CONTINUED_FLAG != 0 && self.packet.is_empty();
+
+        for (segments, is_complete) in page.packets() {
+            if !is_orphan {
+                self.packet.extend_from_slice(segments);
+            }
+
+            if is_complete {
+                if !is_orphan {
+                    self.packets.push_back(std::mem::take(&mut self.packet));
+                }
+                is_orphan = false;
+            }
+        }
+
+        self.sequence = page.sequence.wrapping_add(1);
+        self.is_eos = page.header_type & EOS_FLAG != 0;
+    }
+}
+
+impl <R: Read> Read for OggFlacReader <R> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result <usize> {
+        while self.pos == self.out.len() {
+            match self.next_packet() {
+                Ok(Some(packet)) => {
+                    self.out = packet;
+                    self.pos = 0;
+                },
+                Ok(None) => return Ok(0),
+                Err(err) => return Err(io::Error::new(io::ErrorKind::InvalidData, err)),
+            }
+        }
+
+        let num_copied = out.len().min(self.out.len() - self.pos);
+        out[..num_copied].copy_from_slice(&self.out[self.pos..self.pos + num_copied]);
+        self.pos += num_copied;
+
+        Ok(num_copied)
+    }
+}
+
+impl error::Error for OggError {}
+
+impl fmt::Display for OggError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Error: {:?}", self)
+    }
+}
+
+impl From <io::Error> for OggError {
+    fn from(_: io::Error) -> Self {
+        OggError::ReadError
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    use crate::flac::decoder::FlacDecoder;
+    use crate::flac::metadata::MetadataBlock;
+    use crate::wav::PCMWaveFormatChunk;
+
+    /// The frame of the first decoding example of RFC 9639, of one sample of 25588 and 10416
+    const FRAME: [u8; 15] = [0xff, 0xf8, 0x69, 0x18, 0x00, 0x00, 0xbf, 0x03, 0x58, 0xfd, 0x03, 0x12, 0x8b, 0xaa, 0x9a];
+
+    fn native_stream() -> Vec <u8> {
+        let streaminfo = StreamInfo::new(&PCMWaveFormatChunk {
+            num_channels: 2,
+            samp_rate: 44100,
+            bps: 16,
+            channel_mask: None,
+        });
+
+        [
+            &b"fLaC"[..],
+            &streaminfo.build_block().build_bytes(false),
+            &MetadataBlock::padding(300).build_bytes(true),
+            &FRAME,
+            &FRAME,
+        ].concat()
+    }
+
+    #[test]
+    fn sample_page() {
+        let page = OggPage {
+            header_type: CONTINUED_FLAG,
+            granule_position: 4096,
+            serial: 7,
+            sequence: 3,
+            lacing_values: vec![255, 10, 255],
+            data: vec![1; 520],
+        };
+        let mut bytes = page.build_bytes();
+
+        assert_eq!(OggPage::read(&mut &bytes[..]).unwrap(), Some(page.clone()));
+        assert_eq!(page.packets(), vec![(&page.data[..265], true), (&page.data[265..], false)]);
+
+        bytes[40] ^= 1;
+        assert!(matches!(OggPage::read(&mut &bytes[..]), Err(OggError::CrcError)));
+        assert!(matches!(OggPage::read(&mut &bytes[1..]), Err(OggError::CaptureError)));
+        assert!(matches!(OggPage::read(&mut &[][..]), Ok(None)));
+    }
+
+    #[test]
+    fn sample_mux_demux() {
+        let native = native_stream();
+        let mut writer = OggFlacWriter::new(Vec::new(), 0x1234);
+        writer.write_all(&native[..10]).unwrap();
+        writer.write_all(&native[10..]).unwrap();
+        let ogg = writer.finish().unwrap();
+
+        let mut pages = Vec::new();
+        let mut reader = &ogg[..];
+        while let Some(page) = OggPage::read(&mut reader).unwrap() {
+            pages.push(page);
+        }
+
+        assert_eq!(pages.len(), 3);
+        assert_eq!((pages[0].header_type, pages[0].data.len()), (BOS_FLAG, MAPPING_PACKET_LEN));
+        assert_eq!(&pages[0].data[7..9], &[0, 1]);
+        assert_eq!(pages[1].granule_position, 0);
+        assert_eq!((pages[2].header_type, pages[2].granule_position, pages[2].data.len()), (EOS_FLAG, 2, 30));
+
+        let mut demuxed = Vec::new();
+        OggFlacReader::new(&ogg[..]).unwrap().read_to_end(&mut demuxed).unwrap();
+        assert_eq!(demuxed, native);
+
+        let mut decoder = FlacDecoder::new(OggFlacReader::new(&ogg[..]).unwrap()).unwrap();
+        assert_eq!(decoder.next_frame().unwrap().unwrap().sample_block(), vec![vec![25588, 10416]]);
+    }
+
+    #[test]
+    fn sample_patch_and_long_packet() {
+        let native = native_stream();
+        let mut writer = OggFlacWriter::new(Cursor::new(vec![0xee; 5]), 1);
+        writer.out.set_position(5);
+        writer.write_all(&native).unwrap();
+
+        let long_packet: Vec <u8> = (0..70000).map(|i| i as u8).collect();
+        writer.write_packet(&long_packet, 3).unwrap();
+
+        let mut streaminfo = StreamInfo::parse(&native[8..42]).unwrap();
+        streaminfo.total_samples = 3;
+        let ogg = writer.finish_and_patch(&streaminfo).unwrap().into_inner();
+
+        let mut reader = OggFlacReader::new(&ogg[5..]).unwrap();
+        let mut header = vec![0; 42];
+        reader.read_exact(&mut header).unwrap();
+        assert_eq!(StreamInfo::parse(&header[8..]), Some(streaminfo));
+
+        let packets: Vec <Vec <u8>> = std::iter::from_fn(|| reader.next_packet().unwrap()).collect();
+        assert_eq!(packets.len(), 4);
+        assert_eq!(packets[3], long_packet);
+    }
+}
@@ -0,0 +1,156 @@
+use super::FlacDecoderError;
+
+/// Represents a reader of big-endian bit fields from a slice of bytes
+/// 
+/// Reading past the end of the slice fails with
+/// `FlacDecoderError::TruncatedError`, so that the caller can retry once
+/// more bytes are available.
+pub struct BitReader <'a> {
+    data: &'a [u8],
+    bit_pos: usize,
+}
+
+impl <'a> BitReader <'a> {
+    /// Create a new bit reader at the start of `data`
+    pub fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            bit_pos: 0,
+        }
+    }
+
+    /// Get the underlying slice of bytes
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Get the number of whole bytes read so far
+    pub fn byte_pos(&self) -> usize {
+        self.bit_pos.div_ceil(8)
+    }
+
+    /// Skip to the start of the next byte, unless already there
+    pub fn align(&mut self) {
+        self.bit_pos = self.byte_pos() * 8;
+    }
+
+    /// Read an unsigned number of `num_bits` bits, up to 64
+    /// 
+    /// # Errors
+    /// Returns `FlacDecoderError::TruncatedError` if there are not enough bits left.
+    pub fn read_bits(&mut self, num_bits: u32) -> Result <u64, FlacDecoderError> {
+        if self.bit_pos + num_bits as usize > self.data.len() * 8 {
+            return Err(FlacDecoderError::TruncatedError);
+        }
+
+        let mut value = 0u64;
+        let mut num_left = num_bits;
+
+        while num_left > 0 {
+            let byte = self.data[self.bit_pos / 8];
+            let bit_offset = (self.bit_pos % 8) as u32;
+            let num_taken = num_left.min(8 - bit_offset);
+            let bits = (byte >> (8 - bit_offset - num_taken)) & (0xff >> (8 - num_taken));
+
+            value = (value << num_taken) | bits as u64;
+            num_left -= num_taken;
+            self.bit_pos += num_taken as usize;
+        }
+
+        Ok(value)
+    }
+
+    /// Read a two's complement signed number of `num_bits` bits, up to 64
+    /// 
+    /// # Errors
+    /// Returns `FlacDecoderError::TruncatedError` if there are not enough bits left.
+    pub fn read_signed(&mut self, num_bits: u32) -> Result <i64, FlacDecoderError> {
+        let value = self.read_bits(num_bits)?;
+
+        if num_bits == 0 || num_bits == 64 {
+            return Ok(value as i64);
+        }
+
+        let shift = 64 - num_bits;
+
+        Ok(((value << shift) as i64) >> shift)
+    }
+
+    /// Read a unary number, that is the number of `0` bits before the next `1` bit
+    /// 
+    /// # Errors
+    /// Returns `FlacDecoderError::TruncatedError` if there is no `1` bit left.
+    pub fn read_unary(&mut self) -> Result <u64, FlacDecoderError> {
+        let mut value = 0;
+
+        while self.read_bits(1)? == 0 {
+            value += 1;
+        }
+
+        Ok(value)
+    }
+
+    /// Read a number in the extended UTF-8 encoding of FLAC frame headers
+    /// 
+    /// The inverse of `Utf8Encoder::encode`, reading up to 7 bytes for a
+    /// number of up to 36 bits.
+    /// 
+    /// # Errors
+    /// Returns `FlacDecoderError::HeaderError` if the bytes are not a valid
+    /// coded number.
+    pub fn read_utf8(&mut self) -> Result <u64, FlacDecoderError> {
+        let first = self.read_bits(8)? as u8;
+        let num_extra = match first.leading_ones() {
+            0 => return Ok(first as u64),
+            1 | 8 => return Err(FlacDecoderError::HeaderError),
+            n => n as usize - 1,
+        };
+
+        let mut value = (first & (0x7f >> (num_extra + 1))) as u64;
+
+        for _ in 0..num_extra {
+            let byte = self.read_bits(8)?;
+
+            if byte & 0xc0 != 0x80 {
+                return Err(FlacDecoderError::HeaderError);
+            }
+
+            value = (value << 6) | (byte & 0x3f);
+        }
+
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flac::encoder::utf8::Utf8Encoder;
+
+    #[test]
+    fn sample_read_bits() {
+        let data = [0b1011_0011, 0b0101_1111, 0xff];
+        let mut reader = BitReader::new(&data);
+
+        assert_eq!(reader.read_bits(3).unwrap(), 0b101);
+        assert_eq!(reader.read_signed(7).unwrap(), -51);
+        assert_eq!(reader.read_unary().unwrap(), 1);
+        assert_eq!(reader.byte_pos(), 2);
+
+        reader.align();
+        assert_eq!(reader.read_bits(8).unwrap(), 0xff);
+        assert!(matches!(reader.read_bits(1), Err(FlacDecoderError::TruncatedError)));
+    }
+
+    #[test]
+    fn sample_read_utf8() {
+        for num in [0, 0x7f, 0x164, 0xffff, 0x4000000, 0xfffffffff] {
+            let bytes = Utf8Encoder::encode(num);
+
+            assert_eq!(BitReader::new(&bytes).read_utf8().unwrap(), num);
+        }
+
+        assert!(matches!(BitReader::new(&[0x80]).read_utf8(), Err(FlacDecoderError::HeaderError)));
+        assert!(matches!(BitReader::new(&[0xc5, 0x24]).read_utf8(), Err(FlacDecoderError::HeaderError)));
+    }
+}
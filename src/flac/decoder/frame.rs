@@ -0,0 +1,403 @@
+use crate::flac::FRAME_SYNC_CODE;
+use crate::flac::encoder::crc::CrcOptions;
+use crate::flac::encoder::stereo::ChannelAssignment;
+use crate::flac::metadata::streaminfo::StreamInfo;
+
+use super::bitreader::BitReader;
+use super::FlacDecoderError;
+
+/// Represents the header of an audio frame
+/// 
+/// `number` is the frame number for fixed block-size streams and the number
+/// of the first sample of the frame for variable block-size streams. Values
+/// coded as "from STREAMINFO" in the header are filled in from STREAMINFO.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameHeader {
+    pub is_variable_blocksize: bool,
+    pub block_size: u32,
+    pub sample_rate: u32,
+    pub channel_assignment: ChannelAssignment,
+    pub num_channels: u8,
+    pub bit_depth: u8,
+    pub number: u64,
+}
+
+/// Represents a decoded audio frame
+/// 
+/// `channels` holds the samples of each channel, after undoing the stereo
+/// decorrelation of the frame.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodedFrame {
+    pub header: FrameHeader,
+    pub channels: Vec <Vec <i64>>,
+}
+
+impl FrameHeader {
+    /// Read a frame header, including its CRC-8
+    /// 
+    /// # Errors
+    /// Returns `FlacDecoderError::SyncError` if the header does not start with
+    /// the sync code, `FlacDecoderError::HeaderError` if it holds a reserved
+    /// value or its CRC-8 does not match, and `FlacDecoderError::TruncatedError`
+    /// if `reader` ends within the header.
+    pub fn read(reader: &mut BitReader, streaminfo: &StreamInfo) -> Result <Self, FlacDecoderError> {
+        if reader.read_bits(15)? != FRAME_SYNC_CODE {
+            return Err(FlacDecoderError::SyncError);
+        }
+
+        let is_variable_blocksize = reader.read_bits(1)? == 1;
+        let block_size_code = reader.read_bits(4)?;
+        let sample_rate_code = reader.read_bits(4)?;
+        let (channel_assignment, num_channels) = ChannelAssignment::from_value(reader.read_bits(4)? as u8)
+            .ok_or(FlacDecoderError::HeaderError)?;
+        let bit_depth = match reader.read_bits(3)? {
+            0 => streaminfo.bps,
+            1 => 8,
+            2 => 12,
+            4 => 16,
+            5 => 20,
+            6 => 24,
+            7 => 32,
+            _ => return Err(FlacDecoderError::HeaderError),
+        };
+
+        if reader.read_bits(1)? != 0 {
+            return Err(FlacDecoderError::HeaderError);
+        }
+
+        let number = reader.read_utf8()?;
+
+        let block_size = match block_size_code {
+            1 => 192,
+            2..=5 => 576 << (block_size_code - 2),
+            6 => reader.read_bits(8)? as u32 + 1,
+            7 => reader.read_bits(16)? as u32 + 1,
+            8..=15 => 256 << (block_size_code - 8),
+            _ => return Err(FlacDecoderError::HeaderError),
+        };
+
+        let sample_rate = match sample_rate_code {
+            0 => streaminfo.sample_rate,
+            1 => 88200,
+            2 => 176400,
+            3 => 192000,
+            4 => 8000,
+            5 => 16000,
+            6 => 22050,
+            7 => 24000,
+            8 => 32000,
+            9 => 44100,
+            10 => 48000,
+            11 => 96000,
+            12 => reader.read_bits(8)? as u32 * 1000,
+            13 => reader.read_bits(16)? as u32,
+            14 => reader.read_bits(16)? as u32 * 10,
+            _ => return Err(FlacDecoderError::HeaderError),
+        };
+
+        let header_len = reader.byte_pos();
+        let crc = reader.read_bits(8)? as u8;
+
+        if CrcOptions::new(0b0000_0111u8, 8).build_crc8(&reader.data()[..header_len]) != crc {
+            return Err(FlacDecoderError::HeaderError);
+        }
+
+        Ok(Self {
+            is_variable_blocksize,
+            block_size,
+            sample_rate,
+            channel_assignment,
+            num_channels,
+            bit_depth,
+            number,
+        })
+    }
+}
+
+impl DecodedFrame {
+    /// Decode the audio frame at the start of `data`
+    /// 
+    /// Returns the frame and its length in bytes, including the CRC-16.
+    /// 
+    /// # Errors
+    /// Returns the errors of `FrameHeader::read`, `FlacDecoderError::SubframeError`
+    /// or `FlacDecoderError::ResidualError` if a subframe is malformed,
+    /// `FlacDecoderError::CrcError` if the CRC-16 does not match, and
+    /// `FlacDecoderError::TruncatedError` if `data` ends within the frame.
+    pub fn decode(data: &[u8], streaminfo: &StreamInfo) -> Result <(Self, usize), FlacDecoderError> {
+        let mut reader = BitReader::new(data);
+        let header = FrameHeader::read(&mut reader, streaminfo)?;
+
+        let subframes = header.channel_assignment
+            .bit_depths(header.bit_depth, header.num_channels)
+            .into_iter()
+            .map(|bit_depth| Self::decode_subframe(&mut reader, header.block_size as usize, bit_depth as u32))
+            .collect::<Result <Vec <Vec <i64>>, FlacDecoderError>>()?;
+
+        reader.align();
+        let frame_len = reader.byte_pos();
+        let crc = reader.read_bits(16)? as u16;
+        let crc_data = data[..frame_len].iter().map(|&byte| byte as u16).collect::<Vec <u16>>();
+
+        if CrcOptions::new(0b1000_0000_0000_0101u16, 16).build_crc16(&crc_data) != crc {
+            return Err(FlacDecoderError::CrcError);
+        }
+
+        let sample_block = header.channel_assignment.join(&subframes);
+        let channels = ChannelAssignment::Independent.split(&sample_block);
+
+        Ok((Self {
+            header,
+            channels,
+        }, frame_len + 2))
+    }
+
+    /// Get the samples of this frame as a block of inter-channel samples
+    pub fn sample_block(&self) -> Vec <Vec <i64>> {
+        ChannelAssignment::Independent.join(&self.channels)
+    }
+
+    /// Decode a subframe of `block_size` samples of `bit_depth` bits
+    /// 
+    /// The subframe header is a zero bit, the 6-bit subframe type and the
+    /// wasted bits flag, followed by the unary-coded number of wasted bits
+    /// minus one if the flag is set.
+    fn decode_subframe(reader: &mut BitReader, block_size: usize, bit_depth: u32) -> Result <Vec <i64>, FlacDecoderError> {
+        if reader.read_bits(1)? != 0 {
+            return Err(FlacDecoderError::SubframeError);
+        }
+
+        let subframe_type = reader.read_bits(6)?;
+        let wasted_bits = match reader.read_bits(1)? {
+            1 => reader.read_unary()? as u32 + 1,
+            _ => 0,
+        };
+
+        if wasted_bits >= bit_depth {
+            return Err(FlacDecoderError::SubframeError);
+        }
+
+        let bit_depth = bit_depth - wasted_bits;
+
+        let mut samples = match subframe_type {
+            0 => vec![reader.read_signed(bit_depth)?; block_size],
+            1 => (0..block_size)
+                .map(|_| reader.read_signed(bit_depth))
+                .collect::<Result <Vec <i64>, FlacDecoderError>>()?,
+            8..=12 => Self::decode_fixed(reader, block_size, bit_depth, subframe_type as usize - 8)?,
+            32..=63 => Self::decode_lpc(reader, block_size, bit_depth, subframe_type as usize - 31)?,
+            _ => return Err(FlacDecoderError::SubframeError),
+        };
+
+        if wasted_bits > 0 {
+            for sample in &mut samples {
+                *sample <<= wasted_bits;
+            }
+        }
+
+        Ok(samples)
+    }
+
+    /// Decode a FIXED subframe of the given predictor order
+    fn decode_fixed(reader: &mut BitReader, block_size: usize, bit_depth: u32, order: usize) -> Result <Vec <i64>, FlacDecoderError> {
+        const COEFS: [&[i64]; 5] = [&[], &[1], &[2, -1], &[3, -3, 1], &[4, -6, 4, -1]];
+
+        let mut samples = Self::read_warmup(reader, block_size, bit_depth, order)?;
+        let residuals = Self::decode_residual(reader, block_size, order)?;
+
+        Self::restore(&mut samples, &residuals, COEFS[order], 0);
+
+        Ok(samples)
+    }
+
+    /// Decode an LPC subframe of the given predictor order
+    /// 
+    /// The warm-up samples are followed by the 4-bit precision of the
+    /// coefficients minus one, the 5-bit signed right shift of the prediction
+    /// and the coefficients themselves.
+    fn decode_lpc(reader: &mut BitReader, block_size: usize, bit_depth: u32, order: usize) -> Result <Vec <i64>, FlacDecoderError> {
+        let mut samples = Self::read_warmup(reader, block_size, bit_depth, order)?;
+
+        let precision = reader.read_bits(4)? as u32 + 1;
+        if precision == 16 {
+            return Err(FlacDecoderError::SubframeError);
+        }
+
+        let shift = reader.read_signed(5)?;
+        if shift < 0 {
+            return Err(FlacDecoderError::SubframeError);
+        }
+
+        let coefs = (0..order)
+            .map(|_| reader.read_signed(precision))
+            .collect::<Result <Vec <i64>, FlacDecoderError>>()?;
+        let residuals = Self::decode_residual(reader, block_size, order)?;
+
+        Self::restore(&mut samples, &residuals, &coefs, shift as u32);
+
+        Ok(samples)
+    }
+
+    /// Read the unencoded warm-up samples of a predicted subframe
+    fn read_warmup(reader: &mut BitReader, block_size: usize, bit_depth: u32, order: usize) -> Result <Vec <i64>, FlacDecoderError> {
+        if order > block_size {
+            return Err(FlacDecoderError::SubframeError);
+        }
+
+        let mut samples = Vec::with_capacity(block_size);
+
+        for _ in 0..order {
+            samples.push(reader.read_signed(bit_depth)?);
+        }
+
+        Ok(samples)
+    }
+
+    /// Restore samples from their residuals by adding back the prediction
+    /// 
+    /// Each prediction is the sum of `coefs[j] * samples[i - 1 - j]`, shifted
+    /// right by `shift` bits.
+    fn restore(samples: &mut Vec <i64>, residuals: &[i64], coefs: &[i64], shift: u32) {
+        for residual in residuals {
+            let n = samples.len();
+            let prediction: i64 = coefs
+                .iter()
+                .enumerate()
+                .map(|(j, coef)| coef * samples[n - 1 - j])
+                .sum();
+
+            samples.push((prediction >> shift) + residual);
+        }
+    }
+
+    /// Decode the partitioned Rice-coded residuals of a predicted subframe
+    /// 
+    /// The 2-bit coding method selects 4-bit or 5-bit Rice parameters and is
+    /// followed by the 4-bit partition order. The first partition holds
+    /// `order` fewer residuals than the others. A partition whose parameter
+    /// has all bits set holds unencoded residuals of the 5-bit width that
+    /// follows instead.
+    fn decode_residual(reader: &mut BitReader, block_size: usize, order: usize) -> Result <Vec <i64>, FlacDecoderError> {
+        let param_len = match reader.read_bits(2)? {
+            0 => 4,
+            1 => 5,
+            _ => return Err(FlacDecoderError::ResidualError),
+        };
+        let escape_param = (1 << param_len) - 1;
+
+        let partition_order = reader.read_bits(4)?;
+        let partition_len = block_size >> partition_order;
+
+        if partition_len << partition_order != block_size || partition_len < order {
+            return Err(FlacDecoderError::ResidualError);
+        }
+
+        let mut residuals = Vec::with_capacity(block_size - order);
+
+        for partition in 0..(1usize << partition_order) {
+            let num_residuals = if partition == 0 { partition_len - order } else { partition_len };
+            let param = reader.read_bits(param_len)?;
+
+            if param == escape_param {
+                let raw_len = reader.read_bits(5)? as u32;
+
+                for _ in 0..num_residuals {
+                    residuals.push(reader.read_signed(raw_len)?);
+                }
+            }
+            else {
+                for _ in 0..num_residuals {
+                    let quotient = reader.read_unary()?;
+                    let value = (quotient << param) | reader.read_bits(param as u32)?;
+
+                    residuals.push((value >> 1) as i64 ^ -((value & 1) as i64));
+                }
+            }
+        }
+
+        Ok(residuals)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn streaminfo(bps: u8) -> StreamInfo {
+        StreamInfo {
+            min_block_size: 16,
+            max_block_size: 16,
+            min_frame_size: 0,
+            max_frame_size: 0,
+            sample_rate: 44100,
+            num_channels: 2,
+            bps,
+            total_samples: 0,
+            md5: [0; 16],
+        }
+    }
+
+    fn hex(text: &str) -> Vec <u8> {
+        (0..text.len()).step_by(2).map(|i| u8::from_str_radix(&text[i..i + 2], 16).unwrap()).collect()
+    }
+
+    #[test]
+    fn sample_verbatim_wasted_bits() {
+        // The frame of the first decoding example of RFC 9639
+        let data = hex("fff869180000bf0358fd03128baa9a");
+        let (frame, len) = DecodedFrame::decode(&data, &streaminfo(16)).unwrap();
+
+        assert_eq!(len, data.len());
+        assert_eq!(frame.header.block_size, 1);
+        assert_eq!(frame.header.sample_rate, 44100);
+        assert_eq!(frame.header.bit_depth, 16);
+        assert_eq!(frame.channels, vec![vec![25588], vec![10416]]);
+    }
+
+    #[test]
+    fn sample_fixed_lpc_mid_side() {
+        // Mid channel: FIXED order 2 with two Rice partitions. Side channel:
+        // LPC order 2 with 5-bit parameters and an escaped first partition.
+        let data = hex("fff869a8010f6d14005f007f04d8305e01e128b996f0ba0e3684000a0002da9ccf3351f989e09f7b10461c30e430e1800802");
+        let (frame, len) = DecodedFrame::decode(&data, &streaminfo(16)).unwrap();
+
+        assert_eq!(len, data.len());
+        assert_eq!(frame.header.channel_assignment, ChannelAssignment::MidSide);
+        assert_eq!(frame.header.number, 1);
+        assert_eq!(frame.channels, vec![
+            vec![100, 130, 170, 220, 260, 300, 310, 305, 290, 250, 200, 150, 90, 40, 0, -50],
+            vec![90, 125, 160, 215, 250, 290, 305, 300, 280, 245, 190, 145, 85, 30, -5, -60],
+        ]);
+    }
+
+    #[test]
+    fn sample_constant_left_side() {
+        // Variable block size, 8 kHz and 24 bits from the header, a CONSTANT
+        // left channel with 3 wasted bits and a VERBATIM side channel
+        let data = hex("fff9748c1000037e0120000702fffe280001083ffd9608000380b83f");
+        let (frame, _) = DecodedFrame::decode(&data, &streaminfo(16)).unwrap();
+
+        assert!(frame.header.is_variable_blocksize);
+        assert_eq!(frame.header.number, 16);
+        assert_eq!(frame.header.sample_rate, 8000);
+        assert_eq!(frame.header.bit_depth, 24);
+        assert_eq!(frame.sample_block(), vec![vec![56, 1000], vec![56, -1000], vec![56, 5000], vec![56, -8388608]]);
+    }
+
+    #[test]
+    fn sample_errors() {
+        let data = hex("fff869180000bf0358fd03128baa9a");
+        let info = streaminfo(16);
+
+        assert!(matches!(DecodedFrame::decode(&data[..10], &info), Err(FlacDecoderError::TruncatedError)));
+        assert!(matches!(DecodedFrame::decode(&data[1..], &info), Err(FlacDecoderError::SyncError)));
+
+        let mut bad_header = data.clone();
+        bad_header[4] ^= 1;
+        assert!(matches!(DecodedFrame::decode(&bad_header, &info), Err(FlacDecoderError::HeaderError)));
+
+        let mut bad_audio = data.clone();
+        bad_audio[8] ^= 1;
+        assert!(matches!(DecodedFrame::decode(&bad_audio, &info), Err(FlacDecoderError::CrcError)));
+    }
+}
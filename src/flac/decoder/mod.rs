@@ -0,0 +1,508 @@
+pub mod bitreader;
+pub mod frame;
+pub mod verify;
+
+use core::fmt;
+use std::error;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+
+use crate::wav::{PCMWaveFormatChunk, WaveWriter, WaveWriterError};
+
+use super::container::ogg::{OggError, OggFlacReader};
+use super::metadata::{MetadataBlock, MetadataError};
+use super::metadata::foreign::{ForeignMetadata, ForeignMetadataError};
+use super::metadata::streaminfo::StreamInfo;
+
+use self::frame::DecodedFrame;
+
+/// Number of bytes read from the stream at a time
+const READ_LEN: usize = 1 << 16;
+
+/// Represents a decoder of FLAC streams
+/// 
+/// The metadata blocks are read when the decoder is created, and the audio
+/// frames are then decoded one at a time, either with `next_frame` or by
+/// iterating over the decoder. Bytes that do not start a valid frame header
+/// are skipped until the next frame sync code. The MD5 signature of the
+/// decoded samples is computed along the way and checked by `finish`.
+pub struct FlacDecoder <R: Read> {
+    reader: R,
+    blocks: Vec <MetadataBlock>,
+    streaminfo: StreamInfo,
+    buf: Vec <u8>,
+    pos: usize,
+    is_eof: bool,
+    md5: md5::Context,
+    num_samples: u64,
+}
+
+/// Represents an error in the FLAC decoder
+#[derive(Debug)]
+pub enum FlacDecoderError {
+    ReadError,
+    WriteError,
+    NotFlacError,
+    MetadataError,
+    StreaminfoError,
+    SyncError,
+    HeaderError,
+    SubframeError,
+    ResidualError,
+    CrcError,
+    TruncatedError,
+    Md5Error,
+    ForeignMetadataError,
+    OggError,
+}
+
+/// Represents the decoded audio of a FLAC stream as the bytes of a WAV `data` chunk
+struct PCMBytes <'a, R: Read> {
+    decoder: &'a mut FlacDecoder <R>,
+    format: PCMWaveFormatChunk,
+    buf: Vec <u8>,
+    pos: usize,
+    error: Option <FlacDecoderError>,
+}
+
+impl FlacDecoder <BufReader <File>> {
+    /// Open a FLAC file for decoding
+    /// 
+    /// # Errors
+    /// Returns the errors of `FlacDecoder::new`.
+    pub fn open(file_path: &str) -> Result <Self, FlacDecoderError> {
+        Self::new(BufReader::new(File::open(file_path)?))
+    }
+}
+
+impl FlacDecoder <OggFlacReader <BufReader <File>>> {
+    /// Open an Ogg FLAC file for decoding
+    /// 
+    /// The audio frames are those of the first Ogg FLAC logical stream, as
+    /// demuxed by `OggFlacReader`.
+    /// 
+    /// # Errors
+    /// Returns `FlacDecoderError::OggError` if the file holds no Ogg FLAC
+    /// stream, and the errors of `FlacDecoder::new`.
+    pub fn open_ogg(file_path: &str) -> Result <Self, FlacDecoderError> {
+        Self::new(OggFlacReader::new(BufReader::new(File::open(file_path)?))?)
+    }
+}
+
+impl <R: Read> FlacDecoder <R> {
+    /// Create a new decoder of the FLAC stream of `reader`
+    /// 
+    /// # Errors
+    /// Returns `FlacDecoderError::NotFlacError` if the stream does not start
+    /// with the `fLaC` marker, `FlacDecoderError::StreaminfoError` if it does
+    /// not start with a valid STREAMINFO block, and `FlacDecoderError::MetadataError`
+    /// if the metadata is otherwise malformed.
+    pub fn new(mut reader: R) -> Result <Self, FlacDecoderError> {
+        let blocks = MetadataBlock::read_all(&mut reader)?;
+        let streaminfo = StreamInfo::parse(&blocks[0].data).ok_or(FlacDecoderError::StreaminfoError)?;
+
+        Ok(Self {
+            reader,
+            blocks,
+            streaminfo,
+            buf: Vec::new(),
+            pos: 0,
+            is_eof: false,
+            md5: md5::Context::new(),
+            num_samples: 0,
+        })
+    }
+
+    /// Get the metadata blocks of the stream
+    pub fn blocks(&self) -> &Vec <MetadataBlock> {
+        &self.blocks
+    }
+
+    /// Get the STREAMINFO block of the stream
+    pub fn streaminfo(&self) -> &StreamInfo {
+        &self.streaminfo
+    }
+
+    /// Get the PCM format of the decoded audio
+    pub fn format(&self) -> PCMWaveFormatChunk {
+        PCMWaveFormatChunk {
+            num_channels: self.streaminfo.num_channels as u16,
+            samp_rate: self.streaminfo.sample_rate,
+            bps: self.streaminfo.bps as u16,
+            channel_mask: None,
+        }
+    }
+
+    /// Decode the next audio frame
+    /// 
+    /// Returns `None` at the end of the stream.
+    /// 
+    /// # Errors
+    /// Returns `FlacDecoderError::CrcError`, `FlacDecoderError::SubframeError`
+    /// or `FlacDecoderError::ResidualError` if a frame is corrupt,
+    /// `FlacDecoderError::TruncatedError` if the stream ends within a frame,
+    /// and `FlacDecoderError::ReadError` if the stream cannot be read.
+    pub fn next_frame(&mut self) -> Result <Option <DecodedFrame>, FlacDecoderError> {
+        loop {
+            if self.buf.len() - self.pos < READ_LEN && !self.is_eof {
+                self.fill()?;
+                continue;
+            }
+
+            if !self.find_sync() {
+                if self.is_eof {
+                    return Ok(None);
+                }
+
+                self.fill()?;
+                continue;
+            }
+
+            match DecodedFrame::decode(&self.buf[self.pos..], &self.streaminfo) {
+                Ok((frame, frame_len)) => {
+                    self.pos += frame_len;
+                    self.update_md5(&frame);
+
+                    return Ok(Some(frame));
+                },
+                Err(FlacDecoderError::TruncatedError) if !self.is_eof => self.fill()?,
+                // A false sync code, or a frame header damaged beyond use
+                Err(FlacDecoderError::SyncError | FlacDecoderError::HeaderError) => self.pos += 1,
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// Finish decoding and check the decoded audio against STREAMINFO
+    /// 
+    /// The check is skipped for the values that STREAMINFO leaves unknown.
+    /// 
+    /// # Errors
+    /// Returns `FlacDecoderError::TruncatedError` if fewer samples were decoded
+    /// than STREAMINFO holds, and `FlacDecoderError::Md5Error` if the MD5
+    /// signature of the decoded samples does not match.
+    pub fn finish(self) -> Result <(), FlacDecoderError> {
+        if self.streaminfo.total_samples != 0 && self.num_samples != self.streaminfo.total_samples {
+            return Err(FlacDecoderError::TruncatedError);
+        }
+
+        if self.streaminfo.md5 != [0; 16] && self.md5.compute().0 != self.streaminfo.md5 {
+            return Err(FlacDecoderError::Md5Error);
+        }
+
+        Ok(())
+    }
+
+    /// Move to the next frame sync code in the buffer
+    /// 
+    /// Returns `false` if there is none, keeping a last byte that may start one.
+    fn find_sync(&mut self) -> bool {
+        let sync = self.buf[self.pos..]
+            .windows(2)
+            .position(|pair| pair[0] == 0xff && pair[1] & 0xfe == 0xf8);
+
+        match sync {
+            Some(offset) => {
+                self.pos += offset;
+                true
+            },
+            None => {
+                self.pos = self.buf.len().saturating_sub(1).max(self.pos);
+                false
+            },
+        }
+    }
+
+    /// Drop the bytes before the current position and read more of the stream
+    fn fill(&mut self) -> Result <(), FlacDecoderError> {
+        self.buf.drain(..self.pos);
+        self.pos = 0;
+
+        let num_read = self.reader.by_ref().take(READ_LEN as u64).read_to_end(&mut self.buf)?;
+        if num_read == 0 {
+            self.is_eof = true;
+        }
+
+        Ok(())
+    }
+
+    /// Add the samples of a decoded frame to the MD5 signature
+    /// 
+    /// The samples are interleaved, signed and little-endian, as in
+    /// `StreamInfoCollector`.
+    fn update_md5(&mut self, frame: &DecodedFrame) {
+        let sample_bytes = (self.streaminfo.bps as usize).div_ceil(8);
+        let sample_block = frame.sample_block();
+        let mut buf = Vec::with_capacity(sample_block.len() * frame.channels.len() * sample_bytes);
+
+        for inter_sample in &sample_block {
+            for sample in inter_sample {
+                buf.extend_from_slice(&sample.to_le_bytes()[..sample_bytes]);
+            }
+        }
+
+        self.md5.consume(&buf);
+        self.num_samples += sample_block.len() as u64;
+    }
+}
+
+impl <R: Read> Iterator for FlacDecoder <R> {
+    type Item = Result <DecodedFrame, FlacDecoderError>;
+
+    fn next(&mut self) -> Option <Self::Item> {
+        self.next_frame().transpose()
+    }
+}
+
+impl <'a, R: Read> PCMBytes <'a, R> {
+    fn new(decoder: &'a mut FlacDecoder <R>) -> Self {
+        let format = decoder.format();
+
+        Self {
+            decoder,
+            format,
+            buf: Vec::new(),
+            pos: 0,
+            error: None,
+        }
+    }
+}
+
+impl <R: Read> Read for PCMBytes <'_, R> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result <usize> {
+        while self.pos == self.buf.len() {
+            match self.decoder.next_frame() {
+                Ok(Some(frame)) => {
+                    self.buf = WaveWriter::build_sample_bytes(&self.format, &frame.sample_block());
+                    self.pos = 0;
+                },
+                Ok(None) => return Ok(0),
+                Err(err) => {
+                    self.error = Some(err);
+                    return Err(io::Error::from(io::ErrorKind::InvalidData));
+                },
+            }
+        }
+
+        let num_copied = out.len().min(self.buf.len() - self.pos);
+        out[..num_copied].copy_from_slice(&self.buf[self.pos..self.pos + num_copied]);
+        self.pos += num_copied;
+
+        Ok(num_copied)
+    }
+}
+
+/// Decode a FLAC file into a PCM WAV file
+/// 
+/// If the FLAC file holds the foreign metadata of a WAV file, as kept by
+/// `flac --keep-foreign-metadata`, the original WAV file is rebuilt byte
+/// for byte. Otherwise, a plain WAV file is written by `WaveWriter`.
+/// 
+/// # Errors
+/// Returns the errors of `FlacDecoder::next_frame` and `FlacDecoder::finish`,
+/// `FlacDecoderError::ForeignMetadataError` if the foreign metadata is
+/// malformed or does not match the audio, and `FlacDecoderError::WriteError`
+/// if the WAV file cannot be written.
+pub fn decode_to_wave(flac_path: &str, wav_path: &str) -> Result <(), FlacDecoderError> {
+    let mut decoder = FlacDecoder::open(flac_path)?;
+
+    match ForeignMetadata::from_blocks(decoder.blocks())? {
+        Some(foreign) => {
+            let mut out = BufWriter::new(File::create(wav_path).map_err(|_| FlacDecoderError::WriteError)?);
+            let mut audio = PCMBytes::new(&mut decoder);
+            let result = foreign.write_wave(&mut out, &mut audio);
+
+            if let Some(err) = audio.error.take() {
+                return Err(err);
+            }
+            result?;
+            out.flush().map_err(|_| FlacDecoderError::WriteError)?;
+        },
+        None => {
+            let mut writer = WaveWriter::create(wav_path, &decoder.format())?;
+
+            while let Some(frame) = decoder.next_frame()? {
+                writer.write_block(&frame.sample_block())?;
+            }
+
+            writer.finish()?;
+        },
+    }
+
+    decoder.finish()
+}
+
+impl error::Error for FlacDecoderError {}
+
+impl fmt::Display for FlacDecoderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Error: {:?}", self)
+    }
+}
+
+impl From <io::Error> for FlacDecoderError {
+    fn from(_: io::Error) -> Self {
+        FlacDecoderError::ReadError
+    }
+}
+
+impl From <MetadataError> for FlacDecoderError {
+    fn from(err: MetadataError) -> Self {
+        match err {
+            MetadataError::ReadError => FlacDecoderError::ReadError,
+            MetadataError::NotFlacError => FlacDecoderError::NotFlacError,
+            MetadataError::StreaminfoError => FlacDecoderError::StreaminfoError,
+            _ => FlacDecoderError::MetadataError,
+        }
+    }
+}
+
+impl From <ForeignMetadataError> for FlacDecoderError {
+    fn from(err: ForeignMetadataError) -> Self {
+        match err {
+            ForeignMetadataError::WriteError => FlacDecoderError::WriteError,
+            _ => FlacDecoderError::ForeignMetadataError,
+        }
+    }
+}
+
+impl From <OggError> for FlacDecoderError {
+    fn from(err: OggError) -> Self {
+        match err {
+            OggError::ReadError => FlacDecoderError::ReadError,
+            _ => FlacDecoderError::OggError,
+        }
+    }
+}
+
+impl From <WaveWriterError> for FlacDecoderError {
+    fn from(_: WaveWriterError) -> Self {
+        FlacDecoderError::WriteError
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use crate::flac::metadata::MetadataBlockType;
+    use crate::flac::metadata::foreign::RIFF_APPLICATION_ID;
+    use crate::flac::metadata::application::ApplicationBlock;
+
+    /// The frame of the first decoding example of RFC 9639, of one sample of 25588 and 10416
+    const FRAME: [u8; 15] = [0xff, 0xf8, 0x69, 0x18, 0x00, 0x00, 0xbf, 0x03, 0x58, 0xfd, 0x03, 0x12, 0x8b, 0xaa, 0x9a];
+
+    fn stream_bytes(md5: [u8; 16], extra_blocks: &[MetadataBlock], audio: &[u8]) -> Vec <u8> {
+        let info = StreamInfo {
+            min_block_size: 1,
+            max_block_size: 1,
+            min_frame_size: 0,
+            max_frame_size: 0,
+            sample_rate: 44100,
+            num_channels: 2,
+            bps: 16,
+            total_samples: 0,
+            md5,
+        };
+
+        let mut blocks = vec![info.build_block()];
+        blocks.extend_from_slice(extra_blocks);
+
+        let mut bytes = b"fLaC".to_vec();
+        for (i, block) in blocks.iter().enumerate() {
+            bytes.extend(block.build_bytes(i + 1 == blocks.len()));
+        }
+        bytes.extend_from_slice(audio);
+
+        bytes
+    }
+
+    fn md5_of(bytes: &[u8]) -> [u8; 16] {
+        md5::compute(bytes).0
+    }
+
+    #[test]
+    fn sample_decode_stream() -> Result <(), FlacDecoderError> {
+        // Garbage and a false sync code between the frames are skipped
+        let audio = [&FRAME[..], &[0x00, 0xff, 0xf8, 0x00], &FRAME[..]].concat();
+        let md5 = md5_of(&[0xf4, 0x63, 0xb0, 0x28, 0xf4, 0x63, 0xb0, 0x28]);
+        let bytes = stream_bytes(md5, &[], &audio);
+        let mut decoder = FlacDecoder::new(&bytes[..])?;
+
+        assert_eq!(decoder.streaminfo().sample_rate, 44100);
+
+        let blocks = decoder
+            .by_ref()
+            .map(|frame| frame.map(|frame| frame.sample_block()))
+            .collect::<Result <Vec <_>, _>>()?;
+        assert_eq!(blocks, vec![vec![vec![25588, 10416]]; 2]);
+
+        decoder.finish()
+    }
+
+    #[test]
+    fn sample_decode_errors() -> Result <(), FlacDecoderError> {
+        let bytes = stream_bytes([1; 16], &[], &FRAME);
+        let mut decoder = FlacDecoder::new(&bytes[..])?;
+        decoder.next_frame()?;
+        assert!(matches!(decoder.finish(), Err(FlacDecoderError::Md5Error)));
+
+        let bytes = stream_bytes([0; 16], &[], &FRAME[..12]);
+        let mut decoder = FlacDecoder::new(&bytes[..])?;
+        assert!(matches!(decoder.next_frame(), Err(FlacDecoderError::TruncatedError)));
+
+        assert!(matches!(FlacDecoder::new(&b"RIFF\0\0\0\0"[..]), Err(FlacDecoderError::NotFlacError)));
+
+        let bytes = [&b"fLaC"[..], &MetadataBlock::new(MetadataBlockType::Streaminfo, vec![0; 8]).build_bytes(true)].concat();
+        assert!(matches!(FlacDecoder::new(&bytes[..]), Err(FlacDecoderError::StreaminfoError)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn sample_decode_to_wave() -> Result <(), FlacDecoderError> {
+        let flac_name = "midp_sample_decode_to_wave.flac.part";
+        let wav_name = "midp_sample_decode_to_wave.wav.part";
+
+        fs::write(flac_name, stream_bytes([0; 16], &[], &FRAME))?;
+        let result = decode_to_wave(flac_name, wav_name);
+        let wav_bytes = fs::read(wav_name);
+        fs::remove_file(flac_name)?;
+        fs::remove_file(wav_name)?;
+
+        result?;
+        let wav_bytes = wav_bytes?;
+        assert_eq!(wav_bytes.len(), 44 + 4);
+        assert_eq!(&wav_bytes[40..], &[4, 0, 0, 0, 0xf4, 0x63, 0xb0, 0x28]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn sample_decode_to_foreign_wave() -> Result <(), FlacDecoderError> {
+        let flac_name = "midp_sample_decode_to_foreign_wave.flac.part";
+        let wav_name = "midp_sample_decode_to_foreign_wave.wav.part";
+        let chunks: [&[u8]; 4] = [
+            b"RIFF\x2a\0\0\0WAVE",
+            b"fmt \x10\0\0\0\x01\0\x02\0\x44\xac\0\0\x10\xb1\x02\0\x04\0\x10\0",
+            b"data\x04\0\0\0",
+            b"bext\x01\0\0\0\x07\0",
+        ];
+        let blocks: Vec <MetadataBlock> = chunks
+            .iter()
+            .map(|chunk| ApplicationBlock::new(RIFF_APPLICATION_ID, chunk.to_vec()).build_block())
+            .collect();
+
+        fs::write(flac_name, stream_bytes([0; 16], &blocks, &FRAME))?;
+        let result = decode_to_wave(flac_name, wav_name);
+        let wav_bytes = fs::read(wav_name);
+        fs::remove_file(flac_name)?;
+        fs::remove_file(wav_name)?;
+
+        result?;
+        let expected = [chunks[0], chunks[1], chunks[2], &[0xf4, 0x63, 0xb0, 0x28], chunks[3]].concat();
+        assert_eq!(wav_bytes?, expected);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,167 @@
+use core::fmt;
+use std::error;
+
+use crate::flac::metadata::streaminfo::{StreamInfo, StreamInfoCollector};
+use crate::wav::PCMWaveFormatChunk;
+
+use super::frame::DecodedFrame;
+use super::FlacDecoderError;
+
+/// Represents the first sample that differs between a block and its decoded audio frame
+/// 
+/// `sample_number` is the number of the inter-channel sample from the start
+/// of the stream, and `sample_idx` its index within the frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SampleMismatch {
+    pub frame_index: u64,
+    pub sample_number: u64,
+    pub sample_idx: usize,
+    pub channel: usize,
+    pub expected: i64,
+    pub decoded: i64,
+}
+
+/// Represents an error in the verification of encoded audio frames
+/// 
+/// The `u64` values are frame indices.
+#[derive(Debug)]
+pub enum VerifyError {
+    DecodeError(u64, FlacDecoderError),
+    FrameLengthError(u64),
+    BlockSizeError(u64),
+    MismatchError(SampleMismatch),
+    Md5Error,
+}
+
+/// Represents a verifier of audio frames right after they are encoded
+/// 
+/// Every encoded frame is decoded again and compared with the block of
+/// inter-channel samples it was encoded from, as `flac --verify` does. The
+/// MD5 signature of the decoded samples is computed along the way and
+/// compared with the signature of the input samples when finished.
+pub struct FrameVerifier {
+    streaminfo: StreamInfo,
+    collector: StreamInfoCollector,
+    frame_index: u64,
+    sample_number: u64,
+}
+
+impl FrameVerifier {
+    /// Create a new verifier for audio of the given format
+    pub fn new(format: &PCMWaveFormatChunk) -> Self {
+        Self {
+            streaminfo: StreamInfo::new(format),
+            collector: StreamInfoCollector::new(format),
+            frame_index: 0,
+            sample_number: 0,
+        }
+    }
+
+    /// Decode an encoded audio frame and compare it with its block of inter-channel samples
+    /// 
+    /// # Errors
+    /// Returns `VerifyError::DecodeError` if the frame cannot be decoded,
+    /// `VerifyError::FrameLengthError` if it does not span exactly the bytes of
+    /// `frame`, `VerifyError::BlockSizeError` if it does not hold as many
+    /// samples as `sample_block`, and `VerifyError::MismatchError` with the
+    /// first differing sample otherwise.
+    pub fn verify(&mut self, frame: &[u8], sample_block: &[Vec <i64>]) -> Result <(), VerifyError> {
+        let (decoded, frame_len) = DecodedFrame::decode(frame, &self.streaminfo)
+            .map_err(|err| VerifyError::DecodeError(self.frame_index, err))?;
+
+        if frame_len != frame.len() {
+            return Err(VerifyError::FrameLengthError(self.frame_index));
+        }
+
+        let decoded_block = decoded.sample_block();
+
+        if decoded_block.len() != sample_block.len() {
+            return Err(VerifyError::BlockSizeError(self.frame_index));
+        }
+
+        for (sample_idx, (expected, decoded)) in sample_block.iter().zip(&decoded_block).enumerate() {
+            if let Some(channel) = (0..expected.len()).find(|&channel| decoded.get(channel) != Some(&expected[channel])) {
+                return Err(VerifyError::MismatchError(SampleMismatch {
+                    frame_index: self.frame_index,
+                    sample_number: self.sample_number + sample_idx as u64,
+                    sample_idx,
+                    channel,
+                    expected: expected[channel],
+                    decoded: decoded.get(channel).copied().unwrap_or(0),
+                }));
+            }
+        }
+
+        self.collector.update(&decoded_block, frame_len);
+        self.frame_index += 1;
+        self.sample_number += sample_block.len() as u64;
+
+        Ok(())
+    }
+
+    /// Finish verifying and compare the MD5 signature of the decoded samples with `md5`
+    /// 
+    /// # Errors
+    /// Returns `VerifyError::Md5Error` if the signatures differ.
+    pub fn finish(self, md5: &[u8; 16]) -> Result <(), VerifyError> {
+        if self.collector.finish().md5 != *md5 {
+            return Err(VerifyError::Md5Error);
+        }
+
+        Ok(())
+    }
+}
+
+impl error::Error for VerifyError {}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Error: {:?}", self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The frame of the first decoding example of RFC 9639, of one sample of 25588 and 10416
+    const FRAME: [u8; 15] = [0xff, 0xf8, 0x69, 0x18, 0x00, 0x00, 0xbf, 0x03, 0x58, 0xfd, 0x03, 0x12, 0x8b, 0xaa, 0x9a];
+
+    fn stereo_verifier() -> FrameVerifier {
+        FrameVerifier::new(&PCMWaveFormatChunk {
+            num_channels: 2,
+            samp_rate: 44100,
+            bps: 16,
+            channel_mask: None,
+        })
+    }
+
+    #[test]
+    fn sample_verify() {
+        let mut verifier = stereo_verifier();
+
+        assert!(verifier.verify(&FRAME, &[vec![25588, 10416]]).is_ok());
+        assert!(verifier.finish(&md5::compute([0xf4, 0x63, 0xb0, 0x28]).0).is_ok());
+        assert!(matches!(stereo_verifier().finish(&[0; 16]), Err(VerifyError::Md5Error)));
+    }
+
+    #[test]
+    fn sample_mismatch() {
+        let mut verifier = stereo_verifier();
+        verifier.verify(&FRAME, &[vec![25588, 10416]]).unwrap();
+
+        let result = verifier.verify(&FRAME, &[vec![25588, 10417]]);
+        assert!(matches!(result, Err(VerifyError::MismatchError(SampleMismatch {
+            frame_index: 1,
+            sample_number: 1,
+            sample_idx: 0,
+            channel: 1,
+            expected: 10417,
+            decoded: 10416,
+        }))));
+
+        assert!(matches!(verifier.verify(&FRAME, &vec![vec![0, 0]; 2]), Err(VerifyError::BlockSizeError(1))));
+        assert!(matches!(verifier.verify(&[&FRAME[..], &[0]].concat(), &[vec![25588, 10416]]), Err(VerifyError::FrameLengthError(1))));
+        assert!(matches!(verifier.verify(&FRAME[..14], &[vec![25588, 10416]]), Err(VerifyError::DecodeError(1, FlacDecoderError::TruncatedError))));
+    }
+}
@@ -0,0 +1,165 @@
+use std::collections::VecDeque;
+
+/// Minimum size of an audio frame other than the last one in inter-channel samples
+pub const MIN_BLOCK_SIZE: u16 = 16;
+
+/// Ratio between the activity of two neighbouring windows above which a block is split
+const TRANSIENT_RATIO: f64 = 4.0;
+
+/// Represents the options of variable block-size encoding
+/// 
+/// Every block is a whole number of windows of `min_block_size` inter-channel
+/// samples and holds at most `max_block_size` inter-channel samples, except
+/// for the last block, which may be shorter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VariableBlockSizeOptions {
+    pub min_block_size: u16,
+    pub max_block_size: u16,
+}
+
+/// Represents an iterator that groups inter-channel samples into blocks of variable sizes
+/// 
+/// This wraps an iterator of inter-channel samples, such as a `PCMWaveDataChunk`,
+/// and looks ahead by up to `max_block_size` samples. The lookahead is cut into
+/// windows of `min_block_size` samples and the activity of each window is measured
+/// as the mean absolute difference between consecutive samples. The block ends at
+/// the first window whose activity rises or falls by more than `TRANSIENT_RATIO`
+/// from the previous window, so that transients start and end their own short
+/// blocks, while stationary regions are merged into blocks of `max_block_size`.
+pub struct BlockSplitter <I: Iterator <Item = Vec <i64>>> {
+    samples: I,
+    window_size: usize,
+    max_windows: usize,
+    pending: VecDeque <Vec <i64>>,
+}
+
+impl VariableBlockSizeOptions {
+    /// Create new variable block-size options
+    pub fn new(min_block_size: u16, max_block_size: u16) -> Self {
+        Self {
+            min_block_size,
+            max_block_size,
+        }
+    }
+
+    /// Check whether these options are valid
+    /// 
+    /// `min_block_size` must be at least `MIN_BLOCK_SIZE` and at most `max_block_size`.
+    pub fn is_valid(&self) -> bool {
+        self.min_block_size >= MIN_BLOCK_SIZE && self.min_block_size <= self.max_block_size
+    }
+}
+
+impl Default for VariableBlockSizeOptions {
+    fn default() -> Self {
+        Self::new(256, 4096)
+    }
+}
+
+impl <I: Iterator <Item = Vec <i64>>> BlockSplitter <I> {
+    /// Create a new splitter over some inter-channel samples
+    /// 
+    /// The options are expected to be valid, as checked by
+    /// `VariableBlockSizeOptions::is_valid`.
+    pub fn new(samples: I, options: &VariableBlockSizeOptions) -> Self {
+        let window_size = options.min_block_size.max(1) as usize;
+
+        Self {
+            samples,
+            window_size,
+            max_windows: (options.max_block_size as usize / window_size).max(1),
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Get the number of pending samples to put in the next block
+    fn split_len(&mut self) -> usize {
+        let activities: Vec <f64> = self.pending
+            .make_contiguous()
+            .chunks(self.window_size)
+            .map(Self::activity)
+            .collect();
+
+        let split = activities
+            .windows(2)
+            .position(|pair| pair[0].max(pair[1]) > TRANSIENT_RATIO * pair[0].min(pair[1]));
+
+        match split {
+            Some(idx) => (idx + 1) * self.window_size,
+            None => self.pending.len(),
+        }
+    }
+
+    /// Measure the activity of a window of inter-channel samples
+    /// 
+    /// One is added so that near-silent windows do not count as transients.
+    fn activity(window: &[Vec <i64>]) -> f64 {
+        let total: i64 = window
+            .windows(2)
+            .flat_map(|pair| pair[0].iter().zip(&pair[1]).map(|(a, b)| (b - a).abs()))
+            .sum();
+
+        total as f64 / window.len() as f64 + 1.0
+    }
+}
+
+impl <I: Iterator <Item = Vec <i64>>> Iterator for BlockSplitter <I> {
+    type Item = Vec <Vec <i64>>;
+
+    fn next(&mut self) -> Option <Self::Item> {
+        let lookahead = self.window_size * self.max_windows;
+
+        while self.pending.len() < lookahead {
+            match self.samples.next() {
+                Some(sample) => self.pending.push_back(sample),
+                None => break,
+            }
+        }
+
+        if self.pending.is_empty() {
+            return None;
+        }
+
+        let num_samples = self.split_len();
+
+        Some(self.pending.drain(..num_samples).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block_sizes(samples: Vec <i64>, options: &VariableBlockSizeOptions) -> Vec <usize> {
+        BlockSplitter::new(samples.into_iter().map(|sample| vec![sample, -sample]), options)
+            .map(|block| block.len())
+            .collect()
+    }
+
+    #[test]
+    fn sample_stationary() {
+        // A steady sawtooth is merged into blocks of the maximum size
+        let samples = (0..1000).map(|i| (i % 20) * 100).collect();
+
+        assert_eq!(block_sizes(samples, &VariableBlockSizeOptions::new(64, 256)), vec![256, 256, 256, 232]);
+    }
+
+    #[test]
+    fn sample_transient() {
+        // Silence followed by a burst at sample 320 and silence again from sample 448
+        let samples = (0..1024)
+            .map(|i| if (320..448).contains(&i) { (i % 2) * 20000 - 10000 } else { 0 })
+            .collect();
+
+        assert_eq!(block_sizes(samples, &VariableBlockSizeOptions::new(64, 512)), vec![320, 128, 512, 64]);
+    }
+
+    #[test]
+    fn sample_options() {
+        assert!(VariableBlockSizeOptions::default().is_valid());
+        assert!(VariableBlockSizeOptions::new(16, 16).is_valid());
+        assert!(!VariableBlockSizeOptions::new(0, 4096).is_valid());
+        assert!(!VariableBlockSizeOptions::new(8, 4096).is_valid());
+        assert!(!VariableBlockSizeOptions::new(4096, 1024).is_valid());
+    }
+}
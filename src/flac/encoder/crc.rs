@@ -29,7 +29,7 @@ impl CrcOptions <u8> {
     /// Encode data using CRC8 encoding
     /// 
     /// This method is available only if `CrcOptions` is of type `u8`.
-    pub fn build_crc8(&self, data: &Vec <u8>) -> u8 {
+    pub fn build_crc8(&self, data: &[u8]) -> u8 {
         let mut crc: u8 = 0;
 
         for &curr_byte in data {
@@ -52,11 +52,11 @@ impl CrcOptions <u16> {
     /// Encode data using CRC16 encoding
     /// 
     /// This method is available only if `CrcOptions` is of type `u16`.
-    pub fn build_crc16(&self, data: &Vec <u16>) -> u16 {
+    pub fn build_crc16(&self, data: &[u16]) -> u16 {
         let mut crc = 0u16;
     
         for &b in data {
-            crc ^= u16::from(b) << (self.poly_len - 8);
+            crc ^= b << (self.poly_len - 8);
     
             for _ in 0..8 {
                 if crc & (1 << (self.poly_len - 1)) != 0 {
@@ -71,6 +71,30 @@ impl CrcOptions <u16> {
     }
 }
 
+impl CrcOptions <u32> {
+    /// Encode data using CRC32 encoding
+    /// 
+    /// This method is available only if `CrcOptions` is of type `u32`. The CRC
+    /// starts from zero and is not reflected nor inverted, as used by Ogg pages.
+    pub fn build_crc32(&self, data: &[u8]) -> u32 {
+        let mut crc = 0u32;
+
+        for &b in data {
+            crc ^= u32::from(b) << (self.poly_len - 8);
+
+            for _ in 0..8 {
+                if crc & (1 << (self.poly_len - 1)) != 0 {
+                    crc = (crc << 1) ^ self.poly;
+                } else {
+                    crc <<= 1;
+                }
+            }
+        }
+
+        crc
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -122,4 +146,13 @@ mod tests {
 
         assert_eq!(ans, 0xaa9a);
     }
+
+    #[test]
+    fn sample_crc32_01() {
+        let in_vec = b"123456789".to_vec();
+        let ans = CrcOptions::new(0x04c1_1db7u32, 32)
+            .build_crc32(&in_vec);
+
+        assert_eq!(ans, 0x89a1_897f);
+    }
 }
\ No newline at end of file
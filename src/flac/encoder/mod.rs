@@ -1,3 +1,7 @@
+pub mod blocking;
 pub mod crc;
+pub mod parallel;
+pub mod preset;
 pub mod rice;
+pub mod stereo;
 pub mod utf8;
\ No newline at end of file
@@ -0,0 +1,221 @@
+use core::fmt;
+use std::collections::BTreeMap;
+use std::error;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{self, Receiver, SyncSender, TryRecvError};
+use std::thread::{self, JoinHandle};
+
+/// Number of blocks in flight per worker thread
+const BLOCKS_PER_WORKER: usize = 2;
+
+/// Represents a block of inter-channel samples to encode, with its sequence number and frame number
+type FrameJob = (u64, Vec <Vec <i64>>, u64);
+
+/// Represents a block with its audio frame, or `None` if the encoding panicked
+type PendingFrame = (Vec <Vec <i64>>, Option <Vec <u8>>);
+
+/// Represents a block of inter-channel samples with its audio frame
+pub type EncodedFrame = (Vec <Vec <i64>>, Vec <u8>);
+
+/// Represents a pool of worker threads encoding audio frames in order
+/// 
+/// Blocks of inter-channel samples are pushed along with their frame number
+/// and encoded concurrently by the workers. Frames finished out of order wait
+/// in a reorder buffer, so that `next` always returns them in the order the
+/// blocks were pushed. Each frame only depends on its block and its frame
+/// number, so the frames are the same as if they were encoded one by one.
+/// 
+/// At most `BLOCKS_PER_WORKER` blocks per worker are in flight between `push`
+/// and `next`, which bounds the memory use. The caller should take frames with
+/// `next` while `is_full` holds before pushing another block.
+pub struct FramePool {
+    jobs: Option <SyncSender <FrameJob>>,
+    frames: Receiver <(u64, PendingFrame)>,
+    workers: Vec <JoinHandle <()>>,
+    reorder: BTreeMap <u64, PendingFrame>,
+    max_in_flight: usize,
+    num_pushed: u64,
+    num_taken: u64,
+}
+
+/// Represents an error in the pool of worker threads
+#[derive(Debug)]
+pub enum FramePoolError {
+    WorkerError,
+}
+
+impl FramePool {
+    /// Create a new pool of `num_threads` worker threads
+    /// 
+    /// `encode` is called with a block of inter-channel samples and its frame
+    /// number, and returns the bytes of the audio frame.
+    pub fn new(num_threads: usize, encode: impl Fn(&[Vec <i64>], u64) -> Vec <u8> + Send + Sync + 'static) -> Self {
+        let num_threads = num_threads.max(1);
+        let max_in_flight = num_threads * BLOCKS_PER_WORKER;
+        let (job_tx, job_rx) = mpsc::sync_channel::<FrameJob>(max_in_flight);
+        let (frame_tx, frame_rx) = mpsc::channel();
+        let job_rx = Arc::new(Mutex::new(job_rx));
+        let encode = Arc::new(encode);
+
+        let workers = (0..num_threads)
+            .map(|_| {
+                let job_rx = Arc::clone(&job_rx);
+                let frame_tx = frame_tx.clone();
+                let encode = Arc::clone(&encode);
+
+                thread::spawn(move || loop {
+                    // The lock is only held while waiting for the next job
+                    let job = job_rx.lock().map(|job_rx| job_rx.recv());
+                    let Ok(Ok((seq, sample_block, frame_number))) = job else {
+                        break;
+                    };
+                    let frame = panic::catch_unwind(AssertUnwindSafe(|| encode(&sample_block, frame_number))).ok();
+
+                    if frame_tx.send((seq, (sample_block, frame))).is_err() {
+                        break;
+                    }
+                })
+            })
+            .collect();
+
+        Self {
+            jobs: Some(job_tx),
+            frames: frame_rx,
+            workers,
+            reorder: BTreeMap::new(),
+            max_in_flight,
+            num_pushed: 0,
+            num_taken: 0,
+        }
+    }
+
+    /// Check whether as many blocks as allowed are in flight
+    pub fn is_full(&self) -> bool {
+        self.num_pushed - self.num_taken >= self.max_in_flight as u64
+    }
+
+    /// Push a block of inter-channel samples to encode with the given frame number
+    /// 
+    /// # Errors
+    /// Returns `FramePoolError::WorkerError` if the worker threads are gone.
+    pub fn push(&mut self, sample_block: Vec <Vec <i64>>, frame_number: u64) -> Result <(), FramePoolError> {
+        let jobs = self.jobs.as_ref().ok_or(FramePoolError::WorkerError)?;
+
+        jobs.send((self.num_pushed, sample_block, frame_number)).map_err(|_| FramePoolError::WorkerError)?;
+        self.num_pushed += 1;
+
+        Ok(())
+    }
+
+    /// Take the next encoded frame in order, along with its block
+    /// 
+    /// If `wait` is set, this waits until the next frame is encoded. Otherwise,
+    /// it only returns a frame that is already encoded. Returns `None` if no
+    /// frame is ready or no block is in flight.
+    /// 
+    /// # Errors
+    /// Returns `FramePoolError::WorkerError` if the encoding of a frame panicked
+    /// or the worker threads are gone.
+    pub fn next(&mut self, wait: bool) -> Result <Option <EncodedFrame>, FramePoolError> {
+        loop {
+            if let Some((sample_block, frame)) = self.reorder.remove(&self.num_taken) {
+                self.num_taken += 1;
+
+                return frame
+                    .map(|frame| Some((sample_block, frame)))
+                    .ok_or(FramePoolError::WorkerError);
+            }
+
+            if self.num_taken == self.num_pushed {
+                return Ok(None);
+            }
+
+            let (seq, pending) = if wait {
+                self.frames.recv().map_err(|_| FramePoolError::WorkerError)?
+            }
+            else {
+                match self.frames.try_recv() {
+                    Ok(encoded) => encoded,
+                    Err(TryRecvError::Empty) => return Ok(None),
+                    Err(TryRecvError::Disconnected) => return Err(FramePoolError::WorkerError),
+                }
+            };
+
+            self.reorder.insert(seq, pending);
+        }
+    }
+}
+
+impl Drop for FramePool {
+    fn drop(&mut self) {
+        // Closing the job queue lets the workers finish
+        self.jobs = None;
+
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl error::Error for FramePoolError {}
+
+impl fmt::Display for FramePoolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Error: {:?}", self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn encode(sample_block: &[Vec <i64>], frame_number: u64) -> Vec <u8> {
+        // Early frames take longer so that they finish out of order
+        thread::sleep(Duration::from_millis(8u64.saturating_sub(frame_number)));
+
+        let sum: i64 = sample_block.iter().flatten().sum();
+        vec![frame_number as u8, sum as u8]
+    }
+
+    #[test]
+    fn sample_ordered_frames() -> Result <(), FramePoolError> {
+        let mut pool = FramePool::new(3, encode);
+        let mut frames = Vec::new();
+
+        for frame_number in 0..16 {
+            while pool.is_full() {
+                frames.extend(pool.next(true)?);
+            }
+
+            assert!(pool.num_pushed - pool.num_taken < 6);
+            pool.push(vec![vec![frame_number as i64, 1]], frame_number)?;
+            frames.extend(pool.next(false)?);
+        }
+
+        while let Some(frame) = pool.next(true)? {
+            frames.push(frame);
+        }
+
+        let expected: Vec <_> = (0..16).map(|i| (vec![vec![i as i64, 1]], vec![i as u8, i as u8 + 1])).collect();
+        assert_eq!(frames, expected);
+
+        Ok(())
+    }
+
+    #[test]
+    fn sample_worker_panic() {
+        let mut pool = FramePool::new(2, |_: &[Vec <i64>], frame_number| {
+            assert!(frame_number != 1);
+            vec![0]
+        });
+
+        for frame_number in 0..3 {
+            pool.push(vec![vec![0]], frame_number).unwrap();
+        }
+
+        assert!(pool.next(true).unwrap().is_some());
+        assert!(matches!(pool.next(true), Err(FramePoolError::WorkerError)));
+    }
+}
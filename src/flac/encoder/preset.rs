@@ -0,0 +1,254 @@
+use std::f64::consts::PI;
+
+use crate::flac::lpc::var::MAX_LPC_ORDER;
+
+use super::blocking::MIN_BLOCK_SIZE;
+use super::rice::MAX_PARTITION_ORDER;
+use super::stereo::StereoMode;
+
+/// Highest compression preset
+pub const MAX_PRESET: u8 = 8;
+
+/// Compression preset of the default options, as for `flac`
+pub const DEFAULT_PRESET: u8 = 5;
+
+/// Represents the window applied to a block of samples before computing its LPC coefficients
+/// 
+/// `Tukey(p)` is flat in the middle and tapers with a cosine over a fraction
+/// `p` of its length, ranging from the rectangle window at 0 to the Hann window
+/// at 1. `SubdivideTukey(n)` tries a Tukey window of `p = 0.5` over each part
+/// of the block divided into 1 to `n` equal parts, and keeps the window that
+/// predicts best.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Apodization {
+    Rectangle,
+    Hann,
+    Welch,
+    Tukey(f64),
+    SubdivideTukey(u8),
+}
+
+/// Represents the options of how audio frames are compressed
+/// 
+/// The audio is cut into blocks of `block_size` inter-channel samples unless
+/// variable block sizes are used. LPC subframes have orders of at most
+/// `max_lpc_order`, or are not used if it is 0, and quantized coefficients of
+/// `qlp_precision` bits, or of the precision of `VarPredictor::get_best_precision`
+/// if it is 0. The Rice partition order is searched from `min_partition_order`
+/// to `max_partition_order`. If `exhaustive` is set, every subframe type,
+/// predictor order and window is encoded to keep the smallest subframe by its
+/// exact size, instead of choosing the predictor order by its residuals.
+/// 
+/// The presets 0 to 8 match the compression levels of libFLAC, and the
+/// default options are those of `DEFAULT_PRESET`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CompressionOptions {
+    pub block_size: u16,
+    pub max_lpc_order: u8,
+    pub qlp_precision: u8,
+    pub min_partition_order: u8,
+    pub max_partition_order: u8,
+    pub stereo_mode: StereoMode,
+    pub apodization: Apodization,
+    pub exhaustive: bool,
+}
+
+/// Compression options of each preset, as given by `CompressionOptions::preset`
+/// 
+/// |  Preset  | Block size | LPC order | Partition orders |  Stereo  |      Apodization      |
+/// |----------|------------|-----------|------------------|----------|-----------------------|
+/// |        0 |       1152 |         0 |           0 to 3 |     none |           tukey(0.5)  |
+/// |        1 |       1152 |         0 |           0 to 3 | estimate |           tukey(0.5)  |
+/// |        2 |       1152 |         0 |           0 to 3 |    exact |           tukey(0.5)  |
+/// |        3 |       4096 |         6 |           0 to 4 |     none |           tukey(0.5)  |
+/// |        4 |       4096 |         8 |           0 to 4 | estimate |           tukey(0.5)  |
+/// |        5 |       4096 |         8 |           0 to 5 |    exact |           tukey(0.5)  |
+/// |        6 |       4096 |         8 |           0 to 6 |    exact |  subdivide_tukey(2)   |
+/// |        7 |       4096 |        12 |           0 to 6 |    exact |  subdivide_tukey(2)   |
+/// |        8 |       4096 |        12 |           0 to 6 |    exact |  subdivide_tukey(3)   |
+/// 
+/// The loose mid-side stereo of libFLAC is `StereoMode::Estimate`, and its
+/// full mid-side stereo is `StereoMode::Exact`. No preset searches
+/// exhaustively or fixes the coefficient precision.
+const PRESETS: [(u16, u8, u8, StereoMode, Apodization); 9] = [
+    (1152, 0, 3, StereoMode::Independent, Apodization::Tukey(0.5)),
+    (1152, 0, 3, StereoMode::Estimate, Apodization::Tukey(0.5)),
+    (1152, 0, 3, StereoMode::Exact, Apodization::Tukey(0.5)),
+    (4096, 6, 4, StereoMode::Independent, Apodization::Tukey(0.5)),
+    (4096, 8, 4, StereoMode::Estimate, Apodization::Tukey(0.5)),
+    (4096, 8, 5, StereoMode::Exact, Apodization::Tukey(0.5)),
+    (4096, 8, 6, StereoMode::Exact, Apodization::SubdivideTukey(2)),
+    (4096, 12, 6, StereoMode::Exact, Apodization::SubdivideTukey(2)),
+    (4096, 12, 6, StereoMode::Exact, Apodization::SubdivideTukey(3)),
+];
+
+impl Apodization {
+    /// Check whether this window is valid
+    /// 
+    /// The fraction of a Tukey window must be within 0 and 1, and a block
+    /// must be divided into at least one part.
+    pub fn is_valid(&self) -> bool {
+        match *self {
+            Apodization::Tukey(p) => (0.0..=1.0).contains(&p),
+            Apodization::SubdivideTukey(num_parts) => num_parts >= 1,
+            _ => true,
+        }
+    }
+
+    /// Get the windows of `len` samples to try
+    /// 
+    /// All windows but those of `SubdivideTukey` are a single window. A block
+    /// divided into `n` parts gets one window per part, which is zero outside
+    /// of the part, so `SubdivideTukey(n)` gets `n * (n + 1) / 2` windows.
+    pub fn windows(&self, len: usize) -> Vec <Vec <f64>> {
+        match *self {
+            Apodization::SubdivideTukey(num_parts) => (1..=num_parts.max(1) as usize)
+                .flat_map(|num_parts| (0..num_parts).map(move |part| (num_parts, part)))
+                .map(|(num_parts, part)| {
+                    let start = len * part / num_parts;
+                    let end = len * (part + 1) / num_parts;
+                    let mut window = vec![0.0; len];
+                    window[start..end].copy_from_slice(&Self::tukey(end - start, 0.5));
+
+                    window
+                })
+                .collect(),
+            Apodization::Rectangle => vec![vec![1.0; len]],
+            Apodization::Hann => vec![Self::hann(len)],
+            Apodization::Welch => vec![Self::welch(len)],
+            Apodization::Tukey(p) => vec![Self::tukey(len, p)],
+        }
+    }
+
+    /// Get the Hann window of `len` samples
+    fn hann(len: usize) -> Vec <f64> {
+        let n = len.saturating_sub(1).max(1) as f64;
+
+        (0..len)
+            .map(|i| 0.5 - 0.5 * (2.0 * PI * i as f64 / n).cos())
+            .collect()
+    }
+
+    /// Get the Welch window of `len` samples
+    fn welch(len: usize) -> Vec <f64> {
+        let half = (len.saturating_sub(1) as f64 / 2.0).max(0.5);
+
+        (0..len)
+            .map(|i| 1.0 - ((i as f64 - half) / half).powi(2))
+            .collect()
+    }
+
+    /// Get the Tukey window of `len` samples tapering over a fraction `p` of them
+    /// 
+    /// Each taper spans `p * len / 2` samples, as computed by libFLAC.
+    fn tukey(len: usize, p: f64) -> Vec <f64> {
+        if p <= 0.0 {
+            return vec![1.0; len];
+        }
+        if p >= 1.0 {
+            return Self::hann(len);
+        }
+
+        let taper_len = (p / 2.0 * len as f64) as usize;
+        let mut window = vec![1.0; len];
+
+        if taper_len > 1 {
+            let n = (taper_len - 1) as f64;
+
+            for i in 0..taper_len {
+                let value = 0.5 - 0.5 * (PI * i as f64 / n).cos();
+                window[i] = value;
+                window[len - 1 - i] = value;
+            }
+        }
+
+        window
+    }
+}
+
+impl CompressionOptions {
+    /// Get the compression options of a preset from 0, the fastest, to 8, the smallest
+    /// 
+    /// Returns `None` if `level` is above `MAX_PRESET`.
+    pub fn preset(level: u8) -> Option <Self> {
+        let &(block_size, max_lpc_order, max_partition_order, stereo_mode, apodization) = PRESETS.get(level as usize)?;
+
+        Some(Self {
+            block_size,
+            max_lpc_order,
+            qlp_precision: 0,
+            min_partition_order: 0,
+            max_partition_order,
+            stereo_mode,
+            apodization,
+            exhaustive: false,
+        })
+    }
+
+    /// Check whether these options are valid
+    /// 
+    /// `block_size` must be at least `MIN_BLOCK_SIZE`, `max_lpc_order` at most
+    /// `MAX_LPC_ORDER`, and `qlp_precision` either 0 or from 5 to 15 bits. The
+    /// partition orders must be in order and at most `MAX_PARTITION_ORDER`, and
+    /// the apodization must be valid.
+    pub fn is_valid(&self) -> bool {
+        self.block_size >= MIN_BLOCK_SIZE
+            && self.max_lpc_order <= MAX_LPC_ORDER
+            && (self.qlp_precision == 0 || (5..=15).contains(&self.qlp_precision))
+            && self.min_partition_order <= self.max_partition_order
+            && self.max_partition_order <= MAX_PARTITION_ORDER
+            && self.apodization.is_valid()
+    }
+}
+
+impl Default for CompressionOptions {
+    fn default() -> Self {
+        Self::preset(DEFAULT_PRESET).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sample_presets() {
+        let fastest = CompressionOptions::preset(0).unwrap();
+        assert_eq!((fastest.block_size, fastest.max_lpc_order, fastest.max_partition_order), (1152, 0, 3));
+        assert_eq!(fastest.stereo_mode, StereoMode::Independent);
+
+        let smallest = CompressionOptions::preset(MAX_PRESET).unwrap();
+        assert_eq!((smallest.block_size, smallest.max_lpc_order, smallest.max_partition_order), (4096, 12, 6));
+        assert_eq!(smallest.apodization, Apodization::SubdivideTukey(3));
+
+        assert_eq!(CompressionOptions::preset(DEFAULT_PRESET), Some(CompressionOptions::default()));
+        assert!(CompressionOptions::preset(MAX_PRESET + 1).is_none());
+        assert!((0..=MAX_PRESET).all(|level| CompressionOptions::preset(level).unwrap().is_valid()));
+
+        let options = CompressionOptions {
+            qlp_precision: 4,
+            ..CompressionOptions::default()
+        };
+        assert!(!options.is_valid());
+
+        let options = CompressionOptions {
+            block_size: 0,
+            ..CompressionOptions::default()
+        };
+        assert!(!options.is_valid());
+    }
+
+    #[test]
+    fn sample_windows() {
+        let tukey = &Apodization::Tukey(0.5).windows(16)[0];
+        assert_eq!(tukey.len(), 16);
+        assert!((tukey[2] - 0.75).abs() < 1e-9);
+        assert_eq!((tukey[0], tukey[3], tukey[8], tukey[15]), (0.0, 1.0, 1.0, 0.0));
+        assert_eq!(Apodization::Tukey(0.0).windows(4), vec![vec![1.0; 4]]);
+
+        let windows = Apodization::SubdivideTukey(3).windows(12);
+        assert_eq!(windows.len(), 6);
+        assert!(windows[5][..8].iter().all(|&value| value == 0.0));
+        assert!(windows[5][8..].contains(&1.0));
+    }
+}
@@ -0,0 +1,329 @@
+use std::ops::RangeInclusive;
+use crate::flac::bitstream::BitstreamWriter;
+
+/// Highest Rice partition order allowed in FLAC
+pub const MAX_PARTITION_ORDER: u8 = 15;
+
+/// The largest Rice parameter, which fits the 5-bit parameter field of the
+/// second residual coding method without colliding with its escape code
+pub const MAX_RICE_PARAM: u8 = 30;
+
+/// Represents a Rice encoder
+///
+/// This encoder is expected to encode `num_samples` residuals from a predictor of
+/// order `predictor_order`. Note that Rice encoding in FLAC is only available
+/// for LPC and FIXED audio subframes. The partition orders searched may be
+/// narrowed with `with_partition_orders`.
+pub struct RiceEncoderOptions {
+    num_samples: u64,
+    predictor_order: u8,
+    partition_orders: Option <(u8, u8)>,
+}
+
+/// Represents a Rice-encoded stream
+///
+/// Rice encoding is _not necessarily_ byte-aligned. The `extra_bits_len`
+/// value denotes the number of LSBits in the last byte of the `stream`
+/// that are _not_ part of the encoding.
+#[derive(Debug)]
+pub struct RiceEncodedStream {
+    pub stream: Vec <u8>,
+    pub param: u8,
+    pub extra_bits_len: u8,
+}
+
+impl RiceEncoderOptions {
+    /// Create a builder to the Rice encoder
+    pub fn new(num_samples: u64, predictor_order: u8) -> Self {
+        Self {
+            num_samples,
+            predictor_order,
+            partition_orders: None,
+        }
+    }
+
+    /// Search only the partition orders from `min_partition_order` to `max_partition_order`
+    /// 
+    /// The orders are still bounded by `max_rice_partition_order`, so that a
+    /// maximum above what the block allows falls back to the highest allowed order.
+    pub fn with_partition_orders(mut self, min_partition_order: u8, max_partition_order: u8) -> Self {
+        self.partition_orders = Some((min_partition_order, max_partition_order));
+        self
+    }
+
+    /// Get the range of partition orders to search
+    /// 
+    /// The range spans from `min_rice_partition_order` to `max_rice_partition_order`
+    /// of the `num_samples` samples, or over the partition orders set by
+    /// `with_partition_orders` within the same maximum.
+    fn partition_order_range(&self) -> RangeInclusive <u8> {
+        let max_order = Self::max_rice_partition_order(self.num_samples);
+
+        match self.partition_orders {
+            Some((min_order, max_partition_order)) => min_order.min(max_order)..=max_partition_order.min(max_order),
+            None => Self::min_rice_partition_order()..=max_order,
+        }
+    }
+
+    /// Get the minimum partition order
+    /// 
+    /// The default minimum partition order is zero
+    fn min_rice_partition_order() -> u8 {
+        0
+    }
+
+    /// Get the maximum partition order
+    /// 
+    /// The maximum partition order is computed as the lowest power of two
+    /// that makes up the block size, or the index of the least significant
+    /// 1 bit in the block size. Note that odd-sized block sizes can only
+    /// have a partition order of 0 as the number of partitions should be
+    /// a power of two.
+    fn max_rice_partition_order(block_size: u64) -> u8 {
+        if block_size == 0 {
+            return 0;
+        }
+
+        (block_size.trailing_zeros() as u8).min(MAX_PARTITION_ORDER)
+    }
+
+    /// Compute the best partition order and best Rice parameters for each partition
+    /// 
+    /// The best partition order is computed based on the order that yields the minimum
+    /// total number of bits of the resulting Rice encoding, among the orders of
+    /// `partition_order_range`.
+    /// 
+    /// # Errors
+    /// Returns `None` if no partition order of the range can hold the residuals,
+    /// as described by `best_parameters`.
+    fn best_partition_and_params(&self, residuals: &[i64]) -> Option <(Vec <u8>, u8)> {
+        let mut best: Option <(Vec <u8>, u8, u64)> = None;
+
+        for partition_order in self.partition_order_range() {
+            if let Some((params, num_bits)) = self.best_parameters(partition_order, residuals) {
+                if best.as_ref().is_none_or(|(_, _, best_bits)| num_bits < *best_bits) {
+                    best = Some((params, partition_order, num_bits));
+                }
+            }
+        }
+
+        best.map(|(params, partition_order, _)| (params, partition_order))
+    }
+
+    /// Compute the best Rice parameters for some partition of the residuals
+    /// 
+    /// The best Rice parameter `M` can be approximated using the following:
+    /// 
+    /// `M = log2(abs_r_mean - 1) - log2(n_partition_samples) + 1`.
+    /// 
+    /// Note that in practice, the sum of the absolute value of the residuals
+    /// is used instead of the absolute residual mean `abs_r_mean`. In addition,
+    /// Most implementations will bound `M` to be represented by at most 18 bits.
+    /// 
+    /// Note that only partition order 0 is allowed for odd-length residuals
+    /// as the number of partitions should be a power of two.
+    /// 
+    /// # Errors
+    /// Returns `None` if a best parameter cannot be found for any partition. This
+    /// arises usually if the predictor order is larger than the amount of residuals
+    /// in a partition.
+    fn best_parameters(&self, partition_order: u8, residuals: &[i64]) -> Option <(Vec <u8>, u64)> {
+        let partition_len = self.num_samples >> partition_order;
+        let num_partitions = 1usize << partition_order;
+
+        if partition_len < self.predictor_order as u64
+            || residuals.len() as u64 + self.predictor_order as u64 != self.num_samples {
+            return None;
+        }
+
+        let mut params = Vec::with_capacity(num_partitions);
+        let mut num_bits = 0;
+        let mut start = 0;
+
+        for i in 0..num_partitions {
+            let n_partition_samples = if i == 0 {
+                partition_len - self.predictor_order as u64
+            }
+            else {
+                partition_len
+            };
+            let end = start + n_partition_samples as usize;
+            let partition = residuals[start..end].to_vec();
+            let abs_residual_sum = partition.iter()
+                .fold(0u64, |sum, residual| sum.saturating_add(Self::zigzag(*residual)));
+
+            let param = (0..=MAX_RICE_PARAM)
+                .min_by_key(|param| Self::bits_in_partition_sums(*param, n_partition_samples, abs_residual_sum))
+                .unwrap();
+
+            num_bits += Self::bits_in_partition_exact(param, n_partition_samples, &partition);
+            params.push(param);
+            start = end;
+        }
+
+        let param_len = if params.iter().any(|param| *param > 14) { 5 } else { 4 };
+
+        Some((params, num_bits + param_len * num_partitions as u64))
+    }
+
+    /// Find the exact total number of bits needed to represent a Rice-encoded
+    /// partition of samples
+    /// 
+    /// A residual `r` can be represented using 1 bit for the unary stop mark,
+    /// `rice_param` bits for the truncated binary part of the rice encoding, and
+    /// `zigzag(r) >> rice_param` bits for the unary tally marks.
+    fn bits_in_partition_exact(rice_param: u8, n_partition_samples: u64, residuals: &[i64]) -> u64 {
+        residuals.iter()
+            .fold(n_partition_samples * (1 + rice_param as u64), |sum, residual| {
+                sum.saturating_add(Self::zigzag(*residual) >> rice_param)
+            })
+    }
+
+    /// Find the total number of bits occupied by this encoding
+    /// 
+    /// Rice encoding uses `q + 1` bits for the unary-encoded quotient `q` and
+    /// `rice_param` bits for the binary remainder
+    fn bits_in_partition_sums(rice_param: u8, n_partition_samples: u64, abs_residual_sum: u64) -> u64 {
+        n_partition_samples * (1 + rice_param as u64) + (abs_residual_sum >> rice_param)
+    }
+
+    /// Encode residuals into Rice encoding
+    /// 
+    /// To encode a residual into its Rice encoding, it should be first processed
+    /// using zigzag encoding so that all of the residuals become nonnegative numbers.
+    /// Then, the Rice encoding of each residual is computed.
+    /// 
+    /// Note that the contents are _not_ ensured to be byte-aligned. Hence, this method returns
+    /// the Rice-encoded byte vector containing the number of extra unused bits at the last element.
+    pub fn encode(rice_param: u8, residuals: &[i64]) -> RiceEncodedStream {
+        let mut writer = BitstreamWriter::new();
+
+        for residual in residuals {
+            let value = Self::zigzag(*residual);
+
+            writer.write_unary(value >> rice_param);
+            writer.write_bits(value, rice_param as u32);
+        }
+
+        let (stream, extra_bits_len) = writer.finish();
+
+        RiceEncodedStream {
+            stream,
+            param: rice_param,
+            extra_bits_len,
+        }
+    }
+
+    /// Encode residuals into a partitioned Rice-encoded stream
+    /// 
+    /// This method computes the Rice encoding of a stream of residuals by first partitioning
+    /// the residual into groups. Each group is then found its best Rice parameter and
+    /// each residual in the group is then encoded using the parameter.
+    /// 
+    /// The method returns each Rice-encoded group in chronological order and the partition order,
+    /// respectively. The number of elemenets in the vector of Rice-encoded groups should be less than
+    /// or equal to `2^partition order`.
+    /// 
+    /// Note that each of the contents are _not_ ensured to be byte-aligned. Hence, this method
+    /// returns the Rice-encoded byte stream and the number of extra unused bits at the last byte
+    /// of the stream, respectively.
+    /// 
+    /// # Errors
+    /// Returns `None` if the residuals cannot be partitioned, as described by
+    /// `best_partition_and_params`. Such residuals should be stored as is.
+    pub fn encode_by_partition(&self, residuals: &[i64])  -> Option <(Vec <RiceEncodedStream>, u8)> {
+        let (params, partition_order) = self.best_partition_and_params(residuals)?;
+        let partition_len = (self.num_samples >> partition_order) as usize;
+        let mut start = 0;
+
+        let streams = params.iter()
+            .enumerate()
+            .map(|(i, param)| {
+                let end = if i == 0 {
+                    partition_len - self.predictor_order as usize
+                }
+                else {
+                    start + partition_len
+                };
+                let stream = Self::encode(*param, &residuals[start..end]);
+
+                start = end;
+                stream
+            })
+            .collect();
+
+        Some((streams, partition_order))
+    }
+
+    /// Convert an integer into its zigzag encoding. With this encoding, all
+    /// positive numbers are even and all negative numbers are odd.
+    pub fn zigzag(num: i64) -> u64 {
+        ((num << 1) ^ (num >> 63)) as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_sample_ietf_02() {
+        let in_vec = vec![
+            3194, -1297, 1228, -943,
+            952, -696, 768, -524,
+            599, -401, -13172, -316,
+            274, -267, 134,
+        ];
+
+        let out_vec_ans = vec![
+            0x11, 0xe8, 0xa2, 0x14,
+            0xcc, 0x7a, 0xef, 0xb8,
+            0x6b, 0x7f, 0x00, 0x60,
+            0xbe, 0x57, 0x59, 0x08,
+            0x00, 0x77, 0x3d, 0x3b,
+            0xd1, 0x25, 0x0a, 0xc8,
+            0x60,
+        ];
+
+        let rice_enc_stream = RiceEncoderOptions::encode(11, &in_vec);
+
+        assert_eq!(rice_enc_stream.stream, out_vec_ans);
+        assert_eq!(rice_enc_stream.extra_bits_len, 3);
+    }
+
+    #[test]
+    fn encode_sample_ietf_03() {
+        let in_vec = vec![
+            3, -1, -13,
+        ];
+
+        let out_vec_ans = vec![
+            0xe9, 0x12,
+        ];
+
+        let rice_enc_stream = RiceEncoderOptions::encode(3, &in_vec);
+
+        assert_eq!(rice_enc_stream.stream, out_vec_ans);
+        assert_eq!(rice_enc_stream.extra_bits_len, 1);
+    }
+
+    #[test]
+    fn encode_by_partition_within_orders() {
+        let residuals = (0..252).map(|i| if i < 124 { i % 3 - 1 } else { (i % 7 - 3) * 1000 }).collect::<Vec <i64>>();
+        let (streams, partition_order) = RiceEncoderOptions::new(256, 4).encode_by_partition(&residuals).unwrap();
+
+        assert_eq!(streams.len(), 1 << partition_order);
+        assert!(partition_order >= 1);
+        assert!(streams[0].param < streams[streams.len() - 1].param);
+
+        let (streams, partition_order) = RiceEncoderOptions::new(256, 4)
+            .with_partition_orders(0, 0)
+            .encode_by_partition(&residuals)
+            .unwrap();
+
+        assert_eq!(partition_order, 0);
+        assert_eq!(streams.len(), 1);
+        assert!(RiceEncoderOptions::new(256, 4).best_parameters(7, &residuals).is_none());
+        assert!(RiceEncoderOptions::new(256, 4).encode_by_partition(&residuals[1..]).is_none());
+    }
+}
\ No newline at end of file
@@ -0,0 +1,240 @@
+/// Represents the channel assignment of an audio frame
+/// 
+/// Stereo frames may store a side channel, the difference between the left
+/// and right channels, instead of one of them. The side channel takes one
+/// more bit than the other channels. In `MidSide`, the mid channel is the
+/// average of the left and right channels rounded down, and the bit lost
+/// by the rounding is recovered from the side channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelAssignment {
+    Independent,
+    LeftSide,
+    RightSide,
+    MidSide,
+}
+
+/// Represents how the channel assignment of stereo audio frames is chosen
+/// 
+/// `Estimate` compares the residuals of a second-order fixed predictor on
+/// each candidate channel, while `Exact` compares the size in bits of the
+/// encoded subframes. Frames of other than two channels are always
+/// encoded as independent channels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StereoMode {
+    Independent,
+    #[default]
+    Estimate,
+    Exact,
+}
+
+impl ChannelAssignment {
+    /// Get the 4-bit value of this channel assignment as written in the frame header
+    /// 
+    /// |  Channel assignment  | Value      |
+    /// |----------------------|------------|
+    /// |          Independent | 0000-0111  |
+    /// |            Left/side | 1000       |
+    /// |           Right/side | 1001       |
+    /// |             Mid/side | 1010       |
+    /// 
+    /// For independent channels, the value is the number of channels minus one.
+    pub fn value(&self, num_channels: u8) -> u8 {
+        match self {
+            ChannelAssignment::Independent => num_channels - 1,
+            ChannelAssignment::LeftSide => 8,
+            ChannelAssignment::RightSide => 9,
+            ChannelAssignment::MidSide => 10,
+        }
+    }
+
+    /// Get the channel assignment and the number of channels of a 4-bit value from a frame header
+    ///
+    /// Returns `None` for the reserved values from 11 to 15.
+    pub fn from_value(value: u8) -> Option <(Self, u8)> {
+        match value {
+            0..=7 => Some((ChannelAssignment::Independent, value + 1)),
+            8 => Some((ChannelAssignment::LeftSide, 2)),
+            9 => Some((ChannelAssignment::RightSide, 2)),
+            10 => Some((ChannelAssignment::MidSide, 2)),
+            _ => None,
+        }
+    }
+
+    /// Get the bit depth of each subframe given the bit depth of the audio
+    pub fn bit_depths(&self, bit_depth: u8, num_channels: u8) -> Vec <u8> {
+        match self {
+            ChannelAssignment::Independent => vec![bit_depth; num_channels as usize],
+            ChannelAssignment::LeftSide | ChannelAssignment::MidSide => vec![bit_depth, bit_depth + 1],
+            ChannelAssignment::RightSide => vec![bit_depth + 1, bit_depth],
+        }
+    }
+
+    /// Split a block of inter-channel samples into the samples of each subframe
+    pub fn split(&self, sample_block: &[Vec <i64>]) -> Vec <Vec <i64>> {
+        let num_channels = sample_block.first().map_or(0, |inter_sample| inter_sample.len());
+        let channel = |idx: usize| sample_block.iter().map(|inter_sample| inter_sample[idx]).collect::<Vec <i64>>();
+
+        if *self == ChannelAssignment::Independent {
+            return (0..num_channels).map(channel).collect();
+        }
+
+        let (left, right) = (channel(0), channel(1));
+        let side = left.iter().zip(&right).map(|(l, r)| l - r).collect();
+
+        match self {
+            ChannelAssignment::LeftSide => vec![left, side],
+            ChannelAssignment::RightSide => vec![side, right],
+            _ => {
+                let mid = left.iter().zip(&right).map(|(l, r)| (l + r) >> 1).collect();
+
+                vec![mid, side]
+            },
+        }
+    }
+
+    /// Join the samples of each subframe back into a block of inter-channel samples
+    /// 
+    /// This is the inverse of `split`.
+    pub fn join(&self, channels: &[Vec <i64>]) -> Vec <Vec <i64>> {
+        let block_size = channels.first().map_or(0, |channel| channel.len());
+
+        (0..block_size)
+            .map(|i| match self {
+                ChannelAssignment::Independent => channels.iter().map(|channel| channel[i]).collect(),
+                ChannelAssignment::LeftSide => vec![channels[0][i], channels[0][i] - channels[1][i]],
+                ChannelAssignment::RightSide => vec![channels[0][i] + channels[1][i], channels[1][i]],
+                ChannelAssignment::MidSide => {
+                    let side = channels[1][i];
+                    let mid = (channels[0][i] << 1) | (side & 1);
+
+                    vec![(mid + side) >> 1, (mid - side) >> 1]
+                },
+            })
+            .collect()
+    }
+
+    /// Choose the channel assignment of a block of inter-channel samples
+    pub fn choose(sample_block: &[Vec <i64>], bit_depth: u8, mode: StereoMode, cost: impl Fn(&[i64], u8) -> u64) -> Self {
+        match mode {
+            StereoMode::Independent => ChannelAssignment::Independent,
+            StereoMode::Estimate => Self::estimate(sample_block),
+            StereoMode::Exact => Self::choose_exact(sample_block, bit_depth, cost),
+        }
+    }
+
+    /// Estimate the cheapest channel assignment of a block of inter-channel samples
+    /// 
+    /// The cost of each candidate channel is the sum of the absolute residuals
+    /// of a second-order fixed predictor, which grows with the size of the
+    /// Rice-coded residuals.
+    pub fn estimate(sample_block: &[Vec <i64>]) -> Self {
+        Self::cheapest(sample_block, 0, |samples, _| {
+            samples
+                .windows(3)
+                .map(|w| (w[2] - 2 * w[1] + w[0]).unsigned_abs())
+                .sum()
+        })
+    }
+
+    /// Find the cheapest channel assignment of a block of inter-channel samples
+    /// 
+    /// `cost` is called with the samples and the bit depth of each candidate
+    /// channel and returns the exact size in bits of its encoded subframe.
+    pub fn choose_exact(sample_block: &[Vec <i64>], bit_depth: u8, cost: impl Fn(&[i64], u8) -> u64) -> Self {
+        Self::cheapest(sample_block, bit_depth, cost)
+    }
+
+    /// Find the channel assignment of the lowest total cost of its channels
+    /// 
+    /// Ties are resolved in favor of independent channels, then left/side,
+    /// right/side and mid/side, in this order.
+    fn cheapest(sample_block: &[Vec <i64>], bit_depth: u8, cost: impl Fn(&[i64], u8) -> u64) -> Self {
+        if sample_block.first().map(|inter_sample| inter_sample.len()) != Some(2) {
+            return ChannelAssignment::Independent;
+        }
+
+        let mid_side = ChannelAssignment::MidSide.split(sample_block);
+        let left_right = ChannelAssignment::Independent.split(sample_block);
+
+        let left = cost(&left_right[0], bit_depth);
+        let right = cost(&left_right[1], bit_depth);
+        let mid = cost(&mid_side[0], bit_depth);
+        let side = cost(&mid_side[1], bit_depth + 1);
+
+        [
+            (ChannelAssignment::Independent, left + right),
+            (ChannelAssignment::LeftSide, left + side),
+            (ChannelAssignment::RightSide, right + side),
+            (ChannelAssignment::MidSide, mid + side),
+        ]
+            .into_iter()
+            .min_by_key(|(_, total)| *total)
+            .map(|(assignment, _)| assignment)
+            .unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ASSIGNMENTS: [ChannelAssignment; 4] = [
+        ChannelAssignment::Independent,
+        ChannelAssignment::LeftSide,
+        ChannelAssignment::RightSide,
+        ChannelAssignment::MidSide,
+    ];
+
+    #[test]
+    fn sample_split_and_join() {
+        let sample_block = vec![vec![3, -4], vec![-32768, 32767], vec![7, 7], vec![-1, 0]];
+
+        for assignment in ASSIGNMENTS {
+            let channels = assignment.split(&sample_block);
+
+            assert_eq!(channels.len(), 2);
+            assert_eq!(assignment.join(&channels), sample_block, "{:?}", assignment);
+        }
+
+        assert_eq!(ChannelAssignment::MidSide.split(&sample_block), vec![vec![-1, -1, 7, -1], vec![7, -65535, 0, -1]]);
+    }
+
+    #[test]
+    fn sample_values() {
+        assert_eq!(ChannelAssignment::Independent.value(6), 5);
+        assert_eq!(ChannelAssignment::MidSide.value(2), 10);
+        assert_eq!(ChannelAssignment::from_value(5), Some((ChannelAssignment::Independent, 6)));
+        assert_eq!(ChannelAssignment::from_value(9), Some((ChannelAssignment::RightSide, 2)));
+        assert_eq!(ChannelAssignment::from_value(11), None);
+        assert_eq!(ChannelAssignment::RightSide.bit_depths(16, 2), vec![17, 16]);
+        assert_eq!(ChannelAssignment::Independent.bit_depths(24, 3), vec![24, 24, 24]);
+    }
+
+    #[test]
+    fn sample_estimate() {
+        let common: Vec <i64> = (0..64).map(|i| (i * i * 37) % 1000).collect();
+        // Channels that differ by a constant offset are cheapest with a side channel
+        let offset: Vec <Vec <i64>> = common.iter().map(|&c| vec![c, c + 1]).collect();
+        // Channels that share a common part and differ by opposite parts are cheapest as mid/side
+        let opposite: Vec <Vec <i64>> = common.iter().zip(0..).map(|(&c, i)| vec![c + 50 * ((i * 13) % 7 - 3), c - 50 * ((i * 13) % 7 - 3)]).collect();
+        // A silent right channel is cheapest as is
+        let silent_right: Vec <Vec <i64>> = common.iter().map(|&c| vec![c, 0]).collect();
+
+        assert_eq!(ChannelAssignment::estimate(&offset), ChannelAssignment::LeftSide);
+        assert_eq!(ChannelAssignment::estimate(&opposite), ChannelAssignment::MidSide);
+        assert_eq!(ChannelAssignment::estimate(&silent_right), ChannelAssignment::Independent);
+        assert_eq!(ChannelAssignment::estimate(&vec![vec![1, 2, 3]; 8]), ChannelAssignment::Independent);
+    }
+
+    #[test]
+    fn sample_choose_exact() {
+        let sample_block: Vec <Vec <i64>> = (0..16).map(|i| vec![i, i]).collect();
+        // With a cost of one bit per sample and bit of depth, the extra bit of the side channel decides
+        let depth_cost = |samples: &[i64], bit_depth: u8| samples.len() as u64 * bit_depth as u64;
+        let abs_cost = |samples: &[i64], _| samples.iter().map(|sample| sample.unsigned_abs()).sum();
+
+        assert_eq!(ChannelAssignment::choose_exact(&sample_block, 16, depth_cost), ChannelAssignment::Independent);
+        assert_eq!(ChannelAssignment::choose_exact(&sample_block, 16, abs_cost), ChannelAssignment::LeftSide);
+        assert_eq!(ChannelAssignment::choose(&sample_block, 16, StereoMode::Independent, abs_cost), ChannelAssignment::Independent);
+    }
+}
@@ -1,37 +1,32 @@
 pub struct Utf8Encoder;
 
 impl Utf8Encoder {
-    /// Encode a number into its UTF-9 equivalent encoding
+    /// Encode a number into its UTF-8 equivalent encoding
     /// 
     /// Although UTF-8 encoding is for characters, characters are
-    /// mapped to certain numbers.
+    /// mapped to certain numbers. FLAC extends the encoding up to
+    /// 7 bytes so that numbers of up to 36 bits, such as the sample
+    /// numbers of variable block-size frames, can be encoded.
     pub fn encode(num: u64) -> Vec<u8> {
         let min_num_bits = Self::min_bits_to_represent(num);
-        let num_bin_string = String::from(format!("{num:b}"));
+        let num_bin_string = format!("{num:b}");
         let template = Self::template_generator(min_num_bits);
         let unicode_binary = Self::replace_x(template, num_bin_string);
         
-        return Self::binary_string_to_u8_vector(&unicode_binary.to_string());
+        Self::binary_string_to_u8_vector(&unicode_binary)
     }
 
     // Helper Functions
     fn template_generator(min: u64) -> String {
-        if min >= 0 && min <= 7 {
-            "0xxxxxx".to_string()
-        } else if min >= 8 && min <= 11 {
-            "110xxxxx10xxxxxx".to_string()
-        } else if min >= 12 && min <= 16 {
-            "1110xxxx10xxxxxx10xxxxxx".to_string()
-        } else if min >= 17 && min <= 21 {
-            "11110xxx10xxxxxx10xxxxxx10xx xxxx".to_string()
-        } else if min >= 22 && min <= 26 {
-            "111110xx10xxxxxx10xxxxxx10xxxxxx10xxxxxx".to_string()
-        } else if min >= 27 && min <= 31 {
-            "111110xx10xxxxxx10xxxxxx10xxxxxx10xxxxxx".to_string()
-        } else if min >= 32 && min <= 40 {
-            "1111111010xxxxxx10xxxxxx10xxxxxx10xxxxxx10xxxxxx10xxxxxx".to_string()
-        } else {
-            String::new()
+        match min {
+            0..=7 => "0xxxxxxx".to_string(),
+            8..=11 => "110xxxxx10xxxxxx".to_string(),
+            12..=16 => "1110xxxx10xxxxxx10xxxxxx".to_string(),
+            17..=21 => "11110xxx10xxxxxx10xxxxxx10xxxxxx".to_string(),
+            22..=26 => "111110xx10xxxxxx10xxxxxx10xxxxxx10xxxxxx".to_string(),
+            27..=31 => "1111110x10xxxxxx10xxxxxx10xxxxxx10xxxxxx10xxxxxx".to_string(),
+            32..=36 => "1111111010xxxxxx10xxxxxx10xxxxxx10xxxxxx10xxxxxx10xxxxxx".to_string(),
+            _ => String::new(),
         }
     }
 
@@ -112,4 +107,31 @@ mod tests {
 
         assert_eq!(out_val_ans, out_val);
     }
+
+    #[test]
+    fn sample_03() {
+        let in_val = 0x64;
+        let out_val_ans = vec![0x64u8];
+        let out_val = Utf8Encoder::encode(in_val);
+
+        assert_eq!(out_val_ans, out_val);
+    }
+
+    #[test]
+    fn sample_04() {
+        let in_val = 0x4000000;
+        let out_val_ans = vec![0xfcu8, 0x84u8, 0x80u8, 0x80u8, 0x80u8, 0x80u8];
+        let out_val = Utf8Encoder::encode(in_val);
+
+        assert_eq!(out_val_ans, out_val);
+    }
+
+    #[test]
+    fn sample_05() {
+        let in_val = 0xfffffffff;
+        let out_val_ans = vec![0xfeu8, 0xbfu8, 0xbfu8, 0xbfu8, 0xbfu8, 0xbfu8, 0xbfu8];
+        let out_val = Utf8Encoder::encode(in_val);
+
+        assert_eq!(out_val_ans, out_val);
+    }
 }
\ No newline at end of file
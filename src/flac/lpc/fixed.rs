@@ -6,7 +6,7 @@ impl FixedPredictor {
     /// The predictor orders are from 0 to 4 inclusive and is retrieved
     /// by finding the predictor that yields the *minimum* absolute
     /// sum of residuals for the given `data` and derived predictor.
-    pub fn best_predictor_order(data: &Vec<i64>) -> Option<u8> {
+    pub fn best_predictor_order(data: &[i64]) -> Option<u8> {
         let res_0 = FixedPredictor::get_residuals(data, 0);
         let res_1 = FixedPredictor::get_residuals(data, 1);
         let res_2 = FixedPredictor::get_residuals(data, 2);
@@ -48,7 +48,7 @@ impl FixedPredictor {
     /// `None` is returned if an error occurs in the function. This includes whether
     /// the predictor order provided is not within 0 and 4 inclusive and whether the
     /// size of `data` is less than the predictor order.
-    pub fn get_residuals(data: &Vec <i64>, predictor_order: u8) -> Option <Vec <i64>> {
+    pub fn get_residuals(data: &[i64], predictor_order: u8) -> Option <Vec <i64>> {
         let data_len = data.len();
 
         if data_len == 0 || predictor_order > 4 {
@@ -59,7 +59,7 @@ impl FixedPredictor {
     
         match predictor_order {
             0 => {
-                residual.copy_from_slice(&data);
+                residual.copy_from_slice(data);
             }
             1 => {
                 for i in 1..data_len {
@@ -102,7 +102,7 @@ impl FixedPredictor {
     fn calculate_sum(vector: Option<Vec<i64>>) -> Option<i64> {
         match vector {
             Some(vec) => {
-                let sum: i64 = vec.iter().sum();
+                let sum: i64 = vec.iter().map(|residual| residual.abs()).sum();
                 Some(sum)
             }
             None => None,
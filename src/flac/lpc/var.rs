@@ -0,0 +1,319 @@
+/// Highest LPC order allowed in FLAC
+pub const MAX_LPC_ORDER: u8 = 32;
+
+/// The largest shift of quantized LPC coefficients, which fits the 5-bit signed shift field
+const MAX_QLP_SHIFT: i32 = 15;
+
+pub struct VarPredictor;
+
+impl VarPredictor {
+    /// Get the autocorrelation of a vector of samples
+    /// 
+    /// The function computes the autocorrelations of the provided vector of
+    /// data from `R[0]` until `R[max_lag]`. For example, if `max_lag` is 2, then
+    /// the output contains three elements corresponding to R[0] until R[3],
+    /// respectively
+    pub fn get_autocorrelation(samples: &[i64], max_lag: u8) -> Vec <f64> {
+        (0..=max_lag as usize)
+            .map(|lag| {
+                samples
+                    .iter()
+                    .zip(samples.iter().skip(lag))
+                    .map(|(&a, &b)| a as f64 * b as f64)
+                    .sum()
+            })
+            .collect()
+    }
+
+    /// Get the predictor coefficients
+    /// 
+    /// `autoc` contains the autocorrelation vector where `autoc[i]` corresponds to
+    /// the autocorrelation value of lag `i - 1`. `predictor_order` should be
+    /// less than `autoc.len()`. The coefficients are computed using the Levinson-Durbin
+    /// algorithm.
+    pub fn get_predictor_coeffs(autoc: &[f64], predictor_order: u8) -> Vec <f64> {
+        Self::build_predictor_coeffs(autoc, predictor_order)
+            .pop()
+            .unwrap_or_default()
+    }
+
+    /// Get a the list of LPC coefficients until some provided predictor order inclusive.
+    /// 
+    /// For the return value `lpc_list`, `lpc_list[i]` contains a `Vec` of coefficients
+    /// for predictor order `i + 1`. The Levinson-Durbin algorithm is used to progressively
+    /// compute the LPC coefficients across multiple predictor orders.
+    fn build_predictor_coeffs(autoc: &[f64], max_predictor_order: u8) -> Vec <Vec <f64>> {
+        let mut lpc_list = Vec::with_capacity(max_predictor_order as usize);
+        let mut lpc: Vec <f64> = Vec::with_capacity(max_predictor_order as usize);
+        let mut error = autoc[0];
+
+        for i in 0..max_predictor_order as usize {
+            let mut reflection = autoc[i + 1];
+
+            for j in 0..i {
+                reflection -= lpc[j] * autoc[i - j];
+            }
+
+            // A silent or perfectly predicted signal leaves no error to reduce
+            let reflection = if error > 0.0 { reflection / error } else { 0.0 };
+            let prev = lpc.clone();
+
+            for j in 0..i {
+                lpc[j] = prev[j] - reflection * prev[i - 1 - j];
+            }
+
+            lpc.push(reflection);
+            error *= 1.0 - reflection * reflection;
+            lpc_list.push(lpc.clone());
+        }
+
+        lpc_list
+    }
+
+    /// Quantize the predictor coefficients and find their shift factor
+    /// 
+    /// The shift factor `S` is computed from the maximum absolute value of a coefficient
+    /// `L_max`. This value is computed as `precision - lg(L_max)` or to
+    /// the maximum shift value of the 5-bit signed field, 15, whichever is smaller. Note that it is
+    /// possible for this shift factor to be negative. In that case, the shift value
+    /// will still be used in quantizing the coefficients but its effective value
+    /// will be zero.
+    /// 
+    /// Quantization involves converting the provided floating-point coefficients
+    /// into integers. Each of the values are rounded up or down depending on
+    /// some accummulated rounding error `\epsilon`. Initially, this error is zero.
+    /// For each coefficient `L_i`, the coefficient is multiplied (for positive shift)
+    /// or divided (for negative shift) by `1 << abs(S)` to get the raw value `L_i_r + \epsilon`.
+    /// Then, `L_i_r + \epsilon` is rounded away from zero to get the quantized coefficient.
+    /// The new rounding error `\epsilon = L_i_r + \epsilon - round(L_i_r)` is then updated for the
+    /// next coefficient.
+    pub fn quantize_coeffs(lpc_coefs: &[f64], mut precision: u8) -> (Vec <i64>, u8) {
+        let max_coef = lpc_coefs.iter().fold(0.0f64, |max, coef| max.max(coef.abs()));
+
+        if max_coef <= 0.0 || !max_coef.is_finite() {
+            return (vec![0; lpc_coefs.len()], 0);
+        }
+
+        // One bit of the precision holds the sign of the coefficient
+        precision -= 1;
+
+        let shift = (precision as i32 - (max_coef.log2().floor() as i32 + 1)).min(MAX_QLP_SHIFT);
+        let scale = if shift >= 0 { (1i64 << shift) as f64 } else { 1.0 / (1i64 << -shift) as f64 };
+        let max_qlp_coef = (1i64 << precision) - 1;
+        let min_qlp_coef = -(1i64 << precision);
+        let mut error = 0.0;
+
+        let qlp_coefs = lpc_coefs
+            .iter()
+            .map(|coef| {
+                error += coef * scale;
+                let qlp_coef = (error.round() as i64).clamp(min_qlp_coef, max_qlp_coef);
+                error -= qlp_coef as f64;
+
+                qlp_coef
+            })
+            .collect();
+
+        (qlp_coefs, shift.max(0) as u8)
+    }
+
+    /// Compute the residuals from a given linear predictor
+    /// 
+    /// The resulting vector `residual[i]` corresponds to the `i + predictor_order`th
+    /// signal. The first `predictor_order` values of the residual are the "warm-up"
+    /// samples, or the unencoded samples, equivalent to `&samples[..predictor_order]`.
+    /// 
+    /// The residuals are computed with the `samples` reversed. For some `i`th residual,
+    /// `residual[i] = data[i] - (sum(dot(qlp_coefs, samples[i..(i - predictor_order)])) >> qlp_shift)`.
+    pub fn get_residuals(samples: &[i64], qlp_coefs: &[i64], predictor_order: u8, qlp_shift: u8) -> Vec <i64> {
+        let order = predictor_order as usize;
+
+        (order..samples.len())
+            .map(|i| {
+                let prediction: i64 = qlp_coefs
+                    .iter()
+                    .take(order)
+                    .enumerate()
+                    .map(|(j, coef)| coef * samples[i - 1 - j])
+                    .sum();
+
+                samples[i] - (prediction >> qlp_shift)
+            })
+            .collect()
+    }
+
+    /// compute the quantized LPC coefficients, precision, and shift for the given
+    /// predictor order
+    pub fn get_predictor_coeffs_from_samples(samples: &[i64], predictor_order: u8, bps: u8, block_size: u64) -> (Vec <i64>, u8, u8) {
+        let precision = Self::get_best_precision(bps, block_size);
+        let autoc = Self::get_autocorrelation(samples, predictor_order);
+        let lpc_coefs = Self::get_predictor_coeffs(&autoc, predictor_order);
+        let (qlp_coefs, qlp_shift) = Self::quantize_coeffs(&lpc_coefs, precision);
+
+        (qlp_coefs, precision, qlp_shift)
+    }
+
+    /// Get the quantized LPC coefficients, precision, and shift for the best predictor order
+    /// for the given sample
+    /// 
+    /// This function selects the best predictor order by finding the order that yields the
+    /// absolute minimum sum of residuals. Note that the maximmum predictor order is 32.
+    pub fn get_best_lpc(samples: &[i64], bps: u8, block_size: u64) -> (Vec <i64>, u8, u8) {
+        let max_order = MAX_LPC_ORDER.min(samples.len().saturating_sub(1).min(u8::MAX as usize) as u8).max(1);
+
+        Self::get_best_lpc_windowed(samples, bps, block_size, max_order, 0, &[vec![1.0; samples.len()]])
+            .unwrap_or_else(|| Self::get_predictor_coeffs_from_samples(samples, 1, bps, block_size))
+    }
+
+    /// Get the quantized LPC coefficients, precision, and shift for the best predictor order
+    /// and window for the given sample
+    /// 
+    /// As with `get_best_lpc`, the order and window that yield the absolute minimum sum of
+    /// residuals are selected among the candidates of `get_lpc_windowed`.
+    /// 
+    /// Returns `None` if there is no candidate.
+    pub fn get_best_lpc_windowed(samples: &[i64], bps: u8, block_size: u64, max_order: u8, precision: u8, windows: &[Vec <f64>]) -> Option <(Vec <i64>, u8, u8)> {
+        Self::get_lpc_windowed(samples, bps, block_size, max_order, precision, windows)
+            .into_iter()
+            .min_by_key(|(qlp_coefs, _, qlp_shift)| {
+                Self::get_residuals(samples, qlp_coefs, qlp_coefs.len() as u8, *qlp_shift)
+                    .iter()
+                    .fold(0u64, |sum, residual| sum.saturating_add(residual.unsigned_abs()))
+            })
+    }
+
+    /// Get the quantized LPC coefficients, precision, and shift of every predictor order
+    /// and window for the given sample
+    /// 
+    /// The coefficients of every predictor order up to `max_order` are computed from the
+    /// samples weighted by each of the `windows`, and quantized with `precision` bits, or
+    /// with the precision of `get_best_precision` if it is 0. The candidates are ordered
+    /// by window first and by predictor order second.
+    /// 
+    /// Windows that leave only silence have no candidate, and neither has a `max_order` of 0.
+    pub fn get_lpc_windowed(samples: &[i64], bps: u8, block_size: u64, max_order: u8, precision: u8, windows: &[Vec <f64>]) -> Vec <(Vec <i64>, u8, u8)> {
+        let precision = if precision == 0 { Self::get_best_precision(bps, block_size) } else { precision };
+        let max_order = max_order.min(samples.len().saturating_sub(1).min(u8::MAX as usize) as u8);
+
+        if max_order == 0 {
+            return Vec::new();
+        }
+
+        windows
+            .iter()
+            .map(|window| Self::get_windowed_autocorrelation(samples, window, max_order))
+            .filter(|autoc| autoc[0] != 0.0)
+            .flat_map(|autoc| Self::build_predictor_coeffs(&autoc, max_order))
+            .map(|lpc_coefs| {
+                let (qlp_coefs, qlp_shift) = Self::quantize_coeffs(&lpc_coefs, precision);
+                (qlp_coefs, precision, qlp_shift)
+            })
+            .collect()
+    }
+
+    /// Get the autocorrelation of a vector of samples weighted by a window
+    /// 
+    /// Each sample is multiplied by the value of `window` at the same index first,
+    /// and the autocorrelations from `R[0]` until `R[max_lag]` are then computed
+    /// as with `get_autocorrelation`.
+    pub fn get_windowed_autocorrelation(samples: &[i64], window: &[f64], max_lag: u8) -> Vec <f64> {
+        let windowed: Vec <f64> = samples
+            .iter()
+            .zip(window)
+            .map(|(&sample, &weight)| sample as f64 * weight)
+            .collect();
+
+        (0..=max_lag as usize)
+            .map(|lag| windowed.iter().zip(windowed.iter().skip(lag)).map(|(a, b)| a * b).sum())
+            .collect()
+    }
+
+    /// Get the best coefficient precision
+    /// 
+    /// FLAC uses the bit depth and block size to determine the best coefficient
+    /// precision. By default, the precision is 14 bits but can be one of the
+    /// following depending on several parameters:
+    /// 
+    /// | Bit depth | Block size |     Best precision      |
+    /// |-----------|------------|-------------------------|
+    /// |   < 16    |     any    | max(1, 2 + bit_depth/2) |
+    /// |     16    |     192    |           7             |
+    /// |     16    |     384    |           8             |
+    /// |     16    |     576    |           9             |
+    /// |     16    |    1152    |          10             |
+    /// |     16    |    2304    |          11             |
+    /// |     16    |    4608    |          12             |
+    /// |     16    |     any    |          13             |
+    /// |   > 16    |     384    |          12             |
+    /// |   > 16    |    1152    |          13             |
+    /// |   > 16    |     any    |          14             |
+    pub fn get_best_precision(bps: u8, block_size: u64) -> u8 {
+        match (bps, block_size) {
+            (0..=15, _) => 1.max(2 + bps / 2),
+            (16, 0..=192) => 7,
+            (16, 193..=384) => 8,
+            (16, 385..=576) => 9,
+            (16, 577..=1152) => 10,
+            (16, 1153..=2304) => 11,
+            (16, 2305..=4608) => 12,
+            (16, _) => 13,
+            (_, 0..=384) => 12,
+            (_, 385..=1152) => 13,
+            _ => 14,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sample_ietf_02() {
+        let in_vec = vec![
+            0, 79, 111, 78,
+            8, -61, -90, -68,
+            -13, 42, 67, 53,
+            13, -27, -46, -38,
+            -12, 14, 24, 19,
+            6, -4, -5, 0,
+        ];
+
+        let out_vec_ans = vec![
+            3, -1, -13, -10,
+            -6, 2, 8, 8,
+            6, 0, -3, -5,
+            -4, -1, 1, 1,
+            4, 2, 2, 2,
+            0,
+        ];
+
+        let out_vec = VarPredictor::get_residuals(&in_vec, &[7, -6, 2], 3, 2);
+
+        assert_eq!(out_vec_ans, out_vec);
+    }
+
+    #[test]
+    fn sample_windowed_autocorrelation() {
+        let autoc = VarPredictor::get_windowed_autocorrelation(&[1, 2, 3], &[1.0, 1.0, 0.5], 2);
+
+        assert_eq!(autoc, vec![7.25, 5.0, 1.5]);
+    }
+
+    #[test]
+    fn sample_predictor_coeffs() {
+        // A second-order recurrence is predicted exactly from its autocorrelation
+        let samples: Vec <i64> = (0..64).map(|i| (1000.0 * (i as f64 * 0.3).sin()) as i64).collect();
+        let (qlp_coefs, precision, qlp_shift) = VarPredictor::get_predictor_coeffs_from_samples(&samples, 2, 16, 64);
+
+        assert_eq!(precision, 7);
+        assert_eq!(qlp_coefs.len(), 2);
+        assert!(qlp_coefs.iter().all(|coef| (-64..64).contains(coef)));
+
+        let residuals = VarPredictor::get_residuals(&samples, &qlp_coefs, 2, qlp_shift);
+        let residual_sum: i64 = residuals.iter().map(|residual| residual.abs()).sum();
+        let sample_sum: i64 = samples.iter().map(|sample| sample.abs()).sum();
+
+        assert!(residual_sum * 10 < sample_sum);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,139 @@
+use std::fs::File;
+use std::io::BufReader;
+
+use super::{MetadataBlock, MetadataBlockType, MetadataError};
+
+/// Length of an application ID in bytes
+pub const APPLICATION_ID_LEN: usize = 4;
+
+/// Represents an APPLICATION metadata block
+/// 
+/// An APPLICATION block holds proprietary data of a third-party application,
+/// identified by a 4-byte application ID registered with Xiph.Org so that
+/// applications do not mistake each other's data. The payload is opaque to
+/// FLAC. A file may hold any number of APPLICATION blocks, including several
+/// with the same ID, and their order is kept.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApplicationBlock {
+    pub id: [u8; APPLICATION_ID_LEN],
+    pub data: Vec <u8>,
+}
+
+impl ApplicationBlock {
+    /// Create a new APPLICATION block
+    pub fn new(id: [u8; APPLICATION_ID_LEN], data: Vec <u8>) -> Self {
+        Self {
+            id,
+            data,
+        }
+    }
+
+    /// Parse an APPLICATION metadata block
+    /// 
+    /// # Errors
+    /// Returns `MetadataError::BlockTypeError` if `block` is not an APPLICATION
+    /// block, and `MetadataError::BlockDataError` if it is too short to hold an
+    /// application ID.
+    pub fn parse(block: &MetadataBlock) -> Result <Self, MetadataError> {
+        if block.block_type != MetadataBlockType::Application {
+            return Err(MetadataError::BlockTypeError);
+        }
+
+        if block.data.len() < APPLICATION_ID_LEN {
+            return Err(MetadataError::BlockDataError);
+        }
+
+        let (id, data) = block.data.split_at(APPLICATION_ID_LEN);
+
+        Ok(Self::new([id[0], id[1], id[2], id[3]], data.to_vec()))
+    }
+
+    /// Get all APPLICATION blocks among metadata blocks in order
+    /// 
+    /// # Errors
+    /// Returns `MetadataError::BlockDataError` if an APPLICATION block is malformed.
+    pub fn from_blocks(blocks: &[MetadataBlock]) -> Result <Vec <Self>, MetadataError> {
+        blocks
+            .iter()
+            .filter(|block| block.block_type == MetadataBlockType::Application)
+            .map(Self::parse)
+            .collect()
+    }
+
+    /// Read all APPLICATION blocks of a FLAC file in order
+    /// 
+    /// # Errors
+    /// Returns a `MetadataError` if the file cannot be read or its metadata is
+    /// malformed.
+    pub fn read_all(file_path: &str) -> Result <Vec <Self>, MetadataError> {
+        let mut reader = BufReader::new(File::open(file_path)?);
+
+        Self::from_blocks(&MetadataBlock::read_all(&mut reader)?)
+    }
+
+    /// Read the payloads of the APPLICATION blocks of a FLAC file with the given ID
+    /// 
+    /// # Errors
+    /// Returns a `MetadataError` if the file cannot be read or its metadata is
+    /// malformed.
+    pub fn extract(file_path: &str, id: &[u8; APPLICATION_ID_LEN]) -> Result <Vec <Vec <u8>>, MetadataError> {
+        Ok(Self::read_all(file_path)?
+            .into_iter()
+            .filter(|application| &application.id == id)
+            .map(|application| application.data)
+            .collect())
+    }
+
+    /// Convert this APPLICATION block into its block data
+    pub fn build_bytes(&self) -> Vec <u8> {
+        let mut bytes = Vec::with_capacity(APPLICATION_ID_LEN + self.data.len());
+
+        bytes.extend_from_slice(&self.id);
+        bytes.extend_from_slice(&self.data);
+
+        bytes
+    }
+
+    /// Convert this APPLICATION block into a metadata block
+    pub fn build_block(&self) -> MetadataBlock {
+        MetadataBlock::new(MetadataBlockType::Application, self.build_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sample_build_and_parse() {
+        let application = ApplicationBlock::new(*b"xbat", vec![1, 2, 3]);
+        let block = application.build_block();
+
+        assert_eq!(block.data, vec![b'x', b'b', b'a', b't', 1, 2, 3]);
+        assert_eq!(ApplicationBlock::parse(&block).unwrap(), application);
+    }
+
+    #[test]
+    fn sample_from_blocks() {
+        let blocks = vec![
+            MetadataBlock::new(MetadataBlockType::Streaminfo, vec![0; 34]),
+            ApplicationBlock::new(*b"abcd", vec![1]).build_block(),
+            MetadataBlock::padding(16),
+            ApplicationBlock::new(*b"wxyz", Vec::new()).build_block(),
+            ApplicationBlock::new(*b"abcd", vec![2]).build_block(),
+        ];
+        let applications = ApplicationBlock::from_blocks(&blocks).unwrap();
+
+        assert_eq!(applications.len(), 3);
+        assert_eq!(applications[1], ApplicationBlock::new(*b"wxyz", Vec::new()));
+        assert_eq!(applications[2].data, vec![2]);
+    }
+
+    #[test]
+    fn sample_parse_errors() {
+        let truncated = MetadataBlock::new(MetadataBlockType::Application, vec![b'a', b'b']);
+
+        assert!(matches!(ApplicationBlock::parse(&truncated), Err(MetadataError::BlockDataError)));
+        assert!(matches!(ApplicationBlock::parse(&MetadataBlock::padding(8)), Err(MetadataError::BlockTypeError)));
+    }
+}
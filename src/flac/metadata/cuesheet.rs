@@ -0,0 +1,287 @@
+use crate::cue::CueSheet;
+
+use super::{split_data, MetadataBlock, MetadataBlockType, MetadataError};
+
+/// Track number of the lead-out track of a non-CD cue sheet
+pub const LEAD_OUT_TRACK_NUMBER: u8 = 255;
+
+/// Track number of the lead-out track of a CD-DA cue sheet
+pub const CD_LEAD_OUT_TRACK_NUMBER: u8 = 170;
+
+/// Represents a CUESHEET metadata block
+/// 
+/// All offsets are in inter-channel samples. The offset of a track is
+/// relative to the start of the audio, and the offsets of its index points
+/// are relative to the offset of the track. The last track of `tracks` must
+/// be the lead-out track, which has no index points and whose offset is the
+/// total number of samples.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CuesheetBlock {
+    pub catalog: String,
+    pub lead_in: u64,
+    pub is_cd: bool,
+    pub tracks: Vec <CuesheetTrack>,
+}
+
+/// Represents a track of a CUESHEET metadata block
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CuesheetTrack {
+    pub offset: u64,
+    pub number: u8,
+    pub isrc: String,
+    pub is_audio: bool,
+    pub pre_emphasis: bool,
+    pub indices: Vec <CuesheetIndex>,
+}
+
+/// Represents an index point of a track of a CUESHEET metadata block
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CuesheetIndex {
+    pub offset: u64,
+    pub number: u8,
+}
+
+impl CuesheetBlock {
+    /// Create a non-CD CUESHEET block from a cue sheet
+    /// 
+    /// Each track starts at its `INDEX 00` if it has one, with `INDEX 01`
+    /// following at its start. A lead-out track at `total_samples` is
+    /// appended after the tracks of the cue sheet.
+    pub fn from_cue_sheet(cue_sheet: &CueSheet, total_samples: u64) -> Self {
+        let mut tracks: Vec <CuesheetTrack> = cue_sheet.tracks
+            .iter()
+            .map(|track| {
+                let offset = track.pregap_start.unwrap_or(track.start);
+                let mut indices = Vec::new();
+
+                if track.pregap_start.is_some() {
+                    indices.push(CuesheetIndex {
+                        offset: 0,
+                        number: 0,
+                    });
+                }
+                indices.push(CuesheetIndex {
+                    offset: track.start - offset,
+                    number: 1,
+                });
+
+                CuesheetTrack {
+                    offset,
+                    number: track.number,
+                    isrc: track.isrc.clone().unwrap_or_default(),
+                    is_audio: true,
+                    pre_emphasis: false,
+                    indices,
+                }
+            })
+            .collect();
+
+        tracks.push(CuesheetTrack {
+            offset: total_samples,
+            number: LEAD_OUT_TRACK_NUMBER,
+            isrc: String::new(),
+            is_audio: true,
+            pre_emphasis: false,
+            indices: Vec::new(),
+        });
+
+        Self {
+            catalog: cue_sheet.catalog.clone().unwrap_or_default(),
+            lead_in: 0,
+            is_cd: false,
+            tracks,
+        }
+    }
+
+    /// Parse a CUESHEET metadata block, as laid out by `build_bytes`
+    /// 
+    /// The catalog number and the ISRCs are read up to their first zero byte.
+    /// 
+    /// # Errors
+    /// Returns `MetadataError::BlockTypeError` if `block` is not a CUESHEET
+    /// block, and `MetadataError::BlockDataError` if it is truncated or holds
+    /// strings that are not ASCII.
+    pub fn parse(block: &MetadataBlock) -> Result <Self, MetadataError> {
+        if block.block_type != MetadataBlockType::Cuesheet {
+            return Err(MetadataError::BlockTypeError);
+        }
+
+        let mut data = block.data.as_slice();
+        let catalog = Self::unpadded(split_data(&mut data, 128)?)?;
+        let lead_in = u64::from_be_bytes(split_data(&mut data, 8)?.try_into().unwrap());
+        let is_cd = split_data(&mut data, 1)?[0] & 0x80 != 0;
+
+        split_data(&mut data, 258)?;
+
+        let num_tracks = split_data(&mut data, 1)?[0] as usize;
+        let mut tracks = Vec::with_capacity(num_tracks);
+
+        for _ in 0..num_tracks {
+            let header = split_data(&mut data, 36)?;
+            let mut indices = Vec::with_capacity(header[35] as usize);
+
+            for _ in 0..header[35] {
+                let index = split_data(&mut data, 12)?;
+
+                indices.push(CuesheetIndex {
+                    offset: u64::from_be_bytes(index[..8].try_into().unwrap()),
+                    number: index[8],
+                });
+            }
+
+            tracks.push(CuesheetTrack {
+                offset: u64::from_be_bytes(header[..8].try_into().unwrap()),
+                number: header[8],
+                isrc: Self::unpadded(&header[9..21])?,
+                is_audio: header[21] & 0x80 == 0,
+                pre_emphasis: header[21] & 0x40 != 0,
+                indices,
+            });
+        }
+
+        Ok(Self {
+            catalog,
+            lead_in,
+            is_cd,
+            tracks,
+        })
+    }
+
+    /// Convert this CUESHEET block into its block data
+    /// 
+    /// The catalog number and the ISRCs are ASCII strings padded with
+    /// zero bytes to 128 and 12 bytes respectively, and truncated if longer.
+    pub fn build_bytes(&self) -> Vec <u8> {
+        let mut bytes = Vec::new();
+
+        bytes.extend_from_slice(&Self::padded(&self.catalog, 128));
+        bytes.extend_from_slice(&self.lead_in.to_be_bytes());
+        bytes.push((self.is_cd as u8) << 7);
+        bytes.extend_from_slice(&[0; 258]);
+        bytes.push(self.tracks.len() as u8);
+
+        for track in &self.tracks {
+            bytes.extend_from_slice(&track.offset.to_be_bytes());
+            bytes.push(track.number);
+            bytes.extend_from_slice(&Self::padded(&track.isrc, 12));
+            bytes.push(((!track.is_audio as u8) << 7) | ((track.pre_emphasis as u8) << 6));
+            bytes.extend_from_slice(&[0; 13]);
+            bytes.push(track.indices.len() as u8);
+
+            for index in &track.indices {
+                bytes.extend_from_slice(&index.offset.to_be_bytes());
+                bytes.push(index.number);
+                bytes.extend_from_slice(&[0; 3]);
+            }
+        }
+
+        bytes
+    }
+
+    /// Convert this CUESHEET block into a metadata block
+    pub fn build_block(&self) -> MetadataBlock {
+        MetadataBlock::new(MetadataBlockType::Cuesheet, self.build_bytes())
+    }
+
+    /// Read an ASCII string padded with zero bytes
+    fn unpadded(bytes: &[u8]) -> Result <String, MetadataError> {
+        let len = bytes.iter().position(|&byte| byte == 0).unwrap_or(bytes.len());
+
+        if !bytes[..len].is_ascii() {
+            return Err(MetadataError::BlockDataError);
+        }
+
+        Ok(String::from_utf8_lossy(&bytes[..len]).into_owned())
+    }
+
+    /// Pad or truncate a string to exactly `len` bytes
+    fn padded(value: &str, len: usize) -> Vec <u8> {
+        let mut bytes = value.as_bytes().to_vec();
+        bytes.resize(len, 0);
+
+        bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cue::CueTrack;
+
+    fn cue_track(number: u8, pregap_start: Option <u64>, start: u64) -> CueTrack {
+        CueTrack {
+            number,
+            title: None,
+            performer: None,
+            isrc: None,
+            pregap_start,
+            start,
+        }
+    }
+
+    #[test]
+    fn sample_from_cue_sheet() {
+        let cue_sheet = CueSheet {
+            title: None,
+            performer: None,
+            catalog: Some(String::from("1234567890123")),
+            tracks: vec![cue_track(1, None, 0), cue_track(2, Some(900), 1000)],
+        };
+        let block = CuesheetBlock::from_cue_sheet(&cue_sheet, 5000);
+
+        assert_eq!(block.catalog, "1234567890123");
+        assert_eq!(block.tracks.len(), 3);
+        assert_eq!(block.tracks[0].indices, vec![CuesheetIndex { offset: 0, number: 1 }]);
+        assert_eq!(block.tracks[1].offset, 900);
+        assert_eq!(block.tracks[1].indices, vec![
+            CuesheetIndex { offset: 0, number: 0 },
+            CuesheetIndex { offset: 100, number: 1 },
+        ]);
+        assert_eq!(block.tracks[2].offset, 5000);
+        assert_eq!(block.tracks[2].number, LEAD_OUT_TRACK_NUMBER);
+        assert!(block.tracks[2].indices.is_empty());
+    }
+
+    #[test]
+    fn sample_build_bytes() {
+        let cue_sheet = CueSheet {
+            title: None,
+            performer: None,
+            catalog: None,
+            tracks: vec![cue_track(1, None, 0)],
+        };
+        let bytes = CuesheetBlock::from_cue_sheet(&cue_sheet, 0x1234).build_bytes();
+
+        // Header, one track with one index point, and the lead-out track
+        assert_eq!(bytes.len(), 396 + (36 + 12) + 36);
+        assert_eq!(bytes[395], 2);
+
+        let track = &bytes[396..432];
+        assert_eq!(&track[0..9], &[0, 0, 0, 0, 0, 0, 0, 0, 1]);
+        assert_eq!(track[35], 1);
+        assert_eq!(&bytes[432..444], &[0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0]);
+
+        let lead_out = &bytes[444..];
+        assert_eq!(&lead_out[0..9], &[0, 0, 0, 0, 0, 0, 0x12, 0x34, LEAD_OUT_TRACK_NUMBER]);
+        assert_eq!(lead_out[35], 0);
+    }
+
+    #[test]
+    fn sample_parse() {
+        let cue_sheet = CueSheet {
+            title: None,
+            performer: None,
+            catalog: Some(String::from("1234567890123")),
+            tracks: vec![cue_track(1, None, 0), cue_track(2, Some(900), 1000)],
+        };
+        let mut block = CuesheetBlock::from_cue_sheet(&cue_sheet, 5000);
+        block.tracks[0].isrc = String::from("USRC17607839");
+        block.tracks[1].pre_emphasis = true;
+
+        let metadata_block = block.build_block();
+        assert_eq!(CuesheetBlock::parse(&metadata_block).unwrap(), block);
+
+        let truncated = MetadataBlock::new(MetadataBlockType::Cuesheet, metadata_block.data[..500].to_vec());
+        assert!(matches!(CuesheetBlock::parse(&truncated), Err(MetadataError::BlockDataError)));
+    }
+}
@@ -0,0 +1,307 @@
+use super::application::ApplicationBlock;
+use super::cuesheet::CuesheetBlock;
+use super::picture::Picture;
+use super::seektable::SeekTable;
+use super::streaminfo::StreamInfo;
+use super::updater::{MetadataUpdater, UpdateMode};
+use super::vorbis_comment::VorbisComment;
+use super::{MetadataBlock, MetadataBlockType, MetadataError, MAX_BLOCK_DATA_LEN};
+
+/// Represents a metadata block parsed into the struct of its type
+/// 
+/// A PADDING block is given by its length in bytes, and a block of a reserved
+/// type is kept as it is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TypedBlock {
+    Streaminfo(StreamInfo),
+    Padding(usize),
+    Application(ApplicationBlock),
+    Seektable(SeekTable),
+    VorbisComment(VorbisComment),
+    Cuesheet(CuesheetBlock),
+    Picture(Picture),
+    Reserved(MetadataBlock),
+}
+
+/// Represents an editor of the metadata blocks of an existing FLAC file, in the manner of `metaflac`
+/// 
+/// The metadata blocks are parsed when the file is opened, and can then be
+/// added, removed or changed freely. The changes are written by a
+/// `MetadataUpdater`, so the audio frames are copied verbatim and never
+/// re-encoded: the metadata is overwritten in place if it fits the space of
+/// the old metadata, and the file is otherwise rewritten next to the old one
+/// and renamed over it. PADDING blocks are merged into one at the end of the
+/// metadata as described by `MetadataUpdater`.
+pub struct MetadataEditor {
+    updater: MetadataUpdater,
+    blocks: Vec <TypedBlock>,
+}
+
+impl TypedBlock {
+    /// Parse a metadata block into the struct of its type
+    /// 
+    /// # Errors
+    /// Returns `MetadataError::StreaminfoError` if a STREAMINFO block is not
+    /// 34 bytes long, and `MetadataError::BlockDataError` if a block of any
+    /// other type is malformed.
+    pub fn parse(block: &MetadataBlock) -> Result <Self, MetadataError> {
+        let typed_block = match block.block_type {
+            MetadataBlockType::Streaminfo => TypedBlock::Streaminfo(StreamInfo::parse(&block.data).ok_or(MetadataError::StreaminfoError)?),
+            MetadataBlockType::Padding => TypedBlock::Padding(block.data.len()),
+            MetadataBlockType::Application => TypedBlock::Application(ApplicationBlock::parse(block)?),
+            MetadataBlockType::Seektable => TypedBlock::Seektable(SeekTable::parse(block)?),
+            MetadataBlockType::VorbisComment => TypedBlock::VorbisComment(VorbisComment::parse(&block.data)?),
+            MetadataBlockType::Cuesheet => TypedBlock::Cuesheet(CuesheetBlock::parse(block)?),
+            MetadataBlockType::Picture => TypedBlock::Picture(Picture::parse(block)?),
+            MetadataBlockType::Reserved(_) => TypedBlock::Reserved(block.clone()),
+        };
+
+        Ok(typed_block)
+    }
+
+    /// Get the type of this metadata block
+    pub fn block_type(&self) -> MetadataBlockType {
+        match self {
+            TypedBlock::Streaminfo(_) => MetadataBlockType::Streaminfo,
+            TypedBlock::Padding(_) => MetadataBlockType::Padding,
+            TypedBlock::Application(_) => MetadataBlockType::Application,
+            TypedBlock::Seektable(_) => MetadataBlockType::Seektable,
+            TypedBlock::VorbisComment(_) => MetadataBlockType::VorbisComment,
+            TypedBlock::Cuesheet(_) => MetadataBlockType::Cuesheet,
+            TypedBlock::Picture(_) => MetadataBlockType::Picture,
+            TypedBlock::Reserved(block) => block.block_type,
+        }
+    }
+
+    /// Convert this metadata block back into a metadata block of bytes
+    pub fn build_block(&self) -> MetadataBlock {
+        match self {
+            TypedBlock::Streaminfo(streaminfo) => streaminfo.build_block(),
+            TypedBlock::Padding(len) => MetadataBlock::padding(*len),
+            TypedBlock::Application(application) => application.build_block(),
+            TypedBlock::Seektable(seek_table) => seek_table.build_block(),
+            TypedBlock::VorbisComment(comments) => comments.build_block(),
+            TypedBlock::Cuesheet(cuesheet) => cuesheet.build_block(),
+            TypedBlock::Picture(picture) => picture.build_block(),
+            TypedBlock::Reserved(block) => block.clone(),
+        }
+    }
+}
+
+impl MetadataEditor {
+    /// Open a FLAC file and parse its metadata blocks
+    /// 
+    /// # Errors
+    /// Returns the errors of `MetadataUpdater::open` and of `TypedBlock::parse`.
+    pub fn open(file_path: &str) -> Result <Self, MetadataError> {
+        let updater = MetadataUpdater::open(file_path)?;
+        let blocks = updater.blocks()
+            .iter()
+            .map(TypedBlock::parse)
+            .collect::<Result <Vec <TypedBlock>, MetadataError>>()?;
+
+        Ok(Self {
+            updater,
+            blocks,
+        })
+    }
+
+    /// Get the metadata blocks
+    pub fn blocks(&self) -> &Vec <TypedBlock> {
+        &self.blocks
+    }
+
+    /// Get the metadata blocks for changing
+    pub fn blocks_mut(&mut self) -> &mut Vec <TypedBlock> {
+        &mut self.blocks
+    }
+
+    /// Get the STREAMINFO block, if it was not removed
+    pub fn streaminfo(&self) -> Option <&StreamInfo> {
+        self.blocks.iter().find_map(|block| match block {
+            TypedBlock::Streaminfo(streaminfo) => Some(streaminfo),
+            _ => None,
+        })
+    }
+
+    /// Get the Vorbis comments of the first VORBIS_COMMENT block, if any
+    pub fn vorbis_comment(&self) -> Option <&VorbisComment> {
+        self.blocks.iter().find_map(|block| match block {
+            TypedBlock::VorbisComment(comments) => Some(comments),
+            _ => None,
+        })
+    }
+
+    /// Get the pictures of all PICTURE blocks in order
+    pub fn pictures(&self) -> Vec <&Picture> {
+        self.blocks
+            .iter()
+            .filter_map(|block| match block {
+                TypedBlock::Picture(picture) => Some(picture),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Add a metadata block after the other blocks
+    pub fn add(&mut self, block: TypedBlock) {
+        self.blocks.push(block);
+    }
+
+    /// Remove all metadata blocks of a type and return how many were removed
+    /// 
+    /// The STREAMINFO block is mandatory, so it is never removed.
+    pub fn remove(&mut self, block_type: MetadataBlockType) -> usize {
+        if block_type == MetadataBlockType::Streaminfo {
+            return 0;
+        }
+
+        let num_blocks = self.blocks.len();
+        self.blocks.retain(|block| block.block_type() != block_type);
+
+        num_blocks - self.blocks.len()
+    }
+
+    /// Set the size of the PADDING block written if the file has to be rewritten
+    /// 
+    /// # Errors
+    /// Returns `MetadataError::BlockDataError` if `padding_len` is more than
+    /// `MAX_BLOCK_DATA_LEN` bytes.
+    pub fn set_padding_len(&mut self, padding_len: usize) -> Result <(), MetadataError> {
+        self.updater.set_padding_len(padding_len)
+    }
+
+    /// Write the changed metadata blocks to the file
+    /// 
+    /// Returns whether the metadata was updated in place or by rewriting the file.
+    /// The file is left untouched if the metadata blocks are not valid.
+    /// 
+    /// # Errors
+    /// Returns `MetadataError::StreaminfoError` if the first block is not the
+    /// only STREAMINFO block, `MetadataError::BlockDataError` if a block is too
+    /// long, if there is more than one SEEKTABLE or VORBIS_COMMENT block, or if
+    /// the pictures break the rules of `Picture::validate_set`, and the errors
+    /// of `MetadataUpdater::write`.
+    pub fn write(mut self) -> Result <UpdateMode, MetadataError> {
+        let count = |block_type| self.blocks.iter().filter(|block| block.block_type() == block_type).count();
+
+        if self.blocks.first().map(TypedBlock::block_type) != Some(MetadataBlockType::Streaminfo) || count(MetadataBlockType::Streaminfo) > 1 {
+            return Err(MetadataError::StreaminfoError);
+        }
+        if count(MetadataBlockType::Seektable) > 1 || count(MetadataBlockType::VorbisComment) > 1 {
+            return Err(MetadataError::BlockDataError);
+        }
+
+        let pictures: Vec <Picture> = self.pictures().into_iter().cloned().collect();
+        Picture::validate_set(&pictures).map_err(|_| MetadataError::BlockDataError)?;
+
+        let blocks: Vec <MetadataBlock> = self.blocks.iter().map(TypedBlock::build_block).collect();
+
+        if blocks.iter().any(|block| block.data.len() > MAX_BLOCK_DATA_LEN) {
+            return Err(MetadataError::BlockDataError);
+        }
+
+        *self.updater.blocks_mut() = blocks;
+        self.updater.write()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::io;
+
+    use crate::flac::metadata::fixtures::{self, AUDIO};
+    use crate::flac::metadata::picture::PictureType;
+
+    fn create_flac(file_name: &str) -> Result <(), io::Error> {
+        fixtures::create_flac(file_name, &[
+            MetadataBlock::new(MetadataBlockType::Reserved(9), vec![7, 7]),
+            MetadataBlock::padding(16),
+        ])
+    }
+
+    fn picture() -> Picture {
+        Picture {
+            picture_type: PictureType::FrontCover,
+            mime_type: String::from("image/png"),
+            description: String::from("Cover"),
+            width: 1,
+            height: 1,
+            depth: 24,
+            num_colors: 0,
+            data: vec![0xaa; 100],
+        }
+    }
+
+    #[test]
+    fn sample_edit() -> Result <(), MetadataError> {
+        let file_name = "midp_sample_edit.flac.part";
+        create_flac(file_name)?;
+
+        let result = (|| {
+            let mut editor = MetadataEditor::open(file_name)?;
+            assert_eq!(editor.blocks().len(), 4);
+            assert_eq!(editor.streaminfo().map(|streaminfo| streaminfo.sample_rate), Some(44100));
+            assert_eq!(editor.vorbis_comment().and_then(|comments| comments.get_first("TITLE")), Some("Old"));
+            assert_eq!(editor.blocks()[3], TypedBlock::Padding(16));
+
+            assert_eq!(editor.remove(MetadataBlockType::VorbisComment), 1);
+            assert_eq!(editor.remove(MetadataBlockType::Streaminfo), 0);
+            editor.add(TypedBlock::Picture(picture()));
+            editor.add(TypedBlock::Application(ApplicationBlock::new(*b"test", vec![1, 2, 3])));
+
+            if let Some(TypedBlock::Streaminfo(streaminfo)) = editor.blocks_mut().first_mut() {
+                streaminfo.total_samples = 1;
+            }
+
+            let mode = editor.write()?;
+            let reopened = MetadataEditor::open(file_name)?;
+
+            Ok::<_, MetadataError>((mode, reopened.blocks().clone(), fs::read(file_name)?))
+        })();
+        fs::remove_file(file_name)?;
+
+        let (mode, blocks, bytes) = result?;
+        assert_eq!(mode, UpdateMode::Rewrite);
+        assert!(bytes.ends_with(&AUDIO));
+        assert_eq!(blocks.len(), 5);
+        assert!(matches!(&blocks[0], TypedBlock::Streaminfo(streaminfo) if streaminfo.total_samples == 1));
+        assert_eq!(blocks[1], TypedBlock::Reserved(MetadataBlock::new(MetadataBlockType::Reserved(9), vec![7, 7])));
+        assert_eq!(blocks[2], TypedBlock::Picture(picture()));
+        assert_eq!(blocks[3].block_type(), MetadataBlockType::Application);
+        assert_eq!(blocks[4].block_type(), MetadataBlockType::Padding);
+
+        Ok(())
+    }
+
+    #[test]
+    fn sample_invalid_edit() -> Result <(), MetadataError> {
+        let file_name = "midp_sample_invalid_edit.flac.part";
+        create_flac(file_name)?;
+        let before = fs::read(file_name)?;
+
+        let results = (|| {
+            let mut editor = MetadataEditor::open(file_name)?;
+            let streaminfo = editor.streaminfo().cloned().unwrap();
+            editor.add(TypedBlock::Streaminfo(streaminfo));
+            let duplicate_streaminfo = editor.write();
+
+            let mut editor = MetadataEditor::open(file_name)?;
+            editor.add(TypedBlock::VorbisComment(VorbisComment::new("v")));
+            let duplicate_comments = editor.write();
+
+            Ok::<_, MetadataError>((duplicate_streaminfo, duplicate_comments))
+        })();
+        let after = fs::read(file_name)?;
+        fs::remove_file(file_name)?;
+
+        let (duplicate_streaminfo, duplicate_comments) = results?;
+        assert!(matches!(duplicate_streaminfo, Err(MetadataError::StreaminfoError)));
+        assert!(matches!(duplicate_comments, Err(MetadataError::BlockDataError)));
+        assert_eq!(after, before);
+
+        Ok(())
+    }
+}
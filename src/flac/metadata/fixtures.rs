@@ -0,0 +1,40 @@
+use std::fs;
+use std::io;
+
+use crate::wav::PCMWaveFormatChunk;
+
+use super::MetadataBlock;
+use super::streaminfo::StreamInfo;
+use super::vorbis_comment::VorbisComment;
+
+/// Bytes standing in for the audio frames of a test file
+pub const AUDIO: [u8; 6] = [0xff, 0xf8, 0x01, 0x02, 0x03, 0x04];
+
+/// Write a FLAC file for the tests of the metadata editors
+/// 
+/// The file starts with a STREAMINFO block of 16-bit stereo audio at 44100 Hz
+/// and a VORBIS_COMMENT block with the title `Old`, followed by `blocks` and
+/// then by `AUDIO`.
+pub fn create_flac(file_name: &str, blocks: &[MetadataBlock]) -> Result <(), io::Error> {
+    let streaminfo = StreamInfo::new(&PCMWaveFormatChunk {
+        num_channels: 2,
+        samp_rate: 44100,
+        bps: 16,
+        channel_mask: None,
+    });
+    let mut comments = VorbisComment::new("v");
+    comments.add("TITLE", "Old").unwrap();
+
+    let blocks: Vec <MetadataBlock> = [streaminfo.build_block(), comments.build_block()]
+        .into_iter()
+        .chain(blocks.iter().cloned())
+        .collect();
+
+    let mut bytes = b"fLaC".to_vec();
+    for (i, block) in blocks.iter().enumerate() {
+        bytes.extend(block.build_bytes(i + 1 == blocks.len()));
+    }
+    bytes.extend_from_slice(&AUDIO);
+
+    fs::write(file_name, bytes)
+}
@@ -0,0 +1,281 @@
+use core::fmt;
+use std::error;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+use crate::wav::{WaveReader, WaveReaderError};
+
+use super::application::ApplicationBlock;
+use super::MetadataBlock;
+
+/// Application ID of the APPLICATION blocks holding RIFF chunks
+pub const RIFF_APPLICATION_ID: [u8; 4] = *b"riff";
+
+/// Length of the RIFF header and of a RIFF chunk header in bytes
+const RIFF_HEADER_LEN: usize = 12;
+const CHUNK_HEADER_LEN: usize = 8;
+
+/// Represents the non-audio parts of a WAV file stored in a FLAC file
+/// 
+/// This follows the layout of `flac --keep-foreign-metadata`. Each part is
+/// stored as an APPLICATION block with the `riff` application ID, in the
+/// order of the WAV file: first the 12-byte RIFF header, then every chunk
+/// with its header and pad byte, except for the `data` chunk, of which only
+/// the 8-byte header is stored. The audio itself comes from the FLAC audio
+/// frames, so the original WAV file can be rebuilt byte for byte.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForeignMetadata {
+    chunks: Vec <Vec <u8>>,
+}
+
+/// Represents an error in foreign metadata
+#[derive(Debug)]
+pub enum ForeignMetadataError {
+    ReadError,
+    WriteError,
+    WaveError,
+    HeaderError,
+    DataChunkError,
+}
+
+impl ForeignMetadata {
+    /// Read the non-audio parts of a WAV file
+    /// 
+    /// # Errors
+    /// Returns `ForeignMetadataError::WaveError` if the file is not a RIFF WAVE
+    /// file, and `ForeignMetadataError::DataChunkError` if it does not have
+    /// exactly one `data` chunk.
+    pub fn read_wave(file_path: &str) -> Result <Self, ForeignMetadataError> {
+        let mut fh = File::open(file_path)?;
+        let file_len = fh.metadata()?.len();
+        let locations = WaveReader::read_chunk_list(&mut fh)?;
+
+        let mut header = vec![0u8; RIFF_HEADER_LEN];
+        fh.seek(SeekFrom::Start(0))?;
+        fh.read_exact(&mut header)?;
+
+        let mut chunks = vec![header];
+
+        for location in &locations {
+            let start = location.offset - CHUNK_HEADER_LEN as u64;
+            let end = if &location.id == b"data" {
+                location.offset
+            }
+            else {
+                // The pad byte of the last chunk may be missing
+                (location.offset + location.size as u64 + (location.size as u64 & 1)).min(file_len)
+            };
+
+            let mut chunk = vec![0u8; (end - start) as usize];
+            fh.seek(SeekFrom::Start(start))?;
+            fh.read_exact(&mut chunk)?;
+
+            chunks.push(chunk);
+        }
+
+        let foreign = Self {
+            chunks,
+        };
+        foreign.data_header()?;
+
+        Ok(foreign)
+    }
+
+    /// Get the foreign metadata stored in metadata blocks, if any
+    /// 
+    /// # Errors
+    /// Returns `ForeignMetadataError::HeaderError` if the `riff` blocks do not
+    /// start with a RIFF header or hold a truncated chunk, and
+    /// `ForeignMetadataError::DataChunkError` if they do not hold exactly one
+    /// `data` chunk header.
+    pub fn from_blocks(blocks: &[MetadataBlock]) -> Result <Option <Self>, ForeignMetadataError> {
+        let chunks: Vec <Vec <u8>> = ApplicationBlock::from_blocks(blocks)
+            .map_err(|_| ForeignMetadataError::HeaderError)?
+            .into_iter()
+            .filter(|application| application.id == RIFF_APPLICATION_ID)
+            .map(|application| application.data)
+            .collect();
+
+        if chunks.is_empty() {
+            return Ok(None);
+        }
+
+        let header = &chunks[0];
+        if header.len() != RIFF_HEADER_LEN || &header[0..4] != b"RIFF" || &header[8..12] != b"WAVE" {
+            return Err(ForeignMetadataError::HeaderError);
+        }
+        if chunks[1..].iter().any(|chunk| chunk.len() < CHUNK_HEADER_LEN) {
+            return Err(ForeignMetadataError::HeaderError);
+        }
+
+        let foreign = Self {
+            chunks,
+        };
+        foreign.data_header()?;
+
+        Ok(Some(foreign))
+    }
+
+    /// Get the size in bytes of the audio of the `data` chunk
+    pub fn data_size(&self) -> u32 {
+        self.data_header()
+            .map(|idx| u32::from_le_bytes([self.chunks[idx][4], self.chunks[idx][5], self.chunks[idx][6], self.chunks[idx][7]]))
+            .unwrap_or(0)
+    }
+
+    /// Convert the foreign metadata into `riff` APPLICATION blocks
+    pub fn build_blocks(&self) -> Vec <MetadataBlock> {
+        self.chunks
+            .iter()
+            .map(|chunk| ApplicationBlock::new(RIFF_APPLICATION_ID, chunk.clone()).build_block())
+            .collect()
+    }
+
+    /// Rebuild the original WAV file from the foreign metadata and the audio
+    /// 
+    /// `audio` must yield the content of the `data` chunk, that is `data_size`
+    /// bytes of little-endian interleaved PCM samples. A pad byte is written
+    /// after audio of an odd size.
+    /// 
+    /// # Errors
+    /// Returns `ForeignMetadataError::DataChunkError` if `audio` is shorter
+    /// than `data_size` bytes, and `ForeignMetadataError::WriteError` if the
+    /// output cannot be written.
+    pub fn write_wave(&self, out: &mut impl Write, audio: &mut impl Read) -> Result <(), ForeignMetadataError> {
+        let data_idx = self.data_header()?;
+        let data_size = self.data_size() as u64;
+
+        for (i, chunk) in self.chunks.iter().enumerate() {
+            out.write_all(chunk).map_err(|_| ForeignMetadataError::WriteError)?;
+
+            if i == data_idx {
+                let num_copied = io::copy(&mut audio.take(data_size), out)?;
+
+                if num_copied != data_size {
+                    return Err(ForeignMetadataError::DataChunkError);
+                }
+                if data_size & 1 == 1 {
+                    out.write_all(&[0]).map_err(|_| ForeignMetadataError::WriteError)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Get the index of the only `data` chunk header
+    fn data_header(&self) -> Result <usize, ForeignMetadataError> {
+        let mut data_chunks = self.chunks
+            .iter()
+            .enumerate()
+            .skip(1)
+            .filter(|(_, chunk)| chunk.starts_with(b"data"));
+
+        match (data_chunks.next(), data_chunks.next()) {
+            (Some((idx, chunk)), None) if chunk.len() == CHUNK_HEADER_LEN => Ok(idx),
+            _ => Err(ForeignMetadataError::DataChunkError),
+        }
+    }
+}
+
+impl error::Error for ForeignMetadataError {}
+
+impl fmt::Display for ForeignMetadataError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Error: {:?}", self)
+    }
+}
+
+impl From <io::Error> for ForeignMetadataError {
+    fn from(_: io::Error) -> Self {
+        ForeignMetadataError::ReadError
+    }
+}
+
+impl From <WaveReaderError> for ForeignMetadataError {
+    fn from(_: WaveReaderError) -> Self {
+        ForeignMetadataError::WaveError
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn chunk(id: &[u8; 4], payload: &[u8]) -> Vec <u8> {
+        let mut bytes = id.to_vec();
+        bytes.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(payload);
+
+        if payload.len() & 1 == 1 {
+            bytes.push(0);
+        }
+
+        bytes
+    }
+
+    fn wave_bytes(chunks: &[Vec <u8>]) -> Vec <u8> {
+        let body: Vec <u8> = chunks.concat();
+        let mut bytes = b"RIFF".to_vec();
+        bytes.extend_from_slice(&(body.len() as u32 + 4).to_le_bytes());
+        bytes.extend_from_slice(b"WAVE");
+        bytes.extend(body);
+
+        bytes
+    }
+
+    #[test]
+    fn sample_rebuild() -> Result <(), ForeignMetadataError> {
+        let file_name = "midp_sample_foreign_rebuild.wav.part";
+        let fmt = [1, 0, 1, 0, 0x44, 0xac, 0, 0, 0x88, 0x58, 1, 0, 2, 0, 16, 0];
+        let audio = [1, 2, 3, 4, 5, 6];
+        let bytes = wave_bytes(&[
+            chunk(b"LIST", b"INFOa"),
+            chunk(b"fmt ", &fmt),
+            chunk(b"data", &audio),
+            chunk(b"bext", &[9; 3]),
+        ]);
+        fs::write(file_name, &bytes)?;
+
+        let result = ForeignMetadata::read_wave(file_name);
+        fs::remove_file(file_name)?;
+
+        let foreign = result?;
+        let blocks = foreign.build_blocks();
+        assert_eq!(blocks.len(), 5);
+        assert_eq!(blocks[3].data, b"riffdata\x06\x00\x00\x00".to_vec());
+
+        let parsed = ForeignMetadata::from_blocks(&blocks)?.unwrap();
+        assert_eq!(parsed, foreign);
+        assert_eq!(parsed.data_size(), 6);
+
+        let mut rebuilt = Vec::new();
+        parsed.write_wave(&mut rebuilt, &mut &audio[..])?;
+        assert_eq!(rebuilt, bytes);
+
+        Ok(())
+    }
+
+    #[test]
+    fn sample_errors() -> Result <(), ForeignMetadataError> {
+        let file_name = "midp_sample_foreign_errors.wav.part";
+        fs::write(file_name, wave_bytes(&[chunk(b"data", &[1]), chunk(b"data", &[2])]))?;
+
+        let result = ForeignMetadata::read_wave(file_name);
+        fs::remove_file(file_name)?;
+        assert!(matches!(result, Err(ForeignMetadataError::DataChunkError)));
+
+        assert!(ForeignMetadata::from_blocks(&[MetadataBlock::padding(4)])?.is_none());
+
+        let blocks = vec![ApplicationBlock::new(RIFF_APPLICATION_ID, b"RIFX\0\0\0\0WAVE".to_vec()).build_block()];
+        assert!(matches!(ForeignMetadata::from_blocks(&blocks), Err(ForeignMetadataError::HeaderError)));
+
+        let foreign = ForeignMetadata::from_blocks(&[ApplicationBlock::new(RIFF_APPLICATION_ID, b"RIFF\0\0\0\0WAVE".to_vec()).build_block(),
+            ApplicationBlock::new(RIFF_APPLICATION_ID, b"data\x04\0\0\0".to_vec()).build_block()])?.unwrap();
+        let result = foreign.write_wave(&mut Vec::new(), &mut &[1u8, 2][..]);
+        assert!(matches!(result, Err(ForeignMetadataError::DataChunkError)));
+
+        Ok(())
+    }
+}
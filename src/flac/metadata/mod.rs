@@ -0,0 +1,302 @@
+pub mod application;
+pub mod cuesheet;
+pub mod editor;
+#[cfg(test)]
+mod fixtures;
+pub mod foreign;
+pub mod picture;
+pub mod seektable;
+pub mod streaminfo;
+pub mod updater;
+pub mod vorbis_comment;
+
+use core::fmt;
+use std::error;
+use std::io::{self, Read};
+
+use self::vorbis_comment::VorbisCommentError;
+
+/// Size of the PADDING block written by default after the other metadata blocks
+/// 
+/// The padding leaves room to edit the metadata later without rewriting the
+/// whole file, as done by `MetadataUpdater`.
+pub const DEFAULT_PADDING_LEN: usize = 8192;
+
+/// Maximum length of the data of a metadata block, stored on 24 bits
+pub const MAX_BLOCK_DATA_LEN: usize = (1 << 24) - 1;
+
+/// Represents the type of a FLAC metadata block
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetadataBlockType {
+    Streaminfo,
+    Padding,
+    Application,
+    Seektable,
+    VorbisComment,
+    Cuesheet,
+    Picture,
+    Reserved(u8),
+}
+
+/// Represents a FLAC metadata block
+/// 
+/// A metadata block consists of a 4-byte header and the block data. The
+/// header holds a flag marking the last metadata block before the audio
+/// frames, the block type, and the length of the block data in bytes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetadataBlock {
+    pub block_type: MetadataBlockType,
+    pub data: Vec <u8>,
+}
+
+impl MetadataBlockType {
+    /// Get the 7-bit value of this block type as written in the block header
+    pub fn value(&self) -> u8 {
+        match self {
+            MetadataBlockType::Streaminfo => 0,
+            MetadataBlockType::Padding => 1,
+            MetadataBlockType::Application => 2,
+            MetadataBlockType::Seektable => 3,
+            MetadataBlockType::VorbisComment => 4,
+            MetadataBlockType::Cuesheet => 5,
+            MetadataBlockType::Picture => 6,
+            MetadataBlockType::Reserved(value) => *value,
+        }
+    }
+
+    /// Get the block type of a 7-bit value from a block header
+    /// 
+    /// Returns `None` for the invalid value 127. Values from 7 to 126 are
+    /// reserved for future block types, which are kept as they are.
+    pub fn from_value(value: u8) -> Option <Self> {
+        match value {
+            0 => Some(MetadataBlockType::Streaminfo),
+            1 => Some(MetadataBlockType::Padding),
+            2 => Some(MetadataBlockType::Application),
+            3 => Some(MetadataBlockType::Seektable),
+            4 => Some(MetadataBlockType::VorbisComment),
+            5 => Some(MetadataBlockType::Cuesheet),
+            6 => Some(MetadataBlockType::Picture),
+            7..=126 => Some(MetadataBlockType::Reserved(value)),
+            _ => None,
+        }
+    }
+}
+
+/// Represents an error in reading or writing FLAC metadata
+#[derive(Debug)]
+pub enum MetadataError {
+    ReadError,
+    WriteError,
+    NotFlacError,
+    BlockTypeError,
+    StreaminfoError,
+    BlockDataError,
+}
+
+impl MetadataBlock {
+    /// Create a new metadata block
+    pub fn new(block_type: MetadataBlockType, data: Vec <u8>) -> Self {
+        Self {
+            block_type,
+            data,
+        }
+    }
+
+    /// Create a new PADDING block of `len` zero bytes
+    pub fn padding(len: usize) -> Self {
+        Self::new(MetadataBlockType::Padding, vec![0; len])
+    }
+
+    /// Read all metadata blocks at the start of a FLAC stream
+    /// 
+    /// The `reader` should point to the `fLaC` marker at the very start of the
+    /// stream, and is left pointing to the first audio frame.
+    /// 
+    /// # Errors
+    /// Returns `MetadataError::NotFlacError` if the stream does not start with
+    /// the `fLaC` marker, `MetadataError::BlockTypeError` if a block has the
+    /// invalid type 127, `MetadataError::StreaminfoError` if the first block is
+    /// not a STREAMINFO block, and `MetadataError::ReadError` if the stream ends
+    /// within the metadata.
+    pub fn read_all(reader: &mut impl Read) -> Result <Vec <MetadataBlock>, MetadataError> {
+        let mut marker = [0u8; 4];
+        reader.read_exact(&mut marker)?;
+
+        if &marker != b"fLaC" {
+            return Err(MetadataError::NotFlacError);
+        }
+
+        let mut blocks = Vec::new();
+
+        loop {
+            let mut header = [0u8; 4];
+            reader.read_exact(&mut header)?;
+
+            let block_type = MetadataBlockType::from_value(header[0] & 0x7f)
+                .ok_or(MetadataError::BlockTypeError)?;
+            let len = u32::from_be_bytes([0, header[1], header[2], header[3]]) as usize;
+            let mut data = vec![0; len];
+            reader.read_exact(&mut data)?;
+
+            blocks.push(MetadataBlock::new(block_type, data));
+
+            if header[0] & 0x80 != 0 {
+                break;
+            }
+        }
+
+        if blocks[0].block_type != MetadataBlockType::Streaminfo {
+            return Err(MetadataError::StreaminfoError);
+        }
+
+        Ok(blocks)
+    }
+
+    /// Get the length of this metadata block in bytes, including its header
+    pub fn total_len(&self) -> usize {
+        self.data.len() + 4
+    }
+
+    /// Convert this metadata block into a vector of bytes
+    /// 
+    /// The resulting bytes include the 4-byte block header. Set `is_last` if this
+    /// block is the last metadata block before the audio frames.
+    pub fn build_bytes(&self, is_last: bool) -> Vec <u8> {
+        let len = self.data.len() as u32;
+        let mut bytes = Vec::with_capacity(self.data.len() + 4);
+
+        bytes.push(((is_last as u8) << 7) | self.block_type.value());
+        bytes.extend_from_slice(&len.to_be_bytes()[1..]);
+        bytes.extend_from_slice(&self.data);
+
+        bytes
+    }
+
+    /// Convert a list of metadata blocks into bytes that fill exactly `region_len` bytes
+    /// 
+    /// The blocks are written in order and the unused space is filled with a PADDING
+    /// block, which is marked as the last metadata block if `is_last` is set.
+    /// 
+    /// # Errors
+    /// Returns `None` if the blocks do not fit the region, or if the unused space
+    /// is too small to hold the 4-byte header of a PADDING block or too large for
+    /// a single PADDING block of at most `MAX_BLOCK_DATA_LEN` bytes.
+    pub fn build_region_bytes(blocks: &[MetadataBlock], region_len: usize, is_last: bool) -> Option <Vec <u8>> {
+        let blocks_len: usize = blocks.iter().map(|block| block.total_len()).sum();
+        let pad_len = region_len.checked_sub(blocks_len)?.checked_sub(4)?;
+
+        if pad_len > MAX_BLOCK_DATA_LEN {
+            return None;
+        }
+
+        let mut bytes = Vec::with_capacity(region_len);
+
+        for block in blocks {
+            bytes.extend(block.build_bytes(false));
+        }
+
+        bytes.extend(MetadataBlock::padding(pad_len).build_bytes(is_last));
+
+        Some(bytes)
+    }
+}
+
+/// Split the first `len` bytes off block data being parsed
+/// 
+/// # Errors
+/// Returns `MetadataError::BlockDataError` if the data is shorter than `len` bytes.
+fn split_data <'a>(data: &mut &'a [u8], len: usize) -> Result <&'a [u8], MetadataError> {
+    if data.len() < len {
+        return Err(MetadataError::BlockDataError);
+    }
+
+    let (head, rest) = data.split_at(len);
+    *data = rest;
+
+    Ok(head)
+}
+
+impl error::Error for MetadataError {}
+
+impl fmt::Display for MetadataError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Error: {:?}", self)
+    }
+}
+
+impl From <io::Error> for MetadataError {
+    fn from(_: io::Error) -> Self {
+        MetadataError::ReadError
+    }
+}
+
+impl From <VorbisCommentError> for MetadataError {
+    fn from(_: VorbisCommentError) -> Self {
+        MetadataError::BlockDataError
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sample_block_header() {
+        let block = MetadataBlock::new(MetadataBlockType::VorbisComment, vec![0xaa; 0x0102]);
+        let bytes = block.build_bytes(true);
+
+        assert_eq!(&bytes[..4], &[0x84, 0x00, 0x01, 0x02]);
+        assert_eq!(bytes.len(), 0x0106);
+    }
+
+    #[test]
+    fn sample_padding() {
+        let bytes = MetadataBlock::padding(3).build_bytes(false);
+
+        assert_eq!(bytes, vec![0x01, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00]);
+    }
+
+    #[test]
+    fn sample_region() {
+        let blocks = vec![MetadataBlock::new(MetadataBlockType::Application, vec![1, 2, 3, 4])];
+
+        let bytes = MetadataBlock::build_region_bytes(&blocks, 20, true).unwrap();
+        assert_eq!(bytes.len(), 20);
+        assert_eq!(&bytes[8..12], &[0x81, 0x00, 0x00, 0x08]);
+
+        assert!(MetadataBlock::build_region_bytes(&blocks, 10, true).is_none());
+        assert!(MetadataBlock::build_region_bytes(&blocks, 8, true).is_none());
+        assert!(MetadataBlock::build_region_bytes(&blocks, 12, true).is_some());
+        assert!(MetadataBlock::build_region_bytes(&blocks, 12 + MAX_BLOCK_DATA_LEN + 1, true).is_none());
+    }
+
+    #[test]
+    fn sample_read_all() {
+        let mut bytes = b"fLaC".to_vec();
+        bytes.extend(MetadataBlock::new(MetadataBlockType::Streaminfo, vec![0; 34]).build_bytes(false));
+        bytes.extend(MetadataBlock::new(MetadataBlockType::Reserved(9), vec![7, 7]).build_bytes(false));
+        bytes.extend(MetadataBlock::padding(5).build_bytes(true));
+        bytes.extend_from_slice(&[0xff, 0xf8]);
+
+        let mut reader = bytes.as_slice();
+        let blocks = MetadataBlock::read_all(&mut reader).unwrap();
+
+        assert_eq!(blocks.len(), 3);
+        assert_eq!(blocks[1], MetadataBlock::new(MetadataBlockType::Reserved(9), vec![7, 7]));
+        assert_eq!(blocks.iter().map(|block| block.total_len()).sum::<usize>(), 38 + 6 + 9);
+        assert_eq!(reader, &[0xff, 0xf8]);
+    }
+
+    #[test]
+    fn sample_read_all_errors() {
+        assert!(matches!(MetadataBlock::read_all(&mut &b"OggS"[..]), Err(MetadataError::NotFlacError)));
+
+        let mut bytes = b"fLaC".to_vec();
+        bytes.extend(MetadataBlock::padding(2).build_bytes(true));
+        assert!(matches!(MetadataBlock::read_all(&mut bytes.as_slice()), Err(MetadataError::StreaminfoError)));
+
+        bytes.truncate(7);
+        assert!(matches!(MetadataBlock::read_all(&mut bytes.as_slice()), Err(MetadataError::ReadError)));
+    }
+}
@@ -0,0 +1,470 @@
+use core::fmt;
+use std::error;
+use std::fs;
+use std::io;
+
+use super::{split_data, MetadataBlock, MetadataBlockType, MetadataError};
+
+/// Signature at the start of every PNG file
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a];
+
+/// Represents the type of a picture as defined by ID3v2 APIC frames
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PictureType {
+    Other,
+    FileIcon,
+    OtherFileIcon,
+    FrontCover,
+    BackCover,
+    LeafletPage,
+    Media,
+    LeadArtist,
+    Artist,
+    Conductor,
+    Band,
+    Composer,
+    Lyricist,
+    RecordingLocation,
+    DuringRecording,
+    DuringPerformance,
+    VideoScreenCapture,
+    BrightColouredFish,
+    Illustration,
+    BandLogo,
+    PublisherLogo,
+}
+
+/// Represents a PICTURE metadata block
+/// 
+/// `depth` is the number of bits per pixel and `num_colors` is the number
+/// of colours of an indexed-colour picture, or 0 for other pictures.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Picture {
+    pub picture_type: PictureType,
+    pub mime_type: String,
+    pub description: String,
+    pub width: u32,
+    pub height: u32,
+    pub depth: u32,
+    pub num_colors: u32,
+    pub data: Vec <u8>,
+}
+
+/// Represents an error in reading a picture
+#[derive(Debug)]
+pub enum PictureError {
+    ReadError,
+    UnknownFormatError,
+    HeaderError,
+    FileIconError,
+    DuplicateIconError,
+}
+
+impl PictureType {
+    /// Get the value of this picture type as written in a PICTURE block
+    pub fn value(&self) -> u32 {
+        *self as u32
+    }
+
+    /// Get the picture type of a value from a PICTURE block
+    /// 
+    /// Returns `None` for the values above 20, which are reserved.
+    pub fn from_value(value: u32) -> Option <Self> {
+        let picture_type = match value {
+            0 => PictureType::Other,
+            1 => PictureType::FileIcon,
+            2 => PictureType::OtherFileIcon,
+            3 => PictureType::FrontCover,
+            4 => PictureType::BackCover,
+            5 => PictureType::LeafletPage,
+            6 => PictureType::Media,
+            7 => PictureType::LeadArtist,
+            8 => PictureType::Artist,
+            9 => PictureType::Conductor,
+            10 => PictureType::Band,
+            11 => PictureType::Composer,
+            12 => PictureType::Lyricist,
+            13 => PictureType::RecordingLocation,
+            14 => PictureType::DuringRecording,
+            15 => PictureType::DuringPerformance,
+            16 => PictureType::VideoScreenCapture,
+            17 => PictureType::BrightColouredFish,
+            18 => PictureType::Illustration,
+            19 => PictureType::BandLogo,
+            20 => PictureType::PublisherLogo,
+            _ => return None,
+        };
+
+        Some(picture_type)
+    }
+}
+
+impl Picture {
+    /// Read a PNG or JPEG image file as a picture
+    /// 
+    /// # Errors
+    /// Returns a `PictureError` if the file cannot be read or is neither a PNG
+    /// nor a JPEG image.
+    pub fn read(file_path: &str, picture_type: PictureType, description: &str) -> Result <Picture, PictureError> {
+        Self::from_image(picture_type, description, fs::read(file_path)?)
+    }
+
+    /// Create a picture from the data of a PNG or JPEG image
+    /// 
+    /// The MIME type, dimensions, colour depth and number of colours are taken
+    /// from the headers of the image, so the image itself is not decoded.
+    /// 
+    /// # Errors
+    /// Returns `PictureError::UnknownFormatError` if the data is neither a PNG nor
+    /// a JPEG image, and `PictureError::HeaderError` if its headers are malformed.
+    pub fn from_image(picture_type: PictureType, description: &str, data: Vec <u8>) -> Result <Picture, PictureError> {
+        let (mime_type, (width, height, depth, num_colors)) = if data.starts_with(&PNG_SIGNATURE) {
+            ("image/png", Self::png_info(&data)?)
+        }
+        else if data.starts_with(&[0xff, 0xd8]) {
+            ("image/jpeg", Self::jpeg_info(&data)?)
+        }
+        else {
+            return Err(PictureError::UnknownFormatError);
+        };
+
+        Ok(Picture {
+            picture_type,
+            mime_type: String::from(mime_type),
+            description: String::from(description),
+            width,
+            height,
+            depth,
+            num_colors,
+            data,
+        })
+    }
+
+    /// Parse a PICTURE metadata block, as laid out by `build_bytes`
+    /// 
+    /// # Errors
+    /// Returns `MetadataError::BlockTypeError` if `block` is not a PICTURE
+    /// block, and `MetadataError::BlockDataError` if it is truncated, has a
+    /// reserved picture type, or holds strings that are not UTF-8.
+    pub fn parse(block: &MetadataBlock) -> Result <Picture, MetadataError> {
+        if block.block_type != MetadataBlockType::Picture {
+            return Err(MetadataError::BlockTypeError);
+        }
+
+        let mut data = block.data.as_slice();
+        let picture_type = PictureType::from_value(Self::split_u32(&mut data)?)
+            .ok_or(MetadataError::BlockDataError)?;
+        let mime_type = Self::split_string(&mut data)?;
+        let description = Self::split_string(&mut data)?;
+        let width = Self::split_u32(&mut data)?;
+        let height = Self::split_u32(&mut data)?;
+        let depth = Self::split_u32(&mut data)?;
+        let num_colors = Self::split_u32(&mut data)?;
+        let data_len = Self::split_u32(&mut data)? as usize;
+
+        Ok(Picture {
+            picture_type,
+            mime_type,
+            description,
+            width,
+            height,
+            depth,
+            num_colors,
+            data: split_data(&mut data, data_len)?.to_vec(),
+        })
+    }
+
+    /// Check that a set of pictures can be stored in the same FLAC file
+    /// 
+    /// A file holds at most one picture of type `FileIcon` and one of type
+    /// `OtherFileIcon`, and the `FileIcon` must be a 32x32 PNG image.
+    /// 
+    /// # Errors
+    /// Returns `PictureError::DuplicateIconError` or `PictureError::FileIconError`
+    /// if the pictures break these rules.
+    pub fn validate_set(pictures: &[Picture]) -> Result <(), PictureError> {
+        for icon_type in [PictureType::FileIcon, PictureType::OtherFileIcon] {
+            if pictures.iter().filter(|picture| picture.picture_type == icon_type).count() > 1 {
+                return Err(PictureError::DuplicateIconError);
+            }
+        }
+
+        let is_bad_icon = |picture: &&Picture| {
+            picture.mime_type != "image/png" || picture.width != 32 || picture.height != 32
+        };
+
+        match pictures.iter().filter(|picture| picture.picture_type == PictureType::FileIcon).find(is_bad_icon) {
+            Some(_) => Err(PictureError::FileIconError),
+            None => Ok(()),
+        }
+    }
+
+    /// Convert this picture into the data of a PICTURE block
+    /// 
+    /// All values are big-endian 32-bit integers, and the MIME type, the
+    /// description and the image data are each preceded by their length.
+    pub fn build_bytes(&self) -> Vec <u8> {
+        let mut bytes = Vec::with_capacity(32 + self.mime_type.len() + self.description.len() + self.data.len());
+
+        bytes.extend_from_slice(&self.picture_type.value().to_be_bytes());
+        bytes.extend_from_slice(&(self.mime_type.len() as u32).to_be_bytes());
+        bytes.extend_from_slice(self.mime_type.as_bytes());
+        bytes.extend_from_slice(&(self.description.len() as u32).to_be_bytes());
+        bytes.extend_from_slice(self.description.as_bytes());
+        bytes.extend_from_slice(&self.width.to_be_bytes());
+        bytes.extend_from_slice(&self.height.to_be_bytes());
+        bytes.extend_from_slice(&self.depth.to_be_bytes());
+        bytes.extend_from_slice(&self.num_colors.to_be_bytes());
+        bytes.extend_from_slice(&(self.data.len() as u32).to_be_bytes());
+        bytes.extend_from_slice(&self.data);
+
+        bytes
+    }
+
+    /// Convert this picture into a PICTURE metadata block
+    pub fn build_block(&self) -> MetadataBlock {
+        MetadataBlock::new(MetadataBlockType::Picture, self.build_bytes())
+    }
+
+    /// Split a big-endian 32-bit integer off PICTURE block data
+    fn split_u32(data: &mut &[u8]) -> Result <u32, MetadataError> {
+        Ok(u32::from_be_bytes(split_data(data, 4)?.try_into().unwrap()))
+    }
+
+    /// Split a string preceded by its length off PICTURE block data
+    fn split_string(data: &mut &[u8]) -> Result <String, MetadataError> {
+        let len = Self::split_u32(data)? as usize;
+
+        String::from_utf8(split_data(data, len)?.to_vec()).map_err(|_| MetadataError::BlockDataError)
+    }
+
+    /// Get the width, height, depth and number of colours of a PNG image
+    /// 
+    /// These are read from the IHDR chunk, which always comes first, and from
+    /// the PLTE chunk for indexed-colour images, whose samples are always 8-bit
+    /// RGB regardless of the bit depth of the indices.
+    fn png_info(data: &[u8]) -> Result <(u32, u32, u32, u32), PictureError> {
+        let ihdr = data.get(8..33).ok_or(PictureError::HeaderError)?;
+
+        if &ihdr[4..8] != b"IHDR" {
+            return Err(PictureError::HeaderError);
+        }
+
+        let width = u32::from_be_bytes([ihdr[8], ihdr[9], ihdr[10], ihdr[11]]);
+        let height = u32::from_be_bytes([ihdr[12], ihdr[13], ihdr[14], ihdr[15]]);
+        let bit_depth = ihdr[16] as u32;
+
+        let depth = match ihdr[17] {
+            0 => bit_depth,
+            2 => bit_depth * 3,
+            3 => 8 * 3,
+            4 => bit_depth * 2,
+            6 => bit_depth * 4,
+            _ => return Err(PictureError::HeaderError),
+        };
+
+        let num_colors = match ihdr[17] {
+            3 => Self::png_palette_len(data)? / 3,
+            _ => 0,
+        };
+
+        Ok((width, height, depth, num_colors))
+    }
+
+    /// Get the length of the PLTE chunk of an indexed-colour PNG image
+    fn png_palette_len(data: &[u8]) -> Result <u32, PictureError> {
+        let mut chunks = &data[8..];
+
+        while chunks.len() >= 8 {
+            let len = u32::from_be_bytes([chunks[0], chunks[1], chunks[2], chunks[3]]);
+
+            match &chunks[4..8] {
+                b"PLTE" => return Ok(len),
+                b"IDAT" | b"IEND" => break,
+                _ => {},
+            }
+
+            // Skip the chunk header, the chunk data and its 4-byte CRC
+            chunks = chunks.get(12 + len as usize..).ok_or(PictureError::HeaderError)?;
+        }
+
+        Err(PictureError::HeaderError)
+    }
+
+    /// Get the width, height, depth and number of colours of a JPEG image
+    /// 
+    /// These are read from the first start-of-frame segment. JPEG images
+    /// are never indexed-colour, so the number of colours is always 0.
+    fn jpeg_info(data: &[u8]) -> Result <(u32, u32, u32, u32), PictureError> {
+        let mut pos = 2;
+
+        loop {
+            // Markers may be preceded by any number of 0xff fill bytes
+            while data.get(pos) == Some(&0xff) && data.get(pos + 1) == Some(&0xff) {
+                pos += 1;
+            }
+
+            let marker = match data.get(pos..pos + 2) {
+                Some([0xff, marker]) => *marker,
+                _ => return Err(PictureError::HeaderError),
+            };
+
+            // Standalone markers have no segment
+            if marker == 0x01 || (0xd0..=0xd7).contains(&marker) {
+                pos += 2;
+                continue;
+            }
+
+            // Start of scan or end of image before any start of frame
+            if marker == 0xda || marker == 0xd9 {
+                return Err(PictureError::HeaderError);
+            }
+
+            let segment = data.get(pos + 2..pos + 4).ok_or(PictureError::HeaderError)?;
+            let len = u16::from_be_bytes([segment[0], segment[1]]) as usize;
+
+            // SOF0 to SOF15, except for DHT, JPG and DAC which share the range
+            if (0xc0..=0xcf).contains(&marker) && ![0xc4, 0xc8, 0xcc].contains(&marker) {
+                let sof = data.get(pos + 4..pos + 10).ok_or(PictureError::HeaderError)?;
+                let height = u16::from_be_bytes([sof[1], sof[2]]) as u32;
+                let width = u16::from_be_bytes([sof[3], sof[4]]) as u32;
+
+                return Ok((width, height, sof[0] as u32 * sof[5] as u32, 0));
+            }
+
+            pos += 2 + len;
+        }
+    }
+}
+
+impl error::Error for PictureError {}
+
+impl fmt::Display for PictureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Error: {:?}", self)
+    }
+}
+
+impl From <io::Error> for PictureError {
+    fn from(_: io::Error) -> Self {
+        PictureError::ReadError
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn png_chunk(chunk_type: &[u8], data: &[u8]) -> Vec <u8> {
+        let mut bytes = (data.len() as u32).to_be_bytes().to_vec();
+        bytes.extend_from_slice(chunk_type);
+        bytes.extend_from_slice(data);
+        bytes.extend_from_slice(&[0; 4]);
+
+        bytes
+    }
+
+    fn png(width: u32, height: u32, bit_depth: u8, color_type: u8, palette_len: usize) -> Vec <u8> {
+        let mut ihdr = width.to_be_bytes().to_vec();
+        ihdr.extend_from_slice(&height.to_be_bytes());
+        ihdr.extend_from_slice(&[bit_depth, color_type, 0, 0, 0]);
+
+        let mut bytes = PNG_SIGNATURE.to_vec();
+        bytes.extend(png_chunk(b"IHDR", &ihdr));
+        bytes.extend(png_chunk(b"tEXt", b"a\0b"));
+        if palette_len > 0 {
+            bytes.extend(png_chunk(b"PLTE", &vec![0; palette_len]));
+        }
+        bytes.extend(png_chunk(b"IDAT", &[]));
+        bytes.extend(png_chunk(b"IEND", &[]));
+
+        bytes
+    }
+
+    fn jpeg(width: u16, height: u16, num_components: u8) -> Vec <u8> {
+        let mut bytes = vec![0xff, 0xd8];
+        // APP0 segment
+        bytes.extend_from_slice(&[0xff, 0xe0, 0x00, 0x06, b'J', b'F', b'I', b'F']);
+        // DHT segment, whose marker is within the SOF range
+        bytes.extend_from_slice(&[0xff, 0xff, 0xc4, 0x00, 0x03, 0x00]);
+        // SOF2 segment
+        bytes.extend_from_slice(&[0xff, 0xc2, 0x00, 0x08 + 3 * num_components, 0x08]);
+        bytes.extend_from_slice(&height.to_be_bytes());
+        bytes.extend_from_slice(&width.to_be_bytes());
+        bytes.push(num_components);
+        bytes.extend(vec![0; 3 * num_components as usize]);
+        bytes.extend_from_slice(&[0xff, 0xda, 0xff, 0xd9]);
+
+        bytes
+    }
+
+    #[test]
+    fn sample_png() {
+        let picture = Picture::from_image(PictureType::FrontCover, "Cover", png(600, 400, 8, 6, 0)).unwrap();
+
+        assert_eq!(picture.mime_type, "image/png");
+        assert_eq!((picture.width, picture.height, picture.depth, picture.num_colors), (600, 400, 32, 0));
+    }
+
+    #[test]
+    fn sample_png_indexed() {
+        let picture = Picture::from_image(PictureType::FileIcon, "", png(32, 32, 4, 3, 16 * 3)).unwrap();
+
+        assert_eq!((picture.depth, picture.num_colors), (24, 16));
+        assert!(Picture::validate_set(&[picture]).is_ok());
+    }
+
+    #[test]
+    fn sample_jpeg() {
+        let picture = Picture::from_image(PictureType::BackCover, "", jpeg(1200, 1100, 3)).unwrap();
+
+        assert_eq!(picture.mime_type, "image/jpeg");
+        assert_eq!((picture.width, picture.height, picture.depth, picture.num_colors), (1200, 1100, 24, 0));
+    }
+
+    #[test]
+    fn sample_unknown_format() {
+        let result = Picture::from_image(PictureType::Other, "", b"GIF89a".to_vec());
+
+        assert!(matches!(result, Err(PictureError::UnknownFormatError)));
+        assert!(matches!(Picture::from_image(PictureType::Other, "", vec![0xff, 0xd8]), Err(PictureError::HeaderError)));
+    }
+
+    #[test]
+    fn sample_validate_set() {
+        let icon = Picture::from_image(PictureType::FileIcon, "", png(32, 32, 8, 2, 0)).unwrap();
+        let big_icon = Picture::from_image(PictureType::FileIcon, "", png(64, 64, 8, 2, 0)).unwrap();
+        let cover = Picture::from_image(PictureType::FrontCover, "", jpeg(500, 500, 3)).unwrap();
+
+        assert!(Picture::validate_set(&[icon.clone(), cover.clone(), cover.clone()]).is_ok());
+        assert!(matches!(Picture::validate_set(&[big_icon]), Err(PictureError::FileIconError)));
+        assert!(matches!(Picture::validate_set(&[icon.clone(), icon]), Err(PictureError::DuplicateIconError)));
+    }
+
+    #[test]
+    fn sample_build_bytes() {
+        let picture = Picture {
+            picture_type: PictureType::FrontCover,
+            mime_type: String::from("image/png"),
+            description: String::from("ab"),
+            width: 1,
+            height: 2,
+            depth: 24,
+            num_colors: 0,
+            data: vec![0xaa, 0xbb],
+        };
+
+        assert_eq!(picture.build_bytes(), vec![
+            0, 0, 0, 3,
+            0, 0, 0, 9, b'i', b'm', b'a', b'g', b'e', b'/', b'p', b'n', b'g',
+            0, 0, 0, 2, b'a', b'b',
+            0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0, 24, 0, 0, 0, 0,
+            0, 0, 0, 2, 0xaa, 0xbb,
+        ]);
+        assert_eq!(Picture::parse(&picture.build_block()).unwrap(), picture);
+
+        let mut bytes = picture.build_bytes();
+        bytes[3] = 21;
+        assert!(matches!(Picture::parse(&MetadataBlock::new(MetadataBlockType::Picture, bytes)), Err(MetadataError::BlockDataError)));
+    }
+}
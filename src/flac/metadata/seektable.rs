@@ -0,0 +1,305 @@
+use super::{MetadataBlock, MetadataBlockType, MetadataError};
+
+/// Sample number marking a placeholder seek point
+pub const PLACEHOLDER_SAMPLE_NUMBER: u64 = u64::MAX;
+
+/// Length of a seek point in bytes
+pub const SEEK_POINT_LEN: usize = 18;
+
+/// Represents the spacing of the seek points of a SEEKTABLE block
+/// 
+/// Seek points are placed every `Seconds` seconds or every `Samples`
+/// inter-channel samples starting from the first sample, or at each
+/// inter-channel sample of an `Explicit` list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SeekPointSpacing {
+    Seconds(u64),
+    Samples(u64),
+    Explicit(Vec <u64>),
+}
+
+/// Represents a seek point of a SEEKTABLE block
+/// 
+/// `sample_number` is the first inter-channel sample of the target frame,
+/// `offset` is the offset in bytes of the target frame from the first frame,
+/// and `num_samples` is the number of inter-channel samples in the target frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SeekPoint {
+    pub sample_number: u64,
+    pub offset: u64,
+    pub num_samples: u16,
+}
+
+/// Represents a SEEKTABLE metadata block
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SeekTable {
+    pub points: Vec <SeekPoint>,
+}
+
+/// Represents a builder of a SEEKTABLE block while encoding
+/// 
+/// The sample numbers targeted by the seek points are known before encoding,
+/// so that a SEEKTABLE block of the final size can be reserved. Every encoded
+/// frame is then passed to the builder, and the first frame holding each
+/// target becomes a seek point. Targets that share a frame share its seek
+/// point, and the unused seek points are left as placeholders.
+pub struct SeekTableBuilder {
+    targets: Vec <u64>,
+    next_target: usize,
+    points: Vec <SeekPoint>,
+}
+
+impl Default for SeekPointSpacing {
+    fn default() -> Self {
+        SeekPointSpacing::Seconds(10)
+    }
+}
+
+impl SeekPoint {
+    /// Create a placeholder seek point
+    pub fn placeholder() -> Self {
+        Self {
+            sample_number: PLACEHOLDER_SAMPLE_NUMBER,
+            offset: 0,
+            num_samples: 0,
+        }
+    }
+
+    /// Check whether this seek point is a placeholder
+    pub fn is_placeholder(&self) -> bool {
+        self.sample_number == PLACEHOLDER_SAMPLE_NUMBER
+    }
+}
+
+impl SeekTable {
+    /// Create a SEEKTABLE block of `num_points` placeholder seek points
+    pub fn placeholder(num_points: usize) -> Self {
+        Self {
+            points: vec![SeekPoint::placeholder(); num_points],
+        }
+    }
+
+    /// Parse a SEEKTABLE metadata block, as laid out by `build_bytes`
+    /// 
+    /// # Errors
+    /// Returns `MetadataError::BlockTypeError` if `block` is not a SEEKTABLE
+    /// block, and `MetadataError::BlockDataError` if its length is not a
+    /// multiple of `SEEK_POINT_LEN`.
+    pub fn parse(block: &MetadataBlock) -> Result <Self, MetadataError> {
+        if block.block_type != MetadataBlockType::Seektable {
+            return Err(MetadataError::BlockTypeError);
+        }
+
+        if !block.data.len().is_multiple_of(SEEK_POINT_LEN) {
+            return Err(MetadataError::BlockDataError);
+        }
+
+        let points = block.data
+            .chunks(SEEK_POINT_LEN)
+            .map(|point| SeekPoint {
+                sample_number: u64::from_be_bytes(point[..8].try_into().unwrap()),
+                offset: u64::from_be_bytes(point[8..16].try_into().unwrap()),
+                num_samples: u16::from_be_bytes([point[16], point[17]]),
+            })
+            .collect();
+
+        Ok(Self {
+            points,
+        })
+    }
+
+    /// Convert this SEEKTABLE block into its block data
+    /// 
+    /// Each seek point takes 18 bytes, with all values big-endian:
+    /// `<sample_number:64b><offset:64b><num_samples:16b>`.
+    pub fn build_bytes(&self) -> Vec <u8> {
+        let mut bytes = Vec::with_capacity(self.points.len() * SEEK_POINT_LEN);
+
+        for point in &self.points {
+            bytes.extend_from_slice(&point.sample_number.to_be_bytes());
+            bytes.extend_from_slice(&point.offset.to_be_bytes());
+            bytes.extend_from_slice(&point.num_samples.to_be_bytes());
+        }
+
+        bytes
+    }
+
+    /// Convert this SEEKTABLE block into a metadata block
+    pub fn build_block(&self) -> MetadataBlock {
+        MetadataBlock::new(MetadataBlockType::Seektable, self.build_bytes())
+    }
+}
+
+impl SeekTableBuilder {
+    /// Create a new builder for audio of `total_samples` inter-channel samples
+    /// 
+    /// Targets past the end of the audio are dropped, and so is any spacing of
+    /// zero. Since the number of seek points is fixed here, `total_samples` may
+    /// be an upper bound when the exact length is not known before encoding.
+    pub fn new(spacing: &SeekPointSpacing, samp_rate: u32, total_samples: u64) -> Self {
+        let step = match spacing {
+            SeekPointSpacing::Seconds(secs) => secs * samp_rate as u64,
+            SeekPointSpacing::Samples(num_samples) => *num_samples,
+            SeekPointSpacing::Explicit(_) => 0,
+        };
+
+        let mut targets: Vec <u64> = match spacing {
+            SeekPointSpacing::Explicit(sample_numbers) => sample_numbers.clone(),
+            _ if step == 0 => Vec::new(),
+            _ => (0..total_samples).step_by(step as usize).collect(),
+        };
+        targets.retain(|&target| target < total_samples);
+        targets.sort_unstable();
+        targets.dedup();
+
+        Self {
+            targets,
+            next_target: 0,
+            points: Vec::new(),
+        }
+    }
+
+    /// Get the number of seek points of the SEEKTABLE block
+    pub fn num_points(&self) -> usize {
+        self.targets.len()
+    }
+
+    /// Drop the targets past the end of audio shorter than given to `new`
+    /// 
+    /// This is for audio whose exact length is only known once it is encoded,
+    /// such as audio with trimmed silence, so that the final SEEKTABLE block
+    /// holds no placeholder for a target the audio does not reach.
+    pub fn set_total_samples(&mut self, total_samples: u64) {
+        self.targets.retain(|&target| target < total_samples);
+        self.next_target = self.next_target.min(self.targets.len());
+    }
+
+    /// Add an encoded frame
+    /// 
+    /// `sample_number` is the first inter-channel sample of the frame and `offset`
+    /// is its offset in bytes from the first frame. Frames must be added in order.
+    pub fn update(&mut self, sample_number: u64, offset: u64, num_samples: u16) {
+        let end = sample_number + num_samples as u64;
+
+        if self.targets.get(self.next_target).is_some_and(|&target| target < end) {
+            self.points.push(SeekPoint {
+                sample_number,
+                offset,
+                num_samples,
+            });
+
+            while self.targets.get(self.next_target).is_some_and(|&target| target < end) {
+                self.next_target += 1;
+            }
+        }
+    }
+
+    /// Finish building and get the final SEEKTABLE block
+    pub fn finish(self) -> SeekTable {
+        let mut points = self.points;
+        points.resize(self.targets.len(), SeekPoint::placeholder());
+
+        SeekTable {
+            points,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_numbers(table: &SeekTable) -> Vec <u64> {
+        table.points.iter().map(|point| point.sample_number).collect()
+    }
+
+    #[test]
+    fn sample_build_bytes() {
+        let table = SeekTable {
+            points: vec![
+                SeekPoint {
+                    sample_number: 4096,
+                    offset: 0x1234,
+                    num_samples: 4096,
+                },
+                SeekPoint::placeholder(),
+            ],
+        };
+        let bytes = table.build_bytes();
+
+        assert_eq!(bytes.len(), 2 * SEEK_POINT_LEN);
+        assert_eq!(&bytes[..SEEK_POINT_LEN], &[
+            0, 0, 0, 0, 0, 0, 0x10, 0,
+            0, 0, 0, 0, 0, 0, 0x12, 0x34,
+            0x10, 0,
+        ]);
+        assert_eq!(&bytes[SEEK_POINT_LEN..SEEK_POINT_LEN + 8], &[0xff; 8]);
+        assert_eq!(SeekTable::parse(&table.build_block()).unwrap(), table);
+    }
+
+    #[test]
+    fn sample_parse_errors() {
+        let block = MetadataBlock::new(MetadataBlockType::Seektable, vec![0; SEEK_POINT_LEN + 1]);
+        assert!(matches!(SeekTable::parse(&block), Err(MetadataError::BlockDataError)));
+
+        let block = MetadataBlock::padding(SEEK_POINT_LEN);
+        assert!(matches!(SeekTable::parse(&block), Err(MetadataError::BlockTypeError)));
+    }
+
+    #[test]
+    fn sample_seconds() {
+        // Targets at 0, 2, 4 and 6 seconds of 1 kHz audio, with frames of 1500 samples
+        let mut builder = SeekTableBuilder::new(&SeekPointSpacing::Seconds(2), 1000, 7000);
+
+        for i in 0..5 {
+            builder.update(i * 1500, i * 100, 1500);
+        }
+
+        let table = builder.finish();
+
+        assert_eq!(sample_numbers(&table), vec![0, 1500, 3000, 6000]);
+        assert_eq!(table.points[1].offset, 100);
+        assert_eq!(table.points[3].num_samples, 1500);
+    }
+
+    #[test]
+    fn sample_shared_frame() {
+        // Two targets in the same frame share one seek point
+        let mut builder = SeekTableBuilder::new(&SeekPointSpacing::Samples(1000), 44100, 4096);
+
+        builder.update(0, 0, 4096);
+
+        let table = builder.finish();
+
+        assert_eq!(table.points.len(), 5);
+        assert_eq!(table.points[0].sample_number, 0);
+        assert!(table.points[1..].iter().all(|point| point.is_placeholder()));
+    }
+
+    #[test]
+    fn sample_explicit() {
+        let spacing = SeekPointSpacing::Explicit(vec![5000, 10, 5000, 20000]);
+        let mut builder = SeekTableBuilder::new(&spacing, 44100, 10000);
+
+        assert_eq!(builder.num_points(), 2);
+
+        builder.update(0, 0, 4096);
+        builder.update(4096, 900, 4096);
+        builder.update(8192, 1800, 1808);
+
+        assert_eq!(sample_numbers(&builder.finish()), vec![0, 4096]);
+    }
+
+    #[test]
+    fn sample_shorter_audio() {
+        // Targets at 0, 1000, 2000 and 3000, with audio ending after 2500 samples
+        let mut builder = SeekTableBuilder::new(&SeekPointSpacing::Samples(1000), 44100, 4000);
+
+        builder.update(0, 0, 1500);
+        builder.update(1500, 300, 1000);
+        builder.set_total_samples(2500);
+
+        assert_eq!(builder.num_points(), 3);
+        assert_eq!(sample_numbers(&builder.finish()), vec![0, 1500, PLACEHOLDER_SAMPLE_NUMBER]);
+    }
+}
@@ -0,0 +1,258 @@
+use crate::wav::PCMWaveFormatChunk;
+
+use super::{MetadataBlock, MetadataBlockType};
+
+/// Length of the data of a STREAMINFO block in bytes
+pub const STREAMINFO_LEN: usize = 34;
+
+/// Represents a STREAMINFO metadata block
+/// 
+/// The STREAMINFO block is the mandatory first metadata block of a FLAC file.
+/// A frame size of 0 means that the value is unknown, and so does a total
+/// number of samples of 0 or an MD5 signature of all zeros.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamInfo {
+    pub min_block_size: u16,
+    pub max_block_size: u16,
+    pub min_frame_size: u32,
+    pub max_frame_size: u32,
+    pub sample_rate: u32,
+    pub num_channels: u8,
+    pub bps: u8,
+    pub total_samples: u64,
+    pub md5: [u8; 16],
+}
+
+/// Represents a collector of the STREAMINFO values while encoding
+/// 
+/// Every encoded block of inter-channel samples is passed to the collector
+/// along with the size of its audio frame. The MD5 signature is computed
+/// over the unencoded samples as specified by FLAC: interleaved, signed,
+/// and little-endian, with each sample taking the least number of whole
+/// bytes that fit the bit depth.
+pub struct StreamInfoCollector {
+    info: StreamInfo,
+    md5: md5::Context,
+    last_block_size: Option <u16>,
+    sample_bytes: usize,
+}
+
+impl StreamInfo {
+    /// Create a STREAMINFO block with unknown sizes for audio of the given format
+    pub fn new(format: &PCMWaveFormatChunk) -> Self {
+        Self {
+            min_block_size: 0,
+            max_block_size: 0,
+            min_frame_size: 0,
+            max_frame_size: 0,
+            sample_rate: format.samp_rate,
+            num_channels: format.num_channels as u8,
+            bps: format.bps as u8,
+            total_samples: 0,
+            md5: [0; 16],
+        }
+    }
+
+    /// Parse the 34 bytes of data of a STREAMINFO block
+    /// 
+    /// Returns `None` if the data is not 34 bytes long. The layout is described
+    /// in `build_bytes`.
+    pub fn parse(data: &[u8]) -> Option <Self> {
+        if data.len() != STREAMINFO_LEN {
+            return None;
+        }
+
+        let mut packed = [0u8; 8];
+        packed.copy_from_slice(&data[10..18]);
+        let packed = u64::from_be_bytes(packed);
+
+        let mut md5 = [0u8; 16];
+        md5.copy_from_slice(&data[18..]);
+
+        Some(Self {
+            min_block_size: u16::from_be_bytes([data[0], data[1]]),
+            max_block_size: u16::from_be_bytes([data[2], data[3]]),
+            min_frame_size: u32::from_be_bytes([0, data[4], data[5], data[6]]),
+            max_frame_size: u32::from_be_bytes([0, data[7], data[8], data[9]]),
+            sample_rate: (packed >> 44) as u32,
+            num_channels: ((packed >> 41) & 0x7) as u8 + 1,
+            bps: ((packed >> 36) & 0x1f) as u8 + 1,
+            total_samples: packed & 0xf_ffff_ffff,
+            md5,
+        })
+    }
+
+    /// Convert this STREAMINFO block into its 34 bytes of block data
+    /// 
+    /// The layout of the block data is as follows, with all values big-endian:
+    /// `<min_block_size:16b><max_block_size:16b><min_frame_size:24b><max_frame_size:24b>`
+    /// `<sample_rate:20b><num_channels - 1:3b><bps - 1:5b><total_samples:36b><md5:128b>`.
+    pub fn build_bytes(&self) -> Vec <u8> {
+        let mut bytes = Vec::with_capacity(STREAMINFO_LEN);
+
+        bytes.extend_from_slice(&self.min_block_size.to_be_bytes());
+        bytes.extend_from_slice(&self.max_block_size.to_be_bytes());
+        bytes.extend_from_slice(&self.min_frame_size.to_be_bytes()[1..]);
+        bytes.extend_from_slice(&self.max_frame_size.to_be_bytes()[1..]);
+
+        let packed = ((self.sample_rate as u64 & 0xfffff) << 44)
+            | (((self.num_channels as u64 - 1) & 0x7) << 41)
+            | (((self.bps as u64 - 1) & 0x1f) << 36)
+            | (self.total_samples & 0xf_ffff_ffff);
+
+        bytes.extend_from_slice(&packed.to_be_bytes());
+        bytes.extend_from_slice(&self.md5);
+
+        bytes
+    }
+
+    /// Convert this STREAMINFO block into a metadata block
+    pub fn build_block(&self) -> MetadataBlock {
+        MetadataBlock::new(MetadataBlockType::Streaminfo, self.build_bytes())
+    }
+}
+
+impl StreamInfoCollector {
+    /// Create a new collector for audio of the given format
+    pub fn new(format: &PCMWaveFormatChunk) -> Self {
+        Self {
+            info: StreamInfo::new(format),
+            md5: md5::Context::new(),
+            last_block_size: None,
+            sample_bytes: (format.bps as usize).div_ceil(8),
+        }
+    }
+
+    /// Add an encoded block of inter-channel samples and the size of its frame in bytes
+    pub fn update(&mut self, sample_block: &[Vec <i64>], frame_size: usize) {
+        let block_size = sample_block.len() as u16;
+        let frame_size = frame_size as u32;
+
+        // The minimum block size excludes the last block, so the size of a
+        // block is only accounted for once the next one arrives
+        if let Some(last_block_size) = self.last_block_size {
+            self.info.min_block_size = match self.info.min_block_size {
+                0 => last_block_size,
+                min => min.min(last_block_size),
+            };
+        }
+        self.last_block_size = Some(block_size);
+        self.info.max_block_size = self.info.max_block_size.max(block_size);
+
+        self.info.min_frame_size = match self.info.min_frame_size {
+            0 => frame_size,
+            min => min.min(frame_size),
+        };
+        self.info.max_frame_size = self.info.max_frame_size.max(frame_size);
+        self.info.total_samples += sample_block.len() as u64;
+
+        let mut buf = Vec::with_capacity(sample_block.len() * self.info.num_channels as usize * self.sample_bytes);
+
+        for inter_sample in sample_block {
+            for sample in inter_sample {
+                buf.extend_from_slice(&sample.to_le_bytes()[..self.sample_bytes]);
+            }
+        }
+
+        self.md5.consume(&buf);
+    }
+
+    /// Finish collecting and get the final STREAMINFO block
+    pub fn finish(self) -> StreamInfo {
+        let mut info = self.info;
+
+        if info.min_block_size == 0 {
+            info.min_block_size = info.max_block_size;
+        }
+        info.md5 = self.md5.compute().0;
+
+        info
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format() -> PCMWaveFormatChunk {
+        PCMWaveFormatChunk {
+            num_channels: 2,
+            samp_rate: 44100,
+            bps: 16,
+            channel_mask: None,
+        }
+    }
+
+    #[test]
+    fn sample_build_bytes() {
+        let info = StreamInfo {
+            min_block_size: 4096,
+            max_block_size: 4096,
+            min_frame_size: 0x0e,
+            max_frame_size: 0x01_2345,
+            sample_rate: 44100,
+            num_channels: 2,
+            bps: 16,
+            total_samples: 0x1_2345_6789,
+            md5: [0xab; 16],
+        };
+
+        let bytes = info.build_bytes();
+
+        assert_eq!(bytes.len(), STREAMINFO_LEN);
+        assert_eq!(&bytes[..18], &[
+            0x10, 0x00, 0x10, 0x00,
+            0x00, 0x00, 0x0e, 0x01, 0x23, 0x45,
+            0x0a, 0xc4, 0x42, 0xf1, 0x23, 0x45, 0x67, 0x89,
+        ]);
+        assert_eq!(&bytes[18..], &[0xab; 16]);
+        assert_eq!(StreamInfo::parse(&bytes), Some(info));
+        assert_eq!(StreamInfo::parse(&bytes[1..]), None);
+    }
+
+    #[test]
+    fn sample_collector() {
+        let mut collector = StreamInfoCollector::new(&format());
+
+        collector.update(&vec![vec![1, -1]; 4096], 100);
+        collector.update(&vec![vec![2, -2]; 4096], 80);
+        collector.update(&vec![vec![3, -3]; 10], 20);
+
+        let info = collector.finish();
+
+        assert_eq!(info.min_block_size, 4096);
+        assert_eq!(info.max_block_size, 4096);
+        assert_eq!(info.min_frame_size, 20);
+        assert_eq!(info.max_frame_size, 100);
+        assert_eq!(info.total_samples, 8202);
+    }
+
+    #[test]
+    fn sample_md5() {
+        // 16-bit samples are signed little-endian pairs of bytes
+        let mut collector = StreamInfoCollector::new(&format());
+
+        collector.update(&[vec![1, -1], vec![0x1234, -0x1234]], 10);
+
+        let expected = md5::compute([0x01, 0x00, 0xff, 0xff, 0x34, 0x12, 0xcc, 0xed]);
+
+        assert_eq!(collector.finish().md5, expected.0);
+    }
+
+    #[test]
+    fn sample_md5_24_bit() {
+        let fmt = PCMWaveFormatChunk {
+            num_channels: 1,
+            samp_rate: 48000,
+            bps: 24,
+            channel_mask: None,
+        };
+        let mut collector = StreamInfoCollector::new(&fmt);
+
+        collector.update(&[vec![-2]], 10);
+
+        let expected = md5::compute([0xfe, 0xff, 0xff]);
+
+        assert_eq!(collector.finish().md5, expected.0);
+    }
+}
@@ -0,0 +1,314 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Seek, SeekFrom, Write};
+
+use super::vorbis_comment::VorbisComment;
+use super::{MetadataBlock, MetadataBlockType, MetadataError, DEFAULT_PADDING_LEN, MAX_BLOCK_DATA_LEN};
+
+/// Represents how the metadata of a file was updated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpdateMode {
+    InPlace,
+    Rewrite,
+}
+
+/// Represents an updater of the metadata blocks of an existing FLAC file
+/// 
+/// The metadata blocks are read when the file is opened and can then be
+/// changed freely, as long as the STREAMINFO block stays first. When the
+/// changes are written, PADDING blocks are merged into one at the end of
+/// the metadata. If the new metadata fits the space taken by the old
+/// metadata, it is overwritten in place and the padding absorbs the
+/// difference. Otherwise, the whole file is rewritten with a new PADDING
+/// block of `padding_len` bytes, so that later updates fit in place again.
+pub struct MetadataUpdater {
+    file_path: String,
+    blocks: Vec <MetadataBlock>,
+    region_len: usize,
+    padding_len: usize,
+}
+
+impl MetadataUpdater {
+    /// Open a FLAC file and read its metadata blocks
+    /// 
+    /// # Errors
+    /// Returns a `MetadataError` if the file cannot be read or its metadata is
+    /// malformed.
+    pub fn open(file_path: &str) -> Result <Self, MetadataError> {
+        let mut reader = BufReader::new(File::open(file_path)?);
+        let blocks = MetadataBlock::read_all(&mut reader)?;
+        let region_len = blocks.iter().map(|block| block.total_len()).sum();
+
+        Ok(Self {
+            file_path: String::from(file_path),
+            blocks,
+            region_len,
+            padding_len: DEFAULT_PADDING_LEN,
+        })
+    }
+
+    /// Get the metadata blocks
+    pub fn blocks(&self) -> &Vec <MetadataBlock> {
+        &self.blocks
+    }
+
+    /// Get the metadata blocks for changing
+    pub fn blocks_mut(&mut self) -> &mut Vec <MetadataBlock> {
+        &mut self.blocks
+    }
+
+    /// Set the size of the PADDING block written if the file has to be rewritten
+    /// 
+    /// # Errors
+    /// Returns `MetadataError::BlockDataError` if `padding_len` is more than
+    /// `MAX_BLOCK_DATA_LEN` bytes.
+    pub fn set_padding_len(&mut self, padding_len: usize) -> Result <(), MetadataError> {
+        if padding_len > MAX_BLOCK_DATA_LEN {
+            return Err(MetadataError::BlockDataError);
+        }
+
+        self.padding_len = padding_len;
+
+        Ok(())
+    }
+
+    /// Get the Vorbis comments of the file, if any
+    /// 
+    /// # Errors
+    /// Returns `MetadataError::BlockDataError` if the VORBIS_COMMENT block is malformed.
+    pub fn vorbis_comment(&self) -> Result <Option <VorbisComment>, MetadataError> {
+        match self.blocks.iter().find(|block| block.block_type == MetadataBlockType::VorbisComment) {
+            Some(block) => Ok(Some(VorbisComment::parse(&block.data)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Replace the Vorbis comments of the file
+    /// 
+    /// The first VORBIS_COMMENT block is replaced and any other one is removed.
+    /// If the file has no VORBIS_COMMENT block, one is added after the
+    /// STREAMINFO block.
+    pub fn set_vorbis_comment(&mut self, comments: &VorbisComment) {
+        let block = comments.build_block();
+
+        match self.blocks.iter().position(|block| block.block_type == MetadataBlockType::VorbisComment) {
+            Some(idx) => {
+                self.blocks[idx] = block;
+
+                let mut rest = self.blocks.split_off(idx + 1);
+                rest.retain(|block| block.block_type != MetadataBlockType::VorbisComment);
+                self.blocks.extend(rest);
+            },
+            None => self.blocks.insert(1, block),
+        }
+    }
+
+    /// Write the changed metadata blocks to the file
+    /// 
+    /// Returns whether the metadata was updated in place or by rewriting the file.
+    /// 
+    /// # Errors
+    /// Returns `MetadataError::StreaminfoError` if the first block is no longer a
+    /// STREAMINFO block, `MetadataError::BlockDataError` if a block is longer than
+    /// `MAX_BLOCK_DATA_LEN` bytes, and `MetadataError::WriteError` if the file
+    /// cannot be written. The file is left untouched in the first two cases.
+    pub fn write(self) -> Result <UpdateMode, MetadataError> {
+        let blocks: Vec <MetadataBlock> = self.blocks
+            .iter()
+            .filter(|block| block.block_type != MetadataBlockType::Padding)
+            .cloned()
+            .collect();
+
+        if blocks.first().map(|block| block.block_type) != Some(MetadataBlockType::Streaminfo) {
+            return Err(MetadataError::StreaminfoError);
+        }
+        // The length of a block must fit the 24 bits of its header
+        if blocks.iter().any(|block| block.data.len() > MAX_BLOCK_DATA_LEN) {
+            return Err(MetadataError::BlockDataError);
+        }
+
+        let blocks_len: usize = blocks.iter().map(|block| block.total_len()).sum();
+        let region = if blocks_len == self.region_len {
+            Some(Self::build_blocks_bytes(&blocks))
+        }
+        else {
+            MetadataBlock::build_region_bytes(&blocks, self.region_len, true)
+        };
+
+        match region {
+            Some(region) => {
+                self.write_in_place(&region).map_err(|_| MetadataError::WriteError)?;
+
+                Ok(UpdateMode::InPlace)
+            },
+            None => {
+                let mut blocks = blocks;
+                if self.padding_len > 0 {
+                    blocks.push(MetadataBlock::padding(self.padding_len));
+                }
+
+                self.rewrite(&Self::build_blocks_bytes(&blocks)).map_err(|_| MetadataError::WriteError)?;
+
+                Ok(UpdateMode::Rewrite)
+            },
+        }
+    }
+
+    /// Convert metadata blocks into bytes, marking the last one
+    fn build_blocks_bytes(blocks: &[MetadataBlock]) -> Vec <u8> {
+        blocks
+            .iter()
+            .enumerate()
+            .flat_map(|(i, block)| block.build_bytes(i + 1 == blocks.len()))
+            .collect()
+    }
+
+    /// Overwrite the metadata of the file with a region of the same length
+    fn write_in_place(&self, region: &[u8]) -> io::Result <()> {
+        let mut out_fh = OpenOptions::new().write(true).open(&self.file_path)?;
+
+        out_fh.seek(SeekFrom::Start(4))?;
+        out_fh.write_all(region)
+    }
+
+    /// Rewrite the whole file with new metadata
+    /// 
+    /// The new file is written next to the old one and then renamed over it,
+    /// so the old file is left untouched if anything fails.
+    fn rewrite(&self, metadata: &[u8]) -> io::Result <()> {
+        let tmp_path = format!("{}.tmp", self.file_path);
+        let mut in_fh = BufReader::new(File::open(&self.file_path)?);
+        let mut out_fh = BufWriter::new(File::create(&tmp_path)?);
+
+        in_fh.seek(SeekFrom::Start(4 + self.region_len as u64))?;
+        out_fh.write_all(b"fLaC")?;
+        out_fh.write_all(metadata)?;
+        io::copy(&mut in_fh, &mut out_fh)?;
+        out_fh.flush()?;
+        drop(out_fh);
+
+        fs::rename(&tmp_path, &self.file_path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flac::metadata::fixtures::{create_flac, AUDIO};
+
+    fn update_title(file_name: &str, title: &str) -> Result <(UpdateMode, Vec <u8>), MetadataError> {
+        let mut updater = MetadataUpdater::open(file_name)?;
+        let mut comments = updater.vorbis_comment()?.unwrap();
+        comments.set("TITLE", title).unwrap();
+        updater.set_vorbis_comment(&comments);
+
+        let mode = updater.write()?;
+        let bytes = fs::read(file_name)?;
+
+        Ok((mode, bytes))
+    }
+
+    #[test]
+    fn sample_in_place() -> Result <(), MetadataError> {
+        let file_name = "midp_sample_in_place.flac.part";
+        create_flac(file_name, &[MetadataBlock::padding(100)])?;
+        let len = fs::metadata(file_name)?.len() as usize;
+
+        let result = update_title(file_name, "A longer new title");
+        let reopened = MetadataUpdater::open(file_name).and_then(|updater| updater.vorbis_comment());
+        fs::remove_file(file_name)?;
+
+        let (mode, bytes) = result?;
+        assert_eq!(mode, UpdateMode::InPlace);
+        assert_eq!(bytes.len(), len);
+        assert!(bytes.ends_with(&AUDIO));
+        assert_eq!(reopened?.unwrap().get_first("TITLE"), Some("A longer new title"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn sample_exact_fit() -> Result <(), MetadataError> {
+        // The new title takes exactly the space of the padding, header included
+        let file_name = "midp_sample_exact_fit.flac.part";
+        create_flac(file_name, &[MetadataBlock::padding(2)])?;
+
+        let result = update_title(file_name, "Old987654");
+        fs::remove_file(file_name)?;
+
+        let (mode, bytes) = result?;
+        assert_eq!(mode, UpdateMode::InPlace);
+        assert!(bytes.ends_with(&AUDIO));
+
+        let blocks = MetadataBlock::read_all(&mut bytes.as_slice())?;
+        assert_eq!(blocks.len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn sample_rewrite() -> Result <(), MetadataError> {
+        let file_name = "midp_sample_rewrite.flac.part";
+        create_flac(file_name, &[MetadataBlock::padding(4)])?;
+
+        let result = update_title(file_name, &"x".repeat(200));
+        fs::remove_file(file_name)?;
+
+        let (mode, bytes) = result?;
+        assert_eq!(mode, UpdateMode::Rewrite);
+        assert!(bytes.ends_with(&AUDIO));
+
+        let mut reader = bytes.as_slice();
+        let blocks = MetadataBlock::read_all(&mut reader)?;
+        assert_eq!(reader, &AUDIO);
+        assert_eq!(blocks[2], MetadataBlock::padding(DEFAULT_PADDING_LEN));
+
+        Ok(())
+    }
+
+    #[test]
+    fn sample_padding_len_limit() -> Result <(), MetadataError> {
+        let file_name = "midp_sample_padding_len_limit.flac.part";
+        create_flac(file_name, &[MetadataBlock::padding(4)])?;
+
+        let mut updater = MetadataUpdater::open(file_name)?;
+        fs::remove_file(file_name)?;
+
+        assert!(matches!(updater.set_padding_len(MAX_BLOCK_DATA_LEN + 1), Err(MetadataError::BlockDataError)));
+        assert!(updater.set_padding_len(MAX_BLOCK_DATA_LEN).is_ok());
+
+        Ok(())
+    }
+
+    #[test]
+    fn sample_missing_streaminfo() -> Result <(), MetadataError> {
+        let file_name = "midp_sample_missing_streaminfo.flac.part";
+        create_flac(file_name, &[MetadataBlock::padding(4)])?;
+
+        let mut updater = MetadataUpdater::open(file_name)?;
+        updater.blocks_mut().remove(0);
+        let result = updater.write();
+        fs::remove_file(file_name)?;
+
+        assert!(matches!(result, Err(MetadataError::StreaminfoError)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn sample_block_data_len_limit() -> Result <(), MetadataError> {
+        let file_name = "midp_sample_block_data_len_limit.flac.part";
+        create_flac(file_name, &[MetadataBlock::padding(4)])?;
+        let before = fs::read(file_name)?;
+
+        let mut updater = MetadataUpdater::open(file_name)?;
+        updater.blocks_mut().push(MetadataBlock::new(MetadataBlockType::Application, vec![0; MAX_BLOCK_DATA_LEN + 1]));
+        let result = updater.write();
+        let after = fs::read(file_name)?;
+        fs::remove_file(file_name)?;
+
+        assert!(matches!(result, Err(MetadataError::BlockDataError)));
+        assert_eq!(after, before);
+
+        Ok(())
+    }
+}
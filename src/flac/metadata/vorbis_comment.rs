@@ -0,0 +1,299 @@
+use core::fmt;
+use std::error;
+
+use super::{MetadataBlock, MetadataBlockType};
+
+/// Vendor string written in the Vorbis comments of encoded files
+pub const VENDOR_STRING: &str = concat!("midp ", env!("CARGO_PKG_VERSION"));
+
+/// Represents the Vorbis comments of a FLAC file
+/// 
+/// Vorbis comments are `KEY=value` pairs used to tag the audio, such
+/// as its title, artist, or ReplayGain. A key may appear more than once
+/// to hold several values, such as multiple artists, and the comments
+/// keep the order in which they were added. Keys are compared without
+/// regard to case. Unlike the rest of FLAC, all lengths in a
+/// VORBIS_COMMENT block are little-endian.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VorbisComment {
+    vendor: String,
+    comments: Vec <(String, String)>,
+}
+
+/// Represents an error in Vorbis comments
+#[derive(Debug)]
+pub enum VorbisCommentError {
+    FieldNameError,
+    MissingSeparatorError,
+    EncodingError,
+    TruncatedError,
+}
+
+impl VorbisComment {
+    /// Create new empty Vorbis comments with the given vendor string
+    pub fn new(vendor: &str) -> Self {
+        Self {
+            vendor: String::from(vendor),
+            comments: Vec::new(),
+        }
+    }
+
+    /// Parse the data of a VORBIS_COMMENT block
+    /// 
+    /// # Errors
+    /// Returns a `VorbisCommentError` if the data is truncated, is not valid
+    /// UTF-8, or holds a comment without a valid field name.
+    pub fn parse(data: &[u8]) -> Result <Self, VorbisCommentError> {
+        let mut reader = data;
+        let vendor = Self::read_string(&mut reader)?;
+        let num_comments = Self::read_u32(&mut reader)?;
+        let mut comments = Self::new(&vendor);
+
+        for _ in 0..num_comments {
+            let comment = Self::read_string(&mut reader)?;
+            let (key, value) = comment
+                .split_once('=')
+                .ok_or(VorbisCommentError::MissingSeparatorError)?;
+
+            comments.add(key, value)?;
+        }
+
+        Ok(comments)
+    }
+
+    /// Get the vendor string
+    pub fn vendor(&self) -> &str {
+        &self.vendor
+    }
+
+    /// Replace the vendor string
+    pub fn set_vendor(&mut self, vendor: &str) {
+        self.vendor = String::from(vendor);
+    }
+
+    /// Add a `KEY=value` comment after the existing ones
+    /// 
+    /// Existing values of the same key are kept, so this adds another value
+    /// to a multi-valued field.
+    /// 
+    /// # Errors
+    /// Returns `VorbisCommentError::FieldNameError` if `key` is not a valid
+    /// field name.
+    pub fn add(&mut self, key: &str, value: &str) -> Result <(), VorbisCommentError> {
+        if !Self::is_valid_field_name(key) {
+            return Err(VorbisCommentError::FieldNameError);
+        }
+
+        self.comments.push((String::from(key), String::from(value)));
+
+        Ok(())
+    }
+
+    /// Set the value of a field, replacing all of its existing values
+    /// 
+    /// The new value takes the place of the first existing value, or is added
+    /// after the existing comments if the field is not set yet.
+    /// 
+    /// # Errors
+    /// Returns `VorbisCommentError::FieldNameError` if `key` is not a valid
+    /// field name.
+    pub fn set(&mut self, key: &str, value: &str) -> Result <(), VorbisCommentError> {
+        if !Self::is_valid_field_name(key) {
+            return Err(VorbisCommentError::FieldNameError);
+        }
+
+        match self.comments.iter().position(|(k, _)| k.eq_ignore_ascii_case(key)) {
+            Some(idx) => {
+                self.comments[idx].1 = String::from(value);
+
+                let mut rest = self.comments.split_off(idx + 1);
+                rest.retain(|(k, _)| !k.eq_ignore_ascii_case(key));
+                self.comments.extend(rest);
+            },
+            None => self.comments.push((String::from(key), String::from(value))),
+        }
+
+        Ok(())
+    }
+
+    /// Get all values of a field in order
+    pub fn get(&self, key: &str) -> Vec <&str> {
+        self.comments
+            .iter()
+            .filter(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, value)| value.as_str())
+            .collect()
+    }
+
+    /// Get the first value of a field, if any
+    pub fn get_first(&self, key: &str) -> Option <&str> {
+        self.get(key).first().copied()
+    }
+
+    /// Remove all values of a field and get the number of removed comments
+    pub fn remove(&mut self, key: &str) -> usize {
+        let len = self.comments.len();
+        self.comments.retain(|(k, _)| !k.eq_ignore_ascii_case(key));
+
+        len - self.comments.len()
+    }
+
+    /// Get all comments as `(key, value)` pairs in order
+    pub fn iter(&self) -> impl Iterator <Item = (&str, &str)> {
+        self.comments.iter().map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    /// Get the number of comments
+    pub fn len(&self) -> usize {
+        self.comments.len()
+    }
+
+    /// Check whether there are no comments
+    pub fn is_empty(&self) -> bool {
+        self.comments.is_empty()
+    }
+
+    /// Check whether a field name is valid
+    /// 
+    /// A field name is a non-empty string of printable ASCII characters from
+    /// 0x20 to 0x7D, except for `=` (0x3D).
+    pub fn is_valid_field_name(key: &str) -> bool {
+        !key.is_empty() && key.bytes().all(|b| (0x20..=0x7d).contains(&b) && b != b'=')
+    }
+
+    /// Convert these comments into the data of a VORBIS_COMMENT block
+    pub fn build_bytes(&self) -> Vec <u8> {
+        let mut bytes = Vec::new();
+
+        bytes.extend_from_slice(&(self.vendor.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(self.vendor.as_bytes());
+        bytes.extend_from_slice(&(self.comments.len() as u32).to_le_bytes());
+
+        for (key, value) in &self.comments {
+            let comment = format!("{}={}", key, value);
+
+            bytes.extend_from_slice(&(comment.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(comment.as_bytes());
+        }
+
+        bytes
+    }
+
+    /// Convert these comments into a VORBIS_COMMENT metadata block
+    pub fn build_block(&self) -> MetadataBlock {
+        MetadataBlock::new(MetadataBlockType::VorbisComment, self.build_bytes())
+    }
+
+    /// Read a little-endian 32-bit length from the front of `reader`
+    fn read_u32(reader: &mut &[u8]) -> Result <u32, VorbisCommentError> {
+        if reader.len() < 4 {
+            return Err(VorbisCommentError::TruncatedError);
+        }
+
+        let (len_bytes, rest) = reader.split_at(4);
+        *reader = rest;
+
+        Ok(u32::from_le_bytes([len_bytes[0], len_bytes[1], len_bytes[2], len_bytes[3]]))
+    }
+
+    /// Read a length-prefixed UTF-8 string from the front of `reader`
+    fn read_string(reader: &mut &[u8]) -> Result <String, VorbisCommentError> {
+        let len = Self::read_u32(reader)? as usize;
+
+        if reader.len() < len {
+            return Err(VorbisCommentError::TruncatedError);
+        }
+
+        let (string_bytes, rest) = reader.split_at(len);
+        *reader = rest;
+
+        String::from_utf8(string_bytes.to_vec()).map_err(|_| VorbisCommentError::EncodingError)
+    }
+}
+
+impl Default for VorbisComment {
+    fn default() -> Self {
+        Self::new(VENDOR_STRING)
+    }
+}
+
+impl error::Error for VorbisCommentError {}
+
+impl fmt::Display for VorbisCommentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Error: {:?}", self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sample_build_bytes() {
+        let mut comments = VorbisComment::new("ab");
+        comments.add("TITLE", "x").unwrap();
+
+        assert_eq!(comments.build_bytes(), vec![
+            0x02, 0x00, 0x00, 0x00, b'a', b'b',
+            0x01, 0x00, 0x00, 0x00,
+            0x07, 0x00, 0x00, 0x00, b'T', b'I', b'T', b'L', b'E', b'=', b'x',
+        ]);
+    }
+
+    #[test]
+    fn sample_multi_valued() {
+        let mut comments = VorbisComment::default();
+        comments.add("ARTIST", "First").unwrap();
+        comments.add("TITLE", "Song").unwrap();
+        comments.add("artist", "Second").unwrap();
+
+        assert_eq!(comments.get("Artist"), vec!["First", "Second"]);
+        assert_eq!(comments.get_first("title"), Some("Song"));
+        assert_eq!(comments.get("ISRC"), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn sample_set_and_remove() {
+        let mut comments = VorbisComment::default();
+        comments.add("ARTIST", "First").unwrap();
+        comments.add("TITLE", "Song").unwrap();
+        comments.add("ARTIST", "Second").unwrap();
+
+        comments.set("artist", "Only").unwrap();
+        assert_eq!(comments.iter().collect::<Vec <_>>(), vec![("ARTIST", "Only"), ("TITLE", "Song")]);
+
+        comments.set("ISRC", "USABC1234567").unwrap();
+        assert_eq!(comments.len(), 3);
+
+        assert_eq!(comments.remove("Title"), 1);
+        assert_eq!(comments.get_first("TITLE"), None);
+    }
+
+    #[test]
+    fn sample_field_names() {
+        let mut comments = VorbisComment::default();
+
+        assert!(VorbisComment::is_valid_field_name("ASSET_ID"));
+        assert!(VorbisComment::is_valid_field_name("X-ASSET ID"));
+        assert!(!VorbisComment::is_valid_field_name(""));
+        assert!(!VorbisComment::is_valid_field_name("A=B"));
+        assert!(!VorbisComment::is_valid_field_name("TITLE~"));
+        assert!(!VorbisComment::is_valid_field_name("TÍTULO"));
+        assert!(matches!(comments.add("A=B", "x"), Err(VorbisCommentError::FieldNameError)));
+        assert!(comments.is_empty());
+    }
+
+    #[test]
+    fn sample_parse() {
+        let mut comments = VorbisComment::new("vendor");
+        comments.add("TITLE", "a=b").unwrap();
+        comments.add("ARTIST", "Ñandú").unwrap();
+
+        let parsed = VorbisComment::parse(&comments.build_bytes()).unwrap();
+
+        assert_eq!(parsed, comments);
+        assert_eq!(parsed.get_first("title"), Some("a=b"));
+        assert!(matches!(VorbisComment::parse(&[1, 0, 0, 0]), Err(VorbisCommentError::TruncatedError)));
+    }
+}
@@ -0,0 +1,2197 @@
+pub mod container;
+pub mod decoder;
+pub mod encoder;
+pub mod lpc;
+pub mod bitstream;
+pub mod metadata;
+
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+
+use crate::analysis::BlockAnalyzer;
+use crate::analysis::replaygain::{ReplayGain, ReplayGainAlbum, ReplayGainScanner};
+use crate::analysis::silence::{SilenceTrim, SilenceTrimOptions, SilenceTrimmer};
+use crate::cue::{CueSheet, CueTrack, MAX_TRACK_NUMBER};
+use crate::wav::{PCMWaveFormatChunk, PCMWaveInfo};
+
+use container::ContainerSink;
+use container::matroska::MkaFlacWriter;
+use container::mp4::Mp4FlacWriter;
+use container::ogg::OggFlacWriter;
+use decoder::verify::{FrameVerifier, VerifyError};
+use encoder::blocking::{BlockSplitter, VariableBlockSizeOptions};
+use encoder::crc::CrcOptions;
+use encoder::parallel::{FramePool, FramePoolError};
+use encoder::preset::CompressionOptions;
+use encoder::stereo::ChannelAssignment;
+
+use self::bitstream::BitstreamWriter;
+use self::encoder::rice::{RiceEncodedStream, RiceEncoderOptions};
+use self::encoder::utf8::Utf8Encoder;
+use self::lpc::fixed::FixedPredictor;
+use self::lpc::var::VarPredictor;
+use self::metadata::{MetadataBlock, DEFAULT_PADDING_LEN, MAX_BLOCK_DATA_LEN};
+use self::metadata::application::ApplicationBlock;
+use self::metadata::cuesheet::CuesheetBlock;
+use self::metadata::foreign::ForeignMetadata;
+use self::metadata::picture::{Picture, PictureError};
+use self::metadata::seektable::{SeekPointSpacing, SeekTable, SeekTableBuilder, SEEK_POINT_LEN};
+use self::metadata::streaminfo::{StreamInfo, StreamInfoCollector, STREAMINFO_LEN};
+use self::metadata::vorbis_comment::{VorbisComment, VorbisCommentError, VENDOR_STRING};
+
+/// Size of the PADDING block reserved after metadata that is patched after encoding
+/// 
+/// Some metadata such as ReplayGain is only known once all audio frames are
+/// encoded. The reserved space absorbs the difference in length between the
+/// placeholder values written before encoding and the final values.
+const PATCHED_METADATA_PADDING_LEN: usize = 64;
+
+/// Offset of the SEEKTABLE block, which directly follows the STREAMINFO block
+const SEEKTABLE_OFFSET: u64 = 4 + 4 + STREAMINFO_LEN as u64;
+
+/// Sync code at the start of every audio frame, without the blocking strategy bit
+pub const FRAME_SYNC_CODE: u64 = 0x7ffc;
+
+#[derive(Debug)]
+pub enum FlacWriterError {
+    InvalidFormatError,
+    DataAlignmentError,
+    WriteError,
+    ReadError,
+    MetadataSizeError,
+    FormatMismatchError(usize),
+    TagError,
+    PictureError,
+    ForeignMetadataError,
+    BlockSizeError,
+    CompressionError,
+    TrackCountError,
+    TrackNumberError,
+    SampleRangeError,
+    VerifyError(VerifyError),
+    EncoderThreadError,
+}
+
+pub struct FlacFrame {
+    is_variable_blocksize: bool,
+    block_size: u16,
+    sample_rate: FlacFrameHeaderValueOption <u64>,
+    num_channels: u8,
+    channel_assignment: ChannelAssignment,
+    bit_depth: FlacFrameHeaderValueOption <u8>,
+    frame_index: u64,
+    subframes: Vec <FlacSubframe>,
+    compression: CompressionOptions,
+}
+
+pub struct FlacSubframe {
+    subframe_type: FlacSubframeType,
+    bit_depth: u8,
+    partition_orders: Option <(u8, u8)>,
+}
+
+pub enum FlacFrameHeaderValueOption <T> {
+    Streaminfo(T),
+    InFrame(T),
+}
+
+pub enum FlacSubframeType {
+    None,
+    Constant {value: i64},
+    Fixed {order: u8},
+    Lpc {
+        order: u8,
+        precision: u8,
+        shift: u8, 
+        qlp_coefs: Option <Vec <i64>>,
+    },
+    Verbatim,
+}
+
+pub struct FlacWriter;
+
+/// Represents the metadata blocks written along with the audio frames
+/// 
+/// The `comments` are written as a VORBIS_COMMENT block and each of the
+/// `pictures` as a PICTURE block in order, followed by the `applications` as
+/// APPLICATION blocks in order. If `foreign_metadata` is set, the non-audio
+/// chunks of the WAV file are stored as well so that it can be rebuilt byte
+/// for byte, as with `flac --keep-foreign-metadata`. A SEEKTABLE block is written if
+/// `seek_spacing` is set, and a PADDING block of `padding_len` bytes is
+/// written after all other metadata blocks unless `padding_len` is 0. The
+/// padding lets `MetadataUpdater` edit the metadata later without
+/// rewriting the whole file.
+#[derive(Debug, Clone)]
+pub struct FlacMetadataOptions {
+    pub comments: VorbisComment,
+    pub pictures: Vec <Picture>,
+    pub applications: Vec <ApplicationBlock>,
+    pub foreign_metadata: Option <ForeignMetadata>,
+    pub seek_spacing: Option <SeekPointSpacing>,
+    pub padding_len: usize,
+}
+
+/// Represents the options of how blocks of inter-channel samples are encoded into audio frames
+/// 
+/// The audio frames have variable block sizes if `block_sizes` is set, and
+/// are compressed as given by `compression` otherwise. Every audio frame is
+/// decoded again and compared with its samples if `verify` is set. The audio
+/// frames are encoded by `num_threads` worker threads if it is more than 1.
+#[derive(Debug, Clone, Copy, Default)]
+struct FrameOptions {
+    block_sizes: Option <VariableBlockSizeOptions>,
+    compression: CompressionOptions,
+    verify: bool,
+    num_threads: usize,
+}
+
+/// Represents the options of `FlacWriter::write_from_wave`
+/// 
+/// The metadata blocks are those of `metadata`. If `trim` is set, the leading
+/// and trailing silence of the audio is trimmed as described by `SilenceTrimOptions`.
+/// 
+/// The audio frames have variable block sizes if `block_sizes` is set. The
+/// samples are then grouped into blocks by a `BlockSplitter`, which splits
+/// blocks at transients and merges stationary regions, and each audio frame
+/// header holds the number of its first sample instead of a frame number.
+/// Otherwise, the audio frames have the fixed block size of `compression`.
+/// 
+/// The audio frames are compressed as given by `compression`, which is
+/// `CompressionOptions::default()`, that is preset 5, unless set. Use
+/// `CompressionOptions::preset` for the presets of `flac -0` to `flac -8`, so
+/// that files are comparable with those of libFLAC. Its `stereo_mode` chooses
+/// how stereo audio frames are decorrelated.
+/// 
+/// If `verify` is set, every audio frame is decoded again right after it is
+/// encoded and compared with its samples, and the MD5 signature of all decoded
+/// samples is compared with that of the input, as with `flac --verify`. The
+/// audio frames are encoded by `num_threads` worker threads if it is more
+/// than 1, as described by `FramePool`, and the FLAC file is byte for byte
+/// the same as when encoded on one thread.
+#[derive(Debug, Clone, Default)]
+pub struct FlacWriterOptions {
+    pub metadata: FlacMetadataOptions,
+    pub trim: Option <SilenceTrimOptions>,
+    pub block_sizes: Option <VariableBlockSizeOptions>,
+    pub compression: CompressionOptions,
+    pub verify: bool,
+    pub num_threads: usize,
+}
+
+/// Represents the configuration of a `FlacEncoder`
+/// 
+/// `total_samples` is the number of inter-channel samples that will be pushed,
+/// if known beforehand. A SEEKTABLE block is only written if it is known and
+/// `metadata.seek_spacing` is set, and the foreign metadata of `metadata` is
+/// only checked against the audio length if it is known. The other fields
+/// are described by `FlacWriterOptions`.
+#[derive(Debug, Clone)]
+pub struct EncoderConfig {
+    pub format: PCMWaveFormatChunk,
+    pub total_samples: Option <u64>,
+    pub metadata: FlacMetadataOptions,
+    pub block_sizes: Option <VariableBlockSizeOptions>,
+    pub compression: CompressionOptions,
+    pub verify: bool,
+    pub num_threads: usize,
+}
+
+/// Represents an incremental FLAC encoder writing to any sink
+/// 
+/// Samples are pushed in any amount and encoded into audio frames as soon as
+/// a whole block of them is available, so the encoder can write into sockets,
+/// pipes and in-memory buffers. The STREAMINFO block and the SEEKTABLE block,
+/// if any, are written first with unknown values. `finish` leaves them as is,
+/// which is valid FLAC, while `finish_and_patch` fills in the final values if
+/// the sink is also seekable.
+pub struct FlacEncoder <W: Write> {
+    writer: FlacFileWriter <W>,
+    block_sizes: Option <VariableBlockSizeOptions>,
+    block_size: usize,
+    pending: Vec <Vec <i64>>,
+}
+
+/// Represents a FLAC stream being written frame by frame
+/// 
+/// The STREAMINFO block and the SEEKTABLE block, if any, are written first
+/// with unknown values and patched once all audio frames are written if the
+/// output is seekable.
+struct FlacFileWriter <W: Write = BufWriter <File>> {
+    out: W,
+    fmt_header: PCMWaveFormatChunk,
+    streaminfo: StreamInfoCollector,
+    seek_table: Option <SeekTableBuilder>,
+    blocks_offset: u64,
+    metadata_len: u64,
+    is_variable_blocksize: bool,
+    compression: CompressionOptions,
+    verifier: Option <FrameVerifier>,
+    num_threads: usize,
+    pool: Option <FramePool>,
+    num_submitted_frames: u64,
+    num_submitted_samples: u64,
+    frame_index: u64,
+    sample_number: u64,
+    frame_offset: u64,
+}
+
+impl From <io::Error> for FlacWriterError {
+    fn from(_: io::Error) -> Self {
+        FlacWriterError::WriteError
+    }
+}
+
+impl From <VorbisCommentError> for FlacWriterError {
+    fn from(_: VorbisCommentError) -> Self {
+        FlacWriterError::TagError
+    }
+}
+
+impl From <VerifyError> for FlacWriterError {
+    fn from(err: VerifyError) -> Self {
+        FlacWriterError::VerifyError(err)
+    }
+}
+
+impl From <FramePoolError> for FlacWriterError {
+    fn from(_: FramePoolError) -> Self {
+        FlacWriterError::EncoderThreadError
+    }
+}
+
+impl From <PictureError> for FlacWriterError {
+    fn from(_: PictureError) -> Self {
+        FlacWriterError::PictureError
+    }
+}
+
+impl fmt::Display for FlacWriterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Error: {:?}", self)
+    }
+}
+
+impl Default for FlacMetadataOptions {
+    fn default() -> Self {
+        Self {
+            comments: VorbisComment::default(),
+            pictures: Vec::new(),
+            applications: Vec::new(),
+            foreign_metadata: None,
+            seek_spacing: Some(SeekPointSpacing::default()),
+            padding_len: DEFAULT_PADDING_LEN,
+        }
+    }
+}
+
+impl FlacWriter {
+    /// Create a FLAC file from a PCM Wave file
+    /// 
+    /// Every block of inter-channel samples is passed to each of the `analyzers`
+    /// before it is encoded into an audio frame. This way, analyses such as
+    /// `AudioAnalyzer` run in the same pass as the encoding and the WAV file
+    /// is only read once.
+    /// 
+    /// The metadata blocks are written in this order: STREAMINFO, SEEKTABLE,
+    /// VORBIS_COMMENT, PICTURE, APPLICATION and PADDING, as described by
+    /// `FlacMetadataOptions`. The STREAMINFO block, including the MD5 signature
+    /// of the unencoded audio, and the SEEKTABLE block are patched in once all
+    /// audio frames are written. The audio frames are encoded as described by
+    /// `FlacWriterOptions`.
+    /// 
+    /// Returns the trimmed offsets in inter-channel samples if `options.trim` is set.
+    /// 
+    /// # Errors
+    /// Returns `FlacWriterError::PictureError` if the pictures cannot be stored in
+    /// the same file, as checked by `Picture::validate_set`, and
+    /// `FlacWriterError::MetadataSizeError` if a metadata block is longer than
+    /// `MAX_BLOCK_DATA_LEN` bytes. Returns `FlacWriterError::ForeignMetadataError`
+    /// if the foreign metadata was not read from a WAV file of the same audio
+    /// length, or whenever it is set along with `options.trim`, as the trimmed
+    /// audio cannot rebuild the WAV file.
+    /// 
+    /// Returns `FlacWriterError::BlockSizeError` if `options.block_sizes` is set
+    /// but not valid, as checked by `VariableBlockSizeOptions::is_valid`, and
+    /// `FlacWriterError::CompressionError` if `options.compression` is not valid,
+    /// as checked by `CompressionOptions::is_valid`. Returns
+    /// `FlacWriterError::EncoderThreadError` if a worker thread fails.
+    /// 
+    /// Returns `FlacWriterError::VerifyError` as soon as an audio frame does not
+    /// decode to its samples if `options.verify` is set, with the index of the
+    /// frame and the first mismatching sample, or if the MD5 signatures differ.
+    /// The FLAC file is left incomplete in that case and must not be trusted.
+    pub fn write_from_wave(wav: PCMWaveInfo, file_path: &str, options: &FlacWriterOptions, analyzers: &mut [&mut dyn BlockAnalyzer]) -> Result <Option <SilenceTrim>, FlacWriterError> {
+        match &options.trim {
+            Some(trim_options) => Self::write_wave_trimmed(wav, file_path, trim_options, options, analyzers).map(Some),
+            None => Self::write_wave(wav, file_path, options, analyzers).map(|_| None),
+        }
+    }
+
+    /// Create a FLAC file from a PCM Wave file, as described by `write_from_wave`
+    fn write_wave(wav: PCMWaveInfo, file_path: &str, options: &FlacWriterOptions, analyzers: &mut [&mut dyn BlockAnalyzer]) -> Result <(), FlacWriterError> {
+        let fmt_header = wav.fmt_header;
+        let frame_options = options.frame_options();
+        let blocks = Self::metadata_blocks(Some(Self::wave_data_size(&wav)), &options.metadata)?;
+        let seek_table = Self::seek_table(&wav, &options.metadata);
+        let mut writer = FlacFileWriter::create(file_path, &fmt_header, &blocks, seek_table, &frame_options)?;
+
+        match &frame_options.block_sizes {
+            Some(block_sizes) => {
+                let splitter = BlockSplitter::new(wav.data_chunks.into_iter().flatten(), block_sizes);
+                Self::write_frames(&mut writer, splitter, analyzers)?;
+            },
+            None => Self::write_frames(&mut writer, Self::wave_blocks(wav, frame_options.compression.block_size as usize), analyzers)?,
+        }
+
+        writer.finish()?;
+
+        Ok(())
+    }
+
+    /// Create a FLAC file from a PCM Wave file with its leading and trailing silence trimmed
+    /// 
+    /// The silence is trimmed from the samples of all data chunks before they are
+    /// grouped into audio frames, so the `analyzers` only see the trimmed audio.
+    /// If `trim_options.tag_offsets` is set, the trimmed offsets are also set as
+    /// the `SILENCE_TRIM_LEADING`, `SILENCE_TRIM_TRAILING` and
+    /// `SILENCE_TRIM_TOTAL_SAMPLES` fields of the Vorbis comments so that the
+    /// original timing can be reconstructed.
+    /// 
+    /// The trimmed length is only known at the end, so the SEEKTABLE block is
+    /// reserved for the untrimmed length, and the seek points past the trimmed
+    /// end are given back as padding when it is patched.
+    fn write_wave_trimmed(wav: PCMWaveInfo, file_path: &str, trim_options: &SilenceTrimOptions, options: &FlacWriterOptions, analyzers: &mut [&mut dyn BlockAnalyzer]) -> Result <SilenceTrim, FlacWriterError> {
+        let metadata = &options.metadata;
+
+        if metadata.foreign_metadata.is_some() {
+            return Err(FlacWriterError::ForeignMetadataError);
+        }
+
+        let fmt_header = wav.fmt_header;
+        let frame_options = options.frame_options();
+        let seek_table = Self::seek_table(&wav, metadata);
+        let mut trimmer = SilenceTrimmer::new(wav.data_chunks.into_iter().flatten(), &fmt_header, trim_options);
+        let mut region_len = None;
+
+        let blocks = if trim_options.tag_offsets {
+            let placeholder = SilenceTrim {
+                leading: 0,
+                trailing: 0,
+                num_samples: 0,
+            };
+            let (reserved, len) = Self::reserve_metadata(None, &Self::trim_options(metadata, &placeholder)?)?;
+
+            region_len = Some(len);
+            reserved
+        }
+        else {
+            Self::metadata_blocks(None, metadata)?
+        };
+
+        let mut writer = FlacFileWriter::create(file_path, &fmt_header, &blocks, seek_table, &frame_options)?;
+        let region_offset = writer.blocks_offset();
+
+        match &frame_options.block_sizes {
+            Some(block_sizes) => Self::write_frames(&mut writer, BlockSplitter::new(trimmer.by_ref(), block_sizes), analyzers)?,
+            None => {
+                let block_size = frame_options.compression.block_size as usize;
+                let blocks = std::iter::from_fn(|| {
+                    let sample_block: Vec <Vec <i64>> = trimmer.by_ref().take(block_size).collect();
+                    (!sample_block.is_empty()).then_some(sample_block)
+                });
+
+                Self::write_frames(&mut writer, blocks, analyzers)?;
+            },
+        }
+
+        writer.finish()?;
+
+        let trim = trimmer.trim();
+
+        if let Some(region_len) = region_len {
+            Self::patch_metadata(file_path, &Self::trim_options(metadata, &trim)?, region_offset, region_len)?;
+        }
+
+        Ok(trim)
+    }
+
+    /// Create an Ogg FLAC file from a PCM Wave file
+    /// 
+    /// The FLAC stream is muxed into an Ogg logical stream of the given serial
+    /// number by `OggFlacWriter`, as described by `write_container`.
+    pub fn write_ogg_from_wave(wav: PCMWaveInfo, file_path: &str, serial: u32, analyzers: &mut [&mut dyn BlockAnalyzer]) -> Result <(), FlacWriterError> {
+        let out = OggFlacWriter::new(BufWriter::new(File::create(file_path)?), serial);
+
+        Self::write_container(wav, out, analyzers)
+    }
+
+    /// Create an MP4 file with a FLAC audio track from a PCM Wave file
+    /// 
+    /// The FLAC stream is muxed by `Mp4FlacWriter`, into fragments of
+    /// `frames_per_fragment` audio frames if set, as described by `write_container`.
+    pub fn write_mp4_from_wave(wav: PCMWaveInfo, file_path: &str, frames_per_fragment: Option <u32>, analyzers: &mut [&mut dyn BlockAnalyzer]) -> Result <(), FlacWriterError> {
+        let out_fh = BufWriter::new(File::create(file_path)?);
+        let out = match frames_per_fragment {
+            Some(frames_per_fragment) => Mp4FlacWriter::new_fragmented(out_fh, frames_per_fragment),
+            None => Mp4FlacWriter::new(out_fh),
+        };
+
+        Self::write_container(wav, out, analyzers)
+    }
+
+    /// Create a Matroska audio file with a FLAC track from a PCM Wave file
+    /// 
+    /// The FLAC stream is muxed by `MkaFlacWriter`, as described by `write_container`.
+    pub fn write_mka_from_wave(wav: PCMWaveInfo, file_path: &str, analyzers: &mut [&mut dyn BlockAnalyzer]) -> Result <(), FlacWriterError> {
+        let out = MkaFlacWriter::new(BufWriter::new(File::create(file_path)?));
+
+        Self::write_container(wav, out, analyzers)
+    }
+
+    /// Write the FLAC stream of a PCM Wave file into a container
+    /// 
+    /// The headers of the container are patched with the final STREAMINFO block
+    /// once all audio frames are written. Seeking is done with the index of the
+    /// container, so no SEEKTABLE block is written, and the other metadata
+    /// blocks are those of `FlacMetadataOptions::default()`.
+    fn write_container <M: ContainerSink>(wav: PCMWaveInfo, out: M, analyzers: &mut [&mut dyn BlockAnalyzer]) -> Result <(), FlacWriterError> {
+        let fmt_header = wav.fmt_header;
+        let blocks = Self::metadata_blocks(Some(Self::wave_data_size(&wav)), &FlacMetadataOptions::default())?;
+        let mut writer = FlacFileWriter::new(out, &fmt_header, &blocks, None, &FrameOptions::default())?;
+        let block_size = CompressionOptions::default().block_size as usize;
+
+        Self::write_frames(&mut writer, Self::wave_blocks(wav, block_size), analyzers)?;
+
+        let (streaminfo, out) = writer.finish_stream()?;
+        out.finish_and_patch(&streaminfo)?;
+
+        Ok(())
+    }
+
+    /// Build the metadata blocks described by `options`, other than STREAMINFO and SEEKTABLE
+    /// 
+    /// The foreign metadata is checked against `data_size`, the size in bytes of
+    /// the audio, if it is known.
+    fn metadata_blocks(data_size: Option <u64>, options: &FlacMetadataOptions) -> Result <Vec <MetadataBlock>, FlacWriterError> {
+        Picture::validate_set(&options.pictures)?;
+
+        let mut blocks = vec![options.comments.build_block()];
+        blocks.extend(options.pictures.iter().map(|picture| picture.build_block()));
+        blocks.extend(options.applications.iter().map(|application| application.build_block()));
+
+        if let Some(foreign_metadata) = &options.foreign_metadata {
+            if data_size.is_some_and(|data_size| foreign_metadata.data_size() as u64 != data_size) {
+                return Err(FlacWriterError::ForeignMetadataError);
+            }
+
+            blocks.extend(foreign_metadata.build_blocks());
+        }
+
+        if options.padding_len > 0 {
+            blocks.push(MetadataBlock::padding(options.padding_len));
+        }
+
+        if blocks.iter().any(|block| block.data.len() > MAX_BLOCK_DATA_LEN) {
+            return Err(FlacWriterError::MetadataSizeError);
+        }
+
+        Ok(blocks)
+    }
+
+    /// Create FLAC files from PCM Wave files tagged with ReplayGain
+    /// 
+    /// Each `(wav, file_path)` pair in `tracks` is encoded into its own FLAC file
+    /// with the metadata blocks of `options`, and its ReplayGain 2.0 loudness and
+    /// peak are computed while encoding. The `REPLAYGAIN_TRACK_GAIN` and
+    /// `REPLAYGAIN_TRACK_PEAK` fields are always set on `options.comments`. If
+    /// `is_album` is set, all tracks are treated as one album and the
+    /// `REPLAYGAIN_ALBUM_GAIN` and `REPLAYGAIN_ALBUM_PEAK` fields are set too.
+    /// 
+    /// The final values are only known once the last track is encoded, so the
+    /// metadata blocks are first written with placeholder values followed by a
+    /// PADDING block, and then patched in place in every file at the end. The
+    /// audio frames are compressed as given by `compression`.
+    /// 
+    /// Returns the ReplayGain of each track and the album ReplayGain, if any.
+    /// 
+    /// # Errors
+    /// Returns the errors of `write_from_wave` for the metadata blocks, and
+    /// `FlacWriterError::CompressionError` if the compression options are not
+    /// valid, as checked by `CompressionOptions::is_valid`.
+    pub fn write_from_waves_replaygain(tracks: Vec <(PCMWaveInfo, &str)>, is_album: bool, options: &FlacMetadataOptions, compression: &CompressionOptions) -> Result <(Vec <ReplayGain>, Option <ReplayGain>), FlacWriterError> {
+        let placeholder = ReplayGain {
+            gain: 0.0,
+            peak: 0.0,
+        };
+        let frame_options = FrameOptions {
+            compression: *compression,
+            ..FrameOptions::default()
+        };
+
+        let mut album = ReplayGainAlbum::new();
+        let mut track_gains = Vec::with_capacity(tracks.len());
+        let mut written = Vec::with_capacity(tracks.len());
+
+        for (wav, file_path) in tracks {
+            let fmt_header = wav.fmt_header;
+            let mut scanner = ReplayGainScanner::new(&fmt_header);
+            let placeholder_options = Self::replaygain_options(options, &placeholder, is_album.then_some(&placeholder))?;
+            let (blocks, region_len) = Self::reserve_metadata(Some(Self::wave_data_size(&wav)), &placeholder_options)?;
+            let seek_table = Self::seek_table(&wav, options);
+            let mut writer = FlacFileWriter::create(file_path, &fmt_header, &blocks, seek_table, &frame_options)?;
+            let region_offset = writer.blocks_offset();
+
+            Self::write_frames(&mut writer, Self::wave_blocks(wav, compression.block_size as usize), &mut [&mut scanner])?;
+            writer.finish()?;
+
+            track_gains.push(if is_album { album.add_track(scanner) } else { scanner.finish() });
+            written.push((file_path, region_offset, region_len));
+        }
+
+        let album_gain = is_album.then(|| album.finish());
+
+        for ((file_path, region_offset, region_len), track_gain) in written.into_iter().zip(track_gains.iter()) {
+            let gain_options = Self::replaygain_options(options, track_gain, album_gain.as_ref())?;
+
+            Self::patch_metadata(file_path, &gain_options, region_offset, region_len)?;
+        }
+
+        Ok((track_gains, album_gain))
+    }
+
+    /// Split a PCM Wave file into one FLAC file per track of a cue sheet
+    /// 
+    /// The WAV file is read once, and each track is written to
+    /// `<out_dir>/<track number>.flac` with its own STREAMINFO block and MD5
+    /// signature. A track ends where the next one starts, and the first track
+    /// also holds any audio before its start. The `TRACKNUMBER`, `TITLE`,
+    /// `PERFORMER` and `ALBUM` Vorbis comments are taken from the cue sheet,
+    /// falling back to the performer of the whole sheet. Tracks that start past
+    /// the end of the audio are not written.
+    /// 
+    /// Use `CueSheet::read` for a separate cue sheet file, or
+    /// `CueSheet::from_wave_cue_points` with `WaveReader::read_cue_points` for
+    /// cue points embedded in the WAV file. The audio frames are compressed as
+    /// given by `compression`.
+    /// 
+    /// Returns the paths of the written files.
+    /// 
+    /// # Errors
+    /// Returns `FlacWriterError::InvalidFormatError` if the cue sheet has no tracks,
+    /// `FlacWriterError::TrackNumberError` if two tracks have the same number, as
+    /// they would be written to the same file, and `FlacWriterError::CompressionError`
+    /// if the compression options are not valid, as checked by `CompressionOptions::is_valid`.
+    pub fn split_from_wave(wav: PCMWaveInfo, cue_sheet: &CueSheet, out_dir: &str, compression: &CompressionOptions) -> Result <Vec <String>, FlacWriterError> {
+        if cue_sheet.tracks.is_empty() {
+            return Err(FlacWriterError::InvalidFormatError);
+        }
+
+        let mut numbers: Vec <u8> = cue_sheet.tracks.iter().map(|track| track.number).collect();
+        numbers.sort_unstable();
+
+        if numbers.windows(2).any(|pair| pair[0] == pair[1]) {
+            return Err(FlacWriterError::TrackNumberError);
+        }
+        let fmt_header = wav.fmt_header;
+        let block_size = compression.block_size as u64;
+        let frame_options = FrameOptions {
+            compression: *compression,
+            ..FrameOptions::default()
+        };
+        let track_ranges = cue_sheet.track_ranges(Self::wave_num_samples(&wav));
+        let mut samples = wav.data_chunks.into_iter().flatten().peekable();
+        let mut written = Vec::new();
+
+        for (track_idx, (track, (start, end))) in cue_sheet.tracks.iter().zip(track_ranges).enumerate() {
+            if samples.peek().is_none() {
+                break;
+            }
+
+            let file_path = format!("{}/{:02}.flac", out_dir, track.number);
+            let blocks = vec![Self::track_comments(cue_sheet, track_idx)?.build_block()];
+            let seek_table = Self::default_seek_table(&fmt_header, end - start);
+            let mut writer = FlacFileWriter::create(&file_path, &fmt_header, &blocks, Some(seek_table), &frame_options)?;
+            let mut remaining = end - start;
+
+            while remaining > 0 {
+                let sample_block: Vec <Vec <i64>> = samples.by_ref().take(remaining.min(block_size) as usize).collect();
+
+                if sample_block.is_empty() {
+                    break;
+                }
+
+                remaining -= sample_block.len() as u64;
+                writer.write_block(&sample_block)?;
+            }
+
+            writer.finish()?;
+            written.push(file_path);
+        }
+
+        Ok(written)
+    }
+
+    /// Join PCM Wave files into one FLAC file with an embedded cue sheet
+    /// 
+    /// The samples of all `(wav, title)` pairs in `tracks` are concatenated
+    /// gaplessly, so audio frames may span the boundary between two files. A
+    /// CUESHEET block holds a track starting at each file boundary, and the title
+    /// of each track, if any, is written as a `CUE_TRACKnn_TITLE` Vorbis comment.
+    /// The audio frames are compressed as given by `compression`.
+    /// 
+    /// Returns the cue sheet of the joined file, with track starts in inter-channel
+    /// samples.
+    /// 
+    /// # Errors
+    /// Returns `FlacWriterError::FormatMismatchError` with the index of the first
+    /// WAV file whose format differs from the format of the first one,
+    /// `FlacWriterError::InvalidFormatError` if there are no tracks,
+    /// `FlacWriterError::TrackCountError` if there are more than `MAX_TRACK_NUMBER`
+    /// tracks, and
+    /// `FlacWriterError::CompressionError` if the compression options are not
+    /// valid, as checked by `CompressionOptions::is_valid`.
+    pub fn join_from_waves(tracks: Vec <(PCMWaveInfo, Option <&str>)>, file_path: &str, compression: &CompressionOptions) -> Result <CueSheet, FlacWriterError> {
+        if tracks.len() > MAX_TRACK_NUMBER as usize {
+            return Err(FlacWriterError::TrackCountError);
+        }
+
+        let fmt_header = match tracks.first() {
+            Some((wav, _)) => wav.fmt_header,
+            None => return Err(FlacWriterError::InvalidFormatError),
+        };
+
+        if let Some(track_idx) = tracks.iter().position(|(wav, _)| wav.fmt_header != fmt_header) {
+            return Err(FlacWriterError::FormatMismatchError(track_idx));
+        }
+
+        let mut cue_sheet = CueSheet {
+            title: None,
+            performer: None,
+            catalog: None,
+            tracks: Vec::with_capacity(tracks.len()),
+        };
+        let mut comments = VorbisComment::new(VENDOR_STRING);
+        let mut start = 0;
+
+        for (i, (wav, title)) in tracks.iter().enumerate() {
+            let number = u8::try_from(i + 1).map_err(|_| FlacWriterError::TrackCountError)?;
+
+            cue_sheet.tracks.push(CueTrack {
+                number,
+                title: title.map(String::from),
+                performer: None,
+                isrc: None,
+                pregap_start: None,
+                start,
+            });
+
+            if let Some(title) = title {
+                comments.add(&format!("CUE_TRACK{:02}_TITLE", number), title)?;
+            }
+
+            start += Self::wave_num_samples(wav);
+        }
+
+        let blocks = vec![
+            comments.build_block(),
+            CuesheetBlock::from_cue_sheet(&cue_sheet, start).build_block(),
+        ];
+        let block_size = compression.block_size as usize;
+        let frame_options = FrameOptions {
+            compression: *compression,
+            ..FrameOptions::default()
+        };
+        let mut samples = tracks
+            .into_iter()
+            .flat_map(|(wav, _)| wav.data_chunks)
+            .flatten();
+        let mut writer = FlacFileWriter::create(file_path, &fmt_header, &blocks, Some(Self::default_seek_table(&fmt_header, start)), &frame_options)?;
+
+        loop {
+            let sample_block: Vec <Vec <i64>> = samples.by_ref().take(block_size).collect();
+
+            if sample_block.is_empty() {
+                break;
+            }
+
+            writer.write_block(&sample_block)?;
+        }
+
+        writer.finish()?;
+
+        Ok(cue_sheet)
+    }
+
+    /// Build the Vorbis comments of a track of a cue sheet
+    fn track_comments(cue_sheet: &CueSheet, track_idx: usize) -> Result <VorbisComment, FlacWriterError> {
+        let track = &cue_sheet.tracks[track_idx];
+        let mut comments = VorbisComment::new(VENDOR_STRING);
+
+        comments.add("TRACKNUMBER", &track.number.to_string())?;
+
+        if let Some(title) = &track.title {
+            comments.add("TITLE", title)?;
+        }
+        if let Some(performer) = track.performer.as_ref().or(cue_sheet.performer.as_ref()) {
+            comments.add("PERFORMER", performer)?;
+        }
+        if let Some(album) = &cue_sheet.title {
+            comments.add("ALBUM", album)?;
+        }
+
+        Ok(comments)
+    }
+
+    /// Get the metadata options with the track and album ReplayGain set on their Vorbis comments
+    fn replaygain_options(options: &FlacMetadataOptions, track_gain: &ReplayGain, album_gain: Option <&ReplayGain>) -> Result <FlacMetadataOptions, FlacWriterError> {
+        let mut options = options.clone();
+
+        options.comments.set("REPLAYGAIN_TRACK_GAIN", &track_gain.gain_string())?;
+        options.comments.set("REPLAYGAIN_TRACK_PEAK", &track_gain.peak_string())?;
+
+        if let Some(album_gain) = album_gain {
+            options.comments.set("REPLAYGAIN_ALBUM_GAIN", &album_gain.gain_string())?;
+            options.comments.set("REPLAYGAIN_ALBUM_PEAK", &album_gain.peak_string())?;
+        }
+
+        Ok(options)
+    }
+
+    /// Get the metadata options with the trimmed silence offsets set on their Vorbis comments
+    fn trim_options(options: &FlacMetadataOptions, trim: &SilenceTrim) -> Result <FlacMetadataOptions, FlacWriterError> {
+        let mut options = options.clone();
+
+        options.comments.set("SILENCE_TRIM_LEADING", &trim.leading.to_string())?;
+        options.comments.set("SILENCE_TRIM_TRAILING", &trim.trailing.to_string())?;
+        options.comments.set("SILENCE_TRIM_TOTAL_SAMPLES", &trim.num_samples.to_string())?;
+
+        Ok(options)
+    }
+
+    /// Reserve space for metadata blocks whose contents will be patched after encoding
+    /// 
+    /// The blocks are those of `metadata_blocks` for `options` holding placeholder
+    /// values, and the PADDING block is at least `PATCHED_METADATA_PADDING_LEN`
+    /// bytes long. They must be the last metadata blocks of the file. Returns the
+    /// blocks to write and the total length of the region they take, to be passed
+    /// to `patch_metadata` along with the offset of the region.
+    fn reserve_metadata(data_size: Option <u64>, options: &FlacMetadataOptions) -> Result <(Vec <MetadataBlock>, usize), FlacWriterError> {
+        let options = FlacMetadataOptions {
+            padding_len: options.padding_len.max(PATCHED_METADATA_PADDING_LEN),
+            ..options.clone()
+        };
+        let blocks = Self::metadata_blocks(data_size, &options)?;
+        let region_len = blocks.iter().map(|block| block.data.len() + 4).sum();
+
+        Ok((blocks, region_len))
+    }
+
+    /// Overwrite the metadata region reserved by `reserve_metadata` with the final
+    /// blocks of `options`
+    /// 
+    /// # Errors
+    /// Returns `FlacWriterError::MetadataSizeError` if the final blocks no longer fit
+    /// the reserved region.
+    fn patch_metadata(file_path: &str, options: &FlacMetadataOptions, region_offset: u64, region_len: usize) -> Result <(), FlacWriterError> {
+        let options = FlacMetadataOptions {
+            padding_len: 0,
+            ..options.clone()
+        };
+        let blocks = Self::metadata_blocks(None, &options)?;
+        let region = MetadataBlock::build_region_bytes(&blocks, region_len, true)
+            .ok_or(FlacWriterError::MetadataSizeError)?;
+        let mut out_fh = OpenOptions::new().write(true).open(file_path)?;
+
+        out_fh.seek(SeekFrom::Start(region_offset))?;
+        out_fh.write_all(&region)?;
+
+        Ok(())
+    }
+
+    /// Create a SEEKTABLE builder for a PCM Wave file with the seek point spacing of `options`, if any
+    fn seek_table(wav: &PCMWaveInfo, options: &FlacMetadataOptions) -> Option <SeekTableBuilder> {
+        options.seek_spacing
+            .as_ref()
+            .map(|spacing| SeekTableBuilder::new(spacing, wav.fmt_header.samp_rate, Self::wave_num_samples(wav)))
+    }
+
+    /// Create a SEEKTABLE builder with the default seek point spacing
+    fn default_seek_table(fmt_header: &PCMWaveFormatChunk, total_samples: u64) -> SeekTableBuilder {
+        SeekTableBuilder::new(&SeekPointSpacing::default(), fmt_header.samp_rate, total_samples)
+    }
+
+    /// Get the size in bytes of the audio of all data chunks of a PCM Wave file
+    fn wave_data_size(wav: &PCMWaveInfo) -> u64 {
+        wav.data_chunks.iter().map(|data_chunk| data_chunk.size_bytes as u64).sum()
+    }
+
+    /// Get the number of inter-channel samples of all data chunks of a PCM Wave file
+    fn wave_num_samples(wav: &PCMWaveInfo) -> u64 {
+        wav.data_chunks.iter().map(|data_chunk| data_chunk.num_samples()).sum()
+    }
+
+    /// Get the blocks of `block_size` inter-channel samples of all data chunks of a PCM Wave file
+    fn wave_blocks(wav: PCMWaveInfo, block_size: usize) -> impl Iterator <Item = Vec <Vec <i64>>> {
+        wav.data_chunks
+            .into_iter()
+            .flat_map(move |data_chunk| data_chunk.chunks(block_size))
+    }
+
+    /// Encode blocks of inter-channel samples into audio frames
+    /// 
+    /// Every block of inter-channel samples is passed to each of the `analyzers`
+    /// before it is encoded.
+    fn write_frames <W: Write>(writer: &mut FlacFileWriter <W>, blocks: impl Iterator <Item = Vec <Vec <i64>>>, analyzers: &mut [&mut dyn BlockAnalyzer]) -> Result <(), FlacWriterError> {
+        for sample_block in blocks {
+            for analyzer in analyzers.iter_mut() {
+                analyzer.process_block(&sample_block);
+            }
+
+            writer.write_block(&sample_block)?;
+        }
+
+        Ok(())
+    }
+
+    /// Encode a block of inter-channel samples into the bytes of an audio frame
+    /// 
+    /// `frame_number` is the frame index for fixed block sizes, and the number of
+    /// the first inter-channel sample of the block for variable block sizes. The
+    /// subframes and the channel assignment of stereo blocks are chosen as given
+    /// by `compression`.
+    fn encode_frame(fmt_header: &PCMWaveFormatChunk, sample_block: &[Vec <i64>], frame_number: u64, is_variable_blocksize: bool, compression: &CompressionOptions) -> Vec <u8> {
+        let block_size = sample_block.len() as u16;
+        let sample_rate = FlacFrameHeaderValueOption::InFrame(fmt_header.samp_rate as u64);
+        let num_channels = fmt_header.num_channels as u8;
+        let bit_depth = FlacFrameHeaderValueOption::InFrame(fmt_header.bps as u8);
+
+        let mut frame = if is_variable_blocksize {
+            FlacFrame::new_variable_blocksize(block_size, sample_rate, num_channels, bit_depth, frame_number)
+        }
+        else {
+            FlacFrame::new(block_size, sample_rate, num_channels, bit_depth, frame_number)
+        };
+
+        frame.set_compression_options(compression);
+
+        if num_channels == 2 {
+            let subframe_bits = |samples: &[i64], bit_depth: u8| FlacSubframe::new_compressed(bit_depth, samples, compression).1;
+            frame.set_channel_assignment(ChannelAssignment::choose(sample_block, fmt_header.bps as u8, compression.stereo_mode, subframe_bits));
+        }
+
+        frame.build_bytes(sample_block)
+    }
+}
+
+impl FrameOptions {
+    /// Check whether the block sizes and compression options are valid
+    /// 
+    /// # Errors
+    /// Returns `FlacWriterError::BlockSizeError` if `block_sizes` is set but not
+    /// valid, as checked by `VariableBlockSizeOptions::is_valid`, and
+    /// `FlacWriterError::CompressionError` if `compression` is not valid, as
+    /// checked by `CompressionOptions::is_valid`. Both reject block sizes below
+    /// `MIN_BLOCK_SIZE`, which cannot be written in an audio frame header.
+    fn validate(&self) -> Result <(), FlacWriterError> {
+        if self.block_sizes.is_some_and(|block_sizes| !block_sizes.is_valid()) {
+            return Err(FlacWriterError::BlockSizeError);
+        }
+        if !self.compression.is_valid() {
+            return Err(FlacWriterError::CompressionError);
+        }
+
+        Ok(())
+    }
+}
+
+impl FlacWriterOptions {
+    /// Get the options of how blocks of inter-channel samples are encoded into audio frames
+    fn frame_options(&self) -> FrameOptions {
+        FrameOptions {
+            block_sizes: self.block_sizes,
+            compression: self.compression,
+            verify: self.verify,
+            num_threads: self.num_threads,
+        }
+    }
+}
+
+impl EncoderConfig {
+    /// Create a new configuration for audio of the given format
+    /// 
+    /// The audio length is unknown, the metadata blocks are those of
+    /// `FlacMetadataOptions::default()`, and the audio frames are compressed
+    /// as given by `CompressionOptions::default()`, with no verification and
+    /// a single thread.
+    pub fn new(format: &PCMWaveFormatChunk) -> Self {
+        Self {
+            format: *format,
+            total_samples: None,
+            metadata: FlacMetadataOptions::default(),
+            block_sizes: None,
+            compression: CompressionOptions::default(),
+            verify: false,
+            num_threads: 1,
+        }
+    }
+
+    /// Create a new configuration for audio of the given format with a compression preset
+    /// 
+    /// This is the same as `new` with the compression options of
+    /// `CompressionOptions::preset`. Returns `None` if `level` is above 8.
+    pub fn preset(format: &PCMWaveFormatChunk, level: u8) -> Option <Self> {
+        Some(Self {
+            compression: CompressionOptions::preset(level)?,
+            ..Self::new(format)
+        })
+    }
+
+    /// Get the options of how blocks of inter-channel samples are encoded into audio frames
+    fn frame_options(&self) -> FrameOptions {
+        FrameOptions {
+            block_sizes: self.block_sizes,
+            compression: self.compression,
+            verify: self.verify,
+            num_threads: self.num_threads,
+        }
+    }
+}
+
+impl <W: Write> FlacEncoder <W> {
+    /// Create a new encoder and write the metadata blocks to `sink`
+    /// 
+    /// # Errors
+    /// Returns `FlacWriterError::InvalidFormatError` if the format cannot be
+    /// stored in FLAC, that is unless it has 1 to 8 channels, 4 to 32 bits per
+    /// sample and a sample rate below 1 MHz, and `FlacWriterError::BlockSizeError`
+    /// if `config.block_sizes` is set but not valid, and `FlacWriterError::CompressionError`
+    /// if `config.compression` is not valid. Returns the errors of
+    /// `FlacWriter::write_from_wave` if the metadata blocks cannot be built.
+    pub fn new(config: &EncoderConfig, sink: W) -> Result <Self, FlacWriterError> {
+        let format = &config.format;
+
+        if !(1..=8).contains(&format.num_channels) || !(4..=32).contains(&format.bps) || !(1..1 << 20).contains(&format.samp_rate) {
+            return Err(FlacWriterError::InvalidFormatError);
+        }
+        config.frame_options().validate()?;
+
+        let block_align = format.num_channels as u64 * (format.bps as u64).div_ceil(8);
+        let blocks = FlacWriter::metadata_blocks(config.total_samples.map(|num_samples| num_samples * block_align), &config.metadata)?;
+        let seek_table = match (config.total_samples, &config.metadata.seek_spacing) {
+            (Some(num_samples), Some(spacing)) => Some(SeekTableBuilder::new(spacing, format.samp_rate, num_samples)),
+            _ => None,
+        };
+
+        let writer = FlacFileWriter::new(sink, format, &blocks, seek_table, &config.frame_options())?;
+
+        let block_size = match config.block_sizes {
+            Some(block_sizes) => block_sizes.max_block_size as usize,
+            None => config.compression.block_size as usize,
+        };
+
+        Ok(Self {
+            writer,
+            block_sizes: config.block_sizes,
+            block_size,
+            pending: Vec::with_capacity(block_size),
+        })
+    }
+
+    /// Push samples of every channel to encode
+    /// 
+    /// `channels` holds a slice of samples for each channel, all of the same length.
+    /// The samples are encoded into audio frames as soon as a whole block of them
+    /// is available, and the rest is kept until more samples are pushed.
+    /// 
+    /// # Errors
+    /// Returns `FlacWriterError::InvalidFormatError` if the number of channels
+    /// does not match the format, `FlacWriterError::DataAlignmentError` if the
+    /// channels differ in length, `FlacWriterError::SampleRangeError` if a
+    /// sample does not fit the bits per sample of the format, and
+    /// `FlacWriterError::WriteError` if the sink cannot be written. No sample
+    /// is kept if an error is returned.
+    pub fn push_samples(&mut self, channels: &[&[i32]]) -> Result <(), FlacWriterError> {
+        if channels.len() != self.writer.fmt_header.num_channels as usize {
+            return Err(FlacWriterError::InvalidFormatError);
+        }
+
+        let num_samples = channels[0].len();
+
+        if channels.iter().any(|channel| channel.len() != num_samples) {
+            return Err(FlacWriterError::DataAlignmentError);
+        }
+
+        let max_sample = (1i64 << (self.writer.fmt_header.bps - 1)) - 1;
+
+        if channels.iter().flat_map(|channel| channel.iter()).any(|&sample| !(-max_sample - 1..=max_sample).contains(&(sample as i64))) {
+            return Err(FlacWriterError::SampleRangeError);
+        }
+
+        self.pending.extend((0..num_samples).map(|i| channels.iter().map(|channel| channel[i] as i64).collect::<Vec <i64>>()));
+
+        self.write_pending(false)
+    }
+
+    /// Encode the remaining samples and finish the stream without patching its metadata blocks
+    /// 
+    /// The STREAMINFO block keeps unknown frame sizes, number of samples and MD5
+    /// signature, and the SEEKTABLE block keeps placeholder seek points, even
+    /// if the sink is seekable. Use `finish_and_patch` instead to fill them in
+    /// for seekable sinks such as files. Returns the final STREAMINFO values
+    /// and the sink, so that they can also be stored elsewhere.
+    /// 
+    /// # Errors
+    /// Returns `FlacWriterError::WriteError` if the sink cannot be written, and
+    /// `FlacWriterError::VerifyError` if an audio frame fails verification.
+    pub fn finish(mut self) -> Result <(StreamInfo, W), FlacWriterError> {
+        self.write_pending(true)?;
+        self.writer.finish_stream()
+    }
+
+    /// Encode the pending samples into audio frames
+    /// 
+    /// Only whole blocks are encoded unless `is_last` is set. With variable block
+    /// sizes, the samples are only split once a block of the maximum size is
+    /// pending, so that the splitter sees as much as it would on the whole audio.
+    fn write_pending(&mut self, is_last: bool) -> Result <(), FlacWriterError> {
+        while self.pending.len() >= self.block_size || (is_last && !self.pending.is_empty()) {
+            let num_samples = match &self.block_sizes {
+                Some(block_sizes) => BlockSplitter::new(self.pending.iter().cloned(), block_sizes)
+                    .next()
+                    .map_or(0, |sample_block| sample_block.len()),
+                None => self.block_size.min(self.pending.len()),
+            };
+            let sample_block: Vec <Vec <i64>> = self.pending.drain(..num_samples).collect();
+
+            self.writer.write_block(&sample_block)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl <W: Write + Seek> FlacEncoder <W> {
+    /// Encode the remaining samples, finish the stream and patch its metadata blocks
+    /// 
+    /// The STREAMINFO and SEEKTABLE blocks are patched with the final values. The
+    /// stream may start anywhere in the sink, which is left at the end of the
+    /// stream. Returns the final STREAMINFO values and the sink.
+    /// 
+    /// # Errors
+    /// Returns the errors of `finish`.
+    pub fn finish_and_patch(mut self) -> Result <(StreamInfo, W), FlacWriterError> {
+        self.write_pending(true)?;
+        self.writer.finish_seekable()
+    }
+}
+
+impl FlacFileWriter {
+    /// Create a FLAC file and write its metadata blocks, as described by `new`
+    /// 
+    /// The frame options are checked before the file is created.
+    fn create(file_path: &str, fmt_header: &PCMWaveFormatChunk, blocks: &[MetadataBlock], seek_table: Option <SeekTableBuilder>, frame_options: &FrameOptions) -> Result <Self, FlacWriterError> {
+        frame_options.validate()?;
+
+        Self::new(BufWriter::new(File::create(file_path)?), fmt_header, blocks, seek_table, frame_options)
+    }
+
+    /// Finish writing and patch the STREAMINFO and SEEKTABLE blocks of the file, as described by `finish_seekable`
+    fn finish(self) -> Result <StreamInfo, FlacWriterError> {
+        Ok(self.finish_seekable()?.0)
+    }
+}
+
+impl <W: Write> FlacFileWriter <W> {
+    /// Start a FLAC stream and write its metadata blocks
+    /// 
+    /// A placeholder STREAMINFO block is written first, followed by a placeholder
+    /// SEEKTABLE block if `seek_table` is set, and then by `blocks`. The placeholder
+    /// STREAMINFO block already holds the block sizes of `frame_options`, so that
+    /// it is valid even if it is never patched. The audio frames are encoded as
+    /// given by `frame_options`.
+    /// 
+    /// # Errors
+    /// Returns the errors of `FrameOptions::validate`, and
+    /// `FlacWriterError::WriteError` if the output cannot be written.
+    fn new(mut out: W, fmt_header: &PCMWaveFormatChunk, blocks: &[MetadataBlock], seek_table: Option <SeekTableBuilder>, frame_options: &FrameOptions) -> Result <Self, FlacWriterError> {
+        frame_options.validate()?;
+
+        let (min_block_size, max_block_size) = match frame_options.block_sizes {
+            Some(block_sizes) => (block_sizes.min_block_size, block_sizes.max_block_size),
+            None => (frame_options.compression.block_size, frame_options.compression.block_size),
+        };
+        let placeholder = StreamInfo {
+            min_block_size,
+            max_block_size,
+            ..StreamInfo::new(fmt_header)
+        };
+        let mut head = vec![placeholder.build_block()];
+
+        if let Some(seek_table) = &seek_table {
+            head.push(SeekTable::placeholder(seek_table.num_points()).build_block());
+        }
+
+        let blocks_offset = 4 + head.iter().map(|block| block.data.len() as u64 + 4).sum::<u64>();
+        let num_blocks = head.len() + blocks.len();
+        let metadata_len = blocks_offset + blocks.iter().map(|block| block.data.len() as u64 + 4).sum::<u64>();
+
+        out.write_all(b"fLaC")?;
+
+        for (i, block) in head.iter().chain(blocks.iter()).enumerate() {
+            out.write_all(&block.build_bytes(i + 1 == num_blocks))?;
+        }
+
+        let mut writer = Self {
+            out,
+            fmt_header: *fmt_header,
+            streaminfo: StreamInfoCollector::new(fmt_header),
+            seek_table,
+            blocks_offset,
+            metadata_len,
+            is_variable_blocksize: false,
+            compression: CompressionOptions::default(),
+            verifier: None,
+            num_threads: 1,
+            pool: None,
+            num_submitted_frames: 0,
+            num_submitted_samples: 0,
+            frame_index: 0,
+            sample_number: 0,
+            frame_offset: 0,
+        };
+        writer.set_frame_options(frame_options);
+
+        Ok(writer)
+    }
+
+    /// Get the offset in the file of the metadata blocks passed to `create`
+    fn blocks_offset(&self) -> u64 {
+        self.blocks_offset
+    }
+
+    /// Number the audio frames by their first sample, as required for variable block sizes
+    fn set_variable_blocksize(&mut self) {
+        self.is_variable_blocksize = true;
+    }
+
+    /// Set how the audio frames are compressed, as described by `CompressionOptions`
+    fn set_compression_options(&mut self, compression: &CompressionOptions) {
+        self.compression = *compression;
+    }
+
+    /// Decode every audio frame again and compare it with its samples, as described by `FrameVerifier`
+    fn set_verify(&mut self) {
+        self.verifier = Some(FrameVerifier::new(&self.fmt_header));
+    }
+
+    /// Set how the audio frames are encoded, as described by `FrameOptions`
+    fn set_frame_options(&mut self, frame_options: &FrameOptions) {
+        self.set_compression_options(&frame_options.compression);
+
+        if frame_options.block_sizes.is_some() {
+            self.set_variable_blocksize();
+        }
+        if frame_options.verify {
+            self.set_verify();
+        }
+        self.num_threads = frame_options.num_threads;
+    }
+
+    /// Encode a block of inter-channel samples and write it as an audio frame
+    /// 
+    /// With more than one thread, the block is handed to a `FramePool` and the
+    /// audio frames are written in order as they are finished.
+    fn write_block(&mut self, sample_block: &[Vec <i64>]) -> Result <(), FlacWriterError> {
+        let frame_number = if self.is_variable_blocksize {
+            self.num_submitted_samples
+        }
+        else {
+            self.num_submitted_frames
+        };
+        self.num_submitted_frames += 1;
+        self.num_submitted_samples += sample_block.len() as u64;
+
+        if self.num_threads <= 1 {
+            let frame = FlacWriter::encode_frame(&self.fmt_header, sample_block, frame_number, self.is_variable_blocksize, &self.compression);
+
+            return self.write_frame(sample_block, &frame);
+        }
+
+        let (fmt_header, is_variable_blocksize, compression) = (self.fmt_header, self.is_variable_blocksize, self.compression);
+        let num_threads = self.num_threads;
+
+        self.pool
+            .get_or_insert_with(|| FramePool::new(num_threads, move |sample_block, frame_number| {
+                FlacWriter::encode_frame(&fmt_header, sample_block, frame_number, is_variable_blocksize, &compression)
+            }))
+            .push(sample_block.to_vec(), frame_number)?;
+
+        self.write_encoded(false)
+    }
+
+    /// Write the audio frames finished by the worker threads, in order
+    /// 
+    /// This waits for the next audio frame while the pool is full, and for all
+    /// of them if `is_last` is set.
+    fn write_encoded(&mut self, is_last: bool) -> Result <(), FlacWriterError> {
+        while let Some(pool) = &mut self.pool {
+            let wait = is_last || pool.is_full();
+
+            match pool.next(wait)? {
+                Some((sample_block, frame)) => self.write_frame(&sample_block, &frame)?,
+                None => break,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Write an encoded audio frame and account for it in the STREAMINFO and SEEKTABLE blocks
+    fn write_frame(&mut self, sample_block: &[Vec <i64>], frame: &[u8]) -> Result <(), FlacWriterError> {
+        if let Some(verifier) = &mut self.verifier {
+            verifier.verify(frame, sample_block)?;
+        }
+
+        self.out.write_all(frame)?;
+        self.streaminfo.update(sample_block, frame.len());
+
+        if let Some(seek_table) = &mut self.seek_table {
+            seek_table.update(self.sample_number, self.frame_offset, sample_block.len() as u16);
+        }
+
+        self.frame_index += 1;
+        self.sample_number += sample_block.len() as u64;
+        self.frame_offset += frame.len() as u64;
+
+        Ok(())
+    }
+
+    /// Finish writing and get the final STREAMINFO block, leaving the stream as is
+    /// 
+    /// If the audio frames are verified, the MD5 signature of the decoded samples
+    /// is checked first. Also returns the output and the length of the stream in bytes.
+    fn close(mut self) -> Result <(StreamInfo, W, u64), FlacWriterError> {
+        self.write_encoded(true)?;
+
+        let streaminfo = self.streaminfo.finish();
+
+        if let Some(verifier) = self.verifier {
+            verifier.finish(&streaminfo.md5)?;
+        }
+
+        self.out.flush()?;
+
+        Ok((streaminfo, self.out, self.metadata_len + self.frame_offset))
+    }
+
+    /// Take the final SEEKTABLE block, if any, as the bytes of the space reserved for it
+    /// 
+    /// The block is given with its header. Audio shorter than the seek points
+    /// were reserved for, such as trimmed audio, gives its unused seek points
+    /// back as a PADDING block. All audio frames must be written.
+    fn take_seek_table(&mut self) -> Option <Vec <u8>> {
+        let mut seek_table = self.seek_table.take()?;
+        let reserved_len = 4 + seek_table.num_points() * SEEK_POINT_LEN;
+        let is_last = self.metadata_len == self.blocks_offset;
+
+        seek_table.set_total_samples(self.sample_number);
+
+        let block = seek_table.finish().build_block();
+
+        let region = MetadataBlock::build_region_bytes(std::slice::from_ref(&block), reserved_len, is_last);
+
+        Some(region.unwrap_or_else(|| block.build_bytes(is_last)))
+    }
+
+    /// Finish writing without patching the metadata blocks, as for unseekable outputs
+    fn finish_stream(self) -> Result <(StreamInfo, W), FlacWriterError> {
+        let (streaminfo, out, _) = self.close()?;
+
+        Ok((streaminfo, out))
+    }
+}
+
+impl <W: Write + Seek> FlacFileWriter <W> {
+    /// Finish writing and patch the STREAMINFO and SEEKTABLE blocks with the final values
+    /// 
+    /// The stream may start anywhere in the output, which is left at the end
+    /// of the stream.
+    fn finish_seekable(mut self) -> Result <(StreamInfo, W), FlacWriterError> {
+        self.write_encoded(true)?;
+
+        let seek_table = self.take_seek_table();
+        let (streaminfo, mut out, stream_len) = self.close()?;
+        let stream_offset = out.stream_position()? - stream_len;
+
+        // The STREAMINFO data follows the "fLaC" marker and its 4-byte block header
+        out.seek(SeekFrom::Start(stream_offset + 8))?;
+        out.write_all(&streaminfo.build_bytes())?;
+
+        if let Some(seek_table) = seek_table {
+            out.seek(SeekFrom::Start(stream_offset + SEEKTABLE_OFFSET))?;
+            out.write_all(&seek_table)?;
+        }
+
+        out.seek(SeekFrom::Start(stream_offset + stream_len))?;
+        out.flush()?;
+
+        Ok((streaminfo, out))
+    }
+}
+
+impl FlacFrame {
+    /// Create a new FLAC audio frame
+    /// 
+    /// A FLAC audio frame contains a slice of an audio file. Each frame can
+    /// be compressed preferably using most optimal compression scheme detected.
+    pub fn new(block_size: u16, sample_rate: FlacFrameHeaderValueOption <u64>, num_channels: u8, bit_depth: FlacFrameHeaderValueOption <u8>, frame_index: u64) -> FlacFrame {
+        FlacFrame {
+            is_variable_blocksize: false,
+            block_size,
+            sample_rate,
+            num_channels,
+            channel_assignment: ChannelAssignment::Independent,
+            bit_depth,
+            frame_index,
+            subframes: Vec::new(),
+            compression: CompressionOptions::default(),
+        }
+    }
+
+    /// Create a new FLAC audio frame of a variable block-size stream
+    /// 
+    /// The frame header of such a frame holds the number of the first inter-channel
+    /// sample of the frame instead of the frame index, and the block size may
+    /// differ from one frame to the next.
+    pub fn new_variable_blocksize(block_size: u16, sample_rate: FlacFrameHeaderValueOption <u64>, num_channels: u8, bit_depth: FlacFrameHeaderValueOption <u8>, sample_number: u64) -> FlacFrame {
+        let mut frame = Self::new(block_size, sample_rate, num_channels, bit_depth, sample_number);
+        frame.is_variable_blocksize = true;
+
+        frame
+    }
+
+    /// Set the channel assignment of this audio frame
+    /// 
+    /// Frames hold independent channels unless another channel assignment is set.
+    /// Only stereo frames may use a side channel.
+    pub fn set_channel_assignment(&mut self, channel_assignment: ChannelAssignment) {
+        self.channel_assignment = channel_assignment;
+    }
+
+    /// Set the compression options of the subframes of this audio frame
+    /// 
+    /// Frames use `CompressionOptions::default()` unless other options are set.
+    /// Each subframe is the one of `FlacSubframe::new_compressed`.
+    pub fn set_compression_options(&mut self, compression: &CompressionOptions) {
+        self.compression = *compression;
+    }
+
+    /// Convert this audio frame into a vector of bytes
+    /// 
+    /// An audio frame is ensured to be byte-aligned (i.e. necessary "0" padding
+    /// bits have been appended).
+    pub fn build_bytes(&mut self, sample_block: &[Vec <i64>]) -> Vec <u8> {
+        let channels = self.subframe_channels(sample_block);
+        let mut writer = BitstreamWriter::new();
+
+        self.subframes = channels
+            .iter()
+            .map(|(samples, bit_depth)| FlacSubframe::new_compressed(*bit_depth, samples, &self.compression).0)
+            .collect();
+
+        writer.write_bytes(&self.build_header_bytes());
+
+        for (subframe, (samples, _)) in self.subframes.iter().zip(&channels) {
+            let (bytes, num_unused_bits) = subframe.build_bytes(samples);
+            writer.write_stream(&bytes, num_unused_bits);
+        }
+
+        writer.align();
+
+        let (mut bytes, _) = writer.finish();
+        let crc_data = bytes.iter().map(|&byte| byte as u16).collect::<Vec <u16>>();
+        let crc = CrcOptions::new(0b1000_0000_0000_0101u16, 16).build_crc16(&crc_data);
+
+        bytes.extend_from_slice(&crc.to_be_bytes());
+        bytes
+    }
+
+    /// Determine the block size type of this audio frame
+    /// 
+    /// FLAC encodes the block size depending on the value of the raw
+    /// block size as shown on the table below. Most common block sizes
+    /// have fixed types for easier processing, but any block sizes from
+    /// 1 until 65535 inclusive are supported.
+    /// 
+    /// |      Block size     | Type (bin)       |
+    /// |---------------------|------------------|
+    /// |                 192 |             0001 |
+    /// |                 576 |             0010 |
+    /// |                1152 |             0011 |
+    /// |                2304 |             0100 |
+    /// |                4608 |             0101 |
+    /// |                 256 |             1000 |
+    /// |                 512 |             1001 |
+    /// |                1024 |             1010 |
+    /// |                2048 |             1011 |
+    /// |                4096 |             1100 |
+    /// |                8192 |             1101 |
+    /// |               16384 |             1110 |
+    /// |               32768 |             1111 |
+    /// | 576 * (2 ^ (n - 2)) |     n \in [2, 5] |
+    /// | 256 * (2 ^ (n - 8)) |    n \in [8, 15] |
+    /// |               < 256 |             0110 |
+    /// |             < 65536 |             0111 |
+    fn block_size_type(&self) -> u8 {
+        match self.block_size {
+            192 => 0b0001,
+            576 => 0b0010,
+            1152 => 0b0011,
+            2304 => 0b0100,
+            4608 => 0b0101,
+            256 => 0b1000,
+            512 => 0b1001,
+            1024 => 0b1010,
+            2048 => 0b1011,
+            4096 => 0b1100,
+            8192 => 0b1101,
+            16384 => 0b1110,
+            32768 => 0b1111,
+            1..=256 => 0b0110,
+            _ => 0b0111,
+        }
+    }
+
+    /// Determine the channel assignment type of this audio frame
+    /// 
+    /// FLAC encodes the number of channels and how they are decorrelated
+    /// as shown on the table below. A side channel is the difference between
+    /// the left and right channels, and is encoded with one more bit of depth.
+    /// 
+    /// |  Channel assignment              | Type (bin) |
+    /// |----------------------------------|------------|
+    /// | `n` independent channels         |    `n - 1` |
+    /// | left, side                       |       1000 |
+    /// | side, right                      |       1001 |
+    /// | mid, side                        |       1010 |
+    fn channel_assignment_type(&self) -> u8 {
+        self.channel_assignment.value(self.num_channels)
+    }
+
+    /// Get the samples and the bit depth of each subframe of this audio frame
+    /// 
+    /// The channels of the block are decorrelated as given by the channel assignment.
+    fn subframe_channels(&self, sample_block: &[Vec <i64>]) -> Vec <(Vec <i64>, u8)> {
+        let bit_depths = self.channel_assignment.bit_depths(*self.bit_depth.value(), self.num_channels);
+
+        self.channel_assignment
+            .split(sample_block)
+            .into_iter()
+            .zip(bit_depths)
+            .collect()
+    }
+
+    /// Determine the sample rate type of this audio frame
+    /// 
+    /// FLAC encodes the sample rate depending on its value as shown on the table below.
+    /// Most common sample rates have fixed types for easier processing, but any
+    /// sample rate from 0 until 655350 Hz inclusive are supported. It is also possible
+    /// to get the sample rate from the mandatory STREAMINFO header.
+    /// 
+    /// |  Sample rate (kHz)  | Type (bin)       |
+    /// |---------------------|------------------|
+    /// |     from STREAMINFO |             0000 |
+    /// |               88.2  |             0001 |
+    /// |              176.4  |             0010 |
+    /// |              192    |             0011 |
+    /// |                8    |             0100 |
+    /// |               16    |             0101 |
+    /// |               22.05 |             0110 |
+    /// |               24    |             0111 |
+    /// |               32    |             1000 |
+    /// |               44.1  |             1001 |
+    /// |               48    |             1010 |
+    /// |               96    |             1011 |
+    /// |            < 256    |             1100 |
+    /// |          < 65535    |             1101 |
+    /// |         < 655350    |             1110 |
+    /// |            invalid  |             1111 |
+    fn sample_rate_type(&self) -> u8 {
+        let sample_rate = match self.sample_rate {
+            FlacFrameHeaderValueOption::Streaminfo(_) => return 0b0000,
+            FlacFrameHeaderValueOption::InFrame(sample_rate) => sample_rate,
+        };
+
+        match sample_rate {
+            88200 => 0b0001,
+            176400 => 0b0010,
+            192000 => 0b0011,
+            8000 => 0b0100,
+            16000 => 0b0101,
+            22050 => 0b0110,
+            24000 => 0b0111,
+            32000 => 0b1000,
+            44100 => 0b1001,
+            48000 => 0b1010,
+            96000 => 0b1011,
+            _ if sample_rate % 1000 == 0 && sample_rate < 256000 => 0b1100,
+            _ if sample_rate < 65536 => 0b1101,
+            _ if sample_rate % 10 == 0 && sample_rate < 655360 => 0b1110,
+            // Rates that the header cannot hold are left to STREAMINFO
+            _ => 0b0000,
+        }
+    }
+
+    /// Determine the bit depth type of this audio frame
+    /// 
+    /// FLAC encodes the bit depth depending on its value as shown on the table below.
+    /// Most common bit depths have fixed types for easier processing, but any
+    /// bit depth from 4 until 32 bits inclusive are supported. It is also possible
+    /// to get the bit depth from the mandatory STREAMINFO header.
+    /// 
+    /// |  Bit depth (bits)  | Type (bin) |
+    /// |--------------------|------------|
+    /// |   from STREAMINFO  |        000 |
+    /// |                 8  |        001 |
+    /// |                12  |        010 |
+    /// |           invalid  |        011 |
+    /// |                16  |        100 |
+    /// |                20  |        101 |
+    /// |                24  |        110 |
+    /// |                32  |        111 |
+    fn bit_depth_type(&self) -> u8 {
+        match self.bit_depth {
+            FlacFrameHeaderValueOption::InFrame(8) => 0b001,
+            FlacFrameHeaderValueOption::InFrame(12) => 0b010,
+            FlacFrameHeaderValueOption::InFrame(16) => 0b100,
+            FlacFrameHeaderValueOption::InFrame(20) => 0b101,
+            FlacFrameHeaderValueOption::InFrame(24) => 0b110,
+            FlacFrameHeaderValueOption::InFrame(32) => 0b111,
+            _ => 0b000,
+        }
+    }
+
+    /// Build the header bytes of this audio frame
+    /// 
+    /// An audio frame header is ensured to be byte-aligned (i.e. necessary "0" padding
+    /// bits have been appended).
+    fn build_header_bytes(&self) -> Vec <u8> {
+        let block_size_type = self.block_size_type();
+        let sample_rate_type = self.sample_rate_type();
+        let sample_rate = *self.sample_rate.value();
+        let mut writer = BitstreamWriter::new();
+
+        writer.write_bits(FRAME_SYNC_CODE, 15);
+        writer.write_bits(self.is_variable_blocksize as u64, 1);
+        writer.write_bits(block_size_type as u64, 4);
+        writer.write_bits(sample_rate_type as u64, 4);
+        writer.write_bits(self.channel_assignment_type() as u64, 4);
+        writer.write_bits(self.bit_depth_type() as u64, 3);
+        writer.write_bits(0, 1);
+        writer.write_bytes(&self.build_coded_number_bytes());
+
+        match block_size_type {
+            0b0110 => writer.write_bits(self.block_size as u64 - 1, 8),
+            0b0111 => writer.write_bits(self.block_size as u64 - 1, 16),
+            _ => (),
+        }
+
+        match sample_rate_type {
+            0b1100 => writer.write_bits(sample_rate / 1000, 8),
+            0b1101 => writer.write_bits(sample_rate, 16),
+            0b1110 => writer.write_bits(sample_rate / 10, 16),
+            _ => (),
+        }
+
+        let (mut bytes, _) = writer.finish();
+        let crc = CrcOptions::new(0b0000_0111u8, 8).build_crc8(&bytes);
+
+        bytes.push(crc);
+        bytes
+    }
+
+    /// Build the coded number of this audio frame header
+    /// 
+    /// The coded number is the frame index for fixed block sizes, and the number
+    /// of the first inter-channel sample for variable block sizes, as flagged by
+    /// the blocking strategy bit of the header. It is written in the extended
+    /// UTF-8 encoding of FLAC, which takes up to 6 bytes for a frame index of up
+    /// to 31 bits and up to 7 bytes for a sample number of up to 36 bits.
+    fn build_coded_number_bytes(&self) -> Vec <u8> {
+        Utf8Encoder::encode(self.frame_index)
+    }
+}
+
+impl FlacSubframe {
+    /// Create a new VERBATIM audio frame
+    pub fn new_verbatim(bit_depth: u8) -> Self {
+        Self {
+            subframe_type: FlacSubframeType::Verbatim,
+            bit_depth,
+            partition_orders: None,
+        }
+    }
+
+    /// Create a new CONSTANT audio frame
+    pub fn new_constant(bit_depth: u8, sample_value: i64) -> Self {
+        Self {
+            subframe_type: FlacSubframeType::Constant {value: sample_value},
+            bit_depth,
+            partition_orders: None,
+        }
+    }
+
+    /// Create a new FIXED audio frame that autodetects the best
+    /// predictor order for a given block of samples
+    pub fn new_fixed(bit_depth: u8, samples: &[i64]) -> Option <Self> {
+        FixedPredictor::best_predictor_order(samples).map(|order| Self::new_fixed_by_order(bit_depth, order))
+    }
+
+    /// Create a new FIXED audio frame from some predictor order
+    pub fn new_fixed_by_order(bit_depth: u8, order: u8) -> Self {
+        Self {
+            subframe_type: FlacSubframeType::Fixed {order},
+            bit_depth,
+            partition_orders: None,
+        }
+    }
+
+    /// Create a new LPC audio frame that autodetects the best
+    /// predictor order for a given block of samples
+    pub fn new_variable(bit_depth: u8, block_size: u64, samples: &[i64]) -> Self {
+        let (qlp_coefs, precision, shift) = VarPredictor::get_best_lpc(samples, bit_depth, block_size);
+
+        Self {
+            subframe_type: FlacSubframeType::Lpc {
+                order: qlp_coefs.len() as u8,
+                precision,
+                shift,
+                qlp_coefs: Some(qlp_coefs),
+            },
+            bit_depth,
+            partition_orders: None,
+        }
+    }
+
+    /// Create a new LPC audio frame from some predictor order
+    pub fn new_variable_by_order(bit_depth: u8, block_size: u64, order: u8, samples: &[i64]) -> Self {
+        let (qlp_coefs, precision, shift) = VarPredictor::get_predictor_coeffs_from_samples(samples, order, bit_depth, block_size);
+
+        Self {
+            subframe_type: FlacSubframeType::Lpc {
+                order,
+                precision,
+                shift,
+                qlp_coefs: Some(qlp_coefs),
+            },
+            bit_depth,
+            partition_orders: None,
+        }
+    }
+
+    /// Create the smallest audio subframe allowed by some compression options
+    /// 
+    /// LPC subframes are only tried if `options.max_lpc_order` is not 0, with the
+    /// coefficients of `VarPredictor::get_lpc_windowed` for the windows of
+    /// `options.apodization`. The residuals of each candidate are Rice-encoded with
+    /// the partition orders of `options` to find its exact size.
+    /// 
+    /// Unless `options.exhaustive` is set, the candidates are the FIXED subframe of
+    /// `new_fixed` and the LPC subframe of the least sum of residuals, and the
+    /// VERBATIM subframe is only used if neither is possible. Otherwise, the
+    /// candidates are the CONSTANT subframe if all samples are equal, the VERBATIM
+    /// subframe, the FIXED subframes of every order and the LPC subframes of every
+    /// order and window, which is much slower but never larger.
+    /// 
+    /// Returns the subframe and its exact size in bits.
+    pub fn new_compressed(bit_depth: u8, samples: &[i64], options: &CompressionOptions) -> (Self, u64) {
+        let bits = |mut subframe: Self| {
+            subframe.set_partition_orders(options.min_partition_order, options.max_partition_order);
+            let (bytes, num_unused_bits) = subframe.build_bytes(samples);
+
+            (subframe, bytes.len() as u64 * 8 - num_unused_bits as u64)
+        };
+        let block_size = samples.len() as u64;
+        let windows = options.apodization.windows(samples.len());
+        let lpc_subframe = |(qlp_coefs, precision, shift): (Vec <i64>, u8, u8)| Self {
+            subframe_type: FlacSubframeType::Lpc {
+                order: qlp_coefs.len() as u8,
+                precision,
+                shift,
+                qlp_coefs: Some(qlp_coefs),
+            },
+            bit_depth,
+            partition_orders: None,
+        };
+
+        let candidates: Vec <Self> = if options.exhaustive {
+            let is_constant = !samples.is_empty() && samples.iter().all(|&sample| sample == samples[0]);
+            let lpc = VarPredictor::get_lpc_windowed(samples, bit_depth, block_size, options.max_lpc_order, options.qlp_precision, &windows);
+
+            is_constant
+                .then(|| Self::new_constant(bit_depth, samples[0]))
+                .into_iter()
+                .chain([Self::new_verbatim(bit_depth)])
+                .chain((0..=4).filter(|&order| order as usize <= samples.len()).map(|order| Self::new_fixed_by_order(bit_depth, order)))
+                .chain(lpc.into_iter().map(lpc_subframe))
+                .collect()
+        }
+        else {
+            let lpc = VarPredictor::get_best_lpc_windowed(samples, bit_depth, block_size, options.max_lpc_order, options.qlp_precision, &windows);
+
+            Self::new_fixed(bit_depth, samples)
+                .into_iter()
+                .chain(lpc.map(lpc_subframe))
+                .collect()
+        };
+
+        candidates
+            .into_iter()
+            .map(bits)
+            .min_by_key(|(_, num_bits)| *num_bits)
+            .unwrap_or_else(|| bits(Self::new_verbatim(bit_depth)))
+    }
+
+    /// Search only the Rice partition orders from `min_partition_order` to `max_partition_order`
+    /// 
+    /// Subframes search every partition order allowed by their block size unless
+    /// other partition orders are set.
+    pub fn set_partition_orders(&mut self, min_partition_order: u8, max_partition_order: u8) {
+        self.partition_orders = Some((min_partition_order, max_partition_order));
+    }
+
+    /// Get the residuals of a given block of samples and encode them into a Rice-encoded
+    /// byte stream.
+    /// 
+    /// The partition orders set by `set_partition_orders` are passed on with
+    /// `RiceEncoderOptions::with_partition_orders`.
+    /// 
+    /// Note that the contents are _not_ ensured to be byte-aligned. Hence, this method returns
+    /// the Rice-encoded byte stream and the number of extra unused bits at the last byte
+    /// of the stream, respectively.
+    fn get_encoded_residuals(&self, samples: &[i64]) -> Option <(Vec <RiceEncodedStream>, u8)> {
+        let (order, residuals) = match &self.subframe_type {
+            FlacSubframeType::Fixed {order} => (*order, FixedPredictor::get_residuals(samples, *order)?),
+            FlacSubframeType::Lpc {order, ..} => {
+                let (qlp_coefs, _, shift) = self.lpc_coefs(samples)?;
+                (*order, VarPredictor::get_residuals(samples, &qlp_coefs, *order, shift))
+            },
+            _ => return None,
+        };
+
+        if samples.len() < order as usize {
+            return None;
+        }
+
+        let mut options = RiceEncoderOptions::new(samples.len() as u64, order);
+
+        if let Some((min_partition_order, max_partition_order)) = self.partition_orders {
+            options = options.with_partition_orders(min_partition_order, max_partition_order);
+        }
+
+        options.encode_by_partition(&residuals)
+    }
+
+    /// Get the quantized coefficients, precision and shift of this LPC subframe
+    /// 
+    /// Coefficients that are not set are computed from the samples for the order
+    /// of the subframe. Returns `None` for other subframe types, or if the order is 0.
+    fn lpc_coefs(&self, samples: &[i64]) -> Option <(Vec <i64>, u8, u8)> {
+        match &self.subframe_type {
+            FlacSubframeType::Lpc {order: 0, ..} => None,
+            FlacSubframeType::Lpc {qlp_coefs: Some(qlp_coefs), precision, shift, ..} => Some((qlp_coefs.clone(), *precision, *shift)),
+            FlacSubframeType::Lpc {order, qlp_coefs: None, ..} => {
+                Some(VarPredictor::get_predictor_coeffs_from_samples(samples, *order, self.bit_depth, samples.len() as u64))
+            },
+            _ => None,
+        }
+    }
+
+    /// Compute the number of wasted bits in a block of samples
+    /// 
+    /// The "wasted bits" as defined in FLAC are the maximum number of
+    /// LSBits whose values are 0 for all samples in a block. Instead of encoding samples
+    /// as is, each sample can be shifted by the number of wasted bits to the right first
+    /// before being encoded through one of the four subframe types.
+    fn get_wasted_shift(&self, samples: &[i64]) -> u64 {
+        let bits = samples.iter().fold(0, |bits, sample| bits | sample);
+
+        if bits == 0 {
+            return 0;
+        }
+
+        (bits.trailing_zeros() as u64).min(self.bit_depth.saturating_sub(1) as u64)
+    }
+
+    /// Build the header bytes of this audio subframe
+    /// 
+    /// An audio subframe header is _not_ ensured to be byte-aligned. Hence,
+    /// this method returns the header bytes and the number of extra unused
+    /// bits at the last byte of the stream, respectively.
+    fn build_header_bytes(&self, samples: &[i64]) -> (Vec <u8>, u8) {
+        let subframe_type = match self.subframe_type {
+            FlacSubframeType::Constant {..} => 0,
+            FlacSubframeType::None | FlacSubframeType::Verbatim => 1,
+            FlacSubframeType::Fixed {order} => 8 + order,
+            FlacSubframeType::Lpc {order, ..} => 31 + order,
+        };
+        let wasted_shift = self.get_wasted_shift(samples);
+        let mut writer = BitstreamWriter::new();
+
+        writer.write_bits(0, 1);
+        writer.write_bits(subframe_type as u64, 6);
+
+        if wasted_shift > 0 {
+            writer.write_bits(1, 1);
+            writer.write_unary(wasted_shift - 1);
+        }
+        else {
+            writer.write_bits(0, 1);
+        }
+
+        writer.finish()
+    }
+
+    /// Convert this audio subframe into a vector of bytes. This includes the
+    /// header and the contents defined by one of the four subframe types.
+    /// 
+    /// An audio subframe is _not_ ensured to be byte-aligned. Hence,
+    /// this method returns the bytes and the number of extra unused
+    /// bits at the last byte of the stream, respectively.
+    pub fn build_bytes(&self, samples: &[i64]) -> (Vec <u8>, u8) {
+        let wasted_shift = self.get_wasted_shift(samples);
+        let bit_depth = self.bit_depth as u32 - wasted_shift as u32;
+        let shifted: Vec <i64> = samples.iter().map(|sample| sample >> wasted_shift).collect();
+        let (header, num_unused_bits) = self.build_header_bytes(samples);
+        let mut writer = BitstreamWriter::new();
+
+        writer.write_stream(&header, num_unused_bits);
+
+        match &self.subframe_type {
+            FlacSubframeType::Constant {value} => writer.write_signed(value >> wasted_shift, bit_depth),
+            FlacSubframeType::None | FlacSubframeType::Verbatim => {
+                for sample in &shifted {
+                    writer.write_signed(*sample, bit_depth);
+                }
+            },
+            FlacSubframeType::Fixed {order} | FlacSubframeType::Lpc {order, ..} => {
+                // Subframes that cannot be predicted are stored as is
+                let Some((streams, partition_order)) = self.get_encoded_residuals(&shifted) else {
+                    return Self::new_verbatim(self.bit_depth).build_bytes(samples);
+                };
+
+                for sample in &shifted[..*order as usize] {
+                    writer.write_signed(*sample, bit_depth);
+                }
+
+                if let Some((qlp_coefs, precision, shift)) = self.lpc_coefs(&shifted) {
+                    writer.write_bits(precision as u64 - 1, 4);
+                    writer.write_signed(shift as i64, 5);
+
+                    for coef in &qlp_coefs {
+                        writer.write_signed(*coef, precision as u32);
+                    }
+                }
+
+                // Parameters above 14 need the 5-bit parameters of the second coding method
+                let param_len = if streams.iter().any(|stream| stream.param > 14) { 5 } else { 4 };
+
+                writer.write_bits(param_len as u64 - 4, 2);
+                writer.write_bits(partition_order as u64, 4);
+
+                for stream in &streams {
+                    writer.write_bits(stream.param as u64, param_len);
+                    writer.write_stream(&stream.stream, stream.extra_bits_len);
+                }
+            },
+        }
+
+        writer.finish()
+    }
+}
+
+impl <T> FlacFrameHeaderValueOption <T>  {
+    pub fn value(&self) -> &T {
+        match self {
+            FlacFrameHeaderValueOption::Streaminfo(value) | FlacFrameHeaderValueOption::InFrame(value) => value,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use decoder::FlacDecoder;
+    use decoder::frame::DecodedFrame;
+    use metadata::MetadataBlockType;
+    use crate::wav::{WaveReader, WaveWriter};
+
+    fn temp_path(file_name: &str) -> String {
+        std::env::temp_dir().join(file_name).to_string_lossy().into_owned()
+    }
+
+    fn streaminfo(bps: u8) -> StreamInfo {
+        StreamInfo {
+            min_block_size: 16,
+            max_block_size: 4096,
+            min_frame_size: 0,
+            max_frame_size: 0,
+            sample_rate: 44100,
+            num_channels: 2,
+            bps,
+            total_samples: 0,
+            md5: [0; 16],
+        }
+    }
+
+    fn stereo_samples(num_samples: i64, phase: f64) -> Vec <Vec <i64>> {
+        (0..num_samples)
+            .map(|i| {
+                let left = ((i as f64 * 0.03 + phase).sin() * 12000.0) as i64 + (i * 7919 % 101 - 50);
+                vec![left, left / 2 - (i % 13) * 5]
+            })
+            .collect()
+    }
+
+    fn write_wave(file_path: &str, sample_block: &[Vec <i64>]) -> PCMWaveInfo {
+        let format = PCMWaveFormatChunk {
+            num_channels: 2,
+            samp_rate: 44100,
+            bps: 16,
+            channel_mask: None,
+        };
+
+        let mut writer = WaveWriter::create(file_path, &format).unwrap();
+        writer.write_block(sample_block).unwrap();
+        writer.finish().unwrap();
+
+        WaveReader::open_pcm(file_path).unwrap()
+    }
+
+    fn decode_flac(file_path: &str) -> Vec <Vec <i64>> {
+        let mut decoder = FlacDecoder::open(file_path).unwrap();
+        let mut samples = Vec::new();
+
+        while let Some(frame) = decoder.next_frame().unwrap() {
+            samples.extend(frame.sample_block());
+        }
+
+        // Checks the sample count and MD5 signature of STREAMINFO
+        decoder.finish().unwrap();
+        samples
+    }
+
+    #[test]
+    fn write_from_wave_round_trip() {
+        let sample_block = stereo_samples(10000, 0.0);
+        let wav_path = temp_path("midp_write_from_wave_round_trip.wav.part");
+        let flac_path = temp_path("midp_write_from_wave_round_trip.flac.part");
+        let writers = [
+            FlacWriterOptions::default(),
+            FlacWriterOptions {
+                block_sizes: Some(VariableBlockSizeOptions::default()),
+                ..FlacWriterOptions::default()
+            },
+            FlacWriterOptions {
+                compression: CompressionOptions::preset(8).unwrap(),
+                ..FlacWriterOptions::default()
+            },
+            FlacWriterOptions {
+                num_threads: 2,
+                verify: true,
+                ..FlacWriterOptions::default()
+            },
+        ];
+
+        for options in writers {
+            let wav = write_wave(&wav_path, &sample_block);
+            FlacWriter::write_from_wave(wav, &flac_path, &options, &mut []).unwrap();
+
+            let samples = decode_flac(&flac_path);
+            std::fs::remove_file(&flac_path).unwrap();
+
+            assert_eq!(samples, sample_block);
+        }
+
+        std::fs::remove_file(&wav_path).unwrap();
+    }
+
+    #[test]
+    fn write_from_wave_trimmed_seek_table() {
+        let mut sample_block = vec![vec![0, 0]; 20000];
+        sample_block.extend(stereo_samples(30000, 0.0));
+        sample_block.extend(vec![vec![0, 0]; 40000]);
+
+        let wav_path = temp_path("midp_write_from_wave_trimmed.wav.part");
+        let flac_path = temp_path("midp_write_from_wave_trimmed.flac.part");
+        let options = FlacWriterOptions {
+            metadata: FlacMetadataOptions {
+                seek_spacing: Some(SeekPointSpacing::Samples(10000)),
+                ..FlacMetadataOptions::default()
+            },
+            trim: Some(SilenceTrimOptions::new(f64::NEG_INFINITY, 100)),
+            ..FlacWriterOptions::default()
+        };
+
+        let wav = write_wave(&wav_path, &sample_block);
+        let trim = FlacWriter::write_from_wave(wav, &flac_path, &options, &mut []).unwrap().unwrap();
+        let samples = decode_flac(&flac_path);
+        let blocks = MetadataBlock::read_all(&mut File::open(&flac_path).unwrap()).unwrap();
+
+        std::fs::remove_file(&flac_path).unwrap();
+        std::fs::remove_file(&wav_path).unwrap();
+
+        assert_eq!((trim.leading, trim.trailing), (20000, 40000));
+        assert_eq!(samples.len(), 30000);
+
+        // The seek points reserved past the trimmed end are given back as padding
+        let seek_table = SeekTable::parse(&blocks[1]).unwrap();
+        assert_eq!(seek_table.points.len(), 3);
+        assert!(seek_table.points.iter().all(|point| !point.is_placeholder()));
+        assert_eq!(blocks[2].block_type, MetadataBlockType::Padding);
+        assert_eq!(blocks[2].data.len(), 6 * SEEK_POINT_LEN - 4);
+    }
+
+    #[test]
+    fn join_and_split_round_trip() {
+        let tracks = [stereo_samples(5000, 0.0), stereo_samples(3000, 1.0)];
+        let wav_paths = [temp_path("midp_join_and_split_00.wav.part"), temp_path("midp_join_and_split_01.wav.part")];
+        let flac_path = temp_path("midp_join_and_split.flac.part");
+        let out_dir = temp_path("midp_join_and_split.part");
+        let compression = CompressionOptions::default();
+
+        let waves = wav_paths.iter().zip(&tracks).map(|(wav_path, track)| (write_wave(wav_path, track), None)).collect();
+        let cue_sheet = FlacWriter::join_from_waves(waves, &flac_path, &compression).unwrap();
+        let joined = decode_flac(&flac_path);
+        std::fs::remove_file(&flac_path).unwrap();
+
+        assert_eq!(cue_sheet.tracks.iter().map(|track| track.start).collect::<Vec <_>>(), vec![0, 5000]);
+        assert_eq!(joined, tracks.concat());
+
+        let joined_path = temp_path("midp_join_and_split_joined.wav.part");
+        std::fs::create_dir_all(&out_dir).unwrap();
+        let written = FlacWriter::split_from_wave(write_wave(&joined_path, &joined), &cue_sheet, &out_dir, &compression).unwrap();
+        let split: Vec <Vec <Vec <i64>>> = written.iter().map(|file_path| decode_flac(file_path)).collect();
+
+        let mut duplicate = cue_sheet.clone();
+        duplicate.tracks[1].number = 1;
+        let result = FlacWriter::split_from_wave(write_wave(&joined_path, &joined), &duplicate, &out_dir, &compression);
+
+        std::fs::remove_dir_all(&out_dir).unwrap();
+        for wav_path in wav_paths.iter().chain([&joined_path]) {
+            std::fs::remove_file(wav_path).unwrap();
+        }
+
+        assert_eq!(split, tracks);
+        assert!(matches!(result, Err(FlacWriterError::TrackNumberError)));
+    }
+
+    #[test]
+    fn metadata_padding_len_limit() {
+        let options = FlacMetadataOptions {
+            padding_len: MAX_BLOCK_DATA_LEN + 1,
+            ..FlacMetadataOptions::default()
+        };
+
+        assert!(matches!(FlacWriter::metadata_blocks(None, &options), Err(FlacWriterError::MetadataSizeError)));
+    }
+
+    #[test]
+    fn encoder_sample_range() {
+        let format = PCMWaveFormatChunk {
+            num_channels: 1,
+            samp_rate: 44100,
+            bps: 8,
+            channel_mask: None,
+        };
+        let mut encoder = FlacEncoder::new(&EncoderConfig::new(&format), Vec::new()).unwrap();
+
+        assert!(encoder.push_samples(&[&[-128, 127]]).is_ok());
+        assert!(matches!(encoder.push_samples(&[&[0, 128]]), Err(FlacWriterError::SampleRangeError)));
+        assert!(matches!(encoder.push_samples(&[&[-129]]), Err(FlacWriterError::SampleRangeError)));
+
+        let (streaminfo, _) = encoder.finish().unwrap();
+        assert_eq!(streaminfo.total_samples, 2);
+    }
+
+    #[test]
+    fn placeholder_streaminfo_block_sizes() {
+        let format = PCMWaveFormatChunk {
+            num_channels: 2,
+            samp_rate: 44100,
+            bps: 16,
+            channel_mask: None,
+        };
+        let mut config = EncoderConfig::new(&format);
+
+        // The sink is not seekable, so the placeholder STREAMINFO block is final
+        let (_, bytes) = FlacEncoder::new(&config, Vec::new()).unwrap().finish().unwrap();
+        let info = StreamInfo::parse(&bytes[8..8 + STREAMINFO_LEN]).unwrap();
+        assert_eq!((info.min_block_size, info.max_block_size), (4096, 4096));
+
+        config.block_sizes = Some(VariableBlockSizeOptions {
+            min_block_size: 256,
+            max_block_size: 2048,
+        });
+
+        let (_, bytes) = FlacEncoder::new(&config, Vec::new()).unwrap().finish().unwrap();
+        let info = StreamInfo::parse(&bytes[8..8 + STREAMINFO_LEN]).unwrap();
+        assert_eq!((info.min_block_size, info.max_block_size), (256, 2048));
+    }
+
+    #[test]
+    fn frame_channel_assignments() {
+        let sample_block: Vec <Vec <i64>> = (0..1024)
+            .map(|i| {
+                let left = ((i as f64 * 0.05).sin() * 20000.0) as i64;
+                vec![left, left - (i % 17) * 3]
+            })
+            .collect();
+        let assignments = [
+            ChannelAssignment::Independent,
+            ChannelAssignment::LeftSide,
+            ChannelAssignment::RightSide,
+            ChannelAssignment::MidSide,
+        ];
+
+        for (frame_index, channel_assignment) in assignments.into_iter().enumerate() {
+            let mut frame = FlacFrame::new(1024, FlacFrameHeaderValueOption::InFrame(44100), 2, FlacFrameHeaderValueOption::InFrame(16), frame_index as u64);
+            frame.set_channel_assignment(channel_assignment);
+
+            let bytes = frame.build_bytes(&sample_block);
+            let (decoded, len) = DecodedFrame::decode(&bytes, &streaminfo(16)).unwrap();
+
+            assert_eq!(len, bytes.len());
+            assert_eq!(decoded.header.channel_assignment, channel_assignment);
+            assert_eq!(decoded.header.number, frame_index as u64);
+            assert_eq!(decoded.sample_block(), sample_block);
+        }
+    }
+
+    #[test]
+    fn frame_uncommon_header_values() {
+        // A CONSTANT channel with wasted bits and a noisy channel, at a block
+        // size, sample rate and bit depth that the header codes explicitly
+        let sample_block: Vec <Vec <i64>> = (0..100)
+            .map(|i: i64| vec![-4096, (i * 7919 % 1009 - 504) * 256])
+            .collect();
+        let compression = CompressionOptions {
+            exhaustive: true,
+            ..CompressionOptions::default()
+        };
+
+        let mut frame = FlacFrame::new_variable_blocksize(100, FlacFrameHeaderValueOption::InFrame(11025), 2, FlacFrameHeaderValueOption::InFrame(24), 123456);
+        frame.set_compression_options(&compression);
+
+        let bytes = frame.build_bytes(&sample_block);
+        let (decoded, _) = DecodedFrame::decode(&bytes, &streaminfo(16)).unwrap();
+
+        assert!(decoded.header.is_variable_blocksize);
+        assert_eq!(decoded.header.number, 123456);
+        assert_eq!(decoded.header.block_size, 100);
+        assert_eq!(decoded.header.sample_rate, 11025);
+        assert_eq!(decoded.header.bit_depth, 24);
+        assert_eq!(decoded.sample_block(), sample_block);
+    }
+}
@@ -1,5 +1,7 @@
 pub mod wav;
 pub mod flac;
+pub mod analysis;
+pub mod cue;
 
 fn main() {
     let file_path = String::from("sample_audio/music_a.wav");
@@ -12,11 +14,5 @@ fn main() {
     else if let Ok(wave_obj_safe) = wave_obj {
         println!("File {} opened successfully!", file_path);
         println!("{}", wave_obj_safe);
-
-        for each_chunk in wave_obj_safe.data_chunks {
-            for (i, each_sample) in each_chunk.byte_rate().enumerate() {
-                println!("each sample index {} len {}", i, each_sample.len());
-            }
-        }
     }
 }
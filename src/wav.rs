@@ -1,10 +1,9 @@
 use core::fmt;
 use std::fs::File;
-use std::path::Path;
 use std::error;
-use std::io::{self, Read, Seek, SeekFrom};
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
 
-use byteorder::{ByteOrder, LittleEndian};
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
 
 /// Represents a PCM WAV file
 pub struct PCMWaveInfo {
@@ -26,12 +25,15 @@ pub struct RiffChunk {
 /// A format chunk in a WAV file starts with a magic string
 /// `fmt_` where `_` is a space (0x20 in hex) and then followed by
 /// 20 bytes of metadata denoting information about the audio file
-/// itself such as the sample and bit rates.
-#[derive(Clone, Copy)]
+/// itself such as the sample and bit rates. `channel_mask` is the speaker
+/// layout of a `WAVE_FORMAT_EXTENSIBLE` format chunk, and is `None` for
+/// the default layout of the number of channels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct PCMWaveFormatChunk {
     pub num_channels: u16,
     pub samp_rate: u32,
     pub bps: u16,
+    pub channel_mask: Option <u32>,
 }
 
 /// Represents a data chunk from a WAV file
@@ -43,6 +45,8 @@ pub struct PCMWaveDataChunk {
     pub size_bytes: u32,
     pub format: PCMWaveFormatChunk,
     pub data_buf: io::BufReader<File>,
+    is_big_endian: bool,
+    num_bytes_read: u32,
 }
 
 /// Represents an iterator to a data chunk from a WAV file
@@ -55,6 +59,57 @@ pub struct PCMWaveDataChunkWindow {
     data_chunk: PCMWaveDataChunk
 }
 
+/// Represents the speaker position of a channel in a WAV file
+/// 
+/// The values are the bits used for each speaker in the channel mask of
+/// a `WAVE_FORMAT_EXTENSIBLE` format chunk. Channels in a WAV file always
+/// appear in the order of these bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Speaker {
+    FrontLeft = 0x1,
+    FrontRight = 0x2,
+    FrontCenter = 0x4,
+    LowFrequency = 0x8,
+    BackLeft = 0x10,
+    BackRight = 0x20,
+    FrontLeftOfCenter = 0x40,
+    FrontRightOfCenter = 0x80,
+    BackCenter = 0x100,
+    SideLeft = 0x200,
+    SideRight = 0x400,
+    TopCenter = 0x800,
+    TopFrontLeft = 0x1000,
+    TopFrontCenter = 0x2000,
+    TopFrontRight = 0x4000,
+    TopBackLeft = 0x8000,
+    TopBackCenter = 0x10000,
+    TopBackRight = 0x20000,
+}
+
+/// Represents the location of a chunk in a RIFF file
+/// 
+/// `offset` is the position of the chunk data, just after the 8-byte
+/// chunk header, and `size` is the size of the data without the pad byte
+/// that follows chunks of odd sizes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RiffChunkLocation {
+    pub id: [u8; 4],
+    pub offset: u64,
+    pub size: u32,
+}
+
+/// Represents a cue point from the `cue ` chunk of a WAV file
+/// 
+/// `position` is the offset of the cue point in inter-channel samples and
+/// `label` is the text of the matching `labl` chunk of the associated data
+/// list, if any.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WaveCuePoint {
+    pub id: u32,
+    pub position: u64,
+    pub label: Option <String>,
+}
+
 /// Represents a WAV reader
 pub struct WaveReader;
 
@@ -69,116 +124,126 @@ pub enum WaveReaderError {
     ReadError,
 }
 
+/// Represents a writer of PCM WAV files
+/// 
+/// Samples are written as they come, and the sizes in the RIFF header and
+/// the `data` chunk header are patched when the writer is finished. Audio
+/// of more than two channels or of a bit depth other than 8 or 16 bits is
+/// written as `WAVE_FORMAT_EXTENSIBLE`, as is audio with a channel mask, and
+/// samples of bit depths that are not a multiple of 8 are left-justified
+/// in their container, as WAV requires.
+pub struct WaveWriter {
+    out_fh: BufWriter <File>,
+    format: PCMWaveFormatChunk,
+    data_size: u64,
+}
+
+/// Represents an error in the WAV writer
+#[derive(Debug)]
+pub enum WaveWriterError {
+    WriteError,
+    FormatError,
+    DataSizeError,
+}
+
 impl WaveReader {
     /// Open a PCM WAV file
     /// 
     /// The WAV file located at `file_path` will be represented as a `PCMWaveInfo`
-    /// struct for further processing.
+    /// struct for further processing. Both little-endian `RIFF` and big-endian
+    /// `RIFX` files are read, and chunks other than the format and data chunks
+    /// are skipped.
     /// 
     /// # Errors
     /// Returns a `WaveReaderError` with the appropriate error if something
     /// happens.
     pub fn open_pcm(file_path: &str) -> Result <PCMWaveInfo, WaveReaderError> {
-        //todo!();
-        let wav_file = File::open(file_path)?;
+        let mut fh = File::open(file_path)?;
+        let riff_header = Self::read_riff_chunk(&mut fh)?;
+        let file_len = fh.metadata()?.len();
+        let mut fmt_header = None;
+        let mut data_chunks = Vec::new();
+        let mut offset = 12;
+
+        while offset + 8 <= file_len {
+            let mut chunk_header = [0u8; 8];
+            fh.seek(SeekFrom::Start(offset))?;
+            fh.read_exact(&mut chunk_header)?;
+
+            let size = if riff_header.is_big_endian {
+                BigEndian::read_u32(&chunk_header[4..8])
+            }
+            else {
+                LittleEndian::read_u32(&chunk_header[4..8])
+            };
+
+            match &chunk_header[0..4] {
+                b"fmt " => {
+                    fh.seek(SeekFrom::Start(offset))?;
+                    fmt_header = Some(if riff_header.is_big_endian {
+                        Self::read_fmt_chunk_as::<BigEndian>(&mut fh)?
+                    }
+                    else {
+                        Self::read_fmt_chunk(&mut fh)?
+                    });
+                },
+                b"data" => {
+                    let fmt_info = fmt_header.as_ref().ok_or(WaveReaderError::ChunkTypeError)?;
+                    data_chunks.push(Self::read_data_chunk(offset, fmt_info, riff_header.is_big_endian, File::open(file_path)?)?);
+                },
+                _ => (),
+            }
+
+            // Chunks of odd sizes are followed by a pad byte
+            offset += 8 + size as u64 + (size as u64 & 1);
+        }
 
-        Result
+        Ok(PCMWaveInfo {
+            riff_header,
+            fmt_header: fmt_header.ok_or(WaveReaderError::ChunkTypeError)?,
+            data_chunks,
+        })
     }
 
     /// Read the RIFF header from a PCM WAV file
     /// 
     /// The RIFF header is the first twelve bytes of a PCM WAV
     /// file of the format `<RIFF_magic_str:4B><file_size:4B><RIFF_type_magic_str:4B>`.
-    /// Note that the file handle `fh` should point to the very start of the file.
+    /// The magic string is `RIFF` for little-endian files and `RIFX` for
+    /// big-endian files. Note that the file handle `fh` should point to the
+    /// very start of the file.
     /// 
     /// # Errors
     /// Returns a `WaveReaderError` with the appropriate error if something
     /// happens. This includes file read errors and format errors.
     fn read_riff_chunk(fh: &mut File) -> Result <RiffChunk, WaveReaderError> {
-        //todo!();
-        let wav_file = match File::open(file_path){
-            Ok(file) => wav_file;
-            Err(err) => {
-                return Err(WaveReaderError::ReadError);
-            }
-        }
-        
-        //// CHECKING IF RIFF BA SIYA OR HINDI
-
-        // EXTRACT RIFF HEADER
-        let mut buff = [0u8; 4]; // BUFFER NG RIFF HEADER
-        let first = wav_file.read(&mut buff[...])?;
-        let mut combined = 0u32; // variable that assembles the riff chunk
-        for i in 0..4{
-            combined += buff[3-i];
-            if i == 3{
-                break
-            }
-            combined <<= 8;
-        }
-        // END OF EXTRACTING RIFF HEADER
-        
-        // ACTUAL CHECKING IF RIFF BA SIYA OR NOT
-        if combined == 0x52494646{
-            
-            // EXTRACT WAVE HEADER
-            let mut third_buff = [0u8, 4]; // buffer ng WAVE header
-            wav_file.seek(SeekFrom::Start(8))?;
-            let wave_check = wav_file.read(&mut buff[...])?;
-            let mut wave_form = 0u32; // variable that assembles the wave header chunk
-            for k in 0..4{
-                wave_form += third_buff[3-k];
-                if k == 3{
-                    break
-                }
-                wave_form <<= 8;
-            }
-            // END OF EXTRACTING WAVE HEADER
-
-            // CHECK IF CORRECT WAVE HEADER
-            if wave_check ==  0x57415645{
-                // IF CORRECT WAVE HEADER, PROCEED TO EXTRACT FILE SIZE
-                
-                //EXTRACT FILE SIZE
-                let mut second_buff = [0u8; 4]; // buffer of file size
-                wav_file.seek(SeekFrom::Start(4))?;
-                let mut wav_file_size = wav_file.read(&mut second_buff[...]);  
-                let mut wav_size_to_return = 0u32;
-                for j in 0..4{
-                    wav_size_to_return += second_buff[3-j];
-                    if j == 3{
-                        break
-                    }
-                    wav_size_to_return <<= 8;
-                }
-                //END OF EXTRACTING FILE SIZE
-        
-                //PACKAGE THE FILE SIZE AS RESULT
-                let riff_chunk = RiffChunk{
-                    file_size: wav_size_to_return,
-                    is_big_endian: false,
-                }
+        let mut header = [0u8; 12];
+        fh.read_exact(&mut header)?;
 
-                //RETURN FILE SIZE
-                return Ok(riff_chunk);
-                }
-            
-            // IF EXTRACTED WAVE HEADER IS NOT EQUAL TO THE GIVEN, RETURN ERROR
-            else{
-                return Err(WaveReaderError::NotWaveError);
-            }
+        let is_big_endian = match &header[0..4] {
+            b"RIFF" => false,
+            b"RIFX" => true,
+            _ => return Err(WaveReaderError::NotRiffError),
+        };
+
+        if &header[8..12] != b"WAVE" {
+            return Err(WaveReaderError::NotWaveError);
         }
-        // KAPAG RIFF, HINDI NA MAG-RU-RUN YUNG ELSE SA BABA
 
-        // ELSE ITONG BABA, KUNG HINDI RIFF YUNG FILE
-        else if combined == 
-        
-        else{
-            return Err(WaveReaderError::NotRiffError);
+        let file_size = if is_big_endian {
+            BigEndian::read_u32(&header[4..8])
         }
+        else {
+            LittleEndian::read_u32(&header[4..8])
+        };
+
+        Ok(RiffChunk {
+            file_size,
+            is_big_endian,
+        })
     }
 
-    /// Read the format chunk from a PCM WAV file
+    /// Read the format chunk from a little-endian PCM WAV file
     /// 
     /// The format chunk usually appears immediately after the RIFF header and consists of 24 bytes of metadata.
     /// Note that the file handle `fh` should point to the start of a format chunk.
@@ -187,58 +252,74 @@ impl WaveReader {
     /// Returns a `WaveReaderError` with the appropriate error if something
     /// happens. This includes file read errors and format errors.
     fn read_fmt_chunk(fh: &mut File) -> Result <PCMWaveFormatChunk, WaveReaderError> {
-        //todo!();
-        let wav_file = match File::open(file_path){
-            Ok(file) => wav_file;
-            Err(err) => {
-                return Err(WaveReaderError::ReadError);
-            }
+        Self::read_fmt_chunk_as::<LittleEndian>(fh)
+    }
+
+    /// Read the format chunk from a PCM WAV file of the byte order `B`
+    /// 
+    /// Both plain PCM and `WAVE_FORMAT_EXTENSIBLE` format chunks with the PCM
+    /// subformat are read. For the latter, the bit depth is the number of
+    /// valid bits rather than the size of the sample container, and the
+    /// channel mask is kept unless it is empty or the default one. The file
+    /// handle is left at the end of the format chunk.
+    /// 
+    /// # Errors
+    /// Returns `WaveReaderError::ChunkTypeError` if the chunk is not a format
+    /// chunk, `WaveReaderError::NotPCMError` if the audio is not PCM, and
+    /// `WaveReaderError::DataAlignmentError` if the block alignment does not
+    /// match the format.
+    fn read_fmt_chunk_as<B: ByteOrder>(fh: &mut File) -> Result <PCMWaveFormatChunk, WaveReaderError> {
+        let mut chunk_header = [0u8; 8];
+        fh.read_exact(&mut chunk_header)?;
+
+        let size = B::read_u32(&chunk_header[4..8]) as usize;
+        if &chunk_header[0..4] != b"fmt " || size < 16 {
+            return Err(WaveReaderError::ChunkTypeError);
         }
-        // LIST OF COMPONENTS UNDER THE FORMAT CHUNK
-        let mut fmt_combined = 0u32; // variable that assembles the fmt header
 
+        let mut data = vec![0u8; size];
+        fh.read_exact(&mut data)?;
+        if size & 1 == 1 {
+            fh.seek(SeekFrom::Current(1))?;
+        }
 
+        let format_tag = B::read_u16(&data[0..2]);
+        let mut fmt_info = PCMWaveFormatChunk {
+            num_channels: B::read_u16(&data[2..4]),
+            samp_rate: B::read_u32(&data[4..8]),
+            bps: B::read_u16(&data[14..16]),
+            channel_mask: None,
+        };
 
-        // EXTRACT FMT HEADER
-        wav_file.seek(SeekFrom::Start(12));
-        let mut fourth_buff = [0u8; 4]; // BUFFER NG FMT HEADER
-        let fmt_header = wav_file.read(&mut buff[...])?;
+        match format_tag {
+            0x0001 => (),
+            // WAVE_FORMAT_EXTENSIBLE, whose subformat starts with the format tag
+            0xfffe if size >= 40 && B::read_u16(&data[16..18]) >= 22 => {
+                if B::read_u16(&data[24..26]) != 0x0001 {
+                    return Err(WaveReaderError::NotPCMError);
+                }
 
-        for i in 0..4{
-            fmt_combined += buff[3-i];
-            if i == 3{
-                break
-            }
-            fmt_combined <<= 8;
-        }
-        // END OF EXTRACTING RIFF HEADER
-        
-        // ACTUAL CHECKING IF RIFF BA SIYA OR NOT
-        if combined == 0x52494646{
-            
-            // EXTRACT WAVE HEADER
-            let mut third_buff = [0u8, 4];
-            wav_file.seek(SeekFrom::Start(8))?;
-            let wave_check = wav_file.read(&mut buff[...])?;
-            let mut wave_form = 0u32; // variable that assembles the riff chunk
-            for k in 0..4{
-                wave_form += third_buff[3-k];
-                if k == 3{
-                    break
+                let valid_bps = B::read_u16(&data[18..20]);
+                if valid_bps != 0 {
+                    fmt_info.bps = valid_bps.min(fmt_info.bps);
                 }
-                wave_form <<= 8;
-            }
-            // END OF EXTRACTING WAVE HEADER
+
+                let channel_mask = B::read_u32(&data[20..24]);
+                if channel_mask != 0 && channel_mask != fmt_info.default_channel_mask() {
+                    fmt_info.channel_mask = Some(channel_mask);
+                }
+            },
+            _ => return Err(WaveReaderError::NotPCMError),
         }
-        
-        // KAPAG RIFF, HINDI NA MAG-RU-RUN YUNG ELSE SA BABA
 
-        // ELSE ITONG BABA, KUNG HINDI RIFF YUNG FILE
-        else{
-            return Err(WaveReaderError::NotRiffError);
+        if fmt_info.num_channels == 0 || fmt_info.bps == 0 || fmt_info.bps > 32 {
+            return Err(WaveReaderError::NotPCMError);
+        }
+        if B::read_u16(&data[12..14]) != fmt_info.block_align() {
+            return Err(WaveReaderError::DataAlignmentError);
         }
-    }
 
+        Ok(fmt_info)
     }
 
     /// Read the data chunk from a PCM WAV file
@@ -247,13 +328,308 @@ impl WaveReader {
     /// chunk and contains the samples of the audio itself. Note that
     /// a file can contain multiple data chunks, and it is possible that this
     /// method should be called more than once to completely read the file.
-    /// Note that the file handle `fh` should point to the start of a data chunk.
+    /// Note that `start_pos` should point to the start of a data chunk in
+    /// the file `fh`, which is then owned by the data chunk.
     /// 
     /// # Errors
     /// Returns a `WaveReaderError` with the appropriate error if something
     /// happens. This includes file read errors and format errors.
-    fn read_data_chunk(start_pos: u64, fmt_info: &PCMWaveFormatChunk, mut fh: File) -> Result <PCMWaveDataChunk, WaveReaderError> {
-        todo!();
+    fn read_data_chunk(start_pos: u64, fmt_info: &PCMWaveFormatChunk, is_big_endian: bool, mut fh: File) -> Result <PCMWaveDataChunk, WaveReaderError> {
+        let mut chunk_header = [0u8; 8];
+        fh.seek(SeekFrom::Start(start_pos))?;
+        fh.read_exact(&mut chunk_header)?;
+
+        if &chunk_header[0..4] != b"data" {
+            return Err(WaveReaderError::ChunkTypeError);
+        }
+
+        let size_bytes = if is_big_endian {
+            BigEndian::read_u32(&chunk_header[4..8])
+        }
+        else {
+            LittleEndian::read_u32(&chunk_header[4..8])
+        };
+        let block_align = fmt_info.block_align() as u32;
+
+        if size_bytes % block_align != 0 {
+            return Err(WaveReaderError::DataAlignmentError);
+        }
+
+        // Truncated files keep the whole samples that are there
+        let num_bytes_left = fh.metadata()?.len().saturating_sub(start_pos + 8);
+        let size_bytes = if (size_bytes as u64) > num_bytes_left {
+            num_bytes_left as u32 - num_bytes_left as u32 % block_align
+        }
+        else {
+            size_bytes
+        };
+
+        Ok(PCMWaveDataChunk {
+            size_bytes,
+            format: *fmt_info,
+            data_buf: io::BufReader::new(fh),
+            is_big_endian,
+            num_bytes_read: 0,
+        })
+    }
+}
+
+impl WaveReader {
+    /// List the chunks of a RIFF WAVE file
+    /// 
+    /// The chunks are listed in the order they appear in the file, without
+    /// reading their data. Note that the file handle `fh` should point to the
+    /// very start of the file.
+    /// 
+    /// # Errors
+    /// Returns a `WaveReaderError` if the file is not a RIFF WAVE file or
+    /// cannot be read.
+    pub fn read_chunk_list(fh: &mut File) -> Result <Vec <RiffChunkLocation>, WaveReaderError> {
+        let mut header = [0u8; 12];
+        fh.read_exact(&mut header)?;
+
+        if &header[0..4] != b"RIFF" {
+            return Err(WaveReaderError::NotRiffError);
+        }
+        if &header[8..12] != b"WAVE" {
+            return Err(WaveReaderError::NotWaveError);
+        }
+
+        let file_len = fh.metadata()?.len();
+        let mut offset = 12;
+        let mut chunks = Vec::new();
+
+        while offset + 8 <= file_len {
+            let mut chunk_header = [0u8; 8];
+            fh.seek(SeekFrom::Start(offset))?;
+            fh.read_exact(&mut chunk_header)?;
+
+            let mut id = [0u8; 4];
+            id.copy_from_slice(&chunk_header[0..4]);
+            let size = LittleEndian::read_u32(&chunk_header[4..8]);
+
+            chunks.push(RiffChunkLocation {
+                id,
+                offset: offset + 8,
+                size,
+            });
+
+            // Chunks of odd sizes are followed by a pad byte
+            offset += 8 + size as u64 + (size as u64 & 1);
+        }
+
+        Ok(chunks)
+    }
+
+    /// Read the cue points of a WAV file
+    /// 
+    /// The cue points are read from the `cue ` chunk, and their labels from the
+    /// `labl` chunks of a `LIST` chunk of type `adtl`. A file without a `cue `
+    /// chunk has no cue points.
+    /// 
+    /// # Errors
+    /// Returns a `WaveReaderError` if the file is not a RIFF WAVE file or
+    /// cannot be read.
+    pub fn read_cue_points(file_path: &str) -> Result <Vec <WaveCuePoint>, WaveReaderError> {
+        let mut fh = File::open(file_path)?;
+        let chunks = Self::read_chunk_list(&mut fh)?;
+        let mut cue_points = Vec::new();
+        let mut labels = Vec::new();
+
+        for chunk in &chunks {
+            let mut data = vec![0u8; chunk.size as usize];
+            match &chunk.id {
+                b"cue " | b"LIST" => {
+                    fh.seek(SeekFrom::Start(chunk.offset))?;
+                    fh.read_exact(&mut data)?;
+                },
+                _ => continue,
+            }
+
+            if &chunk.id == b"cue " {
+                cue_points.extend(Self::parse_cue_chunk(&data)?);
+            }
+            else if data.starts_with(b"adtl") {
+                labels.extend(Self::parse_adtl_labels(&data[4..]));
+            }
+        }
+
+        for cue_point in &mut cue_points {
+            cue_point.label = labels
+                .iter()
+                .find(|(id, _)| *id == cue_point.id)
+                .map(|(_, label)| label.clone());
+        }
+
+        Ok(cue_points)
+    }
+
+    /// Parse the data of a `cue ` chunk
+    /// 
+    /// The data is a count of cue points followed by 24 bytes for each
+    /// cue point, with its sample offset in the last four bytes.
+    fn parse_cue_chunk(data: &[u8]) -> Result <Vec <WaveCuePoint>, WaveReaderError> {
+        if data.len() < 4 {
+            return Err(WaveReaderError::ChunkTypeError);
+        }
+
+        let num_points = LittleEndian::read_u32(&data[0..4]) as usize;
+        let points = &data[4..];
+
+        if points.len() < num_points * 24 {
+            return Err(WaveReaderError::ChunkTypeError);
+        }
+
+        Ok(points
+            .chunks_exact(24)
+            .take(num_points)
+            .map(|point| WaveCuePoint {
+                id: LittleEndian::read_u32(&point[0..4]),
+                position: LittleEndian::read_u32(&point[20..24]) as u64,
+                label: None,
+            })
+            .collect())
+    }
+
+    /// Parse the `labl` subchunks of an associated data list
+    fn parse_adtl_labels(mut data: &[u8]) -> Vec <(u32, String)> {
+        let mut labels = Vec::new();
+
+        while data.len() >= 8 {
+            let size = LittleEndian::read_u32(&data[4..8]) as usize;
+            let body = &data[8..(8 + size).min(data.len())];
+
+            if &data[0..4] == b"labl" && body.len() >= 4 {
+                let text = &body[4..];
+                let text = text.split(|&b| b == 0).next().unwrap_or(text);
+
+                labels.push((LittleEndian::read_u32(&body[0..4]), String::from_utf8_lossy(text).into_owned()));
+            }
+
+            data = &data[(8 + size + (size & 1)).min(data.len())..];
+        }
+
+        labels
+    }
+}
+
+impl WaveWriter {
+    /// Create a new PCM WAV file of the given format
+    /// 
+    /// # Errors
+    /// Returns `WaveWriterError::FormatError` if the format has no channels
+    /// or a bit depth outside of 1 to 32 bits, and `WaveWriterError::WriteError`
+    /// if the file cannot be written.
+    pub fn create(file_path: &str, format: &PCMWaveFormatChunk) -> Result <Self, WaveWriterError> {
+        if format.num_channels == 0 || format.bps == 0 || format.bps > 32 {
+            return Err(WaveWriterError::FormatError);
+        }
+
+        let mut out_fh = BufWriter::new(File::create(file_path)?);
+        out_fh.write_all(&Self::build_header_bytes(format))?;
+
+        Ok(Self {
+            out_fh,
+            format: *format,
+            data_size: 0,
+        })
+    }
+
+    /// Write a block of inter-channel samples
+    /// 
+    /// # Errors
+    /// Returns `WaveWriterError::DataSizeError` if the audio grows beyond the
+    /// 4 GiB limit of WAV files, and `WaveWriterError::WriteError` if the file
+    /// cannot be written.
+    pub fn write_block(&mut self, sample_block: &[Vec <i64>]) -> Result <(), WaveWriterError> {
+        let bytes = Self::build_sample_bytes(&self.format, sample_block);
+
+        self.data_size += bytes.len() as u64;
+        if self.data_size > u32::MAX as u64 - 64 {
+            return Err(WaveWriterError::DataSizeError);
+        }
+
+        self.out_fh.write_all(&bytes)?;
+
+        Ok(())
+    }
+
+    /// Finish the WAV file by writing the pad byte and patching the chunk sizes
+    /// 
+    /// # Errors
+    /// Returns `WaveWriterError::WriteError` if the file cannot be written.
+    pub fn finish(mut self) -> Result <(), WaveWriterError> {
+        if self.data_size & 1 == 1 {
+            self.out_fh.write_all(&[0])?;
+        }
+
+        let header_len = Self::build_header_bytes(&self.format).len() as u64;
+        let riff_size = header_len - 8 + self.data_size + (self.data_size & 1);
+
+        self.out_fh.seek(SeekFrom::Start(4))?;
+        self.out_fh.write_all(&(riff_size as u32).to_le_bytes())?;
+        self.out_fh.seek(SeekFrom::Start(header_len - 4))?;
+        self.out_fh.write_all(&(self.data_size as u32).to_le_bytes())?;
+        self.out_fh.flush()?;
+
+        Ok(())
+    }
+
+    /// Convert a block of inter-channel samples into the bytes of a `data` chunk
+    /// 
+    /// Samples of up to 8 bits are unsigned, and wider samples are signed and
+    /// little-endian.
+    pub fn build_sample_bytes(format: &PCMWaveFormatChunk, sample_block: &[Vec <i64>]) -> Vec <u8> {
+        let container_bytes = (format.bps as usize).div_ceil(8);
+        let shift = container_bytes * 8 - format.bps as usize;
+        let mut bytes = Vec::with_capacity(sample_block.len() * format.num_channels as usize * container_bytes);
+
+        for inter_sample in sample_block {
+            for sample in inter_sample {
+                let sample = sample << shift;
+
+                if container_bytes == 1 {
+                    bytes.push((sample + 128) as u8);
+                }
+                else {
+                    bytes.extend_from_slice(&sample.to_le_bytes()[..container_bytes]);
+                }
+            }
+        }
+
+        bytes
+    }
+
+    /// Build the RIFF header, the `fmt ` chunk and the `data` chunk header
+    /// 
+    /// The sizes in the RIFF header and the `data` chunk header are left at 0.
+    fn build_header_bytes(format: &PCMWaveFormatChunk) -> Vec <u8> {
+        let container_bytes = (format.bps as u32).div_ceil(8);
+        let block_align = container_bytes * format.num_channels as u32;
+        let is_extensible = format.num_channels > 2 || (format.bps != 8 && format.bps != 16) || format.channel_mask.is_some();
+
+        let mut fmt_data = Vec::with_capacity(40);
+        fmt_data.extend_from_slice(&(if is_extensible { 0xfffeu16 } else { 1 }).to_le_bytes());
+        fmt_data.extend_from_slice(&format.num_channels.to_le_bytes());
+        fmt_data.extend_from_slice(&format.samp_rate.to_le_bytes());
+        fmt_data.extend_from_slice(&(format.samp_rate * block_align).to_le_bytes());
+        fmt_data.extend_from_slice(&(block_align as u16).to_le_bytes());
+        fmt_data.extend_from_slice(&(container_bytes as u16 * 8).to_le_bytes());
+
+        if is_extensible {
+            fmt_data.extend_from_slice(&22u16.to_le_bytes());
+            fmt_data.extend_from_slice(&format.bps.to_le_bytes());
+            fmt_data.extend_from_slice(&format.channel_mask().to_le_bytes());
+            // KSDATAFORMAT_SUBTYPE_PCM
+            fmt_data.extend_from_slice(&[0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x80, 0x00, 0x00, 0xaa, 0x00, 0x38, 0x9b, 0x71]);
+        }
+
+        let mut bytes = b"RIFF\0\0\0\0WAVEfmt ".to_vec();
+        bytes.extend_from_slice(&(fmt_data.len() as u32).to_le_bytes());
+        bytes.extend(fmt_data);
+        bytes.extend_from_slice(b"data\0\0\0\0");
+
+        bytes
     }
 }
 
@@ -261,26 +637,48 @@ impl error::Error for WaveReaderError {}
 
 impl fmt::Display for WaveReaderError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        todo!()
+        write!(f, "Error: {:?}", self)
     }
 }
 
 impl From <io::Error> for WaveReaderError {
     fn from(_: io::Error) -> Self {
-        todo!("Convert an I/O error into a WaveReaderError")
+        WaveReaderError::ReadError
+    }
+}
+
+impl error::Error for WaveWriterError {}
+
+impl fmt::Display for WaveWriterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Error: {:?}", self)
+    }
+}
+
+impl From <io::Error> for WaveWriterError {
+    fn from(_: io::Error) -> Self {
+        WaveWriterError::WriteError
     }
 }
 
 impl fmt::Display for PCMWaveInfo {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        todo!("Display a PCMWaveInfo struct in this format: WAVE File <FileSize> bytes, <BitDepth>-bit <NumChannels> channels, <SampleRate>Hz, <NumDataChunks> data chunks")
+        write!(
+            f,
+            "WAVE File {} bytes, {}-bit {} channels, {}Hz, {} data chunks",
+            self.riff_header.file_size,
+            self.fmt_header.bps,
+            self.fmt_header.num_channels,
+            self.fmt_header.samp_rate,
+            self.data_chunks.len(),
+        )
     }
 }
 
 impl PCMWaveFormatChunk {
     /// Get or calculate the byte rate of this PCM WAV file
     fn byte_rate(&self) -> u32 {
-        todo!();
+        self.samp_rate * self.block_align() as u32
     }
 
     /// Get or calculate the block alignment of this PCM WAV file
@@ -289,7 +687,68 @@ impl PCMWaveFormatChunk {
     /// in bytes. An *inter-channel sample* is a sample with all of its
     /// channels collated together.
     fn block_align(&self) -> u16 {
-        todo!();
+        self.bps.div_ceil(8) * self.num_channels
+    }
+
+    /// Get the default channel mask of this PCM WAV file
+    /// 
+    /// Plain PCM WAV files do not store a channel mask, so the default
+    /// speaker layout for the number of channels is used. For example, six
+    /// channels are assumed to be 5.1 audio (`FL FR FC LFE BL BR`).
+    pub fn default_channel_mask(&self) -> u32 {
+        match self.num_channels {
+            1 => 0x4,
+            2 => 0x3,
+            3 => 0x7,
+            4 => 0x33,
+            5 => 0x37,
+            6 => 0x3f,
+            7 => 0x70f,
+            8 => 0x63f,
+            _ => 0,
+        }
+    }
+
+    /// Get the channel mask of this PCM WAV file
+    /// 
+    /// This is the channel mask of the format chunk, if any, or else the
+    /// default channel mask.
+    pub fn channel_mask(&self) -> u32 {
+        self.channel_mask.unwrap_or_else(|| self.default_channel_mask())
+    }
+
+    /// Get the speaker position of each channel of this PCM WAV file
+    /// 
+    /// The speakers are taken from the channel mask. Channels beyond the
+    /// speakers of the mask have no known position and are `None`.
+    pub fn channel_layout(&self) -> Vec <Option <Speaker>> {
+        let mut layout = Speaker::from_mask(self.channel_mask())
+            .into_iter()
+            .map(Some)
+            .collect::<Vec <_>>();
+
+        layout.resize(self.num_channels as usize, None);
+        layout
+    }
+}
+
+impl Speaker {
+    const ALL: [Speaker; 18] = [
+        Speaker::FrontLeft, Speaker::FrontRight, Speaker::FrontCenter,
+        Speaker::LowFrequency, Speaker::BackLeft, Speaker::BackRight,
+        Speaker::FrontLeftOfCenter, Speaker::FrontRightOfCenter, Speaker::BackCenter,
+        Speaker::SideLeft, Speaker::SideRight, Speaker::TopCenter,
+        Speaker::TopFrontLeft, Speaker::TopFrontCenter, Speaker::TopFrontRight,
+        Speaker::TopBackLeft, Speaker::TopBackCenter, Speaker::TopBackRight,
+    ];
+
+    /// Get the speakers set in a channel mask in channel order
+    pub fn from_mask(mask: u32) -> Vec <Speaker> {
+        Self::ALL
+            .iter()
+            .copied()
+            .filter(|&speaker| mask & speaker as u32 != 0)
+            .collect()
     }
 }
 
@@ -297,7 +756,35 @@ impl Iterator for PCMWaveDataChunk {
     type Item = Vec <i64>;
 
     fn next(&mut self) -> Option <Self::Item> {
-        todo!("Return one inter-channel sample at a time")
+        let block_align = self.format.block_align() as usize;
+        if self.num_bytes_read as usize + block_align > self.size_bytes as usize {
+            return None;
+        }
+
+        let mut bytes = vec![0u8; block_align];
+        self.data_buf.read_exact(&mut bytes).ok()?;
+        self.num_bytes_read += block_align as u32;
+
+        // Samples of bit depths that are not a multiple of 8 are left-justified
+        let container_bytes = self.format.bps.div_ceil(8) as usize;
+        let shift = container_bytes * 8 - self.format.bps as usize;
+
+        Some(bytes
+            .chunks_exact(container_bytes)
+            .map(|sample| {
+                let sample = if container_bytes == 1 {
+                    sample[0] as i64 - 128
+                }
+                else if self.is_big_endian {
+                    BigEndian::read_int(sample, container_bytes)
+                }
+                else {
+                    LittleEndian::read_int(sample, container_bytes)
+                };
+
+                sample >> shift
+            })
+            .collect())
     }
 }
 
@@ -305,17 +792,36 @@ impl Iterator for PCMWaveDataChunkWindow {
     type Item = Vec <Vec <i64>>;
 
     fn next(&mut self) -> Option <Self::Item> {
-        todo!("Return self.chunk_size amount of inter-channel samples at a time")
+        let sample_block: Vec <Vec <i64>> = self.data_chunk.by_ref().take(self.chunk_size).collect();
+
+        if sample_block.is_empty() {
+            None
+        }
+        else {
+            Some(sample_block)
+        }
     }
 }
 
 impl PCMWaveDataChunk {
+    /// Get the number of inter-channel samples in this data chunk
+    /// 
+    /// Each sample of a channel takes the least number of whole bytes
+    /// that fit the bit depth of the format.
+    pub fn num_samples(&self) -> u64 {
+        let sample_bytes = (self.format.bps as u64).div_ceil(8) * self.format.num_channels as u64;
+
+        self.size_bytes as u64 / sample_bytes
+    }
+
     /// Consume a data chunk and get an iterator
     /// 
-    /// This method is used to get a *single* inter-channel
-    /// sample from a data chunk.
+    /// This method is used to get one second of inter-channel samples,
+    /// that is the byte rate worth of samples, at a time from a data chunk.
     pub fn chunks_byte_rate(self) -> PCMWaveDataChunkWindow {
-        todo!();
+        let chunk_size = self.format.byte_rate() / self.format.block_align() as u32;
+
+        self.chunks(chunk_size as usize)
     }
 
     /// Consume a data chunk and get an iterator
@@ -326,7 +832,10 @@ impl PCMWaveDataChunk {
     /// return a `Vec` of size *at most* 44100 with each element as another `Vec`
     /// of size 2.
     pub fn chunks(self, chunk_size: usize) -> PCMWaveDataChunkWindow {
-        todo!();
+        PCMWaveDataChunkWindow {
+            chunk_size: chunk_size.max(1),
+            data_chunk: self,
+        }
     }
 }
 
@@ -519,6 +1028,7 @@ mod tests {
                         num_channels: 1,
                         samp_rate: 44100,
                         bps: 8,
+                        channel_mask: None,
                     },
                 )),
             it_valid_01: (
@@ -537,6 +1047,7 @@ mod tests {
                         num_channels: 2,
                         samp_rate: 44100,
                         bps: 8,
+                        channel_mask: None,
                     },
                 )),
             it_valid_02: (
@@ -555,12 +1066,288 @@ mod tests {
                         num_channels: 2,
                         samp_rate: 44100,
                         bps: 16,
+                        channel_mask: None,
                     },
                 )),
         }
     }
 
+    #[cfg(test)]
     mod read_data_fmt {
-        // TODO
+        use super::*;
+
+        fn write_and_open(file_name: &str, format: &PCMWaveFormatChunk, sample_block: &[Vec <i64>]) -> Result <PCMWaveInfo, WaveReaderError> {
+            let mut writer = WaveWriter::create(file_name, format).map_err(|_| WaveReaderError::ReadError)?;
+            writer.write_block(sample_block).map_err(|_| WaveReaderError::ReadError)?;
+            writer.finish().map_err(|_| WaveReaderError::ReadError)?;
+
+            let wav = WaveReader::open_pcm(file_name);
+            std::fs::remove_file(file_name)?;
+
+            wav
+        }
+
+        macro_rules! internal_tests {
+            ($($name:ident: $value:expr,)*) => {
+            $(
+                #[test]
+                fn $name() -> Result <(), WaveReaderError> {
+                    let (format, sample_block, display): (PCMWaveFormatChunk, Vec <Vec <i64>>, &str) = $value;
+
+                    let file_name = format!("midp_{}.wav.part", stringify!($name));
+                    let wav = write_and_open(&file_name, &format, &sample_block)?;
+
+                    assert_eq!(wav.to_string(), display);
+                    assert_eq!(wav.fmt_header, format);
+
+                    let samples: Vec <Vec <i64>> = wav.data_chunks
+                        .into_iter()
+                        .flat_map(|data_chunk| data_chunk.chunks(2))
+                        .flatten()
+                        .collect();
+                    assert_eq!(samples, sample_block);
+
+                    Ok(())
+                }
+            )*
+            }
+        }
+
+        internal_tests! {
+            it_unsigned_8_bit: (
+                PCMWaveFormatChunk { num_channels: 2, samp_rate: 8000, bps: 8, channel_mask: None },
+                vec![vec![0, -128], vec![127, 1], vec![-1, 64]],
+                "WAVE File 42 bytes, 8-bit 2 channels, 8000Hz, 1 data chunks",
+            ),
+            it_signed_16_bit: (
+                PCMWaveFormatChunk { num_channels: 1, samp_rate: 44100, bps: 16, channel_mask: None },
+                vec![vec![-32768], vec![32767], vec![0x1234]],
+                "WAVE File 42 bytes, 16-bit 1 channels, 44100Hz, 1 data chunks",
+            ),
+            it_extensible_20_bit: (
+                PCMWaveFormatChunk { num_channels: 3, samp_rate: 48000, bps: 20, channel_mask: None },
+                vec![vec![1, -1, 0x7ffff], vec![-0x80000, 0, 2]],
+                "WAVE File 78 bytes, 20-bit 3 channels, 48000Hz, 1 data chunks",
+            ),
+        }
+
+        #[test]
+        fn it_big_endian() -> Result <(), WaveReaderError> {
+            let mut content = b"RIFX\0\0\0\x28WAVEfmt \0\0\0\x10\0\x01\0\x01\0\0\x1f\x40\0\0\x3e\x80\0\x02\0\x10data\0\0\0\x04".to_vec();
+            content.extend_from_slice(&[0x12, 0x34, 0xff, 0xfe]);
+
+            let file_name = "midp_it_big_endian.wav.part";
+            std::fs::write(file_name, &content)?;
+            let wav = WaveReader::open_pcm(file_name);
+            std::fs::remove_file(file_name)?;
+
+            let samples: Vec <Vec <i64>> = wav?.data_chunks.into_iter().flatten().collect();
+            assert_eq!(samples, vec![vec![0x1234], vec![-2]]);
+
+            Ok(())
+        }
+
+        #[test]
+        fn it_misaligned_data() -> Result <(), WaveReaderError> {
+            let mut content = b"RIFF\x27\0\0\0WAVEfmt \x10\0\0\0\x01\0\x01\0\x40\x1f\0\0\x80\x3e\0\0\x02\0\x10\0data\x03\0\0\0".to_vec();
+            content.extend_from_slice(&[0x00, 0x00, 0x00, 0x00]);
+
+            let file_name = "midp_it_misaligned_data.wav.part";
+            std::fs::write(file_name, &content)?;
+            let wav = WaveReader::open_pcm(file_name);
+            std::fs::remove_file(file_name)?;
+
+            assert!(matches!(wav, Err(WaveReaderError::DataAlignmentError)));
+
+            Ok(())
+        }
+    }
+
+    #[cfg(test)]
+    mod channel_layout {
+        use super::*;
+
+        #[test]
+        fn it_stereo() {
+            let fmt = PCMWaveFormatChunk {
+                num_channels: 2,
+                samp_rate: 44100,
+                bps: 16,
+                channel_mask: None,
+            };
+
+            assert_eq!(fmt.channel_layout(), vec![Some(Speaker::FrontLeft), Some(Speaker::FrontRight)]);
+        }
+
+        #[test]
+        fn it_surround_51() {
+            let fmt = PCMWaveFormatChunk {
+                num_channels: 6,
+                samp_rate: 48000,
+                bps: 24,
+                channel_mask: None,
+            };
+
+            assert_eq!(fmt.channel_layout(), vec![
+                Some(Speaker::FrontLeft), Some(Speaker::FrontRight), Some(Speaker::FrontCenter),
+                Some(Speaker::LowFrequency), Some(Speaker::BackLeft), Some(Speaker::BackRight),
+            ]);
+        }
+
+        #[test]
+        fn it_unknown() {
+            let fmt = PCMWaveFormatChunk {
+                num_channels: 10,
+                samp_rate: 48000,
+                bps: 24,
+                channel_mask: None,
+            };
+
+            assert_eq!(fmt.channel_layout(), vec![None; 10]);
+        }
+
+        #[test]
+        fn it_channel_mask() -> Result <(), WaveReaderError> {
+            let format = PCMWaveFormatChunk {
+                num_channels: 4,
+                samp_rate: 48000,
+                bps: 16,
+                channel_mask: Some(0x603),
+            };
+
+            let file_name = "midp_it_channel_mask.wav.part";
+            let mut writer = WaveWriter::create(file_name, &format).map_err(|_| WaveReaderError::ReadError)?;
+            writer.write_block(&[vec![1, 2, 3, 4]]).map_err(|_| WaveReaderError::ReadError)?;
+            writer.finish().map_err(|_| WaveReaderError::ReadError)?;
+            let wav = WaveReader::open_pcm(file_name);
+            std::fs::remove_file(file_name)?;
+
+            let fmt = wav?.fmt_header;
+            assert_eq!(fmt, format);
+            assert_eq!(fmt.channel_layout(), vec![
+                Some(Speaker::FrontLeft), Some(Speaker::FrontRight), Some(Speaker::SideLeft), Some(Speaker::SideRight),
+            ]);
+
+            Ok(())
+        }
+
+        #[test]
+        fn it_from_mask() {
+            assert_eq!(Speaker::from_mask(0x604), vec![Speaker::FrontCenter, Speaker::SideLeft, Speaker::SideRight]);
+        }
+    }
+
+    #[cfg(test)]
+    mod cue_points {
+        use super::*;
+        use std::io::Write;
+
+        fn chunk(id: &[u8], data: &[u8]) -> Vec <u8> {
+            let mut bytes = id.to_vec();
+            bytes.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(data);
+            if data.len() % 2 == 1 {
+                bytes.push(0);
+            }
+
+            bytes
+        }
+
+        fn cue_point(id: u32, position: u32) -> Vec <u8> {
+            let mut bytes = id.to_le_bytes().to_vec();
+            bytes.extend_from_slice(&position.to_le_bytes());
+            bytes.extend_from_slice(b"data");
+            bytes.extend_from_slice(&[0; 8]);
+            bytes.extend_from_slice(&position.to_le_bytes());
+
+            bytes
+        }
+
+        #[test]
+        fn it_reads_cue_points() -> Result <(), WaveReaderError> {
+            let mut cue_data = 2u32.to_le_bytes().to_vec();
+            cue_data.extend(cue_point(1, 0));
+            cue_data.extend(cue_point(2, 44100));
+
+            let mut labl_data = 2u32.to_le_bytes().to_vec();
+            labl_data.extend_from_slice(b"Chorus\0");
+
+            let mut adtl_data = b"adtl".to_vec();
+            adtl_data.extend(chunk(b"labl", &labl_data));
+
+            let mut body = b"WAVE".to_vec();
+            body.extend(chunk(b"fmt ", &[0; 16]));
+            body.extend(chunk(b"data", &[0; 3]));
+            body.extend(chunk(b"cue ", &cue_data));
+            body.extend(chunk(b"LIST", &adtl_data));
+
+            let mut content = b"RIFF".to_vec();
+            content.extend_from_slice(&(body.len() as u32).to_le_bytes());
+            content.extend(body);
+
+            let file_name = "midp_it_reads_cue_points.wav.part";
+            File::create(file_name)?.write_all(&content)?;
+
+            let chunks = WaveReader::read_chunk_list(&mut File::open(file_name)?);
+            let cue_points = WaveReader::read_cue_points(file_name);
+            std::fs::remove_file(file_name)?;
+
+            let ids: Vec <[u8; 4]> = chunks?.iter().map(|chunk| chunk.id).collect();
+            assert_eq!(ids, vec![*b"fmt ", *b"data", *b"cue ", *b"LIST"]);
+            assert_eq!(cue_points?, vec![
+                WaveCuePoint { id: 1, position: 0, label: None },
+                WaveCuePoint { id: 2, position: 44100, label: Some(String::from("Chorus")) },
+            ]);
+
+            Ok(())
+        }
+    }
+
+    #[cfg(test)]
+    mod write_wave {
+        use super::*;
+
+        fn write_and_read(file_name: &str, format: &PCMWaveFormatChunk, sample_block: &[Vec <i64>]) -> Result <Vec <u8>, WaveWriterError> {
+            let mut writer = WaveWriter::create(file_name, format)?;
+            writer.write_block(sample_block)?;
+            writer.finish()?;
+
+            let bytes = std::fs::read(file_name)?;
+            std::fs::remove_file(file_name)?;
+
+            Ok(bytes)
+        }
+
+        #[test]
+        fn it_writes_pcm() -> Result <(), WaveWriterError> {
+            let format = PCMWaveFormatChunk { num_channels: 2, samp_rate: 8000, bps: 16, channel_mask: None };
+            let bytes = write_and_read("midp_it_writes_pcm.wav.part", &format, &[vec![1, -2], vec![0x1234, -32768]])?;
+
+            let mut expected = b"RIFF\x2c\0\0\0WAVEfmt \x10\0\0\0\x01\0\x02\0\x40\x1f\0\0\0\x7d\0\0\x04\0\x10\0data\x08\0\0\0".to_vec();
+            expected.extend_from_slice(&[0x01, 0x00, 0xfe, 0xff, 0x34, 0x12, 0x00, 0x80]);
+            assert_eq!(bytes, expected);
+
+            Ok(())
+        }
+
+        #[test]
+        fn it_writes_extensible() -> Result <(), WaveWriterError> {
+            let format = PCMWaveFormatChunk { num_channels: 3, samp_rate: 48000, bps: 20, channel_mask: None };
+            let bytes = write_and_read("midp_it_writes_extensible.wav.part", &format, &[vec![1, -1, 0x7ffff]])?;
+
+            assert_eq!(bytes.len(), 12 + 48 + 8 + 10);
+            assert_eq!(LittleEndian::read_u32(&bytes[4..8]), 70);
+            assert_eq!(LittleEndian::read_u16(&bytes[20..22]), 0xfffe);
+            assert_eq!(LittleEndian::read_u16(&bytes[34..36]), 24);
+            assert_eq!(LittleEndian::read_u16(&bytes[38..40]), 20);
+            assert_eq!(LittleEndian::read_u32(&bytes[40..44]), 0x7);
+            assert_eq!(&bytes[60..64], b"data");
+            assert_eq!(LittleEndian::read_u32(&bytes[64..68]), 9);
+            assert_eq!(&bytes[68..], &[0x10, 0x00, 0x00, 0xf0, 0xff, 0xff, 0xf0, 0xff, 0x7f, 0x00]);
+
+            assert!(matches!(WaveWriter::create("midp_unused.wav.part", &PCMWaveFormatChunk { num_channels: 1, samp_rate: 8000, bps: 33, channel_mask: None }), Err(WaveWriterError::FormatError)));
+
+            Ok(())
+        }
     }
 }
\ No newline at end of file